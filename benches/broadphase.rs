@@ -0,0 +1,114 @@
+//! Compares the broadphases on colliders moving around every step.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use glam::Vec2;
+use rustyengine::core::collision::broadphase::{Broadphase, BruteForce, DynamicTree, SpatialHash};
+use rustyengine::core::collision::shapes::Aabb;
+use std::hint::black_box;
+
+/// How many colliders are moving in each run.
+const COUNTS: [usize; 3] = [50, 500, 5000];
+
+/// The size of the colliders, and of the cells of the spatial hash.
+const SIZE: f32 = 16.0;
+
+/// How far colliders move every step.
+const SPEED: f32 = 2.0;
+
+/// A collider bouncing around a square area.
+#[derive(Debug, Clone, Copy)]
+struct Body {
+    position: Vec2,
+    velocity: Vec2,
+}
+
+/// Spreads `count` bodies over an area where each one overlaps a few
+/// others, going in every direction. Always the same ones, so runs can be
+/// compared.
+fn bodies(count: usize) -> (Vec<Body>, f32) {
+    let side = (count as f32).sqrt() * SIZE * 2.0;
+    let mut seed = 0x2545_f491_u32;
+    let mut random = move || {
+        // xorshift
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        seed as f32 / u32::MAX as f32
+    };
+
+    let bodies = (0..count)
+        .map(|_| Body {
+            position: Vec2::new(random(), random()) * side,
+            velocity: Vec2::from_angle(random() * std::f32::consts::TAU) * SPEED,
+        })
+        .collect();
+
+    (bodies, side)
+}
+
+/// Moves every body a step, turning around at the sides of the area.
+fn step(bodies: &mut [Body], side: f32) {
+    for body in bodies {
+        body.position += body.velocity;
+        for axis in 0..2 {
+            if body.position[axis] < 0.0 || body.position[axis] > side {
+                body.velocity[axis] = -body.velocity[axis];
+            }
+        }
+    }
+}
+
+fn bounds(body: &Body) -> Aabb {
+    Aabb::from_position_size(body.position, Vec2::splat(SIZE))
+}
+
+/// Benchmarks moving every body and finding the pairs overlapping, then
+/// querying the area around each body, as systems do every step.
+fn bench<B: Broadphase<usize>>(criterion: &mut Criterion, name: &str, broadphase: impl Fn() -> B) {
+    let mut group = criterion.benchmark_group(name);
+
+    for count in COUNTS {
+        let (mut moving, side) = bodies(count);
+        let mut pairs_broadphase = broadphase();
+        group.bench_with_input(BenchmarkId::new("pairs", count), &count, |bencher, _| {
+            bencher.iter(|| {
+                step(&mut moving, side);
+                for (key, body) in moving.iter().enumerate() {
+                    pairs_broadphase.insert(key, bounds(body));
+                }
+                black_box(pairs_broadphase.pairs())
+            })
+        });
+
+        let (mut moving, side) = bodies(count);
+        let mut region_broadphase = broadphase();
+        group.bench_with_input(
+            BenchmarkId::new("query_region", count),
+            &count,
+            |bencher, _| {
+                bencher.iter(|| {
+                    step(&mut moving, side);
+                    for (key, body) in moving.iter().enumerate() {
+                        region_broadphase.insert(key, bounds(body));
+                    }
+                    for body in &moving {
+                        let around = bounds(body);
+                        let region = Aabb::new(around.min - SIZE, around.max + SIZE);
+                        black_box(region_broadphase.query_region(&region));
+                    }
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn broadphases(criterion: &mut Criterion) {
+    bench(criterion, "brute force", BruteForce::new);
+    bench(criterion, "spatial hash", || SpatialHash::new(SIZE));
+    bench(criterion, "dynamic tree", DynamicTree::new);
+}
+
+criterion_group!(benches, broadphases);
+criterion_main!(benches);
@@ -0,0 +1,576 @@
+use super::camera::{Camera2D, Drawable};
+use super::rect::Rect;
+use super::sprite::Sprite;
+use glam::Vec2;
+use miniquad::*;
+use std::collections::HashMap;
+
+/// The shortest duration a frame can last, in seconds.
+const MIN_FRAME_DURATION: f32 = 0.001;
+
+/// Determines what happens when an animation clip reaches its last frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaybackMode {
+    /// Starts over from the first frame.
+    Loop,
+    /// Plays backwards until reaching the first frame, then forwards again.
+    PingPong,
+    /// Stops at the last frame.
+    Once,
+}
+
+/// A single frame of an animation clip.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnimationFrame {
+    /// The index of the frame in the sprite sheet.
+    pub index: usize,
+    /// How long the frame is shown, in seconds.
+    pub duration: f32,
+}
+
+/// A sequence of frames played back one after the other.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationClip {
+    /// The frames of the clip, in order.
+    pub frames: Vec<AnimationFrame>,
+    /// What to do after the last frame.
+    pub mode: PlaybackMode,
+}
+
+impl AnimationClip {
+    /// Creates a clip with the given frames.
+    /// Frames can't last less than a millisecond.
+    pub fn new(frames: Vec<AnimationFrame>, mode: PlaybackMode) -> Self {
+        let frames = frames
+            .into_iter()
+            .map(|frame| AnimationFrame {
+                duration: frame.duration.max(MIN_FRAME_DURATION),
+                ..frame
+            })
+            .collect();
+
+        Self { frames, mode }
+    }
+
+    /// Creates a clip showing every frame in `indices` for `frame_duration` seconds.
+    ///
+    /// # Example
+    /// ```rust
+    /// // the first four frames of the sheet, at 10 frames per second
+    /// let run = AnimationClip::from_indices(&[0, 1, 2, 3], 0.1, PlaybackMode::Loop);
+    /// ```
+    pub fn from_indices(indices: &[usize], frame_duration: f32, mode: PlaybackMode) -> Self {
+        let frames = indices
+            .iter()
+            .map(|&index| AnimationFrame {
+                index,
+                duration: frame_duration,
+            })
+            .collect();
+
+        Self::new(frames, mode)
+    }
+
+    /// Gets how long a single run through every frame lasts, in seconds.
+    pub fn duration(&self) -> f32 {
+        self.frames.iter().map(|frame| frame.duration).sum()
+    }
+}
+
+/// Something that happened to a clip while advancing an animation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnimationEvent {
+    /// A `PlaybackMode::Once` clip reached its last frame.
+    Finished(String),
+    /// A looping or ping-ponging clip completed a cycle and started another one.
+    Looped(String),
+}
+
+/// Plays named animation clips. Only keeps track of time and frames, so it
+/// can be used with anything that has frames.
+#[derive(Debug, Clone)]
+pub struct AnimationPlayer {
+    clips: HashMap<String, AnimationClip>,
+    /// The name of the clip being played.
+    current: Option<String>,
+    /// The position in the current clip's frames, not the sheet index.
+    frame: usize,
+    /// The time spent on the current frame, in seconds.
+    elapsed: f32,
+    /// Whether a ping-ponging clip is playing backwards.
+    reversed: bool,
+    /// Whether a `PlaybackMode::Once` clip reached its end.
+    finished: bool,
+    /// Multiplies the delta time when advancing.
+    pub speed: f32,
+    /// Whether the player is stopped on its current frame.
+    pub paused: bool,
+    /// The events that happened during the last `advance`.
+    events: Vec<AnimationEvent>,
+}
+
+impl AnimationPlayer {
+    /// Creates a player without clips.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a clip, replacing any other with the same name. Replacing the
+    /// clip being played plays the new one from its first frame.
+    pub fn add_clip(&mut self, name: &str, clip: AnimationClip) {
+        self.clips.insert(name.to_string(), clip);
+
+        if self.current.as_deref() == Some(name) {
+            self.restart(name);
+        }
+    }
+
+    /// Gets the clip with the given name.
+    pub fn clip(&self, name: &str) -> Option<&AnimationClip> {
+        self.clips.get(name)
+    }
+
+    /// Plays the clip with the given name. Keeps going if the clip is
+    /// already playing, see `restart` to play it from its first frame.
+    /// Returns whether the clip exists.
+    pub fn play(&mut self, name: &str) -> bool {
+        if self.current.as_deref() == Some(name) {
+            return true;
+        }
+
+        self.restart(name)
+    }
+
+    /// Plays the clip with the given name from its first frame.
+    /// Returns whether the clip exists.
+    pub fn restart(&mut self, name: &str) -> bool {
+        if !self.clips.contains_key(name) {
+            warn!("Tried to play the missing animation clip {:?}", name);
+            return false;
+        }
+
+        self.current = Some(name.to_string());
+        self.frame = 0;
+        self.elapsed = 0.0;
+        self.reversed = false;
+        self.finished = false;
+
+        true
+    }
+
+    /// Gets the name of the clip being played.
+    pub fn current_clip(&self) -> Option<&str> {
+        self.current.as_deref()
+    }
+
+    /// Gets the sheet index of the frame being shown.
+    pub fn current_frame(&self) -> Option<usize> {
+        let clip = self.clips.get(self.current.as_ref()?)?;
+
+        clip.frames.get(self.frame).map(|frame| frame.index)
+    }
+
+    /// Whether a `PlaybackMode::Once` clip reached its last frame.
+    pub const fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Gets the events that happened during the last `advance`.
+    pub fn events(&self) -> &[AnimationEvent] {
+        &self.events
+    }
+
+    /// Moves the current clip forward by `dt` seconds, going through as
+    /// many frames as needed.
+    pub fn advance(&mut self, dt: f32) {
+        self.events.clear();
+
+        if self.paused || self.finished {
+            return;
+        }
+
+        let (name, clip) = match self
+            .current
+            .as_ref()
+            .and_then(|name| Some((name, self.clips.get(name)?)))
+        {
+            Some((name, clip)) if !clip.frames.is_empty() => (name, clip),
+            _ => return,
+        };
+
+        self.elapsed += dt * self.speed;
+
+        while self.elapsed >= clip.frames[self.frame].duration {
+            self.elapsed -= clip.frames[self.frame].duration;
+            let last = clip.frames.len() - 1;
+
+            match clip.mode {
+                PlaybackMode::Loop if self.frame == last => {
+                    self.frame = 0;
+                    self.events.push(AnimationEvent::Looped(name.clone()));
+                }
+                PlaybackMode::Loop => self.frame += 1,
+                PlaybackMode::Once if self.frame == last => {
+                    self.finished = true;
+                    self.elapsed = 0.0;
+                    self.events.push(AnimationEvent::Finished(name.clone()));
+                    break;
+                }
+                PlaybackMode::Once => self.frame += 1,
+                PlaybackMode::PingPong if last == 0 => {
+                    self.events.push(AnimationEvent::Looped(name.clone()));
+                }
+                PlaybackMode::PingPong if self.reversed => {
+                    self.frame -= 1;
+                    if self.frame == 0 {
+                        self.reversed = false;
+                        self.events.push(AnimationEvent::Looped(name.clone()));
+                    }
+                }
+                PlaybackMode::PingPong => {
+                    self.frame += 1;
+                    self.reversed = self.frame == last;
+                }
+            }
+        }
+    }
+}
+
+impl Default for AnimationPlayer {
+    fn default() -> Self {
+        let clips = HashMap::new();
+        let current = None;
+        let frame = 0;
+        let elapsed = 0.0;
+        let reversed = false;
+        let finished = false;
+        let speed = 1.0;
+        let paused = false;
+        let events = Vec::new();
+
+        Self {
+            clips,
+            current,
+            frame,
+            elapsed,
+            reversed,
+            finished,
+            speed,
+            paused,
+            events,
+        }
+    }
+}
+
+/// A function called with the events fired by an `AnimatedSprite`.
+pub type AnimationCallback = Box<dyn FnMut(&AnimationEvent)>;
+
+/// Splits a sprite sheet of `sheet_size` pixels into a grid of `frame_size`
+/// frames, numbered left to right and top to bottom.
+pub fn grid_frames(sheet_size: (u32, u32), frame_size: Vec2) -> Vec<Rect> {
+    let columns = (sheet_size.0 as f32 / frame_size.x).floor() as usize;
+    let rows = (sheet_size.1 as f32 / frame_size.y).floor() as usize;
+
+    (0..rows)
+        .flat_map(|row| (0..columns).map(move |column| (column, row)))
+        .map(|(column, row)| {
+            let position = Vec2::new(column as f32, row as f32) * frame_size;
+            Rect::from_position_size(position, frame_size)
+        })
+        .collect()
+}
+
+/// A sprite rendering frames out of a sprite sheet, driven by an `AnimationPlayer`.
+pub struct AnimatedSprite {
+    /// The sprite showing the current frame.
+    pub sprite: Sprite,
+    /// The animation clips and their playback state.
+    pub player: AnimationPlayer,
+    /// The regions of the sheet each frame index refers to.
+    frames: Vec<Rect>,
+    /// Called with every event fired while updating.
+    callbacks: Vec<AnimationCallback>,
+    /// The last time the sprite was updated, used to get the delta time.
+    last_update: Option<f64>,
+}
+
+impl AnimatedSprite {
+    /// Creates an animated sprite out of a sprite and the regions of its
+    /// texture each frame index refers to.
+    ///
+    /// # Example
+    /// ```rust
+    /// use glam::Vec2;
+    ///
+    /// let sprite = Sprite::new(Vec2::new(64.0, 32.0), texture);
+    /// let frames = grid_frames(texture.size(), Vec2::new(16.0, 16.0));
+    ///
+    /// let mut hero = AnimatedSprite::new(sprite, frames);
+    /// hero.player.add_clip("idle", AnimationClip::from_indices(&[0, 1], 0.5, PlaybackMode::Loop));
+    /// hero.on_event(|event| println!("{:?}", event));
+    /// hero.player.play("idle");
+    /// ```
+    pub fn new(sprite: Sprite, frames: Vec<Rect>) -> Self {
+        let mut animated_sprite = Self {
+            sprite,
+            player: AnimationPlayer::new(),
+            frames,
+            callbacks: Vec::new(),
+            last_update: None,
+        };
+        animated_sprite.sync_frame();
+
+        animated_sprite
+    }
+
+    /// Registers a function called with every event fired while updating.
+    pub fn on_event<F>(&mut self, callback: F)
+    where
+        F: FnMut(&AnimationEvent) + 'static,
+    {
+        self.callbacks.push(Box::new(callback));
+    }
+
+    /// Advances the animation by `dt` seconds, updating the frame shown and
+    /// firing the callbacks.
+    pub fn update_with_delta(&mut self, dt: f32) {
+        self.player.advance(dt);
+        self.sync_frame();
+
+        for event in self.player.events() {
+            self.callbacks
+                .iter_mut()
+                .for_each(|callback| callback(event));
+        }
+    }
+
+    /// Makes the sprite render the region of the current frame.
+    fn sync_frame(&mut self) {
+        let frame = self.player.current_frame().unwrap_or(0);
+
+        if let Some(source) = self.frames.get(frame) {
+            self.sprite.source = Some(*source);
+        }
+    }
+}
+
+impl EventHandler for AnimatedSprite {
+    fn update(&mut self, _ctx: &mut Context) {
+        let now = date::now();
+        let dt = self.last_update.map_or(0.0, |last| now - last);
+        self.last_update = Some(now);
+
+        self.update_with_delta(dt as f32);
+    }
+
+    fn draw(&mut self, ctx: &mut Context) {
+        self.sprite.draw(ctx);
+    }
+}
+
+impl Drawable for AnimatedSprite {
+    fn draw_with_camera(&mut self, ctx: &mut Context, camera: &Camera2D) {
+        self.sprite.draw_with_camera(ctx, camera);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replacing_the_current_clip_restarts_it() {
+        let mut player = AnimationPlayer::new();
+        player.add_clip(
+            "run",
+            AnimationClip::from_indices(&[0, 1, 2, 3], 0.1, PlaybackMode::Loop),
+        );
+        player.play("run");
+        player.advance(0.35);
+        assert_eq!(player.current_frame(), Some(3));
+
+        player.add_clip(
+            "run",
+            AnimationClip::from_indices(&[4, 5], 0.1, PlaybackMode::Loop),
+        );
+        assert_eq!(player.current_frame(), Some(4));
+
+        player.advance(0.15);
+        assert_eq!(player.current_frame(), Some(5));
+    }
+
+    #[test]
+    fn replacing_another_clip_keeps_playing() {
+        let mut player = AnimationPlayer::new();
+        player.add_clip(
+            "run",
+            AnimationClip::from_indices(&[0, 1, 2, 3], 0.1, PlaybackMode::Loop),
+        );
+        player.play("run");
+        player.advance(0.25);
+
+        player.add_clip(
+            "idle",
+            AnimationClip::from_indices(&[8], 0.1, PlaybackMode::Loop),
+        );
+
+        assert_eq!(player.current_frame(), Some(2));
+    }
+
+    /// How long each frame lasts, exact in binary so frames end on time.
+    const FRAME: f32 = 0.25;
+
+    /// Creates a player playing a clip called "clip" of `indices`.
+    fn playing(indices: &[usize], mode: PlaybackMode) -> AnimationPlayer {
+        let mut player = AnimationPlayer::new();
+        player.add_clip("clip", AnimationClip::from_indices(indices, FRAME, mode));
+        assert!(player.play("clip"));
+
+        player
+    }
+
+    /// Advances a frame at a time, getting the frame shown after each one.
+    fn frames(player: &mut AnimationPlayer, count: usize) -> Vec<usize> {
+        (0..count)
+            .map(|_| {
+                player.advance(FRAME);
+                player.current_frame().unwrap()
+            })
+            .collect()
+    }
+
+    fn looped() -> AnimationEvent {
+        AnimationEvent::Looped("clip".to_string())
+    }
+
+    #[test]
+    fn starts_over_when_looping() {
+        let mut player = playing(&[4, 5, 6], PlaybackMode::Loop);
+        assert_eq!(player.current_frame(), Some(4));
+
+        assert_eq!(frames(&mut player, 2), [5, 6]);
+        assert!(player.events().is_empty());
+        assert_eq!(frames(&mut player, 1), [4]);
+        assert_eq!(player.events(), [looped()]);
+        assert_eq!(frames(&mut player, 1), [5]);
+        assert!(player.events().is_empty());
+    }
+
+    #[test]
+    fn goes_back_and_forth_when_ping_ponging() {
+        let mut player = playing(&[4, 5, 6], PlaybackMode::PingPong);
+
+        assert_eq!(frames(&mut player, 3), [5, 6, 5]);
+        assert!(player.events().is_empty());
+        assert_eq!(frames(&mut player, 1), [4]);
+        assert_eq!(player.events(), [looped()]);
+        assert_eq!(frames(&mut player, 3), [5, 6, 5]);
+    }
+
+    #[test]
+    fn stops_at_the_last_frame_when_played_once() {
+        let mut player = playing(&[4, 5, 6], PlaybackMode::Once);
+
+        assert_eq!(frames(&mut player, 2), [5, 6]);
+        assert!(!player.is_finished());
+        assert_eq!(frames(&mut player, 1), [6]);
+        assert!(player.is_finished());
+        assert_eq!(
+            player.events(),
+            [AnimationEvent::Finished("clip".to_string())]
+        );
+
+        // finishing only happens once
+        assert_eq!(frames(&mut player, 2), [6, 6]);
+        assert!(player.events().is_empty());
+
+        assert!(player.restart("clip"));
+        assert!(!player.is_finished());
+        assert_eq!(player.current_frame(), Some(4));
+    }
+
+    #[test]
+    fn goes_through_several_frames_in_a_single_advance() {
+        let mut player = playing(&[4, 5, 6], PlaybackMode::Loop);
+
+        player.advance(FRAME * 4.0);
+        assert_eq!(player.current_frame(), Some(5));
+        assert_eq!(player.events(), [looped()]);
+
+        // two more cycles, and a bit
+        player.advance(FRAME * 6.5);
+        assert_eq!(player.current_frame(), Some(5));
+        assert_eq!(player.events(), [looped(), looped()]);
+
+        // the bit left over counts towards the next frame
+        player.advance(FRAME / 2.0);
+        assert_eq!(player.current_frame(), Some(6));
+    }
+
+    #[test]
+    fn stays_on_the_last_frame_when_going_past_the_end_at_once() {
+        let mut player = playing(&[4, 5, 6], PlaybackMode::Once);
+
+        player.advance(FRAME * 10.0);
+
+        assert_eq!(player.current_frame(), Some(6));
+        assert_eq!(
+            player.events(),
+            [AnimationEvent::Finished("clip".to_string())]
+        );
+    }
+
+    #[test]
+    fn advances_faster_or_slower_by_the_speed() {
+        let mut player = playing(&[4, 5, 6], PlaybackMode::Loop);
+
+        player.speed = 2.0;
+        assert_eq!(frames(&mut player, 1), [6]);
+
+        player.speed = 0.5;
+        assert_eq!(frames(&mut player, 2), [6, 4]);
+    }
+
+    #[test]
+    fn stays_on_the_same_frame_while_paused() {
+        let mut player = playing(&[4, 5, 6], PlaybackMode::Loop);
+        player.advance(FRAME * 3.0);
+        assert_eq!(player.events(), [looped()]);
+
+        player.paused = true;
+        player.advance(FRAME * 10.0);
+        assert_eq!(player.current_frame(), Some(4));
+        // the events of the last advance are gone
+        assert!(player.events().is_empty());
+
+        player.paused = false;
+        assert_eq!(frames(&mut player, 1), [5]);
+    }
+
+    #[test]
+    fn loops_single_frame_clips_on_every_frame() {
+        for mode in [PlaybackMode::PingPong, PlaybackMode::Loop] {
+            let mut player = playing(&[7], mode);
+
+            assert_eq!(frames(&mut player, 2), [7, 7]);
+            assert_eq!(player.events(), [looped()], "{:?}", mode);
+
+            player.advance(FRAME * 3.0);
+            assert_eq!(player.current_frame(), Some(7));
+            assert_eq!(player.events(), [looped(), looped(), looped()]);
+        }
+    }
+
+    #[test]
+    fn keeps_playing_the_current_clip_unless_restarted() {
+        let mut player = playing(&[4, 5, 6], PlaybackMode::Loop);
+        player.advance(FRAME);
+
+        assert!(player.play("clip"));
+        assert_eq!(player.current_frame(), Some(5));
+        assert!(!player.play("missing"));
+        assert_eq!(player.current_clip(), Some("clip"));
+        assert!(player.restart("clip"));
+        assert_eq!(player.current_frame(), Some(4));
+    }
+}
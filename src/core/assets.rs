@@ -0,0 +1,198 @@
+use super::error::EngineError;
+use super::texture::Texture;
+use miniquad::*;
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// A cheap, cloneable reference to a loaded asset.
+/// Every clone points to the same asset, which stays loaded while any handle is alive.
+pub struct Handle<T> {
+    inner: Rc<T>,
+}
+
+impl<T> Handle<T> {
+    fn new(asset: T) -> Self {
+        Self {
+            inner: Rc::new(asset),
+        }
+    }
+
+    /// Whether both handles point to the same asset.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    /// Gets the amount of handles pointing to the asset, including this one.
+    pub fn count(&self) -> usize {
+        Rc::strong_count(&self.inner)
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Rc::clone(&self.inner),
+        }
+    }
+}
+
+impl<T> Deref for Handle<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+/// Identifies a loaded texture. Loading the same path with the same
+/// parameters twice yields the same texture.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct TextureKey {
+    path: PathBuf,
+    format: TextureFormat,
+    wrap: i32,
+    filter: i32,
+}
+
+impl TextureKey {
+    fn new(path: &Path, texture_params: &TextureParams) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            format: texture_params.format,
+            wrap: texture_params.wrap as i32,
+            filter: texture_params.filter as i32,
+        }
+    }
+}
+
+/// Keeps track of every asset loaded, so that they are only loaded once.
+/// Assets are handed out as `Handle`s, and unloaded by `unload_unused`
+/// once no handle points to them anymore.
+pub struct Assets {
+    textures: HashMap<TextureKey, Handle<Texture>>,
+    /// Whether textures that fail to load should be replaced by a placeholder
+    /// instead of returning an error. Enabled by default on debug builds.
+    pub use_missing_placeholder: bool,
+    /// The texture shown instead of the ones that failed to load.
+    missing_placeholder: Option<Handle<Texture>>,
+}
+
+impl Assets {
+    /// Creates an empty asset manager.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads a texture with nearest filtering, or returns the already loaded one.
+    ///
+    /// # Example
+    /// ```rust
+    /// use std::path::Path;
+    ///
+    /// let mut assets = Assets::new();
+    /// let chest = assets.load_texture(ctx, Path::new("chest.png"));
+    /// let same_chest = assets.load_texture(ctx, Path::new("chest.png"));
+    /// assert!(chest?.ptr_eq(&same_chest?));
+    /// ```
+    pub fn load_texture(
+        &mut self,
+        ctx: &mut Context,
+        path: &Path,
+    ) -> Result<Handle<Texture>, EngineError> {
+        self.load_texture_with_params(
+            ctx,
+            path,
+            TextureParams {
+                filter: FilterMode::Nearest,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Loads a texture with the texture params specified, or returns the
+    /// already loaded one if the path and params match.
+    /// Textures are loaded with the sprite shader.
+    ///
+    /// If the texture can't be loaded and `use_missing_placeholder` is set,
+    /// the error is logged and a placeholder texture is returned instead.
+    pub fn load_texture_with_params(
+        &mut self,
+        ctx: &mut Context,
+        path: &Path,
+        texture_params: TextureParams,
+    ) -> Result<Handle<Texture>, EngineError> {
+        let key = TextureKey::new(path, &texture_params);
+
+        if let Some(texture) = self.textures.get(&key) {
+            debug!("Reusing already loaded texture {:?}", path);
+            return Ok(texture.clone());
+        }
+
+        let shader_params = super::sprite::shader_params();
+        match Texture::try_with_params(ctx, path, shader_params, texture_params) {
+            Ok(texture) => {
+                let texture = Handle::new(texture);
+                self.textures.insert(key, texture.clone());
+
+                Ok(texture)
+            }
+            Err(error) if self.use_missing_placeholder => {
+                error!("{}, using a placeholder instead", error);
+                self.missing_placeholder(ctx)
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Gets the texture shown instead of the ones that failed to load,
+    /// creating it the first time.
+    fn missing_placeholder(&mut self, ctx: &mut Context) -> Result<Handle<Texture>, EngineError> {
+        if let Some(placeholder) = &self.missing_placeholder {
+            return Ok(placeholder.clone());
+        }
+
+        let shader_params = super::sprite::shader_params();
+        let placeholder = Handle::new(Texture::missing(ctx, shader_params)?);
+        self.missing_placeholder = Some(placeholder.clone());
+
+        Ok(placeholder)
+    }
+
+    /// Gets the amount of textures currently loaded.
+    pub fn loaded_textures(&self) -> usize {
+        self.textures.len()
+    }
+
+    /// Unloads every asset that is no longer referenced by any handle.
+    /// Returns the amount of assets unloaded.
+    pub fn unload_unused(&mut self) -> usize {
+        let loaded = self.textures.len();
+        self.textures.retain(|key, texture| {
+            // the only handle left is the one we keep here
+            let used = texture.count() > 1;
+            if !used {
+                info!("Unloading unused texture {:?}", key.path);
+            }
+
+            used
+        });
+
+        loaded - self.textures.len()
+    }
+}
+
+impl Default for Assets {
+    fn default() -> Self {
+        let textures = HashMap::new();
+        let use_missing_placeholder = cfg!(debug_assertions);
+        let missing_placeholder = None;
+
+        Self {
+            textures,
+            use_missing_placeholder,
+            missing_placeholder,
+        }
+    }
+}
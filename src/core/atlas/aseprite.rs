@@ -0,0 +1,278 @@
+//! Imports sprite sheets exported by Aseprite with `--data`, in either
+//! the hash or the array format.
+
+use super::{load_atlas_texture, read_file, AtlasRegion, JsonSheet, TextureAtlas};
+use crate::core::animation::{AnimationClip, AnimationFrame, PlaybackMode};
+use crate::core::assets::Assets;
+use crate::core::error::EngineError;
+use miniquad::Context;
+use serde::de::Error as _;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// The duration given to frames without one, in seconds.
+const DEFAULT_FRAME_DURATION: f32 = 0.1;
+
+/// The direction a tag's frames are played in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TagDirection {
+    /// From the first frame to the last one.
+    #[default]
+    Forward,
+    /// From the last frame to the first one.
+    Reverse,
+    /// Forwards, then backwards.
+    #[serde(rename = "pingpong")]
+    PingPong,
+    /// Backwards, then forwards.
+    #[serde(rename = "pingpong_reverse")]
+    PingPongReverse,
+}
+
+/// A named range of frames, turned into an animation clip.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct FrameTag {
+    /// The name of the tag.
+    pub name: String,
+    /// The first frame of the tag.
+    pub from: usize,
+    /// The last frame of the tag, inclusive.
+    pub to: usize,
+    /// The direction the frames are played in.
+    #[serde(default)]
+    pub direction: TagDirection,
+    /// How many times the tag is played. Aseprite writes it as a string,
+    /// and leaves it out when the tag loops forever.
+    #[serde(default)]
+    pub repeat: Option<String>,
+}
+
+impl FrameTag {
+    /// Converts the tag into a clip, taking each frame's duration from `durations`.
+    /// Tags repeated exactly once are played once, every other tag loops.
+    pub fn clip(&self, durations: &[f32]) -> AnimationClip {
+        let mut indices: Vec<usize> = (self.from..=self.to).collect();
+        if matches!(
+            self.direction,
+            TagDirection::Reverse | TagDirection::PingPongReverse
+        ) {
+            indices.reverse();
+        }
+
+        let frames = indices
+            .into_iter()
+            .map(|index| AnimationFrame {
+                index,
+                duration: durations
+                    .get(index)
+                    .copied()
+                    .unwrap_or(DEFAULT_FRAME_DURATION),
+            })
+            .collect();
+
+        let mode = match (self.direction, self.repeat.as_deref()) {
+            (_, Some("1")) => PlaybackMode::Once,
+            (TagDirection::PingPong | TagDirection::PingPongReverse, _) => PlaybackMode::PingPong,
+            _ => PlaybackMode::Loop,
+        };
+
+        AnimationClip::new(frames, mode)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AsepriteMeta {
+    image: String,
+    #[serde(default)]
+    frame_tags: Vec<FrameTag>,
+}
+
+/// The contents of an Aseprite data file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsepriteSheet {
+    /// The path of the exported image, relative to the data file.
+    pub image: String,
+    /// The regions of every frame, in order.
+    pub regions: Vec<AtlasRegion>,
+    /// How long each frame lasts, in seconds.
+    pub durations: Vec<f32>,
+    /// The tags of the sprite.
+    pub tags: Vec<FrameTag>,
+}
+
+impl AsepriteSheet {
+    /// Parses the JSON written by Aseprite.
+    /// Fails if the JSON is malformed or a tag refers to missing frames.
+    pub fn parse(json: &str) -> serde_json::Result<Self> {
+        let sheet: JsonSheet<AsepriteMeta> = serde_json::from_str(json)?;
+
+        let frame_count = sheet.frames.len();
+        if let Some(tag) = sheet
+            .meta
+            .frame_tags
+            .iter()
+            .find(|tag| tag.from > tag.to || tag.to >= frame_count)
+        {
+            return Err(serde_json::Error::custom(format!(
+                "tag {:?} covers frames {} to {}, but there are only {} frames",
+                tag.name, tag.from, tag.to, frame_count
+            )));
+        }
+
+        let regions = sheet.regions();
+        let durations = sheet
+            .frames
+            .iter()
+            .map(|(_, frame)| {
+                frame
+                    .duration
+                    .map_or(DEFAULT_FRAME_DURATION, |duration| duration / 1000.0)
+            })
+            .collect();
+
+        Ok(Self {
+            image: sheet.meta.image,
+            regions,
+            durations,
+            tags: sheet.meta.frame_tags,
+        })
+    }
+
+    /// Turns every tag into an animation clip, keyed by the tag name.
+    pub fn clips(&self) -> HashMap<String, AnimationClip> {
+        self.tags
+            .iter()
+            .map(|tag| (tag.name.clone(), tag.clip(&self.durations)))
+            .collect()
+    }
+}
+
+/// Loads an Aseprite data file and the image it refers to, returning the
+/// atlas and an animation clip for each tag.
+///
+/// # Example
+/// ```rust
+/// use std::path::Path;
+/// use glam::Vec2;
+///
+/// let (atlas, clips) = aseprite::load(ctx, &mut assets, Path::new("hero.json"))?;
+/// let sprite = Sprite::new(Vec2::new(64.0, 32.0), atlas.texture.clone());
+///
+/// let mut hero = AnimatedSprite::new(sprite, atlas.rects());
+/// clips.into_iter().for_each(|(name, clip)| hero.player.add_clip(&name, clip));
+/// hero.player.play("run");
+/// ```
+pub fn load(
+    ctx: &mut Context,
+    assets: &mut Assets,
+    path: &Path,
+) -> Result<(TextureAtlas, HashMap<String, AnimationClip>), EngineError> {
+    info!("Importing Aseprite sheet {:?}", path);
+    let json = read_file(path)?;
+    let sheet = AsepriteSheet::parse(&json).map_err(|error| EngineError::Parse {
+        path: path.to_path_buf(),
+        message: error.to_string(),
+    })?;
+
+    let texture = load_atlas_texture(ctx, assets, path, &sheet.image)?;
+    let clips = sheet.clips();
+
+    Ok((TextureAtlas::new(texture, sheet.regions), clips))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::rect::Rect;
+    use glam::Vec2;
+
+    const HASH: &str = include_str!("fixtures/aseprite_hash.json");
+    const ARRAY: &str = include_str!("fixtures/aseprite_array.json");
+
+    /// Gets the sheet index of every frame of a clip.
+    fn indices(clip: &AnimationClip) -> Vec<usize> {
+        clip.frames.iter().map(|frame| frame.index).collect()
+    }
+
+    #[test]
+    fn parses_the_hash_format_in_order() {
+        let sheet = AsepriteSheet::parse(HASH).unwrap();
+        let names: Vec<&str> = sheet
+            .regions
+            .iter()
+            .map(|region| region.name.as_str())
+            .collect();
+
+        assert_eq!(sheet.image, "hero.png");
+        assert_eq!(
+            names,
+            [
+                "hero 0.aseprite",
+                "hero 1.aseprite",
+                "hero 2.aseprite",
+                "hero 3.aseprite",
+                "hero 4.aseprite",
+            ]
+        );
+        assert_eq!(sheet.regions[4].rect, Rect::new(0.0, 16.0, 16.0, 16.0));
+        assert_eq!(sheet.regions[4].offset, Vec2::ZERO);
+        assert_eq!(sheet.regions[4].source_size, Vec2::new(16.0, 16.0));
+        assert_eq!(sheet.durations, [0.1, 0.15, 0.08, 0.08, 0.2]);
+    }
+
+    #[test]
+    fn turns_tags_into_clips() {
+        let clips = AsepriteSheet::parse(HASH).unwrap().clips();
+
+        assert_eq!(clips.len(), 3);
+        assert_eq!(indices(&clips["idle"]), [0, 1]);
+        assert_eq!(clips["idle"].mode, PlaybackMode::Loop);
+        assert_eq!(clips["idle"].frames[1].duration, 0.15);
+        // repeated once, so played once even though it ping-pongs
+        assert_eq!(indices(&clips["attack"]), [2, 3, 4]);
+        assert_eq!(clips["attack"].mode, PlaybackMode::Once);
+        assert_eq!(indices(&clips["dash"]), [3, 2, 1]);
+        assert_eq!(clips["dash"].mode, PlaybackMode::Loop);
+    }
+
+    #[test]
+    fn parses_trimmed_frames_in_the_array_format() {
+        let sheet = AsepriteSheet::parse(ARRAY).unwrap();
+        let slime = &sheet.regions[0];
+
+        assert_eq!(sheet.image, "slime.png");
+        assert_eq!(sheet.regions.len(), 3);
+        assert_eq!(slime.name, "slime 0.aseprite");
+        assert_eq!(slime.rect, Rect::new(1.0, 1.0, 12.0, 9.0));
+        assert_eq!(slime.offset, Vec2::new(2.0, 7.0));
+        assert_eq!(slime.source_size, Vec2::new(16.0, 16.0));
+        assert!(!slime.rotated);
+        assert_eq!(sheet.durations, [0.12, 0.12, 0.24]);
+    }
+
+    #[test]
+    fn plays_reversed_ping_pong_tags_backwards() {
+        let clips = AsepriteSheet::parse(ARRAY).unwrap().clips();
+
+        assert_eq!(indices(&clips["bounce"]), [2, 1, 0]);
+        assert_eq!(clips["bounce"].mode, PlaybackMode::PingPong);
+    }
+
+    #[test]
+    fn rejects_tags_past_the_last_frame() {
+        let json = HASH.replace(r#""from": 2, "to": 4"#, r#""from": 2, "to": 5"#);
+        let error = AsepriteSheet::parse(&json).unwrap_err();
+
+        assert!(error.to_string().contains("attack"));
+    }
+
+    #[test]
+    fn rejects_array_frames_without_a_name() {
+        let json = ARRAY.replace(r#""filename": "slime 1.aseprite","#, "");
+
+        assert!(AsepriteSheet::parse(&json).is_err());
+    }
+}
@@ -0,0 +1,238 @@
+use super::assets::{Assets, Handle};
+use super::error::EngineError;
+use super::rect::Rect;
+use super::sprite::Sprite;
+use super::texture::Texture;
+use glam::Vec2;
+use miniquad::Context;
+use serde::de::{self, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+pub mod aseprite;
+pub mod texture_packer;
+
+/// A named region of a texture atlas.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtlasRegion {
+    /// The name of the region, usually the file name of the original image.
+    pub name: String,
+    /// The area of the atlas texture covered by the region, in pixels.
+    pub rect: Rect,
+    /// Whether the region was packed rotated 90 degrees clockwise, making
+    /// `rect` as wide as the original image is tall. Sprites don't undo the
+    /// rotation when rendering it.
+    pub rotated: bool,
+    /// Where the region goes in the original image, when it was trimmed of
+    /// its transparent borders. Zero for untrimmed regions.
+    pub offset: Vec2,
+    /// The size of the original image, before trimming and rotating.
+    pub source_size: Vec2,
+}
+
+/// A texture holding many images, each one in its own named region.
+pub struct TextureAtlas {
+    /// The texture all the regions belong to.
+    pub texture: Handle<Texture>,
+    /// The regions, in the order they were exported.
+    regions: Vec<AtlasRegion>,
+    /// Maps a region name to its position in `regions`.
+    names: HashMap<String, usize>,
+}
+
+impl TextureAtlas {
+    /// Creates an atlas out of a texture and its regions.
+    pub fn new(texture: Handle<Texture>, regions: Vec<AtlasRegion>) -> Self {
+        let names = regions
+            .iter()
+            .enumerate()
+            .map(|(index, region)| (region.name.clone(), index))
+            .collect();
+
+        Self {
+            texture,
+            regions,
+            names,
+        }
+    }
+
+    /// Gets the region with the given name.
+    pub fn region(&self, name: &str) -> Option<&AtlasRegion> {
+        self.names.get(name).map(|&index| &self.regions[index])
+    }
+
+    /// Gets every region, in the order they were exported. Animation frame
+    /// indices refer to positions in this list.
+    pub fn regions(&self) -> &[AtlasRegion] {
+        &self.regions
+    }
+
+    /// Gets the rectangle of every region, in the order they were exported.
+    /// Meant to be used as the frames of an `AnimatedSprite`.
+    pub fn rects(&self) -> Vec<Rect> {
+        self.regions.iter().map(|region| region.rect).collect()
+    }
+
+    /// Creates a sprite rendering the region with the given name.
+    pub fn sprite(&self, position: Vec2, name: &str) -> Option<Sprite> {
+        let region = self.region(name)?;
+
+        Some(Sprite::from_region(
+            position,
+            self.texture.clone(),
+            region.rect,
+        ))
+    }
+}
+
+/// Reads a whole data file into a string.
+fn read_file(path: &Path) -> Result<String, EngineError> {
+    fs::read_to_string(path).map_err(|source| EngineError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Loads the image an atlas data file refers to. The image path is
+/// relative to the directory of the data file.
+fn load_atlas_texture(
+    ctx: &mut Context,
+    assets: &mut Assets,
+    data_path: &Path,
+    image: &str,
+) -> Result<Handle<Texture>, EngineError> {
+    let image_path = data_path
+        .parent()
+        .map_or_else(|| Path::new(image).to_path_buf(), |dir| dir.join(image));
+
+    assets.load_texture(ctx, &image_path)
+}
+
+/// A rectangle as written by Aseprite and TexturePacker.
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct JsonRect {
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+}
+
+/// A size as written by Aseprite and TexturePacker.
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct JsonSize {
+    w: f32,
+    h: f32,
+}
+
+/// A frame as written by Aseprite and TexturePacker.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonFrame {
+    /// The name of the frame, only present in the array format.
+    #[serde(default)]
+    filename: Option<String>,
+    /// The area of the frame in the sheet, with the width and height it has
+    /// before being rotated.
+    frame: JsonRect,
+    #[serde(default)]
+    rotated: bool,
+    /// Where the trimmed frame goes in the original image.
+    #[serde(default)]
+    sprite_source_size: Option<JsonRect>,
+    /// The size of the original image.
+    #[serde(default)]
+    source_size: Option<JsonSize>,
+    /// How long the frame lasts in milliseconds, only written by Aseprite.
+    #[serde(default)]
+    duration: Option<f32>,
+}
+
+/// The contents of a data file. Both tools share the same layout and only
+/// differ in their `meta`.
+#[derive(Debug, Deserialize)]
+struct JsonSheet<M> {
+    #[serde(deserialize_with = "ordered_frames")]
+    frames: Vec<(String, JsonFrame)>,
+    meta: M,
+}
+
+impl<M> JsonSheet<M> {
+    /// Converts the frames into atlas regions, keeping their order.
+    fn regions(&self) -> Vec<AtlasRegion> {
+        self.frames
+            .iter()
+            .map(|(name, frame)| {
+                let JsonRect { x, y, w, h } = frame.frame;
+                // rotated frames cover the sheet with their sides swapped
+                let rect = if frame.rotated {
+                    Rect::new(x, y, h, w)
+                } else {
+                    Rect::new(x, y, w, h)
+                };
+
+                AtlasRegion {
+                    name: name.clone(),
+                    rect,
+                    rotated: frame.rotated,
+                    offset: frame
+                        .sprite_source_size
+                        .map_or(Vec2::ZERO, |trimmed| Vec2::new(trimmed.x, trimmed.y)),
+                    source_size: frame
+                        .source_size
+                        .map_or(Vec2::new(w, h), |size| Vec2::new(size.w, size.h)),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Reads `frames` either as an object keyed by name (the hash format) or as
+/// an array of frames with a `filename` (the array format), keeping the
+/// order in which they are written in both cases.
+fn ordered_frames<'de, D>(deserializer: D) -> Result<Vec<(String, JsonFrame)>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct FramesVisitor;
+
+    impl<'de> Visitor<'de> for FramesVisitor {
+        type Value = Vec<(String, JsonFrame)>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a map or an array of frames")
+        }
+
+        fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+        where
+            A: MapAccess<'de>,
+        {
+            let mut frames = Vec::with_capacity(map.size_hint().unwrap_or(0));
+            while let Some(entry) = map.next_entry()? {
+                frames.push(entry);
+            }
+
+            Ok(frames)
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let mut frames = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+            while let Some(frame) = seq.next_element::<JsonFrame>()? {
+                let name = frame
+                    .filename
+                    .clone()
+                    .ok_or_else(|| de::Error::missing_field("filename"))?;
+                frames.push((name, frame));
+            }
+
+            Ok(frames)
+        }
+    }
+
+    deserializer.deserialize_any(FramesVisitor)
+}
@@ -0,0 +1,115 @@
+//! Imports atlases exported by TexturePacker with the JSON (hash) or
+//! JSON (array) data formats.
+
+use super::{load_atlas_texture, read_file, AtlasRegion, JsonSheet, TextureAtlas};
+use crate::core::assets::Assets;
+use crate::core::error::EngineError;
+use miniquad::Context;
+use serde::Deserialize;
+use std::path::Path;
+
+#[derive(Debug, Deserialize)]
+struct TexturePackerMeta {
+    image: String,
+}
+
+/// The contents of a TexturePacker data file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TexturePackerSheet {
+    /// The path of the packed image, relative to the data file.
+    pub image: String,
+    /// The packed regions, in order.
+    pub regions: Vec<AtlasRegion>,
+}
+
+impl TexturePackerSheet {
+    /// Parses the JSON written by TexturePacker.
+    pub fn parse(json: &str) -> serde_json::Result<Self> {
+        let sheet: JsonSheet<TexturePackerMeta> = serde_json::from_str(json)?;
+        let regions = sheet.regions();
+
+        Ok(Self {
+            image: sheet.meta.image,
+            regions,
+        })
+    }
+}
+
+/// Loads a TexturePacker data file and the image it refers to.
+///
+/// # Example
+/// ```rust
+/// use std::path::Path;
+/// use glam::Vec2;
+///
+/// let atlas = texture_packer::load(ctx, &mut assets, Path::new("props.json"))?;
+/// let chest = atlas.sprite(Vec2::new(64.0, 32.0), "chest.png");
+/// ```
+pub fn load(
+    ctx: &mut Context,
+    assets: &mut Assets,
+    path: &Path,
+) -> Result<TextureAtlas, EngineError> {
+    info!("Importing TexturePacker atlas {:?}", path);
+    let json = read_file(path)?;
+    let sheet = TexturePackerSheet::parse(&json).map_err(|error| EngineError::Parse {
+        path: path.to_path_buf(),
+        message: error.to_string(),
+    })?;
+
+    let texture = load_atlas_texture(ctx, assets, path, &sheet.image)?;
+
+    Ok(TextureAtlas::new(texture, sheet.regions))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::rect::Rect;
+    use glam::Vec2;
+
+    const HASH: &str = include_str!("fixtures/texture_packer_hash.json");
+    const ARRAY: &str = include_str!("fixtures/texture_packer_array.json");
+
+    #[test]
+    fn parses_trimmed_regions() {
+        let sheet = TexturePackerSheet::parse(HASH).unwrap();
+        let chest = &sheet.regions[0];
+
+        assert_eq!(sheet.image, "props.png");
+        assert_eq!(chest.name, "chest.png");
+        assert_eq!(chest.rect, Rect::new(2.0, 2.0, 30.0, 22.0));
+        assert_eq!(chest.offset, Vec2::new(1.0, 10.0));
+        assert_eq!(chest.source_size, Vec2::new(32.0, 32.0));
+        assert!(!chest.rotated);
+    }
+
+    #[test]
+    fn swaps_the_sides_of_rotated_regions() {
+        let sheet = TexturePackerSheet::parse(HASH).unwrap();
+        let (sword, torch) = (&sheet.regions[1], &sheet.regions[2]);
+
+        assert!(sword.rotated);
+        assert_eq!(sword.rect, Rect::new(34.0, 2.0, 8.0, 28.0));
+        assert_eq!(sword.offset, Vec2::ZERO);
+        assert_eq!(sword.source_size, Vec2::new(28.0, 8.0));
+
+        assert!(torch.rotated);
+        assert_eq!(torch.rect, Rect::new(44.0, 2.0, 20.0, 6.0));
+        assert_eq!(torch.offset, Vec2::new(5.0, 12.0));
+        assert_eq!(torch.source_size, Vec2::new(16.0, 32.0));
+    }
+
+    #[test]
+    fn reads_both_formats_the_same() {
+        assert_eq!(
+            TexturePackerSheet::parse(ARRAY).unwrap(),
+            TexturePackerSheet::parse(HASH).unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_json() {
+        assert!(TexturePackerSheet::parse(&HASH[..HASH.len() / 2]).is_err());
+    }
+}
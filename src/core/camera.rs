@@ -0,0 +1,395 @@
+use super::rect::Rect;
+use super::viewport::Viewport;
+use glam::{Mat4, Vec2, Vec3};
+use miniquad::Context;
+
+/// The smallest zoom a camera can have. Avoids dividing by zero when
+/// computing the visible area.
+const MIN_ZOOM: f32 = 0.01;
+
+/// Settings for a camera following a target.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraFollow {
+    /// The size of the area around the center of the camera in which the
+    /// target can move without the camera moving, in world units.
+    pub dead_zone: Vec2,
+    /// How fast the camera catches up with the target. Higher is faster,
+    /// and `0.0` snaps to it right away.
+    pub smoothing: f32,
+}
+
+impl Default for CameraFollow {
+    fn default() -> Self {
+        Self {
+            dead_zone: Vec2::ZERO,
+            smoothing: 0.0,
+        }
+    }
+}
+
+/// A 2D camera. Determines which part of the world is shown on the screen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera2D {
+    /// The point of the world at the center of the screen.
+    pub position: Vec2,
+    /// How much the world is magnified. `2.0` shows everything twice as big.
+    pub zoom: f32,
+    /// The rotation of the camera, in radians.
+    pub rotation: f32,
+    /// The size of the area shown at zoom `1.0`, in world units.
+    pub view_size: Vec2,
+    /// The area of the world the camera can't look past, if any.
+    pub bounds: Option<Rect>,
+    /// How the camera behaves when following a target.
+    pub follow: CameraFollow,
+}
+
+impl Camera2D {
+    /// Creates a camera showing `view_size` world units, with the world
+    /// origin at the top-left corner of the screen.
+    pub fn new(view_size: Vec2) -> Self {
+        Self {
+            position: view_size / 2.0,
+            zoom: 1.0,
+            rotation: 0.0,
+            view_size,
+            bounds: None,
+            follow: CameraFollow::default(),
+        }
+    }
+
+    /// Creates a camera showing the whole screen, one world unit per pixel.
+    pub fn from_screen(ctx: &Context) -> Self {
+        let (width, height) = ctx.screen_size();
+
+        Self::new(Vec2::new(width, height))
+    }
+
+    /// Gets the size of the area of the world currently shown, taking the zoom into account.
+    pub fn visible_size(&self) -> Vec2 {
+        self.view_size / self.zoom.max(MIN_ZOOM)
+    }
+
+    /// Gets the area of the world currently shown, ignoring the rotation.
+    pub fn visible_rect(&self) -> Rect {
+        let size = self.visible_size();
+
+        Rect::from_position_size(self.position - size / 2.0, size)
+    }
+
+    /// Gets the matrix moving the world so that the camera sits at the origin.
+    pub fn view_matrix(&self) -> Mat4 {
+        Mat4::from_scale(Vec3::new(self.zoom, self.zoom, 1.0))
+            * Mat4::from_rotation_z(-self.rotation)
+            * Mat4::from_translation(-self.position.extend(0.0))
+    }
+
+    /// Gets the matrix projecting the area seen by the camera into clip space.
+    /// The y axis points down, as in screen coordinates.
+    pub fn projection_matrix(&self) -> Mat4 {
+        let half_size = self.view_size / 2.0;
+
+        Mat4::orthographic_rh_gl(
+            -half_size.x,
+            half_size.x,
+            half_size.y,
+            -half_size.y,
+            -1.0,
+            1.0,
+        )
+    }
+
+    /// Gets the projection and view matrices combined.
+    pub fn view_projection(&self) -> Mat4 {
+        self.projection_matrix() * self.view_matrix()
+    }
+
+    /// Converts a point on a screen of `screen_size` pixels into world coordinates.
+    ///
+    /// # Example
+    /// ```rust
+    /// let (width, height) = ctx.screen_size();
+    /// let clicked = camera.screen_to_world(Vec2::new(x, y), Vec2::new(width, height));
+    /// ```
+    pub fn screen_to_world(&self, point: Vec2, screen_size: Vec2) -> Vec2 {
+        let ndc = Vec2::new(
+            point.x / screen_size.x * 2.0 - 1.0,
+            1.0 - point.y / screen_size.y * 2.0,
+        );
+
+        self.view_projection()
+            .inverse()
+            .project_point3(ndc.extend(0.0))
+            .truncate()
+    }
+
+    /// Converts a point in window pixels into world coordinates, going
+    /// through the area of the window covered by `viewport`.
+    pub fn window_to_world(&self, point: Vec2, window_size: Vec2, viewport: &Viewport) -> Vec2 {
+        let point = viewport.window_to_virtual(point, window_size);
+
+        self.screen_to_world(point, viewport.virtual_size)
+    }
+
+    /// Converts a point in world coordinates into a point on a screen of `screen_size` pixels.
+    pub fn world_to_screen(&self, point: Vec2, screen_size: Vec2) -> Vec2 {
+        let ndc = self.view_projection().project_point3(point.extend(0.0));
+
+        Vec2::new(
+            (ndc.x + 1.0) / 2.0 * screen_size.x,
+            (1.0 - ndc.y) / 2.0 * screen_size.y,
+        )
+    }
+
+    /// Moves the camera so that it doesn't show anything outside of its bounds.
+    /// If the bounds are smaller than the visible area, the camera is centered on them.
+    pub fn clamp_to_bounds(&mut self) {
+        let bounds = match self.bounds {
+            Some(bounds) => bounds,
+            None => return,
+        };
+
+        let half_size = self.visible_size() / 2.0;
+        let (min, max) = (bounds.position() + half_size, bounds.max() - half_size);
+        let center = bounds.position() + bounds.size() / 2.0;
+
+        self.position.x = if min.x <= max.x {
+            self.position.x.clamp(min.x, max.x)
+        } else {
+            center.x
+        };
+        self.position.y = if min.y <= max.y {
+            self.position.y.clamp(min.y, max.y)
+        } else {
+            center.y
+        };
+    }
+
+    /// Moves the camera towards `target`, `dt` seconds after the last call.
+    /// The camera stays still while the target is inside the dead zone, and
+    /// is kept inside its bounds.
+    pub fn follow(&mut self, target: Vec2, dt: f32) {
+        let half_dead_zone = self.follow.dead_zone / 2.0;
+        let offset = target - self.position;
+
+        // only move by how much the target left the dead zone
+        let excess = offset - offset.clamp(-half_dead_zone, half_dead_zone);
+        let desired = self.position + excess;
+
+        self.position = if self.follow.smoothing > 0.0 {
+            // frame rate independent exponential smoothing
+            let t = 1.0 - (-self.follow.smoothing * dt).exp();
+            self.position.lerp(desired, t)
+        } else {
+            desired
+        };
+
+        self.clamp_to_bounds();
+    }
+}
+
+impl Default for Camera2D {
+    /// A camera showing the whole virtual resolution.
+    fn default() -> Self {
+        Self::new(Viewport::default().virtual_size)
+    }
+}
+
+/// Something that can be drawn through a camera.
+pub trait Drawable {
+    /// Draws using the view and projection of `camera`.
+    fn draw_with_camera(&mut self, ctx: &mut Context, camera: &Camera2D);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    const SCREEN: Vec2 = Vec2::new(200.0, 100.0);
+
+    /// Creates a camera showing the size of the screen, centered on
+    /// `position`.
+    fn camera(position: Vec2, zoom: f32, rotation: f32) -> Camera2D {
+        Camera2D {
+            position,
+            zoom,
+            rotation,
+            ..Camera2D::new(SCREEN)
+        }
+    }
+
+    fn assert_near(a: Vec2, b: Vec2) {
+        assert!(a.abs_diff_eq(b, 1e-3), "{:?} should be {:?}", a, b);
+    }
+
+    #[test]
+    fn shows_its_position_at_the_center_of_the_screen() {
+        let camera = camera(Vec2::new(40.0, -30.0), 3.0, 1.0);
+
+        assert_near(
+            camera.screen_to_world(SCREEN / 2.0, SCREEN),
+            camera.position,
+        );
+        assert_near(
+            camera.world_to_screen(camera.position, SCREEN),
+            SCREEN / 2.0,
+        );
+    }
+
+    #[test]
+    fn shows_the_world_magnified_by_the_zoom() {
+        let camera = camera(Vec2::ZERO, 2.0, 0.0);
+
+        assert_near(
+            camera.screen_to_world(Vec2::new(200.0, 50.0), SCREEN),
+            Vec2::new(50.0, 0.0),
+        );
+        assert_near(
+            camera.screen_to_world(Vec2::ZERO, SCREEN),
+            Vec2::new(-50.0, -25.0),
+        );
+        assert_eq!(camera.visible_rect(), Rect::new(-50.0, -25.0, 100.0, 50.0));
+    }
+
+    #[test]
+    fn turns_the_world_by_the_rotation() {
+        let camera = camera(Vec2::new(10.0, 10.0), 2.0, FRAC_PI_2);
+
+        // the right of the screen is below the camera
+        assert_near(
+            camera.screen_to_world(Vec2::new(200.0, 50.0), SCREEN),
+            Vec2::new(10.0, 60.0),
+        );
+        assert_near(
+            camera.world_to_screen(Vec2::new(10.0, 60.0), SCREEN),
+            Vec2::new(200.0, 50.0),
+        );
+    }
+
+    #[test]
+    fn converts_points_back_and_forth() {
+        let points = [Vec2::ZERO, Vec2::new(13.0, 87.0), Vec2::new(200.0, 100.0)];
+
+        for (zoom, rotation) in [(1.0, 0.0), (0.5, 0.3), (4.0, -2.0)] {
+            let camera = camera(Vec2::new(-20.0, 35.0), zoom, rotation);
+
+            for point in points {
+                let world = camera.screen_to_world(point, SCREEN);
+                assert_near(camera.world_to_screen(world, SCREEN), point);
+                let screen = camera.world_to_screen(point, SCREEN);
+                assert_near(camera.screen_to_world(screen, SCREEN), point);
+            }
+        }
+    }
+
+    #[test]
+    fn converts_window_points_through_the_viewport() {
+        let viewport = Viewport::default();
+        let camera = Camera2D {
+            zoom: 2.0,
+            rotation: 0.5,
+            ..Camera2D::default()
+        };
+        // twice the virtual resolution, with black bars of 38 and 12 pixels
+        let window_size = viewport.virtual_size * 2.0 + Vec2::new(76.0, 24.0);
+        let point = Vec2::new(100.0, 40.0);
+
+        let window_point = viewport.virtual_to_window(point, window_size);
+        let world = camera.window_to_world(window_point, window_size, &viewport);
+
+        assert_near(window_point, Vec2::new(238.0, 92.0));
+        assert_near(world, camera.screen_to_world(point, viewport.virtual_size));
+        assert_near(camera.world_to_screen(world, viewport.virtual_size), point);
+    }
+
+    #[test]
+    fn stays_still_while_the_target_is_in_the_dead_zone() {
+        let mut camera = camera(Vec2::ZERO, 1.0, 0.0);
+        camera.follow.dead_zone = Vec2::new(40.0, 20.0);
+
+        camera.follow(Vec2::new(19.0, -9.0), 0.1);
+        assert_eq!(camera.position, Vec2::ZERO);
+
+        // only moves by how far the target left it
+        camera.follow(Vec2::new(30.0, -9.0), 0.1);
+        assert_near(camera.position, Vec2::new(10.0, 0.0));
+    }
+
+    #[test]
+    fn snaps_to_the_target_without_smoothing() {
+        let mut camera = camera(Vec2::ZERO, 1.0, 0.0);
+
+        camera.follow(Vec2::new(30.0, 40.0), 0.1);
+
+        assert_eq!(camera.position, Vec2::new(30.0, 40.0));
+    }
+
+    #[test]
+    fn catches_up_with_the_target_at_any_frame_rate() {
+        let target = Vec2::new(100.0, 0.0);
+        let mut slow = camera(Vec2::ZERO, 1.0, 0.0);
+        slow.follow.smoothing = 5.0;
+        let mut fast = slow;
+
+        slow.follow(target, 0.1);
+        for _ in 0..4 {
+            fast.follow(target, 0.025);
+        }
+
+        assert!(slow.position.x > 0.0 && slow.position.x < target.x);
+        assert_near(
+            slow.position,
+            Vec2::new(100.0 * (1.0 - (-0.5_f32).exp()), 0.0),
+        );
+        assert_near(fast.position, slow.position);
+        for _ in 0..100 {
+            slow.follow(target, 0.1);
+        }
+        assert_near(slow.position, target);
+    }
+
+    #[test]
+    fn doesnt_look_past_its_bounds() {
+        let mut camera = camera(Vec2::new(-50.0, 900.0), 1.0, 0.0);
+        camera.bounds = Some(Rect::new(0.0, 0.0, 1000.0, 500.0));
+
+        camera.clamp_to_bounds();
+        assert_eq!(camera.position, Vec2::new(100.0, 450.0));
+
+        // zooming in shows less, so the camera can go further
+        camera.position = Vec2::new(-50.0, 900.0);
+        camera.zoom = 2.0;
+        camera.clamp_to_bounds();
+        assert_eq!(camera.position, Vec2::new(50.0, 475.0));
+
+        // inside the bounds, it doesn't move
+        camera.position = Vec2::new(500.0, 250.0);
+        camera.clamp_to_bounds();
+        assert_eq!(camera.position, Vec2::new(500.0, 250.0));
+    }
+
+    #[test]
+    fn centers_on_bounds_smaller_than_the_view() {
+        let mut camera = camera(Vec2::new(500.0, 500.0), 1.0, 0.0);
+        // narrower than the view, but taller
+        camera.bounds = Some(Rect::new(10.0, 0.0, 100.0, 1000.0));
+
+        camera.clamp_to_bounds();
+        assert_eq!(camera.position, Vec2::new(60.0, 500.0));
+
+        camera.bounds = Some(Rect::new(10.0, 20.0, 100.0, 60.0));
+        camera.clamp_to_bounds();
+        assert_eq!(camera.position, Vec2::new(60.0, 50.0));
+    }
+
+    #[test]
+    fn keeps_inside_its_bounds_while_following() {
+        let mut camera = camera(Vec2::new(100.0, 50.0), 1.0, 0.0);
+        camera.bounds = Some(Rect::new(0.0, 0.0, 300.0, 100.0));
+
+        camera.follow(Vec2::new(1000.0, -40.0), 0.1);
+
+        assert_eq!(camera.position, Vec2::new(200.0, 50.0));
+    }
+}
@@ -0,0 +1,801 @@
+//! Finds what may collide without testing every pair of colliders.
+
+use super::shapes::Aabb;
+use glam::Vec2;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+/// How much the boxes stored in a `DynamicTree` are grown on every side,
+/// so things moving a little don't have to be moved in the tree.
+const TREE_MARGIN: f32 = 4.0;
+
+/// The most cells a thing can be stored in by a `SpatialHash`. Bigger
+/// things would take too long to add, move and remove.
+const MAX_CELLS: u64 = 256;
+
+/// Keeps track of where things are, by their bounding box, to quickly find
+/// the ones that may collide. Things are identified by a key, usually an
+/// `Entity`.
+///
+/// # Example
+/// ```rust
+/// let mut broadphase = SpatialHash::new(32.0);
+/// for (entity, collider, transform) in colliders {
+///     broadphase.insert(entity, collider.world_shape(transform).bounds());
+/// }
+///
+/// for (a, b) in broadphase.pairs() {
+///     // only test these with `Collider::contact`
+/// }
+/// ```
+pub trait Broadphase<K> {
+    /// Adds `key`, covering `bounds`. Keys added already are moved there.
+    fn insert(&mut self, key: K, bounds: Aabb);
+
+    /// Removes `key`, if it was added.
+    fn remove(&mut self, key: K);
+
+    /// Gets the bounds `key` was added with.
+    fn bounds(&self, key: K) -> Option<Aabb>;
+
+    /// Gets every key whose bounds overlap `region`.
+    fn query_region(&self, region: &Aabb) -> Vec<K>;
+
+    /// Gets every key whose bounds contain `point`.
+    fn query_point(&self, point: Vec2) -> Vec<K>;
+
+    /// Gets every pair of keys whose bounds overlap, once, with the
+    /// smallest key first and in order.
+    fn pairs(&self) -> Vec<(K, K)>;
+}
+
+/// Orders the keys of a pair, so each pair is found once.
+fn ordered<K: Ord>(a: K, b: K) -> (K, K) {
+    if a < b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+/// Tests every pair of keys. Fastest for a handful of things, and a
+/// reference for the others.
+#[derive(Debug, Clone, Default)]
+pub struct BruteForce<K> {
+    entries: HashMap<K, Aabb>,
+}
+
+impl<K> BruteForce<K> {
+    /// Creates an empty broadphase.
+    pub fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+}
+
+impl<K: Copy + Eq + Hash + Ord> Broadphase<K> for BruteForce<K> {
+    fn insert(&mut self, key: K, bounds: Aabb) {
+        self.entries.insert(key, bounds);
+    }
+
+    fn remove(&mut self, key: K) {
+        self.entries.remove(&key);
+    }
+
+    fn bounds(&self, key: K) -> Option<Aabb> {
+        self.entries.get(&key).copied()
+    }
+
+    fn query_region(&self, region: &Aabb) -> Vec<K> {
+        self.entries
+            .iter()
+            .filter(|(_, bounds)| bounds.overlaps(region))
+            .map(|(key, _)| *key)
+            .collect()
+    }
+
+    fn query_point(&self, point: Vec2) -> Vec<K> {
+        self.entries
+            .iter()
+            .filter(|(_, bounds)| bounds.contains_point(point))
+            .map(|(key, _)| *key)
+            .collect()
+    }
+
+    fn pairs(&self) -> Vec<(K, K)> {
+        let entries: Vec<(&K, &Aabb)> = self.entries.iter().collect();
+        let mut pairs = Vec::new();
+
+        for (i, (a, a_bounds)) in entries.iter().enumerate() {
+            for (b, b_bounds) in &entries[i + 1..] {
+                if a_bounds.overlaps(b_bounds) {
+                    pairs.push(ordered(**a, **b));
+                }
+            }
+        }
+
+        pairs.sort_unstable();
+        pairs
+    }
+}
+
+/// Splits space into square cells, and remembers which things are in each.
+/// Best when things are about the size of a cell, like tiles and the
+/// characters walking on them. Things covering more than `MAX_CELLS` cells
+/// are kept apart, and checked in every query.
+#[derive(Debug, Clone)]
+pub struct SpatialHash<K> {
+    /// The width and height of every cell.
+    cell_size: f32,
+    /// The keys in every cell with something in it.
+    cells: HashMap<(i32, i32), Vec<K>>,
+    /// The keys covering too many cells to be stored in them.
+    oversized: HashSet<K>,
+    /// The bounds of every key.
+    entries: HashMap<K, Aabb>,
+}
+
+impl<K: Copy + Eq + Hash + Ord> SpatialHash<K> {
+    /// Creates an empty spatial hash, with cells of `cell_size` pixels.
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            oversized: HashSet::new(),
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Gets the first and last cells covered by `bounds`.
+    fn cell_range(&self, bounds: &Aabb) -> ((i32, i32), (i32, i32)) {
+        let cell = |point: Vec2| {
+            let cell = (point / self.cell_size).floor();
+            (cell.x as i32, cell.y as i32)
+        };
+
+        (cell(bounds.min), cell(bounds.max))
+    }
+
+    /// Gets how many cells `bounds` covers.
+    fn cell_count(&self, bounds: &Aabb) -> u64 {
+        let ((first_x, first_y), (last_x, last_y)) = self.cell_range(bounds);
+        let columns = (last_x as i64 - first_x as i64 + 1).max(0) as u64;
+        let rows = (last_y as i64 - first_y as i64 + 1).max(0) as u64;
+
+        columns.saturating_mul(rows)
+    }
+
+    /// Gets every cell covered by `bounds`.
+    fn cells_in(&self, bounds: &Aabb) -> impl Iterator<Item = (i32, i32)> {
+        let ((first_x, first_y), (last_x, last_y)) = self.cell_range(bounds);
+        (first_y..=last_y).flat_map(move |y| (first_x..=last_x).map(move |x| (x, y)))
+    }
+
+    /// Stores `key` in every cell covered by `bounds`, or with the oversized
+    /// keys if there are too many.
+    fn link(&mut self, key: K, bounds: &Aabb) {
+        if self.cell_count(bounds) > MAX_CELLS {
+            self.oversized.insert(key);
+            return;
+        }
+
+        for cell in self.cells_in(bounds).collect::<Vec<_>>() {
+            self.cells.entry(cell).or_default().push(key);
+        }
+    }
+
+    /// Takes `key` out of the cells covered by `bounds`, as stored by `link`.
+    fn unlink(&mut self, key: K, bounds: &Aabb) {
+        if self.oversized.remove(&key) {
+            return;
+        }
+
+        for cell in self.cells_in(bounds).collect::<Vec<_>>() {
+            if let Some(keys) = self.cells.get_mut(&cell) {
+                keys.retain(|other| *other != key);
+                if keys.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+    }
+}
+
+impl<K: Copy + Eq + Hash + Ord> Broadphase<K> for SpatialHash<K> {
+    fn insert(&mut self, key: K, bounds: Aabb) {
+        if let Some(previous) = self.entries.insert(key, bounds) {
+            // things moving inside the same cells stay where they are
+            if self.cell_range(&previous) == self.cell_range(&bounds) {
+                return;
+            }
+            self.unlink(key, &previous);
+        }
+
+        self.link(key, &bounds);
+    }
+
+    fn remove(&mut self, key: K) {
+        if let Some(bounds) = self.entries.remove(&key) {
+            self.unlink(key, &bounds);
+        }
+    }
+
+    fn bounds(&self, key: K) -> Option<Aabb> {
+        self.entries.get(&key).copied()
+    }
+
+    fn query_region(&self, region: &Aabb) -> Vec<K> {
+        let mut found = HashSet::new();
+        let mut check = |key: &K| {
+            if self.entries[key].overlaps(region) {
+                found.insert(*key);
+            }
+        };
+
+        self.oversized.iter().for_each(&mut check);
+        if self.cell_count(region) <= self.cells.len() as u64 {
+            for cell in self.cells_in(region) {
+                self.cells
+                    .get(&cell)
+                    .into_iter()
+                    .flatten()
+                    .for_each(&mut check);
+            }
+        } else {
+            // regions covering more cells than there are stored go through
+            // the stored ones instead
+            let ((first_x, first_y), (last_x, last_y)) = self.cell_range(region);
+            self.cells
+                .iter()
+                .filter(|((x, y), _)| {
+                    (first_x..=last_x).contains(x) && (first_y..=last_y).contains(y)
+                })
+                .flat_map(|(_, keys)| keys)
+                .for_each(&mut check);
+        }
+
+        found.into_iter().collect()
+    }
+
+    fn query_point(&self, point: Vec2) -> Vec<K> {
+        let cell = (point / self.cell_size).floor();
+        let keys = self.cells.get(&(cell.x as i32, cell.y as i32));
+
+        keys.into_iter()
+            .flatten()
+            .chain(&self.oversized)
+            .filter(|key| self.entries[*key].contains_point(point))
+            .copied()
+            .collect()
+    }
+
+    fn pairs(&self) -> Vec<(K, K)> {
+        let mut pairs = HashSet::new();
+
+        for keys in self.cells.values() {
+            for (i, a) in keys.iter().enumerate() {
+                for b in &keys[i + 1..] {
+                    if self.entries[a].overlaps(&self.entries[b]) {
+                        pairs.insert(ordered(*a, *b));
+                    }
+                }
+            }
+        }
+
+        for a in &self.oversized {
+            for (b, b_bounds) in &self.entries {
+                if a != b && self.entries[a].overlaps(b_bounds) {
+                    pairs.insert(ordered(*a, *b));
+                }
+            }
+        }
+
+        let mut pairs: Vec<(K, K)> = pairs.into_iter().collect();
+        pairs.sort_unstable();
+        pairs
+    }
+}
+
+/// A node of a `DynamicTree`.
+#[derive(Debug, Clone)]
+struct Node<K> {
+    /// Contains the boxes of every leaf below, grown by a margin for leaves.
+    aabb: Aabb,
+    parent: Option<usize>,
+    /// The two nodes below branches.
+    children: Option<[usize; 2]>,
+    /// The key of leaves, with their actual bounds.
+    leaf: Option<(K, Aabb)>,
+    /// How many levels of nodes are below, 0 for leaves.
+    height: u32,
+}
+
+/// A tree of boxes, each containing two smaller ones, down to the bounds of
+/// every thing. Works for things of any size and spread, and things moving
+/// a little are left in place.
+#[derive(Debug, Clone)]
+pub struct DynamicTree<K> {
+    nodes: Vec<Node<K>>,
+    root: Option<usize>,
+    /// The nodes of removed things, to be used again.
+    free: Vec<usize>,
+    /// The leaf of every key.
+    leaves: HashMap<K, usize>,
+}
+
+impl<K: Copy + Eq + Hash + Ord> DynamicTree<K> {
+    /// Creates an empty tree.
+    pub fn new() -> Self {
+        Self {
+            nodes: Vec::new(),
+            root: None,
+            free: Vec::new(),
+            leaves: HashMap::new(),
+        }
+    }
+
+    /// Gets how many levels of nodes the tree has.
+    pub fn height(&self) -> u32 {
+        self.root.map_or(0, |root| self.nodes[root].height + 1)
+    }
+
+    /// Removes every key `keep` returns false for.
+    pub fn retain(&mut self, mut keep: impl FnMut(K) -> bool) {
+        let removed: Vec<K> = self
+            .leaves
+            .keys()
+            .copied()
+            .filter(|key| !keep(*key))
+            .collect();
+
+        for key in removed {
+            self.remove(key);
+        }
+    }
+
+    /// Stores a node, reusing a free one if there is one.
+    fn allocate(&mut self, node: Node<K>) -> usize {
+        match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = node;
+                index
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    /// Puts the leaf at `leaf` in the tree, next to the node it grows the
+    /// least.
+    fn insert_leaf(&mut self, leaf: usize) {
+        let root = match self.root {
+            Some(root) => root,
+            None => {
+                self.root = Some(leaf);
+                self.nodes[leaf].parent = None;
+                return;
+            }
+        };
+
+        // go down towards the cheapest sibling, by the perimeter of the boxes
+        let aabb = self.nodes[leaf].aabb;
+        let mut sibling = root;
+        while let Some([left, right]) = self.nodes[sibling].children {
+            let combined = perimeter(&self.nodes[sibling].aabb.union(&aabb));
+            let cost = 2.0 * combined;
+            let inherited = 2.0 * (combined - perimeter(&self.nodes[sibling].aabb));
+
+            let descend_cost = |child: usize| {
+                let child = &self.nodes[child];
+                let grown = perimeter(&child.aabb.union(&aabb));
+                if child.children.is_none() {
+                    grown + inherited
+                } else {
+                    grown - perimeter(&child.aabb) + inherited
+                }
+            };
+            let (left_cost, right_cost) = (descend_cost(left), descend_cost(right));
+
+            if cost < left_cost && cost < right_cost {
+                break;
+            }
+            sibling = if left_cost < right_cost { left } else { right };
+        }
+
+        let old_parent = self.nodes[sibling].parent;
+        let parent = self.allocate(Node {
+            aabb: self.nodes[sibling].aabb.union(&aabb),
+            parent: old_parent,
+            children: Some([sibling, leaf]),
+            leaf: None,
+            height: self.nodes[sibling].height + 1,
+        });
+        self.nodes[sibling].parent = Some(parent);
+        self.nodes[leaf].parent = Some(parent);
+
+        match old_parent {
+            Some(old_parent) => self.replace_child(old_parent, sibling, parent),
+            None => self.root = Some(parent),
+        }
+
+        self.refit(self.nodes[leaf].parent);
+    }
+
+    /// Takes the leaf at `leaf` out of the tree, its sibling taking the
+    /// place of their parent.
+    fn remove_leaf(&mut self, leaf: usize) {
+        let parent = match self.nodes[leaf].parent {
+            Some(parent) => parent,
+            None => {
+                self.root = None;
+                return;
+            }
+        };
+
+        let [left, right] = self.nodes[parent].children.unwrap_or([leaf, leaf]);
+        let sibling = if left == leaf { right } else { left };
+        let grandparent = self.nodes[parent].parent;
+
+        self.nodes[sibling].parent = grandparent;
+        match grandparent {
+            Some(grandparent) => {
+                self.replace_child(grandparent, parent, sibling);
+                self.refit(Some(grandparent));
+            }
+            None => self.root = Some(sibling),
+        }
+
+        self.nodes[parent].children = None;
+        self.free.push(parent);
+        self.nodes[leaf].parent = None;
+    }
+
+    /// Makes `new` a child of `parent` instead of `old`.
+    fn replace_child(&mut self, parent: usize, old: usize, new: usize) {
+        if let Some(children) = &mut self.nodes[parent].children {
+            for child in children.iter_mut().filter(|child| **child == old) {
+                *child = new;
+            }
+        }
+    }
+
+    /// Recomputes the boxes and heights from `node` up to the root,
+    /// balancing the tree on the way.
+    fn refit(&mut self, mut node: Option<usize>) {
+        while let Some(index) = node {
+            let index = self.balance(index);
+            if let Some([left, right]) = self.nodes[index].children {
+                self.nodes[index].aabb = self.nodes[left].aabb.union(&self.nodes[right].aabb);
+                self.nodes[index].height =
+                    1 + self.nodes[left].height.max(self.nodes[right].height);
+            }
+            node = self.nodes[index].parent;
+        }
+    }
+
+    /// Rotates the grandchildren of `node` up when one of its children is
+    /// more than a level taller than the other. Gets the node now in its
+    /// place.
+    fn balance(&mut self, node: usize) -> usize {
+        let [left, right] = match self.nodes[node].children {
+            Some(children) if self.nodes[node].height >= 2 => children,
+            _ => return node,
+        };
+
+        let balance = self.nodes[right].height as i64 - self.nodes[left].height as i64;
+        if balance > 1 {
+            self.rotate(node, right, left)
+        } else if balance < -1 {
+            self.rotate(node, left, right)
+        } else {
+            node
+        }
+    }
+
+    /// Moves the taller child `tall` of `node` up in its place, `node`
+    /// keeping `short` and the shorter child of `tall`.
+    fn rotate(&mut self, node: usize, tall: usize, short: usize) -> usize {
+        let [first, second] = match self.nodes[tall].children {
+            Some(children) => children,
+            None => return node,
+        };
+
+        let parent = self.nodes[node].parent;
+        self.nodes[tall].parent = parent;
+        self.nodes[node].parent = Some(tall);
+        match parent {
+            Some(parent) => self.replace_child(parent, node, tall),
+            None => self.root = Some(tall),
+        }
+
+        let (kept, moved) = if self.nodes[first].height > self.nodes[second].height {
+            (first, second)
+        } else {
+            (second, first)
+        };
+        self.nodes[tall].children = Some([node, kept]);
+        self.nodes[node].children = Some([short, moved]);
+        self.nodes[moved].parent = Some(node);
+
+        for index in [node, tall] {
+            if let Some([left, right]) = self.nodes[index].children {
+                self.nodes[index].aabb = self.nodes[left].aabb.union(&self.nodes[right].aabb);
+                self.nodes[index].height =
+                    1 + self.nodes[left].height.max(self.nodes[right].height);
+            }
+        }
+
+        tall
+    }
+
+    /// Visits the keys of every leaf whose grown box overlaps `region`.
+    fn visit(&self, region: &Aabb, mut visitor: impl FnMut(K, &Aabb)) {
+        let mut stack: Vec<usize> = self.root.into_iter().collect();
+
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !node.aabb.overlaps(region) {
+                continue;
+            }
+
+            match (&node.leaf, node.children) {
+                (Some((key, bounds)), _) => visitor(*key, bounds),
+                (None, Some(children)) => stack.extend(children),
+                (None, None) => (),
+            }
+        }
+    }
+}
+
+impl<K: Copy + Eq + Hash + Ord> Default for DynamicTree<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Copy + Eq + Hash + Ord> Broadphase<K> for DynamicTree<K> {
+    fn insert(&mut self, key: K, bounds: Aabb) {
+        if let Some(&leaf) = self.leaves.get(&key) {
+            self.nodes[leaf].leaf = Some((key, bounds));
+
+            // things moving inside their grown box stay where they are
+            let grown = self.nodes[leaf].aabb;
+            if grown.min.cmple(bounds.min).all() && grown.max.cmpge(bounds.max).all() {
+                return;
+            }
+
+            self.remove_leaf(leaf);
+            self.nodes[leaf].aabb = grow(&bounds);
+            self.insert_leaf(leaf);
+            return;
+        }
+
+        let leaf = self.allocate(Node {
+            aabb: grow(&bounds),
+            parent: None,
+            children: None,
+            leaf: Some((key, bounds)),
+            height: 0,
+        });
+        self.leaves.insert(key, leaf);
+        self.insert_leaf(leaf);
+    }
+
+    fn remove(&mut self, key: K) {
+        if let Some(leaf) = self.leaves.remove(&key) {
+            self.remove_leaf(leaf);
+            self.nodes[leaf].leaf = None;
+            self.free.push(leaf);
+        }
+    }
+
+    fn bounds(&self, key: K) -> Option<Aabb> {
+        let leaf = self.leaves.get(&key)?;
+        self.nodes[*leaf].leaf.map(|(_, bounds)| bounds)
+    }
+
+    fn query_region(&self, region: &Aabb) -> Vec<K> {
+        let mut found = Vec::new();
+        self.visit(region, |key, bounds| {
+            if bounds.overlaps(region) {
+                found.push(key);
+            }
+        });
+
+        found
+    }
+
+    fn query_point(&self, point: Vec2) -> Vec<K> {
+        let mut found = Vec::new();
+        self.visit(&Aabb::new(point, point), |key, bounds| {
+            if bounds.contains_point(point) {
+                found.push(key);
+            }
+        });
+
+        found
+    }
+
+    fn pairs(&self) -> Vec<(K, K)> {
+        let mut pairs = Vec::new();
+
+        for (&key, &leaf) in &self.leaves {
+            let bounds = match self.nodes[leaf].leaf {
+                Some((_, bounds)) => bounds,
+                None => continue,
+            };
+            self.visit(&bounds, |other, other_bounds| {
+                if key < other && bounds.overlaps(other_bounds) {
+                    pairs.push((key, other));
+                }
+            });
+        }
+
+        pairs.sort_unstable();
+        pairs
+    }
+}
+
+/// Gets the perimeter of a box, which the tree keeps low.
+fn perimeter(aabb: &Aabb) -> f32 {
+    let size = aabb.size();
+    2.0 * (size.x + size.y)
+}
+
+/// Grows a box by the margin of the tree.
+fn grow(bounds: &Aabb) -> Aabb {
+    Aabb {
+        min: bounds.min - TREE_MARGIN,
+        max: bounds.max + TREE_MARGIN,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// A change made to every broadphase.
+    #[derive(Debug, Clone)]
+    enum Change {
+        /// Adds a key, or moves it if it was added already.
+        Insert(u32, Aabb),
+        Remove(u32),
+    }
+
+    fn point() -> impl Strategy<Value = Vec2> {
+        (-200.0f32..200.0, -200.0f32..200.0).prop_map(|(x, y)| Vec2::new(x, y))
+    }
+
+    /// Boxes mostly around the size of a cell, some of them too big to be
+    /// stored in cells.
+    fn aabb() -> impl Strategy<Value = Aabb> {
+        let size = prop_oneof![
+            8 => (1.0f32..40.0, 1.0f32..40.0),
+            1 => (400.0f32..2000.0, 1.0f32..2000.0),
+        ];
+
+        (point(), size).prop_map(|(min, (w, h))| Aabb::new(min, min + Vec2::new(w, h)))
+    }
+
+    fn change() -> impl Strategy<Value = Change> {
+        // few keys, so most inserts move something added already
+        prop_oneof![
+            4 => (0u32..24, aabb()).prop_map(|(key, bounds)| Change::Insert(key, bounds)),
+            1 => (0u32..24).prop_map(Change::Remove),
+        ]
+    }
+
+    fn apply(broadphase: &mut impl Broadphase<u32>, changes: &[Change]) {
+        for change in changes {
+            match change {
+                Change::Insert(key, bounds) => broadphase.insert(*key, *bounds),
+                Change::Remove(key) => broadphase.remove(*key),
+            }
+        }
+    }
+
+    fn sorted(mut keys: Vec<u32>) -> Vec<u32> {
+        keys.sort_unstable();
+        keys
+    }
+
+    /// Checks that `broadphase` finds exactly what `BruteForce` does.
+    fn assert_matches_brute_force(
+        broadphase: &mut impl Broadphase<u32>,
+        changes: &[Change],
+        regions: &[Aabb],
+        points: &[Vec2],
+    ) -> Result<(), TestCaseError> {
+        let mut reference = BruteForce::new();
+        apply(&mut reference, changes);
+        apply(broadphase, changes);
+
+        prop_assert_eq!(broadphase.pairs(), reference.pairs());
+        for key in 0..24 {
+            prop_assert_eq!(broadphase.bounds(key), reference.bounds(key));
+        }
+        for region in regions {
+            prop_assert_eq!(
+                sorted(broadphase.query_region(region)),
+                sorted(reference.query_region(region))
+            );
+        }
+        for point in points {
+            prop_assert_eq!(
+                sorted(broadphase.query_point(*point)),
+                sorted(reference.query_point(*point))
+            );
+        }
+
+        Ok(())
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(500))]
+
+        #[test]
+        fn spatial_hash_matches_brute_force(
+            changes in prop::collection::vec(change(), 0..120),
+            regions in prop::collection::vec(aabb(), 8),
+            points in prop::collection::vec(point(), 8),
+        ) {
+            let mut spatial_hash = SpatialHash::new(32.0);
+            assert_matches_brute_force(&mut spatial_hash, &changes, &regions, &points)?;
+        }
+
+        #[test]
+        fn dynamic_tree_matches_brute_force(
+            changes in prop::collection::vec(change(), 0..120),
+            regions in prop::collection::vec(aabb(), 8),
+            points in prop::collection::vec(point(), 8),
+        ) {
+            let mut tree = DynamicTree::new();
+            assert_matches_brute_force(&mut tree, &changes, &regions, &points)?;
+        }
+    }
+
+    #[test]
+    fn keeps_things_covering_too_many_cells_apart() {
+        let mut spatial_hash = SpatialHash::new(16.0);
+        let everything = Aabb::new(Vec2::splat(f32::MIN), Vec2::splat(f32::MAX));
+        let small = Aabb::new(Vec2::ZERO, Vec2::splat(8.0));
+
+        spatial_hash.insert(0, everything);
+        spatial_hash.insert(1, small);
+
+        assert!(spatial_hash.cells.values().flatten().all(|key| *key == 1));
+        assert_eq!(spatial_hash.pairs(), vec![(0, 1)]);
+        assert_eq!(sorted(spatial_hash.query_point(Vec2::ONE)), vec![0, 1]);
+        assert_eq!(sorted(spatial_hash.query_region(&everything)), vec![0, 1]);
+
+        // moving it back into a few cells stores it in them again
+        spatial_hash.insert(0, small);
+        assert!(spatial_hash.oversized.is_empty());
+        assert_eq!(spatial_hash.query_point(Vec2::ONE).len(), 2);
+
+        spatial_hash.remove(0);
+        spatial_hash.remove(1);
+        assert!(spatial_hash.cells.is_empty());
+    }
+
+    #[test]
+    fn removes_the_keys_not_kept() {
+        let mut tree = DynamicTree::new();
+        for key in 0..10u32 {
+            let min = Vec2::new(key as f32 * 10.0, 0.0);
+            tree.insert(key, Aabb::new(min, min + Vec2::splat(15.0)));
+        }
+
+        tree.retain(|key| key % 2 == 0);
+
+        assert_eq!(
+            sorted(tree.query_region(&Aabb::new(Vec2::splat(-1.0), Vec2::splat(200.0)))),
+            vec![0, 2, 4, 6, 8]
+        );
+        assert!(tree.pairs().is_empty());
+    }
+}
@@ -0,0 +1,768 @@
+//! Platformer movement of a box against a `TileMap`.
+
+use super::shapes::Aabb;
+use super::tilemap::{Tile, TileMap};
+use glam::Vec2;
+
+/// Gaps smaller than this, in pixels, count as touching.
+const EPSILON: f32 = 1e-3;
+
+/// How far below the feet, in pixels, the floor is looked for when walking
+/// down slopes, besides the distance walked.
+const SNAP_DISTANCE: f32 = 1.0;
+
+/// What the player wants a `CharacterController` to do during a step.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct CharacterInput {
+    /// Running left (-1.0) and right (1.0).
+    pub x: f32,
+    /// Climbing up (-1.0) and down (1.0). Holding down while jumping drops
+    /// through one-way platforms.
+    pub y: f32,
+    /// Whether the jump button is held.
+    pub jump: bool,
+}
+
+/// Moves a box like a platformer character: running, falling, jumping and
+/// climbing ladders, stopped by the solid tiles of a `TileMap`.
+///
+/// The box moves along one axis at a time, horizontally first, so it slides
+/// along walls and floors. Slopes and one-way platforms are only stood on,
+/// and are looked for under the middle of the box.
+///
+/// With an `InputMap`, the `systems::character_controllers` system steps
+/// entities having one, along with a `Collider` and a `Transform`.
+///
+/// # Example
+/// ```rust
+/// let input = CharacterInput { x: 1.0, y: 0.0, jump: true };
+/// body = character.step(&input, body, &tile_map, time.fixed_delta());
+///
+/// if character.is_grounded() {
+///     println!("Landed");
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct CharacterController {
+    /// The fastest the character runs, in pixels per second.
+    pub run_speed: f32,
+    /// How fast the character gets to its running speed on the ground, in
+    /// pixels per second squared.
+    pub acceleration: f32,
+    /// How fast the character gets to its running speed in the air.
+    pub air_acceleration: f32,
+    /// How fast the character falls faster, in pixels per second squared.
+    pub gravity: f32,
+    /// The fastest the character falls, in pixels per second.
+    pub max_fall_speed: f32,
+    /// How fast the character goes up when jumping, in pixels per second.
+    pub jump_speed: f32,
+    /// What's kept of the speed going up when the jump button is let go
+    /// early, from 0.0 to 1.0. Lower values give more control over the
+    /// height of jumps.
+    pub jump_cut: f32,
+    /// For how many seconds the character can still jump after walking off
+    /// a ledge.
+    pub coyote_time: f32,
+    /// For how many seconds a jump pressed too early, before landing, is
+    /// remembered.
+    pub jump_buffer: f32,
+    /// How fast the character climbs ladders, in pixels per second.
+    pub climb_speed: f32,
+    /// The action running left and right, for the `character_controllers`
+    /// system.
+    pub x_action: String,
+    /// The action climbing up and down.
+    pub y_action: String,
+    /// The action jumping.
+    pub jump_action: String,
+    /// The player whose gamepad moves the character. The current gamepad is
+    /// used when there's none.
+    pub player: Option<usize>,
+    /// How fast the character moves, in pixels per second.
+    pub velocity: Vec2,
+    /// Whether the character stood on something after the last step.
+    grounded: bool,
+    /// Whether the character bumped its head during the last step.
+    on_ceiling: bool,
+    /// Whether the character ran into a wall on its left during the last
+    /// step.
+    on_wall_left: bool,
+    /// Whether the character ran into a wall on its right during the last
+    /// step.
+    on_wall_right: bool,
+    /// Whether the character is on a ladder.
+    climbing: bool,
+    /// Whether the character is going up from a jump, and the jump button
+    /// was held since.
+    jumping: bool,
+    /// Whether the jump button was held during the last step.
+    jump_held: bool,
+    /// The seconds left to jump after leaving the ground.
+    coyote_timer: f32,
+    /// The seconds left for the last jump press to be used.
+    jump_buffer_timer: f32,
+    /// The top of the one-way platforms being dropped through.
+    dropping_from: Option<f32>,
+}
+
+impl CharacterController {
+    /// Creates a character controller with the default settings, moved by
+    /// the "move_x", "move_y" and "jump" actions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes the controller read the gamepad of `player` only.
+    pub fn for_player(mut self, player: usize) -> Self {
+        self.player = Some(player);
+        self
+    }
+
+    /// Checks whether the character stood on something after the last step.
+    pub const fn is_grounded(&self) -> bool {
+        self.grounded
+    }
+
+    /// Checks whether the character bumped its head during the last step.
+    pub const fn is_on_ceiling(&self) -> bool {
+        self.on_ceiling
+    }
+
+    /// Checks whether the character ran into a wall on its left during the
+    /// last step.
+    pub const fn is_on_wall_left(&self) -> bool {
+        self.on_wall_left
+    }
+
+    /// Checks whether the character ran into a wall on its right during the
+    /// last step.
+    pub const fn is_on_wall_right(&self) -> bool {
+        self.on_wall_right
+    }
+
+    /// Checks whether the character is climbing a ladder.
+    pub const fn is_climbing(&self) -> bool {
+        self.climbing
+    }
+
+    /// Moves the character, whose box is `body`, for `delta` seconds with
+    /// `input`. Gets where the box ends up.
+    pub fn step(
+        &mut self,
+        input: &CharacterInput,
+        body: Aabb,
+        tiles: &TileMap,
+        delta: f32,
+    ) -> Aabb {
+        let jump_pressed = input.jump && !self.jump_held;
+        self.jump_held = input.jump;
+        self.jump_buffer_timer = if jump_pressed {
+            self.jump_buffer
+        } else {
+            (self.jump_buffer_timer - delta).max(0.0)
+        };
+        self.coyote_timer = if self.grounded {
+            self.coyote_time
+        } else {
+            (self.coyote_timer - delta).max(0.0)
+        };
+
+        // ladders below the feet are only climbed down
+        if !Self::touches_ladder(&body, tiles, SNAP_DISTANCE) {
+            self.climbing = false;
+        } else if input.y > 0.5 || (input.y < -0.5 && Self::touches_ladder(&body, tiles, 0.0)) {
+            self.climbing = true;
+        }
+
+        if self.jump_buffer_timer > 0.0 && (self.coyote_timer > 0.0 || self.climbing) {
+            self.jump_buffer_timer = 0.0;
+            self.coyote_timer = 0.0;
+
+            if input.y > 0.5 && self.grounded && Self::stands_on_platform(&body, tiles) {
+                self.dropping_from = Some(body.max.y);
+            } else {
+                self.velocity.y = -self.jump_speed;
+                self.climbing = false;
+                self.jumping = true;
+                self.grounded = false;
+            }
+        }
+
+        if self.climbing {
+            self.velocity = Vec2::new(input.x, input.y) * self.climb_speed;
+        } else {
+            let acceleration = if self.grounded {
+                self.acceleration
+            } else {
+                self.air_acceleration
+            };
+            let target = input.x.clamp(-1.0, 1.0) * self.run_speed;
+            let change =
+                (target - self.velocity.x).clamp(-acceleration * delta, acceleration * delta);
+            self.velocity.x += change;
+            self.velocity.y = (self.velocity.y + self.gravity * delta).min(self.max_fall_speed);
+        }
+
+        // letting go of jump early makes for a shorter jump
+        if self.jumping && (!input.jump || self.velocity.y >= 0.0) {
+            if self.velocity.y < 0.0 {
+                self.velocity.y *= self.jump_cut;
+            }
+            self.jumping = false;
+        }
+
+        let motion = self.velocity * delta;
+        let snap = if self.grounded && !self.jumping && !self.climbing {
+            motion.x.abs() + SNAP_DISTANCE
+        } else {
+            0.0
+        };
+
+        self.on_wall_left = false;
+        self.on_wall_right = false;
+        self.on_ceiling = false;
+        self.grounded = false;
+
+        let body = self.move_x(body, motion.x, tiles);
+        let body = self.move_y(body, motion.y, snap, tiles);
+
+        if self.climbing && self.grounded && input.y > 0.0 {
+            self.climbing = false;
+        }
+        if self
+            .dropping_from
+            .is_some_and(|top| self.grounded || body.min.y > top)
+        {
+            self.dropping_from = None;
+        }
+
+        body
+    }
+
+    /// Moves `body` horizontally by `distance`, stopping at walls.
+    fn move_x(&mut self, body: Aabb, distance: f32, tiles: &TileMap) -> Aabb {
+        if distance == 0.0 {
+            return body;
+        }
+
+        let direction = distance.signum();
+        let swept = if distance > 0.0 {
+            Aabb::new(
+                Vec2::new(body.max.x, body.min.y),
+                Vec2::new(body.max.x + distance, body.max.y),
+            )
+        } else {
+            Aabb::new(
+                Vec2::new(body.min.x + distance, body.min.y),
+                Vec2::new(body.min.x, body.max.y),
+            )
+        };
+
+        let mut allowed = distance.abs();
+        for (column, row, tile) in tiles.tiles_in(&swept) {
+            // walls at the top of a slope are walked onto, not into
+            let behind = tiles.get(column - direction as i32, row);
+            let slope_leads_in = match behind {
+                Tile::Slope { left, right } => (if distance > 0.0 { right } else { left }) >= 1.0,
+                _ => false,
+            };
+            if !tile.is_solid() || slope_leads_in {
+                continue;
+            }
+
+            let bounds = tiles.tile_bounds(column, row);
+            let gap = if distance > 0.0 {
+                bounds.min.x - body.max.x
+            } else {
+                body.min.x - bounds.max.x
+            };
+            // walls the box is already inside of don't push it back
+            if gap >= -EPSILON {
+                allowed = allowed.min(gap.max(0.0));
+            }
+        }
+
+        if allowed < distance.abs() {
+            self.velocity.x = 0.0;
+            if distance > 0.0 {
+                self.on_wall_right = true;
+            } else {
+                self.on_wall_left = true;
+            }
+        }
+
+        let offset = Vec2::new(allowed * direction, 0.0);
+        Aabb::new(body.min + offset, body.max + offset)
+    }
+
+    /// Moves `body` vertically by `distance`, stopping at ceilings and
+    /// floors. When falling, floors up to `snap` pixels further or higher
+    /// are stood on as well, so walking over bumps keeps the box grounded.
+    fn move_y(&mut self, body: Aabb, distance: f32, snap: f32, tiles: &TileMap) -> Aabb {
+        if distance < 0.0 {
+            return self.move_up(body, -distance, tiles);
+        }
+
+        let feet = body.center().x;
+        let reach = body.max.y + distance + snap;
+        let swept = Aabb::new(
+            Vec2::new(body.min.x, body.max.y - tiles.tile_size),
+            Vec2::new(body.max.x, reach),
+        );
+
+        let highest = |floor: Option<f32>, surface: f32| {
+            Some(floor.map_or(surface, |floor| floor.min(surface)))
+        };
+        let mut floor = None;
+        let mut slope = None;
+        for (column, row, tile) in tiles.tiles_in(&swept) {
+            let top = tiles.tile_bounds(column, row).min.y;
+            let is_platform_top = matches!(tile, Tile::OneWay)
+                || (tile == Tile::Ladder && tiles.get(column, row - 1) != Tile::Ladder);
+
+            let surface = match tile {
+                Tile::Solid => top,
+                _ if is_platform_top => {
+                    let dropping = self
+                        .dropping_from
+                        .is_some_and(|from| (from - top).abs() <= EPSILON);
+                    if self.climbing || dropping {
+                        continue;
+                    }
+                    top
+                }
+                // slopes are walked up, so they may be above the feet
+                Tile::Slope { .. } if tiles.column_at(feet) == column => {
+                    let surface = tiles.floor_at(column, row, feet);
+                    if surface <= reach {
+                        slope = highest(slope, surface);
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+
+            if surface >= body.max.y - snap - EPSILON && surface <= reach {
+                floor = highest(floor, surface);
+            }
+        }
+
+        // the feet stay on slopes, even when a corner of the box is above
+        // the wall at their top
+        match slope.or(floor) {
+            Some(floor) => {
+                self.grounded = true;
+                self.jumping = false;
+                self.velocity.y = self.velocity.y.min(0.0);
+                let offset = Vec2::new(0.0, floor - body.max.y);
+                Aabb::new(body.min + offset, body.max + offset)
+            }
+            None => {
+                let offset = Vec2::new(0.0, distance);
+                Aabb::new(body.min + offset, body.max + offset)
+            }
+        }
+    }
+
+    /// Moves `body` up by `distance`, stopping at ceilings.
+    fn move_up(&mut self, body: Aabb, distance: f32, tiles: &TileMap) -> Aabb {
+        let swept = Aabb::new(
+            Vec2::new(body.min.x, body.min.y - distance),
+            Vec2::new(body.max.x, body.min.y),
+        );
+
+        let mut allowed = distance;
+        for (column, row, tile) in tiles.tiles_in(&swept) {
+            if !matches!(tile, Tile::Solid | Tile::Slope { .. }) {
+                continue;
+            }
+
+            let gap = body.min.y - tiles.tile_bounds(column, row).max.y;
+            if gap >= -EPSILON {
+                allowed = allowed.min(gap.max(0.0));
+            }
+        }
+
+        if allowed < distance {
+            self.on_ceiling = true;
+            self.jumping = false;
+            self.velocity.y = 0.0;
+        }
+
+        let offset = Vec2::new(0.0, -allowed);
+        Aabb::new(body.min + offset, body.max + offset)
+    }
+
+    /// Checks whether a ladder is behind the middle of `body`, or up to
+    /// `below` pixels below its feet.
+    fn touches_ladder(body: &Aabb, tiles: &TileMap, below: f32) -> bool {
+        let x = body.center().x;
+        let column = Aabb::new(Vec2::new(x, body.min.y), Vec2::new(x, body.max.y + below));
+
+        tiles
+            .tiles_in(&column)
+            .any(|(_, _, tile)| tile == Tile::Ladder)
+    }
+
+    /// Checks whether `body` stands on something it can drop through, the
+    /// top of a one-way platform or a ladder.
+    fn stands_on_platform(body: &Aabb, tiles: &TileMap) -> bool {
+        let below = Aabb::new(
+            Vec2::new(body.min.x, body.max.y),
+            Vec2::new(body.max.x, body.max.y + SNAP_DISTANCE),
+        );
+        tiles
+            .tiles_in(&below)
+            .filter(|(_, _, tile)| *tile != Tile::Empty)
+            .all(|(_, _, tile)| matches!(tile, Tile::OneWay | Tile::Ladder))
+    }
+}
+
+impl Default for CharacterController {
+    fn default() -> Self {
+        let run_speed = 120.0;
+        let acceleration = 1200.0;
+        let air_acceleration = 800.0;
+        let gravity = 1000.0;
+        let max_fall_speed = 400.0;
+        let jump_speed = 320.0;
+        let jump_cut = 0.4;
+        let coyote_time = 0.1;
+        let jump_buffer = 0.1;
+        let climb_speed = 80.0;
+
+        Self {
+            run_speed,
+            acceleration,
+            air_acceleration,
+            gravity,
+            max_fall_speed,
+            jump_speed,
+            jump_cut,
+            coyote_time,
+            jump_buffer,
+            climb_speed,
+            x_action: "move_x".to_owned(),
+            y_action: "move_y".to_owned(),
+            jump_action: "jump".to_owned(),
+            player: None,
+            velocity: Vec2::ZERO,
+            grounded: false,
+            on_ceiling: false,
+            on_wall_left: false,
+            on_wall_right: false,
+            climbing: false,
+            jumping: false,
+            jump_held: false,
+            coyote_timer: 0.0,
+            jump_buffer_timer: 0.0,
+            dropping_from: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DELTA: f32 = 1.0 / 60.0;
+    const TILE_SIZE: f32 = 16.0;
+    const SIZE: Vec2 = Vec2::new(12.0, 14.0);
+
+    const IDLE: CharacterInput = CharacterInput {
+        x: 0.0,
+        y: 0.0,
+        jump: false,
+    };
+
+    fn input(x: f32, y: f32, jump: bool) -> CharacterInput {
+        CharacterInput { x, y, jump }
+    }
+
+    /// A box with its left side at `x` and its feet at `feet`.
+    fn body_at(x: f32, feet: f32) -> Aabb {
+        Aabb::new(Vec2::new(x, feet - SIZE.y), Vec2::new(x + SIZE.x, feet))
+    }
+
+    /// Steps the character `steps` times with the same input.
+    fn run(
+        character: &mut CharacterController,
+        input: &CharacterInput,
+        mut body: Aabb,
+        tiles: &TileMap,
+        steps: usize,
+    ) -> Aabb {
+        for _ in 0..steps {
+            body = character.step(input, body, tiles, DELTA);
+        }
+        body
+    }
+
+    /// Steps the character until it stands on something.
+    fn land(character: &mut CharacterController, body: Aabb, tiles: &TileMap) -> Aabb {
+        let mut body = body;
+        for _ in 0..600 {
+            body = character.step(&IDLE, body, tiles, DELTA);
+            if character.is_grounded() {
+                return body;
+            }
+        }
+        panic!("never landed: {:?}", body);
+    }
+
+    fn room() -> TileMap {
+        TileMap::from_rows(
+            &[
+                "#......#", //
+                "#......#", "#......#", "########",
+            ],
+            TILE_SIZE,
+        )
+    }
+
+    #[test]
+    fn falls_until_it_lands_on_the_floor() {
+        let tiles = room();
+        let mut character = CharacterController::new();
+
+        let body = run(&mut character, &IDLE, body_at(40.0, 14.0), &tiles, 120);
+
+        assert!(character.is_grounded());
+        assert_eq!(body.max.y, 48.0);
+        assert_eq!(character.velocity.y, 0.0);
+        // standing still keeps it grounded
+        let body = run(&mut character, &IDLE, body, &tiles, 30);
+        assert!(character.is_grounded());
+        assert_eq!(body.max.y, 48.0);
+    }
+
+    #[test]
+    fn stops_at_walls_on_either_side() {
+        let tiles = room();
+        let mut character = CharacterController::new();
+        let body = land(&mut character, body_at(40.0, 48.0), &tiles);
+
+        let body = run(&mut character, &input(1.0, 0.0, false), body, &tiles, 90);
+        assert!(character.is_on_wall_right() && !character.is_on_wall_left());
+        assert_eq!(body.max.x, 112.0);
+        assert_eq!(character.velocity.x, 0.0);
+
+        let body = run(&mut character, &input(-1.0, 0.0, false), body, &tiles, 90);
+        assert!(character.is_on_wall_left() && !character.is_on_wall_right());
+        assert_eq!(body.min.x, 16.0);
+
+        run(&mut character, &IDLE, body, &tiles, 1);
+        assert!(!character.is_on_wall_left());
+    }
+
+    #[test]
+    fn bumps_its_head_on_ceilings() {
+        let tiles = TileMap::from_rows(
+            &[
+                "########", //
+                "........", "........", "########",
+            ],
+            TILE_SIZE,
+        );
+        let mut character = CharacterController::new();
+        let mut body = land(&mut character, body_at(40.0, 48.0), &tiles);
+
+        let mut bumped = false;
+        for _ in 0..30 {
+            body = character.step(&input(0.0, 0.0, true), body, &tiles, DELTA);
+            bumped |= character.is_on_ceiling();
+            assert!(body.min.y >= 16.0, "went through the ceiling: {:?}", body);
+        }
+
+        assert!(bumped);
+        let body = land(&mut character, body, &tiles);
+        assert_eq!(body.max.y, 48.0);
+    }
+
+    #[test]
+    fn walks_up_and_down_slopes_without_leaving_the_ground() {
+        let tiles = TileMap::from_rows(
+            &[
+                "..........", //
+                "..........",
+                "..../#####",
+                "##########",
+            ],
+            TILE_SIZE,
+        );
+        let mut character = CharacterController::new();
+        let mut body = land(&mut character, body_at(8.0, 48.0), &tiles);
+
+        for _ in 0..60 {
+            body = character.step(&input(1.0, 0.0, false), body, &tiles, DELTA);
+            assert!(character.is_grounded(), "left the ground at {:?}", body);
+        }
+        assert_eq!(body.max.y, 32.0);
+        assert!(body.min.x > 80.0);
+
+        for _ in 0..60 {
+            body = character.step(&input(-1.0, 0.0, false), body, &tiles, DELTA);
+            assert!(character.is_grounded(), "left the ground at {:?}", body);
+        }
+        assert_eq!(body.max.y, 48.0);
+    }
+
+    #[test]
+    fn stands_on_one_way_platforms_and_drops_through_them() {
+        let tiles = TileMap::from_rows(
+            &[
+                "........", //
+                "........", "..----..", "........", "########",
+            ],
+            TILE_SIZE,
+        );
+        let mut character = CharacterController::new();
+
+        let body = land(&mut character, body_at(40.0, 14.0), &tiles);
+        assert_eq!(body.max.y, 32.0);
+        let body = run(&mut character, &IDLE, body, &tiles, 30);
+        assert_eq!(body.max.y, 32.0);
+
+        // down and jump drops through, rather than jumping
+        let body = character.step(&input(0.0, 1.0, true), body, &tiles, DELTA);
+        assert!(character.velocity.y >= 0.0);
+        let body = land(&mut character, body, &tiles);
+        assert_eq!(body.max.y, 64.0);
+    }
+
+    #[test]
+    fn jumps_up_through_one_way_platforms() {
+        let tiles = TileMap::from_rows(
+            &[
+                "........", //
+                "........", "..----..", "........", "########",
+            ],
+            TILE_SIZE,
+        );
+        let mut character = CharacterController::new();
+        let body = land(&mut character, body_at(40.0, 64.0), &tiles);
+
+        let body = run(&mut character, &input(0.0, 0.0, true), body, &tiles, 10);
+        assert!(!character.is_on_ceiling());
+        let body = land(&mut character, body, &tiles);
+
+        assert_eq!(body.max.y, 32.0);
+    }
+
+    #[test]
+    fn climbs_ladders_up_onto_their_top_and_back_down() {
+        let tiles = TileMap::from_rows(
+            &[
+                "........", //
+                "...H....", "...H....", "...H....", "########",
+            ],
+            TILE_SIZE,
+        );
+        let mut character = CharacterController::new();
+        // centered on the ladder
+        let body = land(&mut character, body_at(50.0, 64.0), &tiles);
+
+        let body = character.step(&input(0.0, -1.0, false), body, &tiles, DELTA);
+        assert!(character.is_climbing());
+        assert!(body.max.y < 64.0);
+
+        // past the top, it stops climbing and stands on it
+        let body = run(&mut character, &input(0.0, -1.0, false), body, &tiles, 120);
+        assert!(!character.is_climbing());
+        assert!(character.is_grounded());
+        assert_eq!(body.max.y, 16.0);
+
+        let body = character.step(&input(0.0, 1.0, false), body, &tiles, DELTA);
+        assert!(character.is_climbing());
+        assert!(body.max.y > 16.0);
+
+        // reaching the floor, it stops climbing
+        let body = run(&mut character, &input(0.0, 1.0, false), body, &tiles, 120);
+        assert!(!character.is_climbing());
+        assert!(character.is_grounded());
+        assert_eq!(body.max.y, 64.0);
+    }
+
+    #[test]
+    fn jumps_shortly_after_walking_off_a_ledge() {
+        let tiles = TileMap::from_rows(
+            &[
+                "........", //
+                "........", "###.....", "###.....",
+            ],
+            TILE_SIZE,
+        );
+        let mut character = CharacterController::new();
+        let mut body = land(&mut character, body_at(30.0, 32.0), &tiles);
+
+        while character.is_grounded() {
+            body = character.step(&input(1.0, 0.0, false), body, &tiles, DELTA);
+        }
+
+        // within the coyote time
+        let mut late = character.clone();
+        let mut early = character.clone();
+        let early_body = run(&mut early, &IDLE, body, &tiles, 2);
+        early.step(&input(0.0, 0.0, true), early_body, &tiles, DELTA);
+        assert!(early.velocity.y < 0.0);
+
+        // past the coyote time
+        let late_body = run(&mut late, &IDLE, body, &tiles, 10);
+        late.step(&input(0.0, 0.0, true), late_body, &tiles, DELTA);
+        assert!(late.velocity.y > 0.0);
+    }
+
+    #[test]
+    fn remembers_jumps_pressed_shortly_before_landing() {
+        let tiles = room();
+        let start = body_at(40.0, 14.0);
+
+        // how many steps the fall lasts
+        let mut character = CharacterController::new();
+        let mut body = start;
+        let mut fall = 0;
+        while !character.is_grounded() {
+            body = character.step(&IDLE, body, &tiles, DELTA);
+            fall += 1;
+        }
+
+        let jumps_when_pressed_before_landing = |steps_before: usize| {
+            let mut character = CharacterController::new();
+            let body = run(&mut character, &IDLE, start, &tiles, fall - steps_before);
+            let body = run(
+                &mut character,
+                &input(0.0, 0.0, true),
+                body,
+                &tiles,
+                steps_before,
+            );
+            character.step(&input(0.0, 0.0, true), body, &tiles, DELTA);
+
+            character.velocity.y < 0.0
+        };
+
+        assert!(jumps_when_pressed_before_landing(3));
+        assert!(!jumps_when_pressed_before_landing(15));
+    }
+
+    #[test]
+    fn jumps_lower_when_jump_is_let_go_early() {
+        let tiles = room();
+        let peak = |held_steps: usize| {
+            let mut character = CharacterController::new();
+            let mut body = land(&mut character, body_at(40.0, 48.0), &tiles);
+            let mut top = body.min.y;
+            for step in 0..60 {
+                let jump = input(0.0, 0.0, step < held_steps);
+                body = character.step(&jump, body, &tiles, DELTA);
+                top = top.min(body.min.y);
+            }
+            48.0 - SIZE.y - top
+        };
+
+        let (full, cut) = (peak(60), peak(4));
+        assert!(full > 40.0, "{full}");
+        assert!(cut < full / 2.0, "{cut} isn't much lower than {full}");
+    }
+}
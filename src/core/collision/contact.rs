@@ -0,0 +1,435 @@
+//! Finds how two shapes overlap. Every shape is handled as a convex hull
+//! of one or more points grown by a radius, so a single algorithm covers
+//! every pair of shapes.
+
+use super::shapes::ColliderShape;
+use super::Manifold;
+use glam::Vec2;
+
+/// Lengths below this, in pixels, count as zero.
+const EPSILON: f32 = 1e-4;
+
+/// How far from perpendicular to the normal an edge can be, as the cosine
+/// of the angle between them, to be taken as touching flat.
+const FLAT_TOLERANCE: f32 = 0.02;
+
+/// A convex hull grown by a radius: a point for circles, a segment for
+/// capsules and a polygon for boxes and polygons.
+struct Hull {
+    points: Vec<Vec2>,
+    radius: f32,
+}
+
+impl Hull {
+    fn new(shape: &ColliderShape) -> Self {
+        match shape {
+            ColliderShape::Aabb(aabb) => Self {
+                points: aabb.corners().to_vec(),
+                radius: 0.0,
+            },
+            ColliderShape::Circle(circle) => Self {
+                points: vec![circle.center],
+                radius: circle.radius,
+            },
+            ColliderShape::Capsule(capsule) => Self {
+                points: vec![capsule.start, capsule.end],
+                radius: capsule.radius,
+            },
+            ColliderShape::Polygon(polygon) => Self {
+                points: polygon.points().to_vec(),
+                radius: 0.0,
+            },
+        }
+    }
+
+    /// Gets the edges of the hull. A point is an edge of length zero.
+    fn edges(&self) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+        let len = self.points.len();
+        let count = if len > 2 { len } else { 1 };
+        (0..count).map(move |i| (self.points[i], self.points[(i + 1) % len]))
+    }
+
+    /// Gets the directions the hull can be separated along: the normals of
+    /// its edges.
+    fn axes(&self) -> impl Iterator<Item = Vec2> + '_ {
+        self.edges()
+            .map(|(start, end)| (end - start).perp().normalize_or_zero())
+            .filter(|axis| *axis != Vec2::ZERO)
+    }
+
+    /// Projects the hull, radius included, on `axis`.
+    fn project(&self, axis: Vec2) -> (f32, f32) {
+        let (min, max) = self.points.iter().map(|point| point.dot(axis)).fold(
+            (f32::INFINITY, f32::NEG_INFINITY),
+            |(min, max), projection| (min.min(projection), max.max(projection)),
+        );
+
+        (min - self.radius, max + self.radius)
+    }
+
+    /// Checks whether `point` is strictly inside the polygon of the hull.
+    fn contains(&self, point: Vec2) -> bool {
+        if self.points.len() < 3 {
+            return false;
+        }
+
+        let sides = self
+            .edges()
+            .map(|(start, end)| (end - start).perp_dot(point - start));
+        let mut inside_left = true;
+        let mut inside_right = true;
+        for side in sides {
+            inside_left &= side > EPSILON;
+            inside_right &= side < -EPSILON;
+        }
+
+        inside_left || inside_right
+    }
+
+    /// Gets the part of the hull furthest along `direction`: a single point,
+    /// or an edge when one is facing it.
+    fn feature(&self, direction: Vec2) -> (Vec2, Vec2) {
+        let len = self.points.len();
+        let furthest = (0..len)
+            .max_by(|&a, &b| {
+                let a = self.points[a].dot(direction);
+                let b = self.points[b].dot(direction);
+                a.total_cmp(&b)
+            })
+            .unwrap_or(0);
+        let point = self.points[furthest];
+
+        let neighbours = [(furthest + len - 1) % len, (furthest + 1) % len];
+        neighbours
+            .into_iter()
+            .filter(|&neighbour| neighbour != furthest)
+            .map(|neighbour| self.points[neighbour])
+            .map(|other| (other, (other - point).normalize_or_zero().dot(direction)))
+            .filter(|(_, cosine)| cosine.abs() < FLAT_TOLERANCE)
+            .min_by(|(_, a), (_, b)| a.abs().total_cmp(&b.abs()))
+            .map_or((point, point), |(other, _)| (point, other))
+    }
+}
+
+/// Finds how `a` overlaps `b`, if it does. Shapes only touching don't
+/// overlap.
+pub(super) fn contact(a: &ColliderShape, b: &ColliderShape) -> Option<Manifold> {
+    let (a, b) = (Hull::new(a), Hull::new(b));
+
+    let (normal, depth) = match closest_between_hulls(&a, &b)? {
+        None => separating_axis(&a, &b)?,
+        Some((closest_a, closest_b)) => {
+            // only the radii overlap, so the closest points give the normal
+            let distance = closest_a.distance(closest_b);
+            let reach = a.radius + b.radius;
+            if distance >= reach {
+                return None;
+            }
+            ((closest_a - closest_b) / distance, reach - distance)
+        }
+    };
+
+    Some(Manifold::new(
+        normal,
+        depth,
+        &contact_points(&a, &b, normal),
+    ))
+}
+
+/// Finds the closest points of the surfaces of `a` and `b`, radii
+/// included, if the shapes don't overlap.
+pub(super) fn closest(a: &ColliderShape, b: &ColliderShape) -> Option<(Vec2, Vec2)> {
+    let (a, b) = (Hull::new(a), Hull::new(b));
+    let (closest_a, closest_b) = closest_between_hulls(&a, &b)??;
+
+    let distance = closest_a.distance(closest_b);
+    if distance <= a.radius + b.radius {
+        return None;
+    }
+
+    let direction = (closest_b - closest_a) / distance;
+    Some((
+        closest_a + direction * a.radius,
+        closest_b - direction * b.radius,
+    ))
+}
+
+/// Finds the closest points of two hulls, without their radii. Gets `None`
+/// inside when the hulls overlap, and nothing for empty hulls.
+fn closest_between_hulls(a: &Hull, b: &Hull) -> Option<Option<(Vec2, Vec2)>> {
+    let (closest_a, closest_b) = a
+        .edges()
+        .flat_map(|edge_a| b.edges().map(move |edge_b| closest_points(edge_a, edge_b)))
+        .min_by(|(a1, b1), (a2, b2)| a1.distance(*b1).total_cmp(&a2.distance(*b2)))?;
+
+    let hulls_overlap = closest_a.distance(closest_b) <= EPSILON
+        || a.points.iter().any(|&point| b.contains(point))
+        || b.points.iter().any(|&point| a.contains(point));
+
+    Some((!hulls_overlap).then_some((closest_a, closest_b)))
+}
+
+/// Finds the shortest way to push `a` out of `b` along the axes of both
+/// hulls, as a normal and a depth. Hulls that are both points are pushed
+/// up.
+fn separating_axis(a: &Hull, b: &Hull) -> Option<(Vec2, f32)> {
+    let mut best: Option<(Vec2, f32)> = None;
+
+    for axis in a.axes().chain(b.axes()) {
+        let (min_a, max_a) = a.project(axis);
+        let (min_b, max_b) = b.project(axis);
+
+        let (normal, depth) = if max_a - min_b < max_b - min_a {
+            (-axis, max_a - min_b)
+        } else {
+            (axis, max_b - min_a)
+        };
+        if depth <= 0.0 {
+            return None;
+        }
+        if best.is_none_or(|(_, best_depth)| depth < best_depth) {
+            best = Some((normal, depth));
+        }
+    }
+
+    best.or(Some((Vec2::new(0.0, -1.0), a.radius + b.radius)))
+}
+
+/// Finds where the hulls touch, halfway through their overlap: between the
+/// point or edge of `b` facing `a` and the one of `a` facing `b`, where
+/// they overlap along the tangent.
+fn contact_points(a: &Hull, b: &Hull, normal: Vec2) -> Vec<Vec2> {
+    let (a_start, a_end) = a.feature(-normal);
+    let (b_start, b_end) = b.feature(normal);
+    let tangent = normal.perp();
+
+    let (a_min, a_max) = min_max(a_start.dot(tangent), a_end.dot(tangent));
+    let (b_min, b_max) = min_max(b_start.dot(tangent), b_end.dot(tangent));
+    let (low, high) = (a_min.max(b_min), a_max.min(b_max));
+    let positions = if high - low <= EPSILON {
+        vec![(low + high) / 2.0]
+    } else {
+        vec![low, high]
+    };
+
+    // the surfaces of both hulls at each position, and how much they overlap
+    let touching: Vec<(f32, Vec2)> = positions
+        .into_iter()
+        .map(|position| {
+            let on_a = along(a_start, a_end, tangent, position) - normal * a.radius;
+            let on_b = along(b_start, b_end, tangent, position) + normal * b.radius;
+            ((on_b - on_a).dot(normal), (on_a + on_b) / 2.0)
+        })
+        .collect();
+
+    // edges only close to flat against each other may not touch at both
+    // ends, and rounding errors may leave them all a hair apart
+    let points: Vec<Vec2> = touching
+        .iter()
+        .filter(|(overlap, _)| *overlap >= -EPSILON)
+        .map(|(_, point)| *point)
+        .collect();
+    if points.is_empty() {
+        let deepest = touching.into_iter().max_by(|(a, _), (b, _)| a.total_cmp(b));
+        return deepest.map(|(_, point)| point).into_iter().collect();
+    }
+
+    points
+}
+
+/// Gets the point of the segment from `start` to `end` at `position` along
+/// `tangent`, clamped to the segment.
+fn along(start: Vec2, end: Vec2, tangent: Vec2, position: f32) -> Vec2 {
+    let span = (end - start).dot(tangent);
+    if span.abs() <= EPSILON {
+        return start;
+    }
+
+    let t = ((position - start.dot(tangent)) / span).clamp(0.0, 1.0);
+    start + (end - start) * t
+}
+
+/// Orders two numbers.
+fn min_max(a: f32, b: f32) -> (f32, f32) {
+    (a.min(b), a.max(b))
+}
+
+/// Finds the closest points between two segments, which may be points.
+fn closest_points((start_a, end_a): (Vec2, Vec2), (start_b, end_b): (Vec2, Vec2)) -> (Vec2, Vec2) {
+    // from Real-Time Collision Detection, by Christer Ericson
+    let (direction_a, direction_b) = (end_a - start_a, end_b - start_b);
+    let offset = start_a - start_b;
+    let (length_a, length_b) = (direction_a.length_squared(), direction_b.length_squared());
+    let along_b = direction_b.dot(offset);
+
+    let (s, t) = if length_a <= EPSILON && length_b <= EPSILON {
+        (0.0, 0.0)
+    } else if length_a <= EPSILON {
+        (0.0, (along_b / length_b).clamp(0.0, 1.0))
+    } else {
+        let along_a = direction_a.dot(offset);
+        if length_b <= EPSILON {
+            ((-along_a / length_a).clamp(0.0, 1.0), 0.0)
+        } else {
+            let cross = direction_a.dot(direction_b);
+            let denominator = length_a * length_b - cross * cross;
+            let s = if denominator > EPSILON {
+                ((cross * along_b - along_a * length_b) / denominator).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+
+            let t = (cross * s + along_b) / length_b;
+            if t < 0.0 {
+                ((-along_a / length_a).clamp(0.0, 1.0), 0.0)
+            } else if t > 1.0 {
+                (((cross - along_a) / length_a).clamp(0.0, 1.0), 1.0)
+            } else {
+                (s, t)
+            }
+        }
+    };
+
+    (start_a + direction_a * s, start_b + direction_b * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::shapes::{Aabb, Capsule, Circle, Polygon};
+    use super::*;
+    use crate::core::transform::Transform;
+    use proptest::prelude::*;
+
+    /// How far off, in pixels, results may be from rounding errors.
+    const TOLERANCE: f32 = 1e-3;
+
+    fn point() -> impl Strategy<Value = Vec2> {
+        (-20.0f32..20.0, -20.0f32..20.0).prop_map(|(x, y)| Vec2::new(x, y))
+    }
+
+    fn aabb() -> impl Strategy<Value = ColliderShape> {
+        (point(), 1.0f32..30.0, 1.0f32..30.0)
+            .prop_map(|(min, w, h)| ColliderShape::from(Aabb::new(min, min + Vec2::new(w, h))))
+    }
+
+    fn circle() -> impl Strategy<Value = ColliderShape> {
+        (point(), 1.0f32..20.0)
+            .prop_map(|(center, radius)| ColliderShape::from(Circle::new(center, radius)))
+    }
+
+    fn capsule() -> impl Strategy<Value = ColliderShape> {
+        (point(), point(), 1.0f32..10.0)
+            .prop_map(|(start, end, radius)| ColliderShape::from(Capsule::new(start, end, radius)))
+    }
+
+    fn polygon() -> impl Strategy<Value = ColliderShape> {
+        let corner = (4.0f32..20.0, 0.0f32..std::f32::consts::TAU)
+            .prop_map(|(distance, angle)| Vec2::from_angle(angle) * distance);
+
+        (point(), prop::collection::vec(corner, 3..8))
+            .prop_map(|(center, corners)| {
+                Polygon::new(corners.into_iter().map(|corner| center + corner))
+            })
+            .prop_filter("the corners are in a line", |polygon| {
+                polygon.points().len() >= 3
+            })
+            .prop_map(ColliderShape::from)
+    }
+
+    fn shape() -> impl Strategy<Value = ColliderShape> {
+        prop_oneof![aabb(), circle(), capsule(), polygon()]
+    }
+
+    /// Gets how far `point` is from `shape`, or 0.0 if it's inside.
+    fn distance(point: Vec2, shape: &ColliderShape) -> f32 {
+        let point = ColliderShape::from(Circle::new(point, 0.0));
+        closest(&point, shape).map_or(0.0, |(a, b)| a.distance(b))
+    }
+
+    fn moved(shape: &ColliderShape, offset: Vec2) -> ColliderShape {
+        shape.transformed(&Transform::from_translation(offset))
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(2000))]
+
+        #[test]
+        fn finds_the_same_contact_from_either_shape(a in shape(), b in shape()) {
+            let (ab, ba) = (contact(&a, &b), contact(&b, &a));
+            prop_assert_eq!(ab.is_some(), ba.is_some());
+
+            if let (Some(ab), Some(ba)) = (ab, ba.map(|ba| ba.flipped())) {
+                prop_assert!(ab.normal.abs_diff_eq(ba.normal, TOLERANCE), "{:?} {:?}", ab, ba);
+                prop_assert!((ab.depth - ba.depth).abs() < TOLERANCE, "{:?} {:?}", ab, ba);
+                prop_assert_eq!(ab.points().len(), ba.points().len());
+                for &point in ab.points() {
+                    let found = ba
+                        .points()
+                        .iter()
+                        .any(|other| other.abs_diff_eq(point, TOLERANCE));
+                    prop_assert!(found, "{:?} {:?}", ab, ba);
+                }
+            }
+        }
+
+        #[test]
+        fn separates_the_shapes(a in shape(), b in shape()) {
+            if let Some(manifold) = contact(&a, &b) {
+                prop_assert!(manifold.depth > 0.0);
+                prop_assert!((manifold.normal.length() - 1.0).abs() < TOLERANCE);
+
+                let separated = moved(&a, manifold.separation());
+                let left = contact(&separated, &b).map_or(0.0, |manifold| manifold.depth);
+                prop_assert!(left < TOLERANCE, "{:?} still overlaps by {}", manifold, left);
+            }
+        }
+
+        #[test]
+        fn touches_both_shapes_halfway_through_the_overlap(a in shape(), b in shape()) {
+            if let Some(manifold) = contact(&a, &b) {
+                prop_assert!(!manifold.points().is_empty());
+
+                for &point in manifold.points() {
+                    let reach = manifold.depth / 2.0 + TOLERANCE;
+                    prop_assert!(distance(point, &a) <= reach, "{} is off {:?}", point, a);
+                    prop_assert!(distance(point, &b) <= reach, "{} is off {:?}", point, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pushes_coincident_circles_up() {
+        let a = ColliderShape::from(Circle::new(Vec2::new(5.0, 5.0), 3.0));
+        let b = ColliderShape::from(Circle::new(Vec2::new(5.0, 5.0), 2.0));
+
+        let manifold = contact(&a, &b).unwrap();
+
+        assert_eq!(manifold.normal, Vec2::new(0.0, -1.0));
+        assert_eq!(manifold.depth, 5.0);
+        assert!(contact(&moved(&a, manifold.separation()), &b).is_none());
+    }
+
+    #[test]
+    fn pushes_a_circle_centered_inside_a_box_out_of_the_closest_side() {
+        let wall = ColliderShape::from(Aabb::new(Vec2::ZERO, Vec2::new(40.0, 20.0)));
+        let ball = ColliderShape::from(Circle::new(Vec2::new(34.0, 8.0), 4.0));
+
+        let manifold = contact(&ball, &wall).unwrap();
+
+        // 6 pixels from the right side, plus the radius
+        assert_eq!(manifold.normal, Vec2::X);
+        assert!((manifold.depth - 10.0).abs() < TOLERANCE);
+        assert!(contact(&moved(&ball, manifold.separation()), &wall).is_none());
+    }
+
+    #[test]
+    fn doesnt_overlap_shapes_only_touching() {
+        let a = ColliderShape::from(Aabb::new(Vec2::ZERO, Vec2::new(10.0, 10.0)));
+        let b = ColliderShape::from(Aabb::new(Vec2::new(10.0, 0.0), Vec2::new(20.0, 10.0)));
+        let c = ColliderShape::from(Circle::new(Vec2::new(15.0, -5.0), 5.0));
+
+        assert!(contact(&a, &b).is_none());
+        assert!(contact(&b, &c).is_none());
+    }
+}
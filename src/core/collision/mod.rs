@@ -0,0 +1,212 @@
+use crate::core::ecs::Entity;
+use crate::core::rect::Rect;
+use crate::core::transform::Transform;
+use glam::Vec2;
+
+pub mod broadphase;
+pub mod character;
+mod contact;
+pub mod shapes;
+pub mod sweep;
+pub mod tilemap;
+pub mod world;
+
+use shapes::{Aabb, Circle, ColliderShape};
+use sweep::Collision;
+
+/// The layer colliders are on, unless put on others.
+pub const DEFAULT_LAYER: u32 = 1;
+
+/// Every layer, as a mask.
+pub const ALL_LAYERS: u32 = u32::MAX;
+
+/// How two shapes overlap, seen from the first one.
+///
+/// # Example
+/// ```rust
+/// let ball = ColliderShape::from(Circle::new(Vec2::new(10.0, 0.0), 8.0));
+/// let wall = ColliderShape::from(Aabb::new(Vec2::new(0.0, -32.0), Vec2::new(4.0, 32.0)));
+///
+/// if let Some(manifold) = ball.contact(&wall) {
+///     // pushes the ball out of the wall
+///     position += manifold.separation();
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Manifold {
+    /// The direction the first shape should move to get out of the second,
+    /// of length 1.
+    pub normal: Vec2,
+    /// How far the first shape should move along the normal to get out of
+    /// the second.
+    pub depth: f32,
+    /// Where the shapes touch, halfway through their overlap. Only the
+    /// first `point_count` are used.
+    points: [Vec2; 2],
+    /// How many points the shapes touch at, 1 or 2.
+    point_count: usize,
+}
+
+impl Manifold {
+    /// Creates a manifold touching at the first two of `points`.
+    pub fn new(normal: Vec2, depth: f32, points: &[Vec2]) -> Self {
+        let point_count = points.len().min(2);
+        let mut manifold_points = [Vec2::ZERO; 2];
+        manifold_points[..point_count].copy_from_slice(&points[..point_count]);
+
+        Self {
+            normal,
+            depth,
+            points: manifold_points,
+            point_count,
+        }
+    }
+
+    /// Gets where the shapes touch: a single point, or both ends of the
+    /// area they touch at when lying flat against each other.
+    pub fn points(&self) -> &[Vec2] {
+        &self.points[..self.point_count]
+    }
+
+    /// Gets how the first shape should move to get out of the second.
+    pub fn separation(&self) -> Vec2 {
+        self.normal * self.depth
+    }
+
+    /// Gets the same manifold, seen from the second shape.
+    pub fn flipped(&self) -> Self {
+        Self {
+            normal: -self.normal,
+            ..*self
+        }
+    }
+}
+
+impl ColliderShape {
+    /// Finds how this shape overlaps `other`, if it does. Shapes only
+    /// touching by an edge don't overlap.
+    pub fn contact(&self, other: &ColliderShape) -> Option<Manifold> {
+        contact::contact(self, other)
+    }
+
+    /// Checks whether this shape overlaps `other`.
+    pub fn overlaps(&self, other: &ColliderShape) -> bool {
+        self.bounds().overlaps(&other.bounds()) && self.contact(other).is_some()
+    }
+}
+
+/// The shape an entity collides as, placed by its `Transform`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Collider {
+    /// The shape, relative to the transform.
+    pub shape: ColliderShape,
+    /// The layers the collider is on, one per bit.
+    pub layer: u32,
+    /// The layers of the colliders it stops at, one per bit.
+    pub mask: u32,
+}
+
+impl Collider {
+    /// Creates a box of `w` by `h` pixels, with its top-left corner at the
+    /// translation of the transform. Rotating the transform rotates the box
+    /// around that corner.
+    pub const fn new(w: f32, h: f32) -> Self {
+        Self {
+            shape: ColliderShape::Aabb(Aabb {
+                min: Vec2::ZERO,
+                max: Vec2::new(w, h),
+            }),
+            layer: DEFAULT_LAYER,
+            mask: ALL_LAYERS,
+        }
+    }
+
+    /// Creates a circle whose bounding box has its top-left corner at the
+    /// translation of the transform, matching a circle `Shape`.
+    pub const fn circle(radius: f32) -> Self {
+        Self {
+            shape: ColliderShape::Circle(Circle::new(Vec2::new(radius, radius), radius)),
+            layer: DEFAULT_LAYER,
+            mask: ALL_LAYERS,
+        }
+    }
+
+    /// Creates a collider of any shape.
+    pub fn from_shape(shape: impl Into<ColliderShape>) -> Self {
+        Self {
+            shape: shape.into(),
+            layer: DEFAULT_LAYER,
+            mask: ALL_LAYERS,
+        }
+    }
+
+    /// Puts the collider on the `layer` bits, stopping only at colliders on
+    /// the `mask` ones.
+    ///
+    /// # Example
+    /// ```rust
+    /// const PLAYERS: u32 = 1 << 1;
+    /// const BULLETS: u32 = 1 << 2;
+    ///
+    /// // bullets go through each other, and stop at walls and players
+    /// let bullet = Collider::circle(2.0).on_layers(BULLETS, ALL_LAYERS & !BULLETS);
+    /// ```
+    pub fn on_layers(self, layer: u32, mask: u32) -> Self {
+        Self {
+            layer,
+            mask,
+            ..self
+        }
+    }
+
+    /// Checks whether this collider stops at `other`, by its mask and the
+    /// layers of the other.
+    pub fn collides_with(&self, other: &Collider) -> bool {
+        self.mask & other.layer != 0
+    }
+
+    /// Gets the shape when placed by `transform`.
+    pub fn world_shape(&self, transform: &Transform) -> ColliderShape {
+        self.shape.transformed(transform)
+    }
+
+    /// Gets the smallest rectangle containing the shape when placed by
+    /// `transform`.
+    pub fn rect(&self, transform: &Transform) -> Rect {
+        self.world_shape(transform).bounds().to_rect()
+    }
+
+    /// Checks whether this shape, placed by `transform`, overlaps `other`
+    /// placed by `other_transform`.
+    pub fn overlaps(
+        &self,
+        transform: &Transform,
+        other: &Collider,
+        other_transform: &Transform,
+    ) -> bool {
+        self.world_shape(transform)
+            .overlaps(&other.world_shape(other_transform))
+    }
+
+    /// Finds how this shape, placed by `transform`, overlaps `other` placed
+    /// by `other_transform`, if it does.
+    pub fn contact(
+        &self,
+        transform: &Transform,
+        other: &Collider,
+        other_transform: &Transform,
+    ) -> Option<Manifold> {
+        self.world_shape(transform)
+            .contact(&other.world_shape(other_transform))
+    }
+}
+
+/// Makes `systems::movement` sweep the `Collider` of an entity along its
+/// `Velocity`, stopping it at the first other collider in the way instead
+/// of going through it. For fast things, such as bullets and balls.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ContinuousCollision {
+    /// What stopped the entity during the last fixed step, with the entity
+    /// hit.
+    pub collision: Option<(Entity, Collision)>,
+}
@@ -0,0 +1,259 @@
+//! The shapes things collide as, in virtual resolution pixels.
+
+use crate::core::rect::Rect;
+use crate::core::transform::Transform;
+use glam::Vec2;
+
+/// An axis-aligned box, from its top-left corner to its bottom-right one.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Aabb {
+    /// The top-left corner.
+    pub min: Vec2,
+    /// The bottom-right corner.
+    pub max: Vec2,
+}
+
+impl Aabb {
+    /// Creates a box between two opposite corners, in any order.
+    pub fn new(a: Vec2, b: Vec2) -> Self {
+        Self {
+            min: a.min(b),
+            max: a.max(b),
+        }
+    }
+
+    /// Creates a box from its top-left corner and its size.
+    pub fn from_position_size(position: Vec2, size: Vec2) -> Self {
+        Self::new(position, position + size)
+    }
+
+    /// Gets the middle of the box.
+    pub fn center(&self) -> Vec2 {
+        (self.min + self.max) / 2.0
+    }
+
+    /// Gets the width and height of the box.
+    pub fn size(&self) -> Vec2 {
+        self.max - self.min
+    }
+
+    /// Gets the four corners of the box, clockwise from the top-left one.
+    pub fn corners(&self) -> [Vec2; 4] {
+        [
+            self.min,
+            Vec2::new(self.max.x, self.min.y),
+            self.max,
+            Vec2::new(self.min.x, self.max.y),
+        ]
+    }
+
+    /// Checks whether `point` is inside the box, edges included.
+    pub fn contains_point(&self, point: Vec2) -> bool {
+        point.cmpge(self.min).all() && point.cmple(self.max).all()
+    }
+
+    /// Checks whether the box overlaps `other`. Boxes only touching by an
+    /// edge don't overlap.
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        self.min.cmplt(other.max).all() && self.max.cmpgt(other.min).all()
+    }
+
+    /// Gets the smallest box containing both boxes.
+    pub fn union(&self, other: &Aabb) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    /// Gets the area covered by the box as a `Rect`.
+    pub fn to_rect(&self) -> Rect {
+        Rect::from_position_size(self.min, self.size())
+    }
+}
+
+impl From<Rect> for Aabb {
+    fn from(rect: Rect) -> Self {
+        Self::from_position_size(rect.position(), rect.size())
+    }
+}
+
+/// A circle.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Circle {
+    pub center: Vec2,
+    pub radius: f32,
+}
+
+impl Circle {
+    /// Creates a circle of `radius` around `center`.
+    pub const fn new(center: Vec2, radius: f32) -> Self {
+        Self { center, radius }
+    }
+}
+
+/// A segment grown by a radius, like a pill. Useful for characters, as it
+/// slides over steps and corners.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Capsule {
+    /// The center of one of the rounded ends.
+    pub start: Vec2,
+    /// The center of the other rounded end.
+    pub end: Vec2,
+    pub radius: f32,
+}
+
+impl Capsule {
+    /// Creates a capsule going from `start` to `end`, `radius` thick on
+    /// every side.
+    pub const fn new(start: Vec2, end: Vec2, radius: f32) -> Self {
+        Self { start, end, radius }
+    }
+}
+
+/// A convex polygon.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Polygon {
+    /// The corners, clockwise on screen and without repetitions.
+    points: Vec<Vec2>,
+}
+
+impl Polygon {
+    /// Creates the smallest convex polygon containing all the `points`, so
+    /// their order doesn't matter and the ones inside are dropped.
+    ///
+    /// # Example
+    /// ```rust
+    /// let triangle = Polygon::new([
+    ///     Vec2::new(0.0, 32.0),
+    ///     Vec2::new(16.0, 0.0),
+    ///     Vec2::new(32.0, 32.0),
+    /// ]);
+    /// ```
+    pub fn new(points: impl IntoIterator<Item = Vec2>) -> Self {
+        let mut points: Vec<Vec2> = points.into_iter().collect();
+        points.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+        points.dedup();
+        if points.len() < 3 {
+            return Self { points };
+        }
+
+        // Andrew's monotone chain, keeping the lower hull then the upper one
+        let turns_left = |hull: &[Vec2], point: Vec2| {
+            let (a, b) = (hull[hull.len() - 2], hull[hull.len() - 1]);
+            (b - a).perp_dot(point - a) > 0.0
+        };
+        let mut hull: Vec<Vec2> = Vec::with_capacity(points.len() + 1);
+        for &point in &points {
+            while hull.len() >= 2 && !turns_left(&hull, point) {
+                hull.pop();
+            }
+            hull.push(point);
+        }
+        let lower_len = hull.len() + 1;
+        for &point in points.iter().rev().skip(1) {
+            while hull.len() >= lower_len && !turns_left(&hull, point) {
+                hull.pop();
+            }
+            hull.push(point);
+        }
+        hull.pop();
+
+        Self { points: hull }
+    }
+
+    /// Gets the corners of the polygon.
+    pub fn points(&self) -> &[Vec2] {
+        &self.points
+    }
+}
+
+/// Any of the shapes things can collide as.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColliderShape {
+    Aabb(Aabb),
+    Circle(Circle),
+    Capsule(Capsule),
+    Polygon(Polygon),
+}
+
+impl ColliderShape {
+    /// Gets the shape placed by `transform`. Boxes that end up rotated
+    /// become polygons.
+    pub fn transformed(&self, transform: &Transform) -> Self {
+        let scale = transform.scale.abs();
+
+        match self {
+            Self::Aabb(aabb) if transform.rotation == 0.0 => Self::Aabb(Aabb::new(
+                transform.transform_point(aabb.min),
+                transform.transform_point(aabb.max),
+            )),
+            Self::Aabb(aabb) => Self::Polygon(Polygon::new(
+                aabb.corners()
+                    .into_iter()
+                    .map(|corner| transform.transform_point(corner)),
+            )),
+            Self::Circle(circle) => Self::Circle(Circle::new(
+                transform.transform_point(circle.center),
+                circle.radius * scale,
+            )),
+            Self::Capsule(capsule) => Self::Capsule(Capsule::new(
+                transform.transform_point(capsule.start),
+                transform.transform_point(capsule.end),
+                capsule.radius * scale,
+            )),
+            Self::Polygon(polygon) => Self::Polygon(Polygon::new(
+                polygon
+                    .points
+                    .iter()
+                    .map(|&point| transform.transform_point(point)),
+            )),
+        }
+    }
+
+    /// Gets the smallest axis-aligned box containing the shape.
+    pub fn bounds(&self) -> Aabb {
+        match self {
+            Self::Aabb(aabb) => *aabb,
+            Self::Circle(circle) => {
+                Aabb::new(circle.center - circle.radius, circle.center + circle.radius)
+            }
+            Self::Capsule(capsule) => Aabb::new(
+                capsule.start.min(capsule.end) - capsule.radius,
+                capsule.start.max(capsule.end) + capsule.radius,
+            ),
+            Self::Polygon(polygon) => {
+                let mut points = polygon.points.iter();
+                let first = points.next().copied().unwrap_or_default();
+                points.fold(Aabb::new(first, first), |bounds, &point| Aabb {
+                    min: bounds.min.min(point),
+                    max: bounds.max.max(point),
+                })
+            }
+        }
+    }
+}
+
+impl From<Aabb> for ColliderShape {
+    fn from(aabb: Aabb) -> Self {
+        Self::Aabb(aabb)
+    }
+}
+
+impl From<Circle> for ColliderShape {
+    fn from(circle: Circle) -> Self {
+        Self::Circle(circle)
+    }
+}
+
+impl From<Capsule> for ColliderShape {
+    fn from(capsule: Capsule) -> Self {
+        Self::Capsule(capsule)
+    }
+}
+
+impl From<Polygon> for ColliderShape {
+    fn from(polygon: Polygon) -> Self {
+        Self::Polygon(polygon)
+    }
+}
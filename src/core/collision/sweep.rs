@@ -0,0 +1,643 @@
+//! Moving shapes without going through what's in the way, however fast
+//! they go.
+
+use super::contact;
+use super::shapes::{Aabb, Circle, ColliderShape};
+use crate::core::transform::Transform;
+use glam::Vec2;
+
+/// Distances below this, in pixels, count as touching.
+const TOUCH_DISTANCE: f32 = 1e-3;
+
+/// The most steps taken towards the time of impact, for shapes without an
+/// exact sweep.
+const MAX_ADVANCES: usize = 64;
+
+/// The most times `move_and_slide` changes direction in a single move.
+pub const MAX_SLIDES: usize = 4;
+
+/// Where a moving shape first touches another.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hit {
+    /// How much of the motion is done when touching, from 0.0 to 1.0.
+    pub time: f32,
+    /// The side of the other shape that is touched, pointing out of it.
+    pub normal: Vec2,
+}
+
+/// What stopped a move.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Collision {
+    /// The obstacle hit, as its index in the obstacles given.
+    pub index: usize,
+    /// The side of the obstacle that is touched, pointing out of it.
+    pub normal: Vec2,
+    /// The part of the motion done before touching the obstacle.
+    pub travel: Vec2,
+    /// The part of the motion left.
+    pub remainder: Vec2,
+}
+
+/// The result of `move_and_slide`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Slide {
+    /// How far the shape moved in total.
+    pub travel: Vec2,
+    /// Everything the shape slid against, in order.
+    pub collisions: Vec<Collision>,
+}
+
+impl ColliderShape {
+    /// Finds when this shape, moving by `motion`, first touches `other`.
+    /// Shapes overlapping from the start aren't hit, so they can move
+    /// apart, and `contact` should be used to push them out instead.
+    ///
+    /// Boxes and circles moving against boxes, and circles against circles,
+    /// are swept exactly. Other shapes are moved in steps small enough not
+    /// to go through anything.
+    pub fn sweep(&self, motion: Vec2, other: &ColliderShape) -> Option<Hit> {
+        if motion == Vec2::ZERO {
+            return None;
+        }
+
+        match (self, other) {
+            (Self::Aabb(aabb), Self::Aabb(other)) => sweep_aabb(aabb, motion, other),
+            (Self::Circle(circle), Self::Aabb(aabb)) => sweep_circle(circle, motion, aabb),
+            (Self::Aabb(aabb), Self::Circle(circle)) => {
+                let hit = sweep_circle(circle, -motion, aabb)?;
+                Some(Hit {
+                    normal: -hit.normal,
+                    ..hit
+                })
+            }
+            (Self::Circle(circle), Self::Circle(other)) => {
+                let time = ray_circle(
+                    circle.center,
+                    motion,
+                    other.center,
+                    circle.radius + other.radius,
+                )?;
+                let normal = (circle.center + motion * time - other.center).normalize_or_zero();
+                Some(Hit { time, normal })
+            }
+            _ => self.sweep_in_steps(motion, other),
+        }
+    }
+
+    /// Sweeps any pair of shapes by conservative advancement: moving this
+    /// one until the gap left between them would close, if they kept
+    /// getting closer as fast as now. The gap between convex shapes closes
+    /// slower and slower, so this never goes through anything.
+    fn sweep_in_steps(&self, motion: Vec2, other: &ColliderShape) -> Option<Hit> {
+        let mut time = 0.0;
+
+        for _ in 0..MAX_ADVANCES {
+            let moved = self.transformed(&Transform::from_translation(motion * time));
+            let (closest, other_closest) = contact::closest(&moved, other)?;
+
+            let gap = other_closest - closest;
+            let distance = gap.length();
+            if distance <= TOUCH_DISTANCE {
+                let normal = touching_normal(&moved, other, gap);
+                return (motion.dot(normal) < 0.0).then_some(Hit { time, normal });
+            }
+
+            let closing = motion.dot(gap) / distance;
+            if closing <= 0.0 {
+                return None;
+            }
+            time += (distance - TOUCH_DISTANCE / 2.0) / closing;
+            if time > 1.0 {
+                return None;
+            }
+        }
+
+        None
+    }
+}
+
+/// Gets the side of `other` that `shape` touches, pointing out of it, with
+/// `gap` going from one to the other. The closest points are too close to
+/// give a precise direction, and sliding along an imprecise side goes into
+/// it, so the shape is pressed in a little for the normal of the contact.
+fn touching_normal(shape: &ColliderShape, other: &ColliderShape, gap: Vec2) -> Vec2 {
+    let direction = gap.normalize_or_zero();
+    let pressed = shape.transformed(&Transform::from_translation(direction * TOUCH_DISTANCE));
+
+    contact::contact(&pressed, other).map_or(-direction, |manifold| manifold.normal)
+}
+
+/// Moves `shape` by `motion`, stopping at the first of the `obstacles` in
+/// the way. Gets what stopped it, if anything did.
+///
+/// # Example
+/// ```rust
+/// match move_and_collide(&bullet, velocity * dt, &walls) {
+///     Some(collision) => println!("Hit wall {}", collision.index),
+///     None => position += velocity * dt,
+/// }
+/// ```
+pub fn move_and_collide(
+    shape: &ColliderShape,
+    motion: Vec2,
+    obstacles: &[ColliderShape],
+) -> Option<Collision> {
+    let (index, hit) = obstacles
+        .iter()
+        .enumerate()
+        .filter_map(|(index, obstacle)| Some((index, shape.sweep(motion, obstacle)?)))
+        .min_by(|(_, a), (_, b)| a.time.total_cmp(&b.time))?;
+
+    Some(Collision {
+        index,
+        normal: hit.normal,
+        travel: motion * hit.time,
+        remainder: motion * (1.0 - hit.time),
+    })
+}
+
+/// Moves `shape` by `motion`, sliding along the `obstacles` in the way
+/// instead of stopping, up to `MAX_SLIDES` times.
+///
+/// # Example
+/// ```rust
+/// let slide = move_and_slide(&player, velocity * dt, &walls);
+/// position += slide.travel;
+/// if slide.collisions.iter().any(|collision| collision.normal.y < 0.0) {
+///     println!("Standing on something");
+/// }
+/// ```
+pub fn move_and_slide(shape: &ColliderShape, motion: Vec2, obstacles: &[ColliderShape]) -> Slide {
+    let mut slide = Slide::default();
+    let mut motion = motion;
+
+    for _ in 0..MAX_SLIDES {
+        let moved = shape.transformed(&Transform::from_translation(slide.travel));
+        let collision = match move_and_collide(&moved, motion, obstacles) {
+            Some(collision) => collision,
+            None => {
+                slide.travel += motion;
+                break;
+            }
+        };
+
+        slide.travel += collision.travel;
+        slide.collisions.push(collision);
+
+        // keep going along the obstacle, without the part going into it
+        motion = collision.remainder
+            - collision.normal * collision.remainder.dot(collision.normal).min(0.0);
+        if motion.length_squared() <= TOUCH_DISTANCE * TOUCH_DISTANCE {
+            break;
+        }
+    }
+
+    slide
+}
+
+/// Sweeps a box moving by `motion` against another.
+pub fn sweep_aabb(aabb: &Aabb, motion: Vec2, other: &Aabb) -> Option<Hit> {
+    // the same as a ray from the middle of the box, against the other box
+    // grown by its half size
+    let half_size = aabb.size() / 2.0;
+    let grown = Aabb {
+        min: other.min - half_size,
+        max: other.max + half_size,
+    };
+    let (enter, exit, normal) = slabs(aabb.center(), motion, &grown)?;
+
+    hit_between(enter, exit, normal, motion)
+}
+
+/// Sweeps a circle moving by `motion` against a box.
+pub fn sweep_circle(circle: &Circle, motion: Vec2, aabb: &Aabb) -> Option<Hit> {
+    // the same as a ray from the center, against the box grown by the
+    // radius with rounded corners
+    let grown = Aabb {
+        min: aabb.min - circle.radius,
+        max: aabb.max + circle.radius,
+    };
+    let (enter, exit, normal) = slabs(circle.center, motion, &grown)?;
+    if exit < 0.0 || enter > 1.0 {
+        return None;
+    }
+
+    let entry = circle.center + motion * enter.max(0.0);
+    let outside_x = entry.x < aabb.min.x || entry.x > aabb.max.x;
+    let outside_y = entry.y < aabb.min.y || entry.y > aabb.max.y;
+    if !(outside_x && outside_y) {
+        return hit_between(enter, exit, normal, motion);
+    }
+
+    let corner = Vec2::new(
+        if entry.x < aabb.min.x {
+            aabb.min.x
+        } else {
+            aabb.max.x
+        },
+        if entry.y < aabb.min.y {
+            aabb.min.y
+        } else {
+            aabb.max.y
+        },
+    );
+    let time = ray_circle(circle.center, motion, corner, circle.radius)?;
+    let normal = (circle.center + motion * time - corner).normalize_or_zero();
+
+    Some(Hit { time, normal })
+}
+
+/// Intersects a ray from `origin` along `motion` with `aabb`, one axis at a
+/// time. Gets when it enters and exits the box, as fractions of `motion`,
+/// and the side it enters by. Rays beside the box, or only touching it,
+/// miss it, so shapes slide along boxes lined up without catching on them.
+fn slabs(origin: Vec2, motion: Vec2, aabb: &Aabb) -> Option<(f32, f32, Vec2)> {
+    let mut enter = f32::NEG_INFINITY;
+    let mut exit = f32::INFINITY;
+    let mut normal = Vec2::ZERO;
+
+    for (axis, side) in [(0, Vec2::X), (1, Vec2::Y)] {
+        if motion[axis] == 0.0 {
+            if origin[axis] <= aabb.min[axis] + TOUCH_DISTANCE
+                || origin[axis] >= aabb.max[axis] - TOUCH_DISTANCE
+            {
+                return None;
+            }
+            continue;
+        }
+
+        let first = (aabb.min[axis] - origin[axis]) / motion[axis];
+        let second = (aabb.max[axis] - origin[axis]) / motion[axis];
+        let (near, far) = (first.min(second), first.max(second));
+        if near > enter {
+            enter = near;
+            normal = side * -motion[axis].signum();
+        }
+        exit = exit.min(far);
+    }
+
+    (enter <= exit).then_some((enter, exit, normal))
+}
+
+/// Turns the entry and exit times of a ray into a hit. Rays starting
+/// inside only hit when barely in, and moving further in.
+fn hit_between(enter: f32, exit: f32, normal: Vec2, motion: Vec2) -> Option<Hit> {
+    if exit < 0.0 || enter > 1.0 {
+        return None;
+    }
+    if enter < 0.0 && -enter * motion.length() > TOUCH_DISTANCE {
+        return None;
+    }
+
+    Some(Hit {
+        time: enter.max(0.0),
+        normal,
+    })
+}
+
+/// Intersects a ray from `origin` along `motion` with a circle. Rays
+/// starting inside only hit when barely in, and moving further in.
+fn ray_circle(origin: Vec2, motion: Vec2, center: Vec2, radius: f32) -> Option<f32> {
+    let offset = origin - center;
+    let (a, b) = (motion.length_squared(), offset.dot(motion));
+    let c = offset.length_squared() - radius * radius;
+    if a == 0.0 || b >= 0.0 {
+        // still or moving away
+        return None;
+    }
+
+    let discriminant = b * b - a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    let time = (-b - discriminant.sqrt()) / a;
+    if time > 1.0 || (time < 0.0 && offset.length() < radius - TOUCH_DISTANCE) {
+        return None;
+    }
+
+    Some(time.max(0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::shapes::{Capsule, Polygon};
+    use super::*;
+    use proptest::prelude::*;
+
+    /// How far off, in pixels or fractions of a motion, results may be.
+    const TOLERANCE: f32 = 1e-2;
+
+    fn point() -> impl Strategy<Value = Vec2> {
+        (-40.0f32..40.0, -40.0f32..40.0).prop_map(|(x, y)| Vec2::new(x, y))
+    }
+
+    fn aabb() -> impl Strategy<Value = ColliderShape> {
+        (point(), 1.0f32..30.0, 1.0f32..30.0)
+            .prop_map(|(min, w, h)| ColliderShape::from(Aabb::new(min, min + Vec2::new(w, h))))
+    }
+
+    fn circle() -> impl Strategy<Value = ColliderShape> {
+        (point(), 1.0f32..15.0)
+            .prop_map(|(center, radius)| ColliderShape::from(Circle::new(center, radius)))
+    }
+
+    fn capsule() -> impl Strategy<Value = ColliderShape> {
+        (point(), -15.0f32..15.0, -15.0f32..15.0, 1.0f32..8.0).prop_map(|(start, x, y, radius)| {
+            ColliderShape::from(Capsule::new(start, start + Vec2::new(x, y), radius))
+        })
+    }
+
+    fn polygon() -> impl Strategy<Value = ColliderShape> {
+        let corner = (4.0f32..15.0, 0.0f32..std::f32::consts::TAU)
+            .prop_map(|(distance, angle)| Vec2::from_angle(angle) * distance);
+
+        (point(), prop::collection::vec(corner, 3..8))
+            .prop_map(|(center, corners)| {
+                Polygon::new(corners.into_iter().map(|corner| center + corner))
+            })
+            .prop_filter("the corners are in a line", |polygon| {
+                polygon.points().len() >= 3
+            })
+            .prop_map(ColliderShape::from)
+    }
+
+    fn shape() -> impl Strategy<Value = ColliderShape> {
+        prop_oneof![aabb(), circle(), capsule(), polygon()]
+    }
+
+    /// Motions of up to a few times the size of the shapes, to go through
+    /// them if anything was wrong.
+    fn motion() -> impl Strategy<Value = Vec2> {
+        (-150.0f32..150.0, -150.0f32..150.0).prop_map(|(x, y)| Vec2::new(x, y))
+    }
+
+    fn moved(shape: &ColliderShape, offset: Vec2) -> ColliderShape {
+        shape.transformed(&Transform::from_translation(offset))
+    }
+
+    /// Gets how much `shape` overlaps the most overlapped of `obstacles`.
+    fn overlap(shape: &ColliderShape, obstacles: &[ColliderShape]) -> f32 {
+        obstacles
+            .iter()
+            .filter_map(|obstacle| contact::contact(shape, obstacle))
+            .map(|manifold| manifold.depth)
+            .fold(0.0, f32::max)
+    }
+
+    fn aabb_at(min: (f32, f32), max: (f32, f32)) -> ColliderShape {
+        Aabb::new(Vec2::new(min.0, min.1), Vec2::new(max.0, max.1)).into()
+    }
+
+    fn assert_hit(hit: Option<Hit>, time: f32, normal: Vec2) {
+        let hit = hit.expect("nothing was hit");
+
+        assert!((hit.time - time).abs() < TOLERANCE / 10.0, "{hit:?}");
+        assert!(hit.normal.abs_diff_eq(normal, 1e-4), "{hit:?}");
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(2000))]
+
+        #[test]
+        fn never_moves_into_an_obstacle(
+            shape in shape(),
+            obstacles in prop::collection::vec(shape(), 1..4),
+            motion in motion(),
+        ) {
+            prop_assume!(overlap(&shape, &obstacles) == 0.0);
+
+            let travel = match move_and_collide(&shape, motion, &obstacles) {
+                Some(collision) => {
+                    prop_assert!((collision.travel + collision.remainder).abs_diff_eq(motion, TOLERANCE));
+                    prop_assert!(collision.normal.dot(motion) <= 0.0, "{:?}", collision);
+                    collision.travel
+                }
+                None => motion,
+            };
+
+            let left = overlap(&moved(&shape, travel), &obstacles);
+            prop_assert!(left < TOLERANCE, "moved {} into an obstacle by {}", travel, left);
+        }
+
+        #[test]
+        fn never_slides_into_an_obstacle(
+            shape in shape(),
+            obstacles in prop::collection::vec(shape(), 1..4),
+            motion in motion(),
+        ) {
+            prop_assume!(overlap(&shape, &obstacles) == 0.0);
+
+            let slide = move_and_slide(&shape, motion, &obstacles);
+
+            prop_assert!(slide.collisions.len() <= MAX_SLIDES);
+            let left = overlap(&moved(&shape, slide.travel), &obstacles);
+            prop_assert!(left < TOLERANCE, "slid {} into an obstacle by {}", slide.travel, left);
+        }
+    }
+
+    #[test]
+    fn sweeps_boxes_against_boxes() {
+        let aabb = Aabb::new(Vec2::ZERO, Vec2::splat(10.0));
+        let wall = Aabb::new(Vec2::new(50.0, -5.0), Vec2::new(60.0, 15.0));
+
+        assert_hit(
+            sweep_aabb(&aabb, Vec2::new(100.0, 0.0), &wall),
+            0.4,
+            Vec2::new(-1.0, 0.0),
+        );
+        // from below, entering by the bottom side
+        let below = Aabb::new(Vec2::new(52.0, 40.0), Vec2::new(56.0, 44.0));
+        assert_hit(
+            sweep_aabb(&below, Vec2::new(0.0, -50.0), &wall),
+            0.5,
+            Vec2::new(0.0, 1.0),
+        );
+        // too short, and going away
+        assert_eq!(sweep_aabb(&aabb, Vec2::new(30.0, 0.0), &wall), None);
+        assert_eq!(sweep_aabb(&aabb, Vec2::new(-100.0, 0.0), &wall), None);
+    }
+
+    #[test]
+    fn slides_along_boxes_only_touched() {
+        let floor = [
+            aabb_at((-100.0, 20.0), (20.0, 30.0)),
+            aabb_at((20.0, 20.0), (200.0, 30.0)),
+        ];
+        let standing = ColliderShape::from(Aabb::new(Vec2::new(0.0, 10.0), Vec2::new(10.0, 20.0)));
+        let rolling = ColliderShape::from(Circle::new(Vec2::new(5.0, 15.0), 5.0));
+
+        // along the top of the floor, over the seam between both boxes
+        for shape in [&standing, &rolling] {
+            assert_eq!(move_and_collide(shape, Vec2::new(100.0, 0.0), &floor), None);
+        }
+        // pressing into it is stopped straight away
+        let pressing = move_and_collide(&standing, Vec2::new(100.0, 1.0), &floor).unwrap();
+        assert_eq!(pressing.travel, Vec2::ZERO);
+        assert_eq!(pressing.normal, Vec2::new(0.0, -1.0));
+    }
+
+    #[test]
+    fn sweeps_circles_against_the_sides_of_boxes() {
+        let circle = Circle::new(Vec2::ZERO, 5.0);
+        let wall = Aabb::new(Vec2::new(50.0, -10.0), Vec2::new(60.0, 10.0));
+
+        assert_hit(
+            sweep_circle(&circle, Vec2::new(100.0, 0.0), &wall),
+            0.45,
+            Vec2::new(-1.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn sweeps_circles_against_the_rounded_corners_of_boxes() {
+        let circle = Circle::new(Vec2::ZERO, 5.0);
+        let block = Aabb::new(Vec2::splat(50.0), Vec2::splat(60.0));
+
+        // straight at the corner, touched a radius before the center gets there
+        let time = 0.5 - 5.0 / (100.0 * 2f32.sqrt());
+        assert_hit(
+            sweep_circle(&circle, Vec2::splat(100.0), &block),
+            time,
+            -Vec2::ONE.normalize(),
+        );
+
+        // through the corner of the grown box, but further than a radius
+        // from the corner itself
+        let passing = Circle::new(Vec2::new(0.0, 92.0), 5.0);
+        assert_eq!(
+            sweep_circle(&passing, Vec2::new(100.0, -100.0), &block),
+            None
+        );
+    }
+
+    #[test]
+    fn sweeps_circles_against_circles() {
+        let a = ColliderShape::from(Circle::new(Vec2::ZERO, 5.0));
+        let b = ColliderShape::from(Circle::new(Vec2::new(50.0, 0.0), 5.0));
+
+        assert_hit(
+            a.sweep(Vec2::new(100.0, 0.0), &b),
+            0.4,
+            Vec2::new(-1.0, 0.0),
+        );
+        // by the side, missing it
+        let beside = ColliderShape::from(Circle::new(Vec2::new(50.0, 10.5), 5.0));
+        assert_eq!(a.sweep(Vec2::new(100.0, 0.0), &beside), None);
+    }
+
+    #[test]
+    fn sweeps_other_shapes_in_steps() {
+        let capsule =
+            ColliderShape::from(Capsule::new(Vec2::new(0.0, -5.0), Vec2::new(0.0, 5.0), 5.0));
+        let polygon = ColliderShape::from(Polygon::new([
+            Vec2::new(50.0, -20.0),
+            Vec2::new(60.0, 0.0),
+            Vec2::new(50.0, 20.0),
+        ]));
+
+        // stopped just before touching
+        let hit = capsule.sweep(Vec2::new(100.0, 0.0), &polygon).unwrap();
+        assert!(hit.time <= 0.45 && hit.time > 0.45 - TOLERANCE, "{hit:?}");
+        assert!(
+            hit.normal.abs_diff_eq(Vec2::new(-1.0, 0.0), 1e-3),
+            "{hit:?}"
+        );
+
+        // going away, and starting overlapped
+        assert_eq!(capsule.sweep(Vec2::new(-100.0, 0.0), &polygon), None);
+        let inside = moved(&capsule, Vec2::new(52.0, 0.0));
+        assert_eq!(inside.sweep(Vec2::new(100.0, 0.0), &polygon), None);
+    }
+
+    #[test]
+    fn stops_fast_balls_at_thin_paddles() {
+        let paddle = [aabb_at((100.0, -30.0), (116.0, 30.0))];
+        let ball = ColliderShape::from(Circle::new(Vec2::ZERO, 4.0));
+        let square = ColliderShape::from(Aabb::new(Vec2::splat(-4.0), Vec2::splat(4.0)));
+
+        // way past the paddle in a single step
+        for ball in [ball, square] {
+            let collision = move_and_collide(&ball, Vec2::new(1000.0, 0.0), &paddle).unwrap();
+
+            assert_eq!(collision.index, 0);
+            assert!(
+                (collision.travel.x - 96.0).abs() < TOLERANCE,
+                "{collision:?}"
+            );
+            assert_eq!(collision.normal, Vec2::new(-1.0, 0.0));
+            assert!(collision
+                .remainder
+                .abs_diff_eq(Vec2::new(904.0, 0.0), TOLERANCE));
+        }
+    }
+
+    #[test]
+    fn collides_with_the_nearest_obstacle() {
+        let shape = ColliderShape::from(Circle::new(Vec2::ZERO, 5.0));
+        let obstacles = [
+            aabb_at((80.0, -10.0), (90.0, 10.0)),
+            aabb_at((40.0, -10.0), (50.0, 10.0)),
+            aabb_at((60.0, -10.0), (70.0, 10.0)),
+        ];
+
+        let collision = move_and_collide(&shape, Vec2::new(100.0, 0.0), &obstacles).unwrap();
+
+        assert_eq!(collision.index, 1);
+        assert!(collision.travel.abs_diff_eq(Vec2::new(35.0, 0.0), 1e-4));
+    }
+
+    #[test]
+    fn slides_along_what_it_hits() {
+        let shape = ColliderShape::from(Aabb::new(Vec2::ZERO, Vec2::splat(10.0)));
+        let floor = aabb_at((-100.0, 20.0), (200.0, 30.0));
+        let wall = aabb_at((30.0, -100.0), (40.0, 20.0));
+        let obstacles = [floor, wall];
+
+        let slide = move_and_slide(&shape, Vec2::new(50.0, 50.0), &obstacles[..1]);
+
+        // down to the floor, then along it for the rest of the way
+        assert!(
+            slide.travel.abs_diff_eq(Vec2::new(50.0, 10.0), 1e-4),
+            "{slide:?}"
+        );
+        assert_eq!(slide.collisions.len(), 1);
+        assert_eq!(slide.collisions[0].normal, Vec2::new(0.0, -1.0));
+
+        // into a corner, stopping at the wall
+        let slide = move_and_slide(&shape, Vec2::new(50.0, 50.0), &obstacles);
+
+        assert!(
+            slide.travel.abs_diff_eq(Vec2::new(20.0, 10.0), 1e-4),
+            "{slide:?}"
+        );
+        let normals: Vec<Vec2> = slide
+            .collisions
+            .iter()
+            .map(|collision| collision.normal)
+            .collect();
+        assert_eq!(normals, vec![Vec2::new(0.0, -1.0), Vec2::new(-1.0, 0.0)]);
+    }
+
+    #[test]
+    fn slides_along_sloped_sides_without_going_in() {
+        // a thin box sliding up the side of a triangle, found by
+        // `never_slides_into_an_obstacle`
+        let shape = aabb_at((-11.080375, 35.91238), (7.318469, 36.91238));
+        let triangle = [ColliderShape::from(Polygon::new([
+            Vec2::new(-1.386302, 12.364762),
+            Vec2::new(16.175194, 8.054254),
+            Vec2::new(15.865923, 21.569065),
+        ]))];
+
+        let slide = move_and_slide(&shape, Vec2::new(56.478573, -134.97015), &triangle);
+
+        let side = Vec2::new(15.865923, 21.569065) - Vec2::new(-1.386302, 12.364762);
+        let normal = slide.collisions[0].normal;
+        assert!(
+            normal.dot(side).abs() < 1e-4,
+            "{normal} isn't the side's normal"
+        );
+        assert!(overlap(&moved(&shape, slide.travel), &triangle) < TOLERANCE);
+    }
+}
@@ -0,0 +1,369 @@
+//! Solid geometry laid out on a grid.
+
+use super::shapes::{Aabb, ColliderShape, Polygon};
+use glam::Vec2;
+
+/// What a cell of a `TileMap` is made of.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Tile {
+    /// Nothing, things go through it.
+    #[default]
+    Empty,
+    /// A wall, blocking from every side.
+    Solid,
+    /// A platform only blocking things falling on its top, which can be
+    /// jumped through from below and dropped through from above.
+    OneWay,
+    /// A ladder, which can be climbed. The top of the highest one can be
+    /// stood on.
+    Ladder,
+    /// A floor going up or down. It's only walked on, and blocks as a wall
+    /// from below.
+    Slope {
+        /// The height of the floor at the left edge, from 0.0 at the bottom
+        /// of the tile to 1.0 at its top.
+        left: f32,
+        /// The height of the floor at the right edge.
+        right: f32,
+    },
+}
+
+impl Tile {
+    /// A slope going up to the right, at 45 degrees.
+    pub const SLOPE_UP_RIGHT: Tile = Tile::Slope {
+        left: 0.0,
+        right: 1.0,
+    };
+
+    /// A slope going up to the left, at 45 degrees.
+    pub const SLOPE_UP_LEFT: Tile = Tile::Slope {
+        left: 1.0,
+        right: 0.0,
+    };
+
+    /// Checks whether the tile blocks from every side.
+    pub const fn is_solid(&self) -> bool {
+        matches!(self, Tile::Solid)
+    }
+}
+
+/// A grid of tiles things collide with, in virtual resolution pixels.
+///
+/// # Example
+/// ```rust
+/// let level = TileMap::from_rows(
+///     &[
+///         "..........",
+///         "....--H...",
+///         "......H./#",
+///         "##########",
+///     ],
+///     16.0,
+/// );
+/// world.insert_resource(level);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileMap {
+    /// Where the top-left corner of the map is.
+    pub position: Vec2,
+    /// The width and height of every tile.
+    pub tile_size: f32,
+    /// The amount of columns.
+    width: usize,
+    /// The amount of rows.
+    height: usize,
+    /// The tiles, row after row from the top.
+    tiles: Vec<Tile>,
+}
+
+impl TileMap {
+    /// Creates an empty map of `width` by `height` tiles, at the origin.
+    pub fn new(width: usize, height: usize, tile_size: f32) -> Self {
+        Self {
+            position: Vec2::ZERO,
+            tile_size,
+            width,
+            height,
+            tiles: vec![Tile::Empty; width * height],
+        }
+    }
+
+    /// Creates a map out of rows of characters, from the top. `#` is solid,
+    /// `-` one-way, `H` a ladder, `/` and `\` slopes going up to the right
+    /// and to the left, and anything else empty.
+    pub fn from_rows(rows: &[&str], tile_size: f32) -> Self {
+        let width = rows
+            .iter()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or(0);
+        let mut tile_map = Self::new(width, rows.len(), tile_size);
+
+        for (row, characters) in rows.iter().enumerate() {
+            for (column, character) in characters.chars().enumerate() {
+                let tile = match character {
+                    '#' => Tile::Solid,
+                    '-' => Tile::OneWay,
+                    'H' => Tile::Ladder,
+                    '/' => Tile::SLOPE_UP_RIGHT,
+                    '\\' => Tile::SLOPE_UP_LEFT,
+                    _ => Tile::Empty,
+                };
+                tile_map.set(column as i32, row as i32, tile);
+            }
+        }
+
+        tile_map
+    }
+
+    /// Gets the amount of columns.
+    pub const fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Gets the amount of rows.
+    pub const fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Gets the tile at `column` and `row`. Tiles outside the map are empty.
+    pub fn get(&self, column: i32, row: i32) -> Tile {
+        self.index(column, row)
+            .map_or(Tile::Empty, |index| self.tiles[index])
+    }
+
+    /// Sets the tile at `column` and `row`. Tiles outside the map are
+    /// ignored.
+    pub fn set(&mut self, column: i32, row: i32, tile: Tile) {
+        if let Some(index) = self.index(column, row) {
+            self.tiles[index] = tile;
+        }
+    }
+
+    /// Gets the column containing `x`.
+    pub fn column_at(&self, x: f32) -> i32 {
+        ((x - self.position.x) / self.tile_size).floor() as i32
+    }
+
+    /// Gets the row containing `y`.
+    pub fn row_at(&self, y: f32) -> i32 {
+        ((y - self.position.y) / self.tile_size).floor() as i32
+    }
+
+    /// Gets the area covered by the tile at `column` and `row`.
+    pub fn tile_bounds(&self, column: i32, row: i32) -> Aabb {
+        let position = self.position + Vec2::new(column as f32, row as f32) * self.tile_size;
+        Aabb::from_position_size(position, Vec2::splat(self.tile_size))
+    }
+
+    /// Gets where the floor of the slope at `column` and `row` is, at `x`.
+    /// Tiles other than slopes have their floor at their top.
+    pub fn floor_at(&self, column: i32, row: i32, x: f32) -> f32 {
+        let bounds = self.tile_bounds(column, row);
+
+        match self.get(column, row) {
+            Tile::Slope { left, right } => {
+                let t = ((x - bounds.min.x) / self.tile_size).clamp(0.0, 1.0);
+                bounds.max.y - (left + (right - left) * t) * self.tile_size
+            }
+            _ => bounds.min.y,
+        }
+    }
+
+    /// Gets the shape of the tile at `column` and `row`, if it blocks from
+    /// every side: a box for solid tiles and a polygon under the floor of
+    /// slopes. One-way platforms and ladders have none.
+    pub fn tile_shape(&self, column: i32, row: i32) -> Option<ColliderShape> {
+        let bounds = self.tile_bounds(column, row);
+
+        match self.get(column, row) {
+            Tile::Solid => Some(bounds.into()),
+            Tile::Slope { left, right } => Some(
+                Polygon::new([
+                    Vec2::new(bounds.min.x, bounds.max.y),
+                    bounds.max,
+                    Vec2::new(bounds.max.x, bounds.max.y - right * self.tile_size),
+                    Vec2::new(bounds.min.x, bounds.max.y - left * self.tile_size),
+                ])
+                .into(),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Gets every tile of the map overlapping `area`, with their column and
+    /// row, empty ones included. Tiles only touching it by an edge, and
+    /// tiles outside the map, are left out.
+    pub fn tiles_in(&self, area: &Aabb) -> impl Iterator<Item = (i32, i32, Tile)> + '_ {
+        let start = ((area.min - self.position) / self.tile_size).floor();
+        let end = ((area.max - self.position) / self.tile_size).ceil() - 1.0;
+        // clamped before being cast, so huge areas stay inside the map
+        let last_tile = Vec2::new(self.width as f32, self.height as f32) - 1.0;
+        let first = start.max(Vec2::ZERO);
+        let last = end.max(start).min(last_tile);
+        let (first_column, first_row) = (first.x as i32, first.y as i32);
+        let (last_column, last_row) = (last.x as i32, last.y as i32);
+
+        (first_row..=last_row).flat_map(move |row| {
+            (first_column..=last_column).map(move |column| (column, row, self.get(column, row)))
+        })
+    }
+
+    /// Gets every tile of the map a line from `origin` going by `motion`
+    /// touches, in the order it reaches them, with their column and row.
+    /// Tiles only touched by an edge or a corner are included.
+    ///
+    /// Unlike `tiles_in`, only the tiles along the line are walked, however
+    /// long and steep it is.
+    pub fn tiles_along(
+        &self,
+        origin: Vec2,
+        motion: Vec2,
+    ) -> impl Iterator<Item = (i32, i32, Tile)> + '_ {
+        // in tiles, from the top-left corner of the map
+        let size = Vec2::new(self.width as f32, self.height as f32);
+        let origin = (origin - self.position) / self.tile_size;
+        let motion = motion / self.tile_size;
+
+        // only the part of the line over the map is walked
+        let (mut enter, mut exit) = (0.0f32, 1.0f32);
+        for axis in 0..2 {
+            if motion[axis] == 0.0 {
+                if !(0.0..=size[axis]).contains(&origin[axis]) {
+                    exit = f32::NEG_INFINITY;
+                }
+                continue;
+            }
+
+            let first = -origin[axis] / motion[axis];
+            let second = (size[axis] - origin[axis]) / motion[axis];
+            enter = enter.max(first.min(second));
+            exit = exit.min(first.max(second));
+        }
+        let start = (origin + motion * enter).clamp(Vec2::ZERO, size);
+        let end = (origin + motion * exit).clamp(Vec2::ZERO, size);
+        let (first, last) = (start.min(end), start.max(end));
+
+        let columns = if enter <= exit {
+            touched(first.x, last.x, self.width)
+        } else {
+            (0, -1)
+        };
+        let slope = motion.y / motion.x;
+
+        walk(columns, motion.x).flat_map(move |column| {
+            // the rows the line goes through in this column
+            let (top, bottom) = if motion.x == 0.0 {
+                (first.y, last.y)
+            } else {
+                let left = (column as f32).max(first.x);
+                let right = (column as f32 + 1.0).min(last.x);
+                let at_left = start.y + (left - start.x) * slope;
+                let at_right = start.y + (right - start.x) * slope;
+                (at_left.min(at_right), at_left.max(at_right))
+            };
+
+            walk(touched(top, bottom, self.height), motion.y)
+                .map(move |row| (column, row, self.get(column, row)))
+        })
+    }
+
+    /// Gets the index of the tile at `column` and `row` in `tiles`.
+    fn index(&self, column: i32, row: i32) -> Option<usize> {
+        let inside =
+            (0..self.width as i32).contains(&column) && (0..self.height as i32).contains(&row);
+        inside.then(|| row as usize * self.width + column as usize)
+    }
+}
+
+/// Gets the first and last of `count` rows or columns touched by a span
+/// from `start` to `end`, in tiles. Spans ending on a line between two
+/// tiles touch both.
+fn touched(start: f32, end: f32, count: usize) -> (i32, i32) {
+    let first = (start.ceil() - 1.0).max(0.0);
+    let last = end.floor().min(count as f32 - 1.0);
+
+    (first as i32, last as i32)
+}
+
+/// Goes from `first` to `last`, or the other way around if `direction` is
+/// negative.
+fn walk((first, last): (i32, i32), direction: f32) -> impl Iterator<Item = i32> {
+    let count = (last - first + 1).max(0);
+    (0..count).map(move |i| if direction < 0.0 { last - i } else { first + i })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(tiles: impl Iterator<Item = (i32, i32, Tile)>) -> Vec<(i32, i32)> {
+        tiles.map(|(column, row, _)| (column, row)).collect()
+    }
+
+    #[test]
+    fn keeps_the_tiles_in_an_area_inside_the_map() {
+        let tile_map = TileMap::new(4, 3, 16.0);
+        let everything = Aabb::new(Vec2::splat(f32::NEG_INFINITY), Vec2::splat(f32::INFINITY));
+        let huge = Aabb::new(Vec2::splat(-f32::MAX), Vec2::splat(f32::MAX));
+        let outside = Aabb::new(Vec2::new(100.0, 0.0), Vec2::new(200.0, 40.0));
+
+        assert_eq!(tile_map.tiles_in(&everything).count(), 12);
+        assert_eq!(tile_map.tiles_in(&huge).count(), 12);
+        assert_eq!(tile_map.tiles_in(&outside).count(), 0);
+        assert_eq!(
+            cells(tile_map.tiles_in(&Aabb::new(Vec2::new(-50.0, 20.0), Vec2::new(20.0, 32.0)))),
+            vec![(0, 1), (1, 1)]
+        );
+    }
+
+    #[test]
+    fn walks_the_tiles_along_a_line_in_order() {
+        let tile_map = TileMap::new(4, 2, 16.0);
+
+        let right = tile_map.tiles_along(Vec2::new(-5.0, 8.0), Vec2::new(40.0, 0.0));
+        assert_eq!(cells(right), vec![(0, 0), (1, 0), (2, 0)]);
+
+        let left = tile_map.tiles_along(Vec2::new(35.0, 24.0), Vec2::new(-40.0, 0.0));
+        assert_eq!(cells(left), vec![(2, 1), (1, 1), (0, 1)]);
+
+        let up = tile_map.tiles_along(Vec2::new(40.0, 30.0), Vec2::new(0.0, -20.0));
+        assert_eq!(cells(up), vec![(2, 1), (2, 0)]);
+    }
+
+    #[test]
+    fn walks_both_sides_of_a_seam_the_line_goes_along() {
+        let tile_map = TileMap::new(4, 2, 16.0);
+
+        let along = tile_map.tiles_along(Vec2::new(4.0, 16.0), Vec2::new(20.0, 0.0));
+        assert_eq!(cells(along), vec![(0, 0), (0, 1), (1, 0), (1, 1)]);
+    }
+
+    #[test]
+    fn only_walks_the_tiles_near_a_diagonal_line() {
+        let tile_map = TileMap::new(100, 100, 1.0);
+
+        let along = cells(tile_map.tiles_along(Vec2::splat(0.5), Vec2::new(99.0, 60.0)));
+
+        // far fewer than the 6000 tiles of the box around it
+        assert!(along.len() < 300, "{} tiles", along.len());
+        assert_eq!(along.first(), Some(&(0, 0)));
+        assert_eq!(along.last(), Some(&(99, 60)));
+        for (column, row) in along {
+            let x = column as f32 + 0.5;
+            let on_line = 0.5 + (x - 0.5) * 60.0 / 99.0;
+            assert!((row as f32 + 0.5 - on_line).abs() < 2.0, "{column}, {row}");
+        }
+    }
+
+    #[test]
+    fn walks_the_map_once_for_endless_lines() {
+        let tile_map = TileMap::new(4, 4, 16.0);
+
+        let endless = tile_map.tiles_along(Vec2::new(8.0, 8.0), Vec2::new(f32::MAX, 0.0));
+        assert_eq!(cells(endless), vec![(0, 0), (1, 0), (2, 0), (3, 0)]);
+
+        let away = tile_map.tiles_along(Vec2::new(-8.0, 8.0), Vec2::new(-f32::MAX, 0.0));
+        assert_eq!(away.count(), 0);
+    }
+}
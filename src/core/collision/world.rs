@@ -0,0 +1,761 @@
+//! Finding what lies along a line, or in the way of a moving shape.
+
+use super::broadphase::{Broadphase, DynamicTree};
+use super::contact;
+use super::shapes::{Aabb, Circle, ColliderShape};
+use super::sweep::Hit;
+use super::tilemap::{Tile, TileMap};
+use super::DEFAULT_LAYER;
+use crate::core::ecs::Entity;
+use crate::core::transform::Transform;
+use glam::Vec2;
+use std::collections::HashMap;
+
+/// Distances below this, in pixels, count as touching.
+const TOUCH_DISTANCE: f32 = 1e-2;
+
+/// What a cast hit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CastTarget {
+    /// The collider of an entity.
+    Entity(Entity),
+    /// A tile of the tile map.
+    Tile { column: i32, row: i32 },
+}
+
+/// Where a ray or a moving shape first touches something.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CastHit {
+    /// What was hit.
+    pub target: CastTarget,
+    /// Where it was touched.
+    pub point: Vec2,
+    /// The side touched, pointing out of what was hit.
+    pub normal: Vec2,
+    /// How far the ray or shape went before touching it.
+    pub distance: f32,
+}
+
+/// Every collider of a world, and its tile map, for raycasts and shape
+/// casts. Kept up to date by `systems::collision_world` when inserted as a
+/// resource.
+///
+/// # Example
+/// ```rust
+/// world.insert_resource(CollisionWorld::new());
+/// schedule.add_after("movement", "collision world", systems::collision_world);
+///
+/// // later, in a system
+/// let collision_world = world.resource::<CollisionWorld>().unwrap();
+/// let sees_player = collision_world
+///     .raycast(eyes, player_position - eyes, 200.0, WALLS | PLAYERS)
+///     .is_some_and(|hit| hit.target == CastTarget::Entity(player));
+/// ```
+#[derive(Debug, Clone)]
+pub struct CollisionWorld {
+    /// The layers the solid tiles of the tile map are on, one per bit.
+    pub tile_layer: u32,
+    /// The tile map, if there is one.
+    tile_map: Option<TileMap>,
+    /// The bounds of every collider.
+    broadphase: DynamicTree<Entity>,
+    /// The shape of every collider, placed in the world, with its layers.
+    colliders: HashMap<Entity, (ColliderShape, u32)>,
+}
+
+impl CollisionWorld {
+    /// Creates a collision world without colliders nor tiles.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the collider of `entity`, placed in the world, on the `layer`
+    /// bits. Entities added already are moved.
+    pub fn insert(&mut self, entity: Entity, shape: ColliderShape, layer: u32) {
+        self.broadphase.insert(entity, shape.bounds());
+        self.colliders.insert(entity, (shape, layer));
+    }
+
+    /// Removes the collider of `entity`, if it was added.
+    pub fn remove(&mut self, entity: Entity) {
+        self.broadphase.remove(entity);
+        self.colliders.remove(&entity);
+    }
+
+    /// Removes the colliders of every entity `keep` returns false for.
+    pub fn retain(&mut self, mut keep: impl FnMut(Entity) -> bool) {
+        let removed: Vec<Entity> = self
+            .colliders
+            .keys()
+            .copied()
+            .filter(|entity| !keep(*entity))
+            .collect();
+
+        for entity in removed {
+            self.remove(entity);
+        }
+    }
+
+    /// Gets the tile map, if there is one.
+    pub fn tile_map(&self) -> Option<&TileMap> {
+        self.tile_map.as_ref()
+    }
+
+    /// Sets the tile map whose solid tiles and slopes are cast against.
+    pub fn set_tile_map(&mut self, tile_map: Option<TileMap>) {
+        self.tile_map = tile_map;
+    }
+
+    /// Finds the first collider or tile on a `mask` layer along a line from
+    /// `origin`, going `max_distance` pixels in `direction`. Shapes the line
+    /// starts in aren't hit, so entities can cast from inside their own
+    /// collider.
+    pub fn raycast(
+        &self,
+        origin: Vec2,
+        direction: Vec2,
+        max_distance: f32,
+        mask: u32,
+    ) -> Option<CastHit> {
+        // infinite rays go as far as a float can
+        let max_distance = max_distance.min(f32::MAX);
+        let motion = direction.normalize_or_zero() * max_distance;
+        if motion == Vec2::ZERO || !motion.is_finite() {
+            return None;
+        }
+
+        let point = ColliderShape::Circle(Circle::new(origin, 0.0));
+        let tiles = self
+            .tile_map
+            .iter()
+            .flat_map(|tile_map| tile_map.tiles_along(origin, motion));
+        let swept = swept_bounds(&point, motion);
+        let (target, _, hit) = self.cast(&swept, tiles, mask, |other| match other {
+            ColliderShape::Aabb(aabb) => ray_aabb(origin, motion, aabb),
+            _ => point.sweep(motion, other),
+        })?;
+
+        Some(CastHit {
+            target,
+            point: origin + motion * hit.time,
+            normal: hit.normal,
+            distance: max_distance * hit.time,
+        })
+    }
+
+    /// Finds the first collider or tile on a `mask` layer that `shape`,
+    /// placed in the world, touches when moving `max_distance` pixels in
+    /// `direction`. Shapes it starts overlapping aren't hit.
+    pub fn shape_cast(
+        &self,
+        shape: &ColliderShape,
+        direction: Vec2,
+        max_distance: f32,
+        mask: u32,
+    ) -> Option<CastHit> {
+        // infinite casts go as far as a float can
+        let max_distance = max_distance.min(f32::MAX);
+        let motion = direction.normalize_or_zero() * max_distance;
+        if motion == Vec2::ZERO || !motion.is_finite() {
+            return None;
+        }
+
+        let swept = swept_bounds(shape, motion);
+        let tiles = self
+            .tile_map
+            .iter()
+            .flat_map(|tile_map| tile_map.tiles_in(&swept));
+        let (target, other, hit) =
+            self.cast(&swept, tiles, mask, |other| shape.sweep(motion, other))?;
+
+        // the shapes are touching, so their closest points are found a
+        // little before
+        let travel = motion * hit.time + hit.normal * TOUCH_DISTANCE;
+        let moved = shape.transformed(&Transform::from_translation(travel));
+        let point =
+            contact::closest(&moved, &other).map_or(moved.bounds().center(), |(_, point)| point);
+
+        Some(CastHit {
+            target,
+            point,
+            normal: hit.normal,
+            distance: max_distance * hit.time,
+        })
+    }
+
+    /// Sweeps something with `sweep` against every collider in `swept`
+    /// and every solid one of `tiles`, on a `mask` layer. Gets the first one
+    /// hit, with its shape.
+    fn cast(
+        &self,
+        swept: &Aabb,
+        tiles: impl Iterator<Item = (i32, i32, Tile)>,
+        mask: u32,
+        sweep: impl Fn(&ColliderShape) -> Option<Hit>,
+    ) -> Option<(CastTarget, ColliderShape, Hit)> {
+        let colliders = self
+            .broadphase
+            .query_region(swept)
+            .into_iter()
+            .filter_map(|entity| {
+                let (other, layer) = &self.colliders[&entity];
+                (layer & mask != 0).then(|| (CastTarget::Entity(entity), other.clone()))
+            });
+
+        let tiles = (self.tile_layer & mask != 0)
+            .then_some(tiles)
+            .into_iter()
+            .flatten()
+            .filter_map(|(column, row, _)| {
+                let other = self.tile_map.as_ref()?.tile_shape(column, row)?;
+                Some((CastTarget::Tile { column, row }, other))
+            });
+
+        colliders
+            .chain(tiles)
+            .filter_map(|(target, other)| {
+                let hit = sweep(&other)?;
+                Some((target, other, hit))
+            })
+            .min_by(|(_, _, a), (_, _, b)| a.time.total_cmp(&b.time))
+    }
+}
+
+/// Gets the box `shape` goes through when moving by `motion`, grown so
+/// what's only touched is found as well.
+fn swept_bounds(shape: &ColliderShape, motion: Vec2) -> Aabb {
+    let bounds = shape.bounds();
+    let swept = bounds.union(&Aabb {
+        min: bounds.min + motion,
+        max: bounds.max + motion,
+    });
+
+    Aabb {
+        min: swept.min - TOUCH_DISTANCE,
+        max: swept.max + TOUCH_DISTANCE,
+    }
+}
+
+/// Intersects a ray from `origin` along `motion` with `aabb`. Unlike boxes
+/// swept against each other, rays going along an edge hit the box, so they
+/// don't go through the seams between boxes lined up. Rays starting inside
+/// don't hit it.
+fn ray_aabb(origin: Vec2, motion: Vec2, aabb: &Aabb) -> Option<Hit> {
+    let mut enter = f32::NEG_INFINITY;
+    let mut exit = f32::INFINITY;
+    let mut normal = Vec2::ZERO;
+
+    for (axis, side) in [(0, Vec2::X), (1, Vec2::Y)] {
+        if motion[axis] == 0.0 {
+            if origin[axis] < aabb.min[axis] || origin[axis] > aabb.max[axis] {
+                return None;
+            }
+            continue;
+        }
+
+        let first = (aabb.min[axis] - origin[axis]) / motion[axis];
+        let second = (aabb.max[axis] - origin[axis]) / motion[axis];
+        let (near, far) = (first.min(second), first.max(second));
+        if near > enter {
+            enter = near;
+            normal = side * -motion[axis].signum();
+        }
+        exit = exit.min(far);
+    }
+
+    let hits = enter <= exit && (0.0..=1.0).contains(&enter);
+    hits.then_some(Hit {
+        time: enter,
+        normal,
+    })
+}
+
+impl Default for CollisionWorld {
+    fn default() -> Self {
+        let tile_layer = DEFAULT_LAYER;
+
+        Self {
+            tile_layer,
+            tile_map: None,
+            broadphase: DynamicTree::new(),
+            colliders: HashMap::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::shapes::{Capsule, Polygon};
+    use super::*;
+    use crate::core::ecs::World;
+
+    /// How far off, in pixels, casts against shapes swept in steps may be.
+    const TOLERANCE: f32 = 2e-2;
+
+    const TILE_SIZE: f32 = 16.0;
+
+    /// Creates entities to add colliders for.
+    fn entities(count: usize) -> Vec<Entity> {
+        let mut world = World::new();
+        (0..count).map(|_| world.spawn()).collect()
+    }
+
+    fn collision_world(rows: &[&str]) -> CollisionWorld {
+        let mut collision_world = CollisionWorld::new();
+        collision_world.set_tile_map(Some(TileMap::from_rows(rows, TILE_SIZE)));
+
+        collision_world
+    }
+
+    fn assert_hit(hit: Option<CastHit>, target: CastTarget, point: Vec2, normal: Vec2) {
+        let hit = hit.unwrap_or_else(|| panic!("{target:?} wasn't hit"));
+
+        assert_eq!(hit.target, target);
+        assert!(hit.point.abs_diff_eq(point, TOLERANCE), "{hit:?}");
+        assert!(hit.normal.abs_diff_eq(normal, TOLERANCE), "{hit:?}");
+    }
+
+    #[test]
+    fn hits_every_kind_of_shape() {
+        // each one's left side is at x = 50, across the ray
+        let shapes = [
+            ColliderShape::from(Aabb::new(Vec2::new(50.0, -10.0), Vec2::new(70.0, 10.0))),
+            Circle::new(Vec2::new(60.0, 0.0), 10.0).into(),
+            Capsule::new(Vec2::new(60.0, -10.0), Vec2::new(60.0, 10.0), 10.0).into(),
+            Polygon::new([
+                Vec2::new(50.0, -10.0),
+                Vec2::new(70.0, -10.0),
+                Vec2::new(70.0, 10.0),
+                Vec2::new(50.0, 10.0),
+            ])
+            .into(),
+        ];
+
+        for (entity, shape) in entities(shapes.len()).into_iter().zip(shapes) {
+            let mut collision_world = CollisionWorld::new();
+            collision_world.insert(entity, shape.clone(), DEFAULT_LAYER);
+
+            let hit = collision_world.raycast(Vec2::ZERO, Vec2::X, 100.0, DEFAULT_LAYER);
+
+            assert_hit(
+                hit,
+                CastTarget::Entity(entity),
+                Vec2::new(50.0, 0.0),
+                Vec2::new(-1.0, 0.0),
+            );
+            assert!(
+                (hit.unwrap().distance - 50.0).abs() < TOLERANCE,
+                "{shape:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn hits_solid_tiles() {
+        let collision_world = collision_world(&["........", "........", "...#...."]);
+
+        let hit = collision_world.raycast(Vec2::new(0.0, 40.0), Vec2::X, 100.0, DEFAULT_LAYER);
+
+        assert_hit(
+            hit,
+            CastTarget::Tile { column: 3, row: 2 },
+            Vec2::new(48.0, 40.0),
+            Vec2::new(-1.0, 0.0),
+        );
+        assert_eq!(hit.unwrap().distance, 48.0);
+    }
+
+    #[test]
+    fn hits_the_floor_of_slopes() {
+        let collision_world = collision_world(&["....", ".../", "####"]);
+
+        // 3/4 of the way up the slope from its left side
+        let hit = collision_world.raycast(Vec2::new(60.0, 0.0), Vec2::Y, 100.0, DEFAULT_LAYER);
+
+        assert_hit(
+            hit,
+            CastTarget::Tile { column: 3, row: 1 },
+            Vec2::new(60.0, 20.0),
+            Vec2::new(-1.0, -1.0).normalize(),
+        );
+        assert!((hit.unwrap().distance - 20.0).abs() < TOLERANCE);
+    }
+
+    #[test]
+    fn only_hits_what_is_on_the_mask() {
+        let [entity] = entities(1)[..] else {
+            unreachable!()
+        };
+        let mut collision_world = collision_world(&["....#"]);
+        collision_world.tile_layer = 0b01;
+        let shape = Aabb::new(Vec2::new(30.0, 0.0), Vec2::new(40.0, 16.0));
+        collision_world.insert(entity, shape.into(), 0b10);
+
+        let raycast = |mask| {
+            collision_world
+                .raycast(Vec2::new(0.0, 8.0), Vec2::X, 100.0, mask)
+                .map(|hit| hit.target)
+        };
+
+        assert_eq!(raycast(0b11), Some(CastTarget::Entity(entity)));
+        assert_eq!(raycast(0b10), Some(CastTarget::Entity(entity)));
+        assert_eq!(raycast(0b01), Some(CastTarget::Tile { column: 4, row: 0 }));
+        assert_eq!(raycast(0b100), None);
+    }
+
+    #[test]
+    fn goes_out_of_the_shape_it_starts_in() {
+        let [inside, ahead] = entities(2)[..] else {
+            unreachable!()
+        };
+        let mut collision_world = collision_world(&["....", "....", "##.."]);
+        let around = Aabb::new(Vec2::splat(-10.0), Vec2::splat(10.0));
+        let next = Aabb::new(Vec2::new(50.0, -10.0), Vec2::new(60.0, 10.0));
+        collision_world.insert(inside, around.into(), DEFAULT_LAYER);
+        collision_world.insert(ahead, next.into(), DEFAULT_LAYER);
+
+        let hit = collision_world.raycast(Vec2::ZERO, Vec2::X, 100.0, DEFAULT_LAYER);
+        assert_eq!(hit.map(|hit| hit.target), Some(CastTarget::Entity(ahead)));
+
+        // out of a tile, into the next one
+        let hit = collision_world.raycast(Vec2::new(8.0, 40.0), Vec2::X, 100.0, DEFAULT_LAYER);
+        assert_hit(
+            hit,
+            CastTarget::Tile { column: 1, row: 2 },
+            Vec2::new(16.0, 40.0),
+            Vec2::new(-1.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn hits_tiles_a_ray_only_grazes() {
+        let collision_world = collision_world(&["....", "....", "####"]);
+
+        // along the top of the floor, from outside the map
+        let along = collision_world.raycast(Vec2::new(-20.0, 32.0), Vec2::X, 100.0, DEFAULT_LAYER);
+        assert_hit(
+            along,
+            CastTarget::Tile { column: 0, row: 2 },
+            Vec2::new(0.0, 32.0),
+            Vec2::new(-1.0, 0.0),
+        );
+        assert_eq!(along.unwrap().distance, 20.0);
+
+        // down the seam between two tiles, which doesn't let it through
+        let seam = collision_world.raycast(Vec2::new(16.0, 0.0), Vec2::Y, 100.0, DEFAULT_LAYER);
+        let seam = seam.expect("the ray went through the seam");
+        assert!(matches!(
+            seam.target,
+            CastTarget::Tile {
+                column: 0 | 1,
+                row: 2
+            }
+        ));
+        assert_eq!(seam.point, Vec2::new(16.0, 32.0));
+        assert_eq!(seam.normal, Vec2::new(0.0, -1.0));
+        assert_eq!(seam.distance, 32.0);
+    }
+
+    #[test]
+    fn hits_nothing_without_a_direction_or_a_distance() {
+        let collision_world = collision_world(&["##", "##"]);
+        let origin = Vec2::new(-8.0, 8.0);
+
+        assert_eq!(
+            collision_world.raycast(origin, Vec2::ZERO, 100.0, DEFAULT_LAYER),
+            None
+        );
+        assert_eq!(
+            collision_world.raycast(origin, Vec2::X, 0.0, DEFAULT_LAYER),
+            None
+        );
+    }
+
+    #[test]
+    fn hits_the_nearest_of_several() {
+        let found = entities(3);
+        let mut collision_world = collision_world(&["......#"]);
+        // added out of order, the middle one is the nearest
+        for (entity, x) in found.iter().zip([70.0, 30.0, 50.0]) {
+            let shape = Aabb::new(Vec2::new(x, 0.0), Vec2::new(x + 10.0, 16.0));
+            collision_world.insert(*entity, shape.into(), DEFAULT_LAYER);
+        }
+        let assert_nearest = |collision_world: &CollisionWorld, target, distance: f32| {
+            let hit = collision_world
+                .raycast(Vec2::new(0.0, 8.0), Vec2::X, 200.0, DEFAULT_LAYER)
+                .unwrap();
+            assert_eq!(hit.target, target);
+            assert!((hit.distance - distance).abs() < 1e-3, "{hit:?}");
+        };
+
+        assert_nearest(&collision_world, CastTarget::Entity(found[1]), 30.0);
+
+        collision_world.remove(found[1]);
+        assert_nearest(&collision_world, CastTarget::Entity(found[2]), 50.0);
+
+        // the tile is nearer than what is left
+        collision_world.remove(found[2]);
+        let shape = Aabb::new(Vec2::new(120.0, 0.0), Vec2::new(130.0, 16.0));
+        collision_world.insert(found[0], shape.into(), DEFAULT_LAYER);
+        assert_nearest(
+            &collision_world,
+            CastTarget::Tile { column: 6, row: 0 },
+            96.0,
+        );
+    }
+
+    #[test]
+    fn measures_the_hit_along_the_ray() {
+        let [entity] = entities(1)[..] else {
+            unreachable!()
+        };
+        let mut collision_world = CollisionWorld::new();
+        let shape = Aabb::new(Vec2::new(20.0, 30.0), Vec2::new(40.0, 50.0));
+        collision_world.insert(entity, shape.into(), DEFAULT_LAYER);
+
+        // not normalized, and reaching past the box
+        let hit = collision_world
+            .raycast(Vec2::ZERO, Vec2::new(3.0, 3.0), 100.0, DEFAULT_LAYER)
+            .unwrap();
+
+        assert_eq!(hit.target, CastTarget::Entity(entity));
+        assert!(
+            hit.point.abs_diff_eq(Vec2::new(30.0, 30.0), 1e-4),
+            "{hit:?}"
+        );
+        assert_eq!(hit.normal, Vec2::new(0.0, -1.0));
+        assert!((hit.distance - 30.0 * 2f32.sqrt()).abs() < 1e-3, "{hit:?}");
+    }
+
+    #[test]
+    fn casts_endless_rays() {
+        let collision_world = collision_world(&["...#"]);
+        let origin = Vec2::new(8.0, 8.0);
+
+        for max_distance in [f32::MAX, f32::INFINITY] {
+            let hit = collision_world
+                .raycast(origin, Vec2::X, max_distance, DEFAULT_LAYER)
+                .unwrap();
+            assert_eq!(hit.target, CastTarget::Tile { column: 3, row: 0 });
+            assert!((hit.distance - 40.0).abs() < 1e-3, "{hit:?}");
+        }
+
+        let mut empty = CollisionWorld::new();
+        empty.set_tile_map(Some(TileMap::new(4000, 4000, TILE_SIZE)));
+        let diagonal = Vec2::new(1.0, 0.7);
+        assert_eq!(
+            empty.raycast(origin, diagonal, f32::INFINITY, DEFAULT_LAYER),
+            None
+        );
+    }
+
+    #[test]
+    fn keeps_solid_tiles_apart_from_the_others() {
+        let collision_world = collision_world(&["-H.#"]);
+
+        // platforms and ladders are gone through
+        let hit = collision_world.raycast(Vec2::new(-4.0, 8.0), Vec2::X, 100.0, DEFAULT_LAYER);
+        assert_eq!(
+            hit.map(|hit| hit.target),
+            Some(CastTarget::Tile { column: 3, row: 0 })
+        );
+    }
+
+    /// A shape of every kind, around `center`, reaching 10 pixels to its
+    /// right.
+    fn shapes_around(center: Vec2) -> [ColliderShape; 4] {
+        [
+            Aabb::new(center - Vec2::new(10.0, 6.0), center + Vec2::new(10.0, 6.0)).into(),
+            Circle::new(center, 10.0).into(),
+            Capsule::new(
+                center - Vec2::new(0.0, 4.0),
+                center + Vec2::new(0.0, 4.0),
+                10.0,
+            )
+            .into(),
+            Polygon::new([
+                center + Vec2::new(-10.0, -6.0),
+                center + Vec2::new(10.0, -6.0),
+                center + Vec2::new(10.0, 6.0),
+                center + Vec2::new(-10.0, 6.0),
+            ])
+            .into(),
+        ]
+    }
+
+    /// Checks that a shape around `center` was stopped against a side
+    /// facing left at `x`.
+    fn assert_stopped_at(hit: Option<CastHit>, target: CastTarget, center: Vec2, x: f32) {
+        let hit = hit.unwrap_or_else(|| panic!("{target:?} wasn't hit"));
+
+        assert_eq!(hit.target, target);
+        assert!(
+            hit.normal.abs_diff_eq(Vec2::new(-1.0, 0.0), TOLERANCE),
+            "{hit:?}"
+        );
+        assert!(
+            (hit.distance - (x - center.x - 10.0)).abs() < TOLERANCE,
+            "{hit:?}"
+        );
+        assert!((hit.point.x - x).abs() < TOLERANCE, "{hit:?}");
+        assert!((hit.point.y - center.y).abs() <= 6.0 + TOLERANCE, "{hit:?}");
+    }
+
+    #[test]
+    fn casts_every_kind_of_shape_against_colliders() {
+        let [wall] = entities(1)[..] else {
+            unreachable!()
+        };
+        let mut collision_world = CollisionWorld::new();
+        let shape = Aabb::new(Vec2::new(50.0, -20.0), Vec2::new(60.0, 20.0));
+        collision_world.insert(wall, shape.into(), DEFAULT_LAYER);
+
+        for shape in shapes_around(Vec2::ZERO) {
+            let hit = collision_world.shape_cast(&shape, Vec2::X, 100.0, DEFAULT_LAYER);
+            assert_stopped_at(hit, CastTarget::Entity(wall), Vec2::ZERO, 50.0);
+        }
+    }
+
+    #[test]
+    fn casts_every_kind_of_shape_against_tiles() {
+        let collision_world = collision_world(&["....", "...#", "...."]);
+        let center = Vec2::new(0.0, 24.0);
+
+        for shape in shapes_around(center) {
+            let hit = collision_world.shape_cast(&shape, Vec2::X, 100.0, DEFAULT_LAYER);
+            assert_stopped_at(hit, CastTarget::Tile { column: 3, row: 1 }, center, 48.0);
+        }
+    }
+
+    #[test]
+    fn only_casts_against_what_is_on_the_mask() {
+        let [entity] = entities(1)[..] else {
+            unreachable!()
+        };
+        let mut collision_world = collision_world(&["......#"]);
+        collision_world.tile_layer = 0b01;
+        let shape = Aabb::new(Vec2::new(50.0, 0.0), Vec2::new(60.0, 16.0));
+        collision_world.insert(entity, shape.into(), 0b10);
+        let circle = ColliderShape::from(Circle::new(Vec2::new(0.0, 8.0), 4.0));
+
+        let shape_cast = |mask| {
+            collision_world
+                .shape_cast(&circle, Vec2::X, 200.0, mask)
+                .map(|hit| hit.target)
+        };
+
+        assert_eq!(shape_cast(0b11), Some(CastTarget::Entity(entity)));
+        assert_eq!(shape_cast(0b10), Some(CastTarget::Entity(entity)));
+        assert_eq!(
+            shape_cast(0b01),
+            Some(CastTarget::Tile { column: 6, row: 0 })
+        );
+        assert_eq!(shape_cast(0b100), None);
+    }
+
+    #[test]
+    fn casts_out_of_what_the_shape_starts_overlapping() {
+        let [around, ahead] = entities(2)[..] else {
+            unreachable!()
+        };
+        // a tile and a collider around where the shapes start
+        let mut collision_world = collision_world(&["#......."]);
+        let big = Aabb::new(Vec2::splat(-30.0), Vec2::splat(30.0));
+        let next = Aabb::new(Vec2::new(50.0, -20.0), Vec2::new(60.0, 40.0));
+        collision_world.insert(around, big.into(), DEFAULT_LAYER);
+        collision_world.insert(ahead, next.into(), DEFAULT_LAYER);
+        let center = Vec2::new(0.0, 8.0);
+
+        for shape in shapes_around(center) {
+            let hit = collision_world.shape_cast(&shape, Vec2::X, 100.0, DEFAULT_LAYER);
+            assert_stopped_at(hit, CastTarget::Entity(ahead), center, 50.0);
+        }
+    }
+
+    #[test]
+    fn casts_against_the_nearest_of_several() {
+        let found = entities(3);
+        let mut collision_world = collision_world(&["......#"]);
+        for (entity, x) in found.iter().zip([70.0, 30.0, 50.0]) {
+            let shape = Aabb::new(Vec2::new(x, 0.0), Vec2::new(x + 10.0, 16.0));
+            collision_world.insert(*entity, shape.into(), DEFAULT_LAYER);
+        }
+        let circle = ColliderShape::from(Circle::new(Vec2::new(0.0, 8.0), 5.0));
+        let assert_nearest = |collision_world: &CollisionWorld, target, distance: f32| {
+            let hit = collision_world
+                .shape_cast(&circle, Vec2::X, 200.0, DEFAULT_LAYER)
+                .unwrap();
+            assert_eq!(hit.target, target);
+            assert!((hit.distance - distance).abs() < 1e-3, "{hit:?}");
+        };
+
+        assert_nearest(&collision_world, CastTarget::Entity(found[1]), 25.0);
+
+        collision_world.remove(found[1]);
+        assert_nearest(&collision_world, CastTarget::Entity(found[2]), 45.0);
+
+        collision_world.remove(found[2]);
+        collision_world.remove(found[0]);
+        assert_nearest(
+            &collision_world,
+            CastTarget::Tile { column: 6, row: 0 },
+            91.0,
+        );
+    }
+
+    #[test]
+    fn measures_the_shape_cast_to_the_point_touched() {
+        let [entity] = entities(1)[..] else {
+            unreachable!()
+        };
+        let mut collision_world = CollisionWorld::new();
+        let shape = Aabb::new(Vec2::new(30.0, 30.0), Vec2::new(50.0, 50.0));
+        collision_world.insert(entity, shape.into(), DEFAULT_LAYER);
+        let circle = ColliderShape::from(Circle::new(Vec2::ZERO, 10.0));
+
+        // straight at the corner, which is touched 10 pixels before the
+        // center would reach it
+        let hit = collision_world
+            .shape_cast(&circle, Vec2::ONE, 100.0, DEFAULT_LAYER)
+            .unwrap();
+
+        assert_eq!(hit.target, CastTarget::Entity(entity));
+        assert!(
+            hit.point.abs_diff_eq(Vec2::splat(30.0), TOLERANCE),
+            "{hit:?}"
+        );
+        assert!(
+            hit.normal.abs_diff_eq(-Vec2::ONE.normalize(), 1e-4),
+            "{hit:?}"
+        );
+        assert!(
+            (hit.distance - (30.0 * 2f32.sqrt() - 10.0)).abs() < 1e-3,
+            "{hit:?}"
+        );
+
+        // stopping short of it
+        let short = collision_world.shape_cast(&circle, Vec2::ONE, 30.0, DEFAULT_LAYER);
+        assert_eq!(short, None);
+    }
+
+    #[test]
+    fn casts_shapes_endlessly_or_not_at_all() {
+        let collision_world = collision_world(&["...#"]);
+        let circle = ColliderShape::from(Circle::new(Vec2::new(8.0, 8.0), 4.0));
+
+        for max_distance in [f32::MAX, f32::INFINITY] {
+            let hit = collision_world
+                .shape_cast(&circle, Vec2::X, max_distance, DEFAULT_LAYER)
+                .unwrap();
+            assert_eq!(hit.target, CastTarget::Tile { column: 3, row: 0 });
+            assert!((hit.distance - 36.0).abs() < 1e-2, "{hit:?}");
+        }
+
+        for (direction, max_distance) in [(Vec2::ZERO, 100.0), (Vec2::X, 0.0)] {
+            assert_eq!(
+                collision_world.shape_cast(&circle, direction, max_distance, DEFAULT_LAYER),
+                None
+            );
+        }
+    }
+}
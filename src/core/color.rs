@@ -1,7 +1,7 @@
-use glam::Vec3;
+use glam::Vec4;
 
-/// A color. It is represented as 3 `rgb` values, without an alpha.
-#[derive(Clone, Copy)]
+/// A color. It is represented as 4 `rgba` values, from 0 to 255.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Color {
     /// The amount of red.
     r: u8,
@@ -9,12 +9,149 @@ pub struct Color {
     g: u8,
     /// The amount of blue.
     b: u8,
+    /// The opacity. 0 is fully transparent, 255 is fully opaque.
+    a: u8,
 }
 
 impl Color {
-    /// Constructs a new color with the given `rgb` values.
+    /// Constructs a new, fully opaque color with the given `rgb` values.
     pub const fn new(r: u8, g: u8, b: u8) -> Self {
-        Self { r, g, b }
+        Self::rgba(r, g, b, 255)
+    }
+
+    /// Constructs a new color with the given `rgba` values.
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// Parses a color written in hexadecimal, as `#rgb`, `#rgba`, `#rrggbb`
+    /// or `#rrggbbaa`. The `#` is optional.
+    /// Returns `None` if `hex` isn't a valid color.
+    ///
+    /// # Example
+    /// ```rust
+    /// let orange = Color::from_hex("#ff8000").unwrap();
+    /// let translucent_red = Color::from_hex("f008").unwrap();
+    /// ```
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        // only ASCII digits, so slicing stays on char boundaries, and no
+        // signs, which `from_str_radix` would accept
+        if !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok();
+        let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+
+        match hex.len() {
+            3 | 4 => {
+                // each digit is repeated, so `f` is `ff`
+                let a = if hex.len() == 4 { digit(3)? } else { 0xF };
+                Some(Self::rgba(
+                    digit(0)? * 17,
+                    digit(1)? * 17,
+                    digit(2)? * 17,
+                    a * 17,
+                ))
+            }
+            6 | 8 => {
+                let a = if hex.len() == 8 { byte(6)? } else { 255 };
+                Some(Self::rgba(byte(0)?, byte(2)?, byte(4)?, a))
+            }
+            _ => None,
+        }
+    }
+
+    /// Constructs a fully opaque color from a hue in degrees, and a
+    /// saturation and a value from 0.0 to 1.0.
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32) -> Self {
+        let hue = hue.rem_euclid(360.0) / 60.0;
+        let saturation = saturation.clamp(0.0, 1.0);
+        let value = value.clamp(0.0, 1.0);
+
+        let chroma = value * saturation;
+        let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+        let (r, g, b) = match hue as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let min = value - chroma;
+
+        Self::from_vec4(Vec4::new(r + min, g + min, b + min, 1.0))
+    }
+
+    /// Gets the hue in degrees, and the saturation and value from 0.0 to 1.0.
+    /// The alpha is ignored.
+    pub fn to_hsv(self) -> (f32, f32, f32) {
+        let [r, g, b, _] = Vec4::from(self).to_array();
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let chroma = max - min;
+
+        let hue = if chroma == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / chroma).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / chroma + 2.0)
+        } else {
+            60.0 * ((r - g) / chroma + 4.0)
+        };
+        let saturation = if max == 0.0 { 0.0 } else { chroma / max };
+
+        (hue, saturation, max)
+    }
+
+    /// Mixes this color with `other`, alpha included. A `t` of 0.0 gives this
+    /// color, and 1.0 gives `other`.
+    pub fn lerp(self, other: Color, t: f32) -> Self {
+        let t = t.clamp(0.0, 1.0);
+
+        Self::from_vec4(Vec4::from(self).lerp(Vec4::from(other), t))
+    }
+
+    /// Gets the same color with the given opacity.
+    pub const fn with_alpha(self, a: u8) -> Self {
+        Self { a, ..self }
+    }
+
+    /// Gets the amount of red.
+    pub const fn r(self) -> u8 {
+        self.r
+    }
+
+    /// Gets the amount of green.
+    pub const fn g(self) -> u8 {
+        self.g
+    }
+
+    /// Gets the amount of blue.
+    pub const fn b(self) -> u8 {
+        self.b
+    }
+
+    /// Gets the opacity.
+    pub const fn a(self) -> u8 {
+        self.a
+    }
+
+    /// Gets the color as `rgba` bytes.
+    pub const fn to_rgba8(self) -> [u8; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+
+    /// Constructs a color out of `rgba` values from 0.0 to 1.0.
+    pub fn from_vec4(color: Vec4) -> Self {
+        let [r, g, b, a] = (color.clamp(Vec4::ZERO, Vec4::ONE) * 255.0)
+            .round()
+            .to_array();
+
+        Self::rgba(r as u8, g as u8, b as u8, a as u8)
     }
 
     pub const RED: Color = Self::new(255, 0, 0);
@@ -32,10 +169,133 @@ impl Color {
     pub const GREY: Color = Self::new(128, 128, 128);
     pub const BLACK: Color = Self::new(0, 0, 0);
     pub const WHITE: Color = Self::new(255, 255, 255);
+    pub const TRANSPARENT: Color = Self::rgba(0, 0, 0, 0);
+}
+
+impl Default for Color {
+    fn default() -> Self {
+        Self::WHITE
+    }
 }
 
-impl From<Color> for Vec3 {
+/// Converts the color to `rgba` values from 0.0 to 1.0, as shaders expect them.
+impl From<Color> for Vec4 {
     fn from(color: Color) -> Self {
-        Vec3::new(color.r as f32, color.g as f32, color.b as f32)
+        Vec4::new(
+            color.r as f32,
+            color.g as f32,
+            color.b as f32,
+            color.a as f32,
+        ) / 255.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_hex_length() {
+        assert_eq!(Color::from_hex("#ff8000"), Some(Color::new(255, 128, 0)));
+        assert_eq!(Color::from_hex("f008"), Some(Color::rgba(255, 0, 0, 136)));
+        assert_eq!(Color::from_hex("#0f0"), Some(Color::new(0, 255, 0)));
+        assert_eq!(
+            Color::from_hex("11223344"),
+            Some(Color::rgba(0x11, 0x22, 0x33, 0x44))
+        );
+    }
+
+    #[test]
+    fn rejects_anything_but_hex_digits() {
+        for hex in [
+            "+f0000", "#+f0000", "-f0", "ff 000", "ffé0", "", "#", "12345",
+        ] {
+            assert_eq!(Color::from_hex(hex), None, "{hex:?}");
+        }
+    }
+
+    #[test]
+    fn builds_colors_in_every_sector_of_the_hue() {
+        let hues = [
+            (0.0, Color::RED),
+            (30.0, Color::ORANGE),
+            (60.0, Color::YELLOW),
+            (90.0, Color::LIGHTGREEN),
+            (120.0, Color::GREEN),
+            (150.0, Color::DARKGREEN),
+            (180.0, Color::CYAN),
+            (210.0, Color::LIGHTBLUE),
+            (240.0, Color::BLUE),
+            (270.0, Color::PURPLE),
+            (300.0, Color::MAGENTA),
+            (330.0, Color::PINK),
+        ];
+
+        for (hue, color) in hues {
+            assert_eq!(Color::from_hsv(hue, 1.0, 1.0), color, "{hue}");
+            let (to_hue, saturation, value) = color.to_hsv();
+            assert!((to_hue - hue).abs() < 1.0, "{hue} gave {to_hue}");
+            assert_eq!((saturation, value), (1.0, 1.0));
+        }
+    }
+
+    #[test]
+    fn wraps_hues_around_the_circle() {
+        assert_eq!(Color::from_hsv(360.0, 1.0, 1.0), Color::RED);
+        assert_eq!(Color::from_hsv(-60.0, 1.0, 1.0), Color::MAGENTA);
+        assert_eq!(Color::from_hsv(720.0 + 120.0, 1.0, 1.0), Color::GREEN);
+    }
+
+    #[test]
+    fn builds_greys_without_saturation() {
+        for hue in [0.0, 100.0, 250.0] {
+            assert_eq!(Color::from_hsv(hue, 0.0, 128.0 / 255.0), Color::GREY);
+        }
+        assert_eq!(Color::from_hsv(42.0, 1.0, 0.0), Color::BLACK);
+
+        let (hue, saturation, value) = Color::GREY.to_hsv();
+        assert_eq!((hue, saturation), (0.0, 0.0));
+        assert!((value - 128.0 / 255.0).abs() < 1e-6);
+        assert_eq!(Color::BLACK.to_hsv(), (0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn clamps_saturation_and_value() {
+        assert_eq!(Color::from_hsv(0.0, 2.0, 5.0), Color::RED);
+        assert_eq!(Color::from_hsv(0.0, -1.0, -1.0), Color::BLACK);
+    }
+
+    #[test]
+    fn survives_a_round_trip_through_hsv() {
+        for r in (0..=255).step_by(15) {
+            for g in (0..=255).step_by(15) {
+                for b in (0..=255).step_by(15) {
+                    let color = Color::new(r, g, b);
+                    let (hue, saturation, value) = color.to_hsv();
+
+                    assert_eq!(Color::from_hsv(hue, saturation, value), color);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn mixes_colors_and_their_alpha() {
+        let from = Color::rgba(0, 100, 200, 0);
+        let to = Color::rgba(255, 0, 100, 255);
+
+        assert_eq!(from.lerp(to, 0.0), from);
+        assert_eq!(from.lerp(to, 1.0), to);
+        assert_eq!(from.lerp(to, 0.5), Color::rgba(128, 50, 150, 128));
+        assert_eq!(
+            Color::TRANSPARENT.lerp(Color::WHITE, 0.25),
+            Color::rgba(64, 64, 64, 64)
+        );
+    }
+
+    #[test]
+    fn clamps_the_mix_to_both_colors() {
+        assert_eq!(Color::BLACK.lerp(Color::WHITE, -1.0), Color::BLACK);
+        assert_eq!(Color::BLACK.lerp(Color::WHITE, 2.0), Color::WHITE);
     }
 }
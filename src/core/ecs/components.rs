@@ -0,0 +1,64 @@
+use crate::core::scene_graph::NodeId;
+use crate::core::transform::Transform;
+use glam::Vec2;
+
+/// How fast an entity moves, in virtual resolution pixels per second.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Velocity(pub Vec2);
+
+/// Where an entity was before the last fixed step. Entities having one are
+/// drawn in between it and their `Transform`, so they move smoothly at any
+/// frame rate.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PreviousTransform(pub Transform);
+
+/// Attaches an entity to a node of the `SceneGraph` resource. Its
+/// `Transform` becomes the local transform of the node, so it follows the
+/// parent of the node around, and it is drawn where the node is in the
+/// world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SceneNode(pub NodeId);
+
+/// Moves an entity with the actions of the `InputMap` resource, by setting
+/// its `Velocity`. Axes without an action are left alone, so another system
+/// may drive them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Controller {
+    /// The action moving the entity left (-1.0) and right (1.0).
+    pub x_action: Option<String>,
+    /// The action moving the entity up (-1.0) and down (1.0).
+    pub y_action: Option<String>,
+    /// The speed of the entity, in pixels per second.
+    pub speed: f32,
+    /// The player whose gamepad moves the entity. The current gamepad is
+    /// used when there's none.
+    pub player: Option<usize>,
+}
+
+impl Controller {
+    /// Creates a controller moving the entity up and down.
+    pub fn vertical(y_action: &str, speed: f32) -> Self {
+        Self {
+            x_action: None,
+            y_action: Some(y_action.to_owned()),
+            speed,
+            player: None,
+        }
+    }
+
+    /// Creates a controller moving the entity in every direction.
+    pub fn four_way(x_action: &str, y_action: &str, speed: f32) -> Self {
+        Self {
+            x_action: Some(x_action.to_owned()),
+            y_action: Some(y_action.to_owned()),
+            speed,
+            player: None,
+        }
+    }
+
+    /// Makes the controller read the gamepad of `player` only.
+    pub const fn for_player(mut self, player: usize) -> Self {
+        self.player = Some(player);
+        self
+    }
+}
@@ -0,0 +1,505 @@
+use super::collision::{character::CharacterController, Collider, ContinuousCollision};
+use super::shape::Shape;
+use super::sprite::Sprite;
+use super::transform::Transform;
+use std::any::{type_name, Any, TypeId};
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::HashMap;
+
+pub mod components;
+pub mod schedule;
+pub mod systems;
+
+use components::{Controller, PreviousTransform, SceneNode, Velocity};
+
+/// Identifies a game object living in a `World`. Ids of despawned entities
+/// are never reused, so they can be kept around safely.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Entity {
+    /// The slot of the entity in the world.
+    index: u32,
+    /// How many entities used the slot before this one.
+    generation: u32,
+}
+
+/// The components of a single type, for every entity having one.
+pub struct Storage<T> {
+    /// The components, indexed by the slot of their entity.
+    components: Vec<Option<(Entity, T)>>,
+    /// The amount of components stored.
+    len: usize,
+}
+
+impl<T> Storage<T> {
+    /// Creates an empty storage.
+    fn new() -> Self {
+        Self {
+            components: Vec::new(),
+            len: 0,
+        }
+    }
+
+    /// Gets the component of an entity.
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        match self.components.get(entity.index as usize) {
+            Some(Some((owner, component))) if *owner == entity => Some(component),
+            _ => None,
+        }
+    }
+
+    /// Gets the component of an entity mutably.
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        match self.components.get_mut(entity.index as usize) {
+            Some(Some((owner, component))) if *owner == entity => Some(component),
+            _ => None,
+        }
+    }
+
+    /// Checks whether the entity has a component in this storage.
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.get(entity).is_some()
+    }
+
+    /// Gets the amount of components stored.
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks whether no entity has this component.
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Iterates over every entity having this component.
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.components
+            .iter()
+            .flatten()
+            .map(|(entity, component)| (*entity, component))
+    }
+
+    /// Iterates mutably over every entity having this component.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        self.components
+            .iter_mut()
+            .flatten()
+            .map(|(entity, component)| (*entity, component))
+    }
+
+    /// Iterates over every entity having both this component and one in
+    /// `other`.
+    ///
+    /// # Example
+    /// ```rust
+    /// let transforms = world.storage::<Transform>();
+    /// let colliders = world.storage::<Collider>();
+    ///
+    /// for (entity, transform, collider) in transforms.join(&colliders) {
+    ///     println!("{:?} covers {:?}", entity, collider.rect(transform));
+    /// }
+    /// ```
+    pub fn join<'a, U>(
+        &'a self,
+        other: &'a Storage<U>,
+    ) -> impl Iterator<Item = (Entity, &'a T, &'a U)> {
+        self.iter()
+            .filter_map(move |(entity, component)| Some((entity, component, other.get(entity)?)))
+    }
+
+    /// Stores the component of an entity, returning the one it replaces.
+    fn insert(&mut self, entity: Entity, component: T) -> Option<T> {
+        let index = entity.index as usize;
+        if index >= self.components.len() {
+            self.components.resize_with(index + 1, || None);
+        }
+
+        let previous = self.components[index].replace((entity, component));
+        match previous {
+            Some((owner, component)) if owner == entity => Some(component),
+            _ => {
+                self.len += 1;
+                None
+            }
+        }
+    }
+
+    /// Removes the component of an entity, returning it.
+    fn remove(&mut self, entity: Entity) -> Option<T> {
+        let slot = self.components.get_mut(entity.index as usize)?;
+        match slot {
+            Some((owner, _)) if *owner == entity => {
+                self.len -= 1;
+                slot.take().map(|(_, component)| component)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A storage whose component type isn't known, so entities can be removed
+/// from every storage at once.
+trait AnyStorage {
+    fn remove_entity(&mut self, entity: Entity);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: 'static> AnyStorage for Storage<T> {
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(entity);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Holds every entity, their components and the resources shared by
+/// systems, such as the `InputHandler` and the `Time`.
+///
+/// Storages and resources are borrowed at runtime, so a system can borrow
+/// several of them at once. Borrowing the same one mutably twice panics.
+///
+/// # Example
+/// ```rust
+/// let mut world = World::new();
+/// let paddle = world.spawn();
+/// world.insert(paddle, Transform::from_translation(Vec2::new(32.0, 120.0)));
+/// world.insert(paddle, Velocity(Vec2::ZERO));
+///
+/// let velocities = world.storage::<Velocity>();
+/// let mut transforms = world.storage_mut::<Transform>();
+/// for (entity, velocity) in velocities.iter() {
+///     if let Some(transform) = transforms.get_mut(entity) {
+///         transform.translation += velocity.0 * dt;
+///     }
+/// }
+/// ```
+#[derive(Default)]
+pub struct World {
+    /// The current generation of every slot.
+    generations: Vec<u32>,
+    /// Whether each slot holds a living entity.
+    alive: Vec<bool>,
+    /// Slots left empty by despawned entities.
+    free: Vec<u32>,
+    storages: HashMap<TypeId, RefCell<Box<dyn AnyStorage>>>,
+    resources: HashMap<TypeId, RefCell<Box<dyn Any>>>,
+}
+
+impl World {
+    /// Creates an empty world, with the built-in components registered.
+    pub fn new() -> Self {
+        let mut world = Self::default();
+        world.register::<Transform>();
+        world.register::<Sprite>();
+        world.register::<Shape>();
+        world.register::<Collider>();
+        world.register::<Velocity>();
+        world.register::<Controller>();
+        world.register::<PreviousTransform>();
+        world.register::<CharacterController>();
+        world.register::<ContinuousCollision>();
+        world.register::<SceneNode>();
+
+        world
+    }
+
+    /// Creates a storage for components of type `T`. Storages are created
+    /// when inserting a component as well, but querying a type that was
+    /// never registered panics.
+    pub fn register<T: 'static>(&mut self) {
+        self.storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| RefCell::new(Box::new(Storage::<T>::new())));
+    }
+
+    /// Creates an entity without components.
+    pub fn spawn(&mut self) -> Entity {
+        match self.free.pop() {
+            Some(index) => {
+                self.alive[index as usize] = true;
+
+                Entity {
+                    index,
+                    generation: self.generations[index as usize],
+                }
+            }
+            None => {
+                self.generations.push(0);
+                self.alive.push(true);
+
+                Entity {
+                    index: self.generations.len() as u32 - 1,
+                    generation: 0,
+                }
+            }
+        }
+    }
+
+    /// Removes an entity along with all of its components.
+    /// Returns whether the entity was alive.
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+
+        for storage in self.storages.values_mut() {
+            storage.get_mut().remove_entity(entity);
+        }
+
+        let index = entity.index as usize;
+        self.alive[index] = false;
+        self.generations[index] += 1;
+        self.free.push(entity.index);
+
+        true
+    }
+
+    /// Checks whether an entity was spawned and not despawned yet.
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        let index = entity.index as usize;
+
+        self.alive.get(index) == Some(&true) && self.generations[index] == entity.generation
+    }
+
+    /// Iterates over every living entity.
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.alive
+            .iter()
+            .enumerate()
+            .filter(|(_, alive)| **alive)
+            .map(|(index, _)| Entity {
+                index: index as u32,
+                generation: self.generations[index],
+            })
+    }
+
+    /// Adds a component to an entity, returning the one of the same type it
+    /// replaces. Does nothing if the entity isn't alive.
+    pub fn insert<T: 'static>(&mut self, entity: Entity, component: T) -> Option<T> {
+        if !self.is_alive(entity) {
+            warn!("Cannot add a {} to a despawned entity", type_name::<T>());
+            return None;
+        }
+
+        self.register::<T>();
+        self.storage_mut::<T>().insert(entity, component)
+    }
+
+    /// Removes a component from an entity, returning it.
+    pub fn remove<T: 'static>(&mut self, entity: Entity) -> Option<T> {
+        self.storages
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|storage| {
+                storage
+                    .get_mut()
+                    .as_any_mut()
+                    .downcast_mut::<Storage<T>>()
+                    .and_then(|storage| storage.remove(entity))
+            })
+    }
+
+    /// Borrows the components of type `T`.
+    ///
+    /// # Panics
+    /// Panics if `T` was never registered, or if the storage is borrowed
+    /// mutably.
+    pub fn storage<T: 'static>(&self) -> Ref<'_, Storage<T>> {
+        Ref::map(self.storage_cell::<T>().borrow(), |storage| {
+            storage.as_any().downcast_ref().unwrap()
+        })
+    }
+
+    /// Borrows the components of type `T` mutably.
+    ///
+    /// # Panics
+    /// Panics if `T` was never registered, or if the storage is borrowed.
+    pub fn storage_mut<T: 'static>(&self) -> RefMut<'_, Storage<T>> {
+        RefMut::map(self.storage_cell::<T>().borrow_mut(), |storage| {
+            storage.as_any_mut().downcast_mut().unwrap()
+        })
+    }
+
+    /// Adds a resource, shared by every system, returning the one of the
+    /// same type it replaces.
+    pub fn insert_resource<T: 'static>(&mut self, resource: T) -> Option<T> {
+        self.resources
+            .insert(TypeId::of::<T>(), RefCell::new(Box::new(resource)))
+            .and_then(|previous| previous.into_inner().downcast().ok())
+            .map(|previous| *previous)
+    }
+
+    /// Borrows a resource, if there is one of type `T`.
+    ///
+    /// # Panics
+    /// Panics if the resource is borrowed mutably.
+    pub fn resource<T: 'static>(&self) -> Option<Ref<'_, T>> {
+        self.resources.get(&TypeId::of::<T>()).map(|resource| {
+            Ref::map(resource.borrow(), |resource| {
+                resource.downcast_ref().unwrap()
+            })
+        })
+    }
+
+    /// Borrows a resource mutably, if there is one of type `T`.
+    ///
+    /// # Panics
+    /// Panics if the resource is borrowed.
+    pub fn resource_mut<T: 'static>(&self) -> Option<RefMut<'_, T>> {
+        self.resources.get(&TypeId::of::<T>()).map(|resource| {
+            RefMut::map(resource.borrow_mut(), |resource| {
+                resource.downcast_mut().unwrap()
+            })
+        })
+    }
+
+    /// Gets the storage of `T`, which must have been registered.
+    fn storage_cell<T: 'static>(&self) -> &RefCell<Box<dyn AnyStorage>> {
+        self.storages.get(&TypeId::of::<T>()).unwrap_or_else(|| {
+            panic!(
+                "The component {} should be registered before querying it",
+                type_name::<T>()
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec2;
+
+    #[derive(Debug, PartialEq)]
+    struct Health(u32);
+
+    #[derive(Debug, PartialEq)]
+    struct Name(&'static str);
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.register::<Health>();
+        world.register::<Name>();
+
+        world
+    }
+
+    #[test]
+    fn reuses_slots_with_a_new_generation() {
+        let mut world = world();
+        let first = world.spawn();
+        world.insert(first, Health(3));
+        assert!(world.despawn(first));
+
+        let second = world.spawn();
+        assert_eq!(second.index, first.index);
+        assert_ne!(second, first);
+        world.insert(second, Health(5));
+
+        assert!(!world.is_alive(first));
+        assert!(world.is_alive(second));
+        assert_eq!(world.storage::<Health>().get(first), None);
+        assert_eq!(world.storage::<Health>().get(second), Some(&Health(5)));
+        assert_eq!(world.storage_mut::<Health>().get_mut(first), None);
+        assert_eq!(world.entities().collect::<Vec<_>>(), [second]);
+    }
+
+    #[test]
+    fn ignores_stale_entities() {
+        let mut world = world();
+        let stale = world.spawn();
+        world.despawn(stale);
+        let current = world.spawn();
+        world.insert(current, Health(1));
+
+        assert!(!world.despawn(stale));
+        assert_eq!(world.insert(stale, Health(2)), None);
+        assert_eq!(world.remove::<Health>(stale), None);
+        assert_eq!(world.storage::<Health>().get(current), Some(&Health(1)));
+    }
+
+    #[test]
+    fn despawning_strips_every_storage() {
+        let mut world = world();
+        let entity = world.spawn();
+        let other = world.spawn();
+        world.insert(entity, Health(3));
+        world.insert(entity, Name("paddle"));
+        world.insert(entity, Transform::from_translation(Vec2::ONE));
+        world.insert(other, Health(4));
+
+        world.despawn(entity);
+
+        assert!(world.storage::<Name>().is_empty());
+        assert!(world.storage::<Transform>().is_empty());
+        assert_eq!(world.storage::<Health>().len(), 1);
+        assert!(world.storage::<Health>().contains(other));
+    }
+
+    #[test]
+    fn replaces_and_removes_components() {
+        let mut world = world();
+        let entity = world.spawn();
+
+        assert_eq!(world.insert(entity, Health(1)), None);
+        assert_eq!(world.insert(entity, Health(2)), Some(Health(1)));
+        assert_eq!(world.storage::<Health>().len(), 1);
+        assert_eq!(world.remove::<Health>(entity), Some(Health(2)));
+        assert_eq!(world.remove::<Health>(entity), None);
+        assert!(world.storage::<Health>().is_empty());
+    }
+
+    #[test]
+    fn joins_the_entities_having_both_components() {
+        let mut world = world();
+        let both = world.spawn();
+        let health_only = world.spawn();
+        let name_only = world.spawn();
+        world.insert(both, Health(1));
+        world.insert(both, Name("both"));
+        world.insert(health_only, Health(2));
+        world.insert(name_only, Name("name only"));
+
+        let health = world.storage::<Health>();
+        let names = world.storage::<Name>();
+        let joined: Vec<_> = health.join(&names).collect();
+
+        assert_eq!(joined, [(both, &Health(1), &Name("both"))]);
+    }
+
+    #[test]
+    fn borrows_several_storages_and_resources_at_once() {
+        let mut world = world();
+        let entity = world.spawn();
+        world.insert(entity, Health(1));
+        world.insert_resource(Name("resource"));
+
+        let health = world.storage::<Health>();
+        let mut names = world.storage_mut::<Name>();
+        let resource = world.resource::<Name>();
+        assert!(names.get_mut(entity).is_none());
+        assert_eq!(health.get(entity), Some(&Health(1)));
+        assert_eq!(resource.as_deref(), Some(&Name("resource")));
+    }
+
+    #[test]
+    fn replaces_resources() {
+        let mut world = world();
+
+        assert_eq!(world.insert_resource(Health(1)), None);
+        assert_eq!(world.insert_resource(Health(2)), Some(Health(1)));
+        world.resource_mut::<Health>().unwrap().0 += 1;
+        assert_eq!(world.resource::<Health>().as_deref(), Some(&Health(3)));
+        assert!(world.resource::<Name>().is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "should be registered")]
+    fn panics_when_querying_unregistered_components() {
+        World::new().storage::<Health>();
+    }
+}
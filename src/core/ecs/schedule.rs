@@ -0,0 +1,158 @@
+use super::World;
+use miniquad::Context;
+
+/// Logic running over the entities of a `World`, once per `Schedule` run.
+/// Any `FnMut(&mut World, &mut Context)` is a system.
+pub trait System {
+    /// Runs the system once.
+    fn run(&mut self, world: &mut World, ctx: &mut Context);
+}
+
+impl<F> System for F
+where
+    F: FnMut(&mut World, &mut Context),
+{
+    fn run(&mut self, world: &mut World, ctx: &mut Context) {
+        self(world, ctx)
+    }
+}
+
+/// An ordered list of named systems.
+///
+/// # Example
+/// ```rust
+/// let mut schedule = Schedule::new();
+/// schedule.add("movement", systems::movement);
+/// schedule.add_before("movement", "controllers", systems::controllers);
+///
+/// // runs the controllers first, then the movement
+/// schedule.run(&mut world, ctx);
+/// ```
+#[derive(Default)]
+pub struct Schedule {
+    systems: Vec<(String, Box<dyn System>)>,
+}
+
+impl Schedule {
+    /// Creates an empty schedule.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a system, running after every other one.
+    pub fn add(&mut self, name: &str, system: impl System + 'static) {
+        self.systems.push((name.to_string(), Box::new(system)));
+    }
+
+    /// Adds a system running right before the one called `before`.
+    /// If there is no such system, it runs after every other one.
+    pub fn add_before(&mut self, before: &str, name: &str, system: impl System + 'static) {
+        let index = self.position(before).unwrap_or(self.systems.len());
+        self.systems
+            .insert(index, (name.to_string(), Box::new(system)));
+    }
+
+    /// Adds a system running right after the one called `after`.
+    /// If there is no such system, it runs after every other one.
+    pub fn add_after(&mut self, after: &str, name: &str, system: impl System + 'static) {
+        let index = self
+            .position(after)
+            .map_or(self.systems.len(), |index| index + 1);
+        self.systems
+            .insert(index, (name.to_string(), Box::new(system)));
+    }
+
+    /// Removes the system called `name`. Returns whether it existed.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let index = self.position(name);
+        if let Some(index) = index {
+            self.systems.remove(index);
+        }
+
+        index.is_some()
+    }
+
+    /// Gets the names of the systems, in the order they run.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.systems.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Runs every system once, in order.
+    pub fn run(&mut self, world: &mut World, ctx: &mut Context) {
+        for (_, system) in &mut self.systems {
+            system.run(world, ctx);
+        }
+    }
+
+    /// Gets the index of the system called `name`.
+    fn position(&self, name: &str) -> Option<usize> {
+        let index = self.systems.iter().position(|(other, _)| other == name);
+        if index.is_none() {
+            warn!("There is no system called {:?} in the schedule", name);
+        }
+
+        index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nothing(_world: &mut World, _ctx: &mut Context) {}
+
+    fn schedule(names: &[&str]) -> Schedule {
+        let mut schedule = Schedule::new();
+        for name in names {
+            schedule.add(name, nothing);
+        }
+
+        schedule
+    }
+
+    fn names(schedule: &Schedule) -> Vec<&str> {
+        schedule.names().collect()
+    }
+
+    #[test]
+    fn runs_systems_in_the_order_they_were_added() {
+        let schedule = schedule(&["input", "movement", "draw"]);
+
+        assert_eq!(names(&schedule), ["input", "movement", "draw"]);
+    }
+
+    #[test]
+    fn adds_systems_before_and_after_others() {
+        let mut schedule = schedule(&["input", "draw"]);
+        schedule.add_before("draw", "movement", nothing);
+        schedule.add_after("input", "controllers", nothing);
+        schedule.add_before("input", "time", nothing);
+        schedule.add_after("draw", "debug", nothing);
+
+        assert_eq!(
+            names(&schedule),
+            ["time", "input", "controllers", "movement", "draw", "debug"]
+        );
+    }
+
+    #[test]
+    fn adds_systems_at_the_end_when_the_label_is_unknown() {
+        let mut schedule = schedule(&["input", "draw"]);
+        schedule.add_before("missing", "before missing", nothing);
+        schedule.add_after("missing", "after missing", nothing);
+
+        assert_eq!(
+            names(&schedule),
+            ["input", "draw", "before missing", "after missing"]
+        );
+    }
+
+    #[test]
+    fn removes_systems() {
+        let mut schedule = schedule(&["input", "movement", "draw"]);
+
+        assert!(schedule.remove("movement"));
+        assert!(!schedule.remove("movement"));
+        assert_eq!(names(&schedule), ["input", "draw"]);
+    }
+}
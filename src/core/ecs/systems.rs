@@ -0,0 +1,384 @@
+use super::components::{Controller, PreviousTransform, SceneNode, Velocity};
+use super::{Entity, World};
+use crate::core::camera::{Camera2D, Drawable};
+use crate::core::collision::broadphase::{Broadphase, DynamicTree};
+use crate::core::collision::character::{CharacterController, CharacterInput};
+use crate::core::collision::shapes::{Aabb, ColliderShape};
+use crate::core::collision::sweep::move_and_collide;
+use crate::core::collision::tilemap::TileMap;
+use crate::core::collision::world::CollisionWorld;
+use crate::core::collision::{Collider, ContinuousCollision};
+use crate::core::input_handler::InputHandler;
+use crate::core::input_map::{InputMap, PRESS_THRESHOLD};
+use crate::core::scene_graph::SceneGraph;
+use crate::core::shape::Shape;
+use crate::core::sprite::Sprite;
+use crate::core::time::Time;
+use crate::core::transform::{Spatial, Transform};
+use miniquad::{Context, EventHandler};
+use std::collections::HashMap;
+
+/// Polls the gamepads of the `InputHandler` resource, if there is one.
+/// Should run once per frame, rather than in every fixed step.
+pub fn input(world: &mut World, ctx: &mut Context) {
+    if let Some(mut input_handler) = world.resource_mut::<InputHandler>() {
+        input_handler.update(ctx);
+    }
+}
+
+/// Sets the velocity of entities with a `Controller` out of the actions of
+/// the `InputMap` resource, as pressed in the `InputHandler` resource.
+pub fn controllers(world: &mut World, _ctx: &mut Context) {
+    let (input_handler, input_map) = match (
+        world.resource::<InputHandler>(),
+        world.resource::<InputMap>(),
+    ) {
+        (Some(input_handler), Some(input_map)) => (input_handler, input_map),
+        _ => return,
+    };
+    let controllers = world.storage::<Controller>();
+    let mut velocities = world.storage_mut::<Velocity>();
+
+    for (entity, controller) in controllers.iter() {
+        let velocity = match velocities.get_mut(entity) {
+            Some(velocity) => velocity,
+            None => continue,
+        };
+
+        let axis = |action: &Option<String>| {
+            let action = action.as_deref()?;
+            Some(match controller.player {
+                Some(player) => input_map.player_value(action, player, &input_handler),
+                None => input_map.value(action, &input_handler),
+            })
+        };
+
+        if let Some(x) = axis(&controller.x_action) {
+            velocity.0.x = x * controller.speed;
+        }
+        if let Some(y) = axis(&controller.y_action) {
+            velocity.0.y = y * controller.speed;
+        }
+    }
+}
+
+/// Moves entities with a `CharacterController` against the `TileMap`
+/// resource, over a fixed step of the `Time` resource, with the actions of
+/// the `InputMap` resource. Their `Collider` is used as a box. Does nothing
+/// without a tile map or a time.
+pub fn character_controllers(world: &mut World, _ctx: &mut Context) {
+    let (tile_map, dt) = match (world.resource::<TileMap>(), world.resource::<Time>()) {
+        (Some(tile_map), Some(time)) => (tile_map, time.fixed_delta()),
+        _ => return,
+    };
+    let input_handler = world.resource::<InputHandler>();
+    let input_map = world.resource::<InputMap>();
+    let colliders = world.storage::<Collider>();
+    let mut characters = world.storage_mut::<CharacterController>();
+    let mut transforms = world.storage_mut::<Transform>();
+
+    for (entity, character) in characters.iter_mut() {
+        let (collider, transform) = match (colliders.get(entity), transforms.get_mut(entity)) {
+            (Some(collider), Some(transform)) => (collider, transform),
+            _ => continue,
+        };
+
+        let input = match (&input_handler, &input_map) {
+            (Some(input_handler), Some(input_map)) => {
+                let value = |action: &str| match character.player {
+                    Some(player) => input_map.player_value(action, player, input_handler),
+                    None => input_map.value(action, input_handler),
+                };
+                CharacterInput {
+                    x: value(&character.x_action),
+                    y: value(&character.y_action),
+                    jump: value(&character.jump_action).abs() >= PRESS_THRESHOLD,
+                }
+            }
+            _ => CharacterInput::default(),
+        };
+
+        let body = collider.world_shape(transform).bounds();
+        let moved = character.step(&input, body, &tile_map, dt);
+        transform.translation += moved.min - body.min;
+    }
+}
+
+/// Stores the `Transform` of entities having a `PreviousTransform`, before
+/// anything moves them. Should run first in fixed steps.
+pub fn remember_transforms(world: &mut World, _ctx: &mut Context) {
+    let transforms = world.storage::<Transform>();
+
+    for (entity, previous) in world.storage_mut::<PreviousTransform>().iter_mut() {
+        if let Some(transform) = transforms.get(entity) {
+            previous.0 = *transform;
+        }
+    }
+}
+
+/// The bounds of every collider, kept by `movement` between fixed steps.
+#[derive(Debug, Clone, Default)]
+struct Obstacles(DynamicTree<Entity>);
+
+/// Moves entities by their `Velocity`, over a fixed step of the `Time`
+/// resource. Does nothing if there is none.
+///
+/// Entities with a `ContinuousCollision` and a `Collider` move after the
+/// others, and stop at the first collider in their way, which is stored in
+/// their `ContinuousCollision`.
+pub fn movement(world: &mut World, _ctx: &mut Context) {
+    let dt = match world.resource::<Time>() {
+        Some(time) => time.fixed_delta(),
+        None => return,
+    };
+    if world.resource::<Obstacles>().is_none() {
+        world.insert_resource(Obstacles::default());
+    }
+    let mut broadphase = match world.resource_mut::<Obstacles>() {
+        Some(obstacles) => obstacles,
+        None => return,
+    };
+    let velocities = world.storage::<Velocity>();
+    let colliders = world.storage::<Collider>();
+    let mut continuous = world.storage_mut::<ContinuousCollision>();
+    let mut transforms = world.storage_mut::<Transform>();
+
+    let is_swept = |entity| continuous.get(entity).is_some() && colliders.get(entity).is_some();
+    for (entity, velocity) in velocities.iter() {
+        if is_swept(entity) {
+            continue;
+        }
+        if let Some(transform) = transforms.get_mut(entity) {
+            transform.translation += velocity.0 * dt;
+        }
+    }
+
+    let obstacles: HashMap<Entity, ColliderShape> = colliders
+        .iter()
+        .filter_map(|(entity, collider)| {
+            Some((entity, collider.world_shape(transforms.get(entity)?)))
+        })
+        .collect();
+    // the tree is kept between steps, so only what left its grown box is
+    // moved in it
+    broadphase
+        .0
+        .retain(|entity| obstacles.contains_key(&entity));
+    for (entity, shape) in &obstacles {
+        broadphase.0.insert(*entity, shape.bounds());
+    }
+
+    for (entity, continuous) in continuous.iter_mut() {
+        continuous.collision = None;
+        let (velocity, collider, transform) = match (
+            velocities.get(entity),
+            colliders.get(entity),
+            transforms.get_mut(entity),
+        ) {
+            (Some(velocity), Some(collider), Some(transform)) => (velocity, collider, transform),
+            _ => continue,
+        };
+
+        let shape = collider.world_shape(transform);
+        let motion = velocity.0 * dt;
+
+        // only what's around the way can be in it, and the entity doesn't
+        // stop at itself nor at colliders outside its mask
+        let bounds = shape.bounds();
+        let swept = bounds.union(&Aabb {
+            min: bounds.min + motion,
+            max: bounds.max + motion,
+        });
+        let (entities, shapes): (Vec<Entity>, Vec<ColliderShape>) = broadphase
+            .0
+            .query_region(&swept)
+            .into_iter()
+            .filter(|other| {
+                *other != entity
+                    && colliders
+                        .get(*other)
+                        .is_some_and(|other| collider.collides_with(other))
+            })
+            .map(|other| (other, obstacles[&other].clone()))
+            .unzip();
+
+        match move_and_collide(&shape, motion, &shapes) {
+            Some(collision) => {
+                transform.translation += collision.travel;
+                continuous.collision = Some((entities[collision.index], collision));
+            }
+            None => transform.translation += motion,
+        }
+    }
+}
+
+/// Keeps the `CollisionWorld` resource up to date with the `Collider` of
+/// entities and the `TileMap` resource. Should run after everything moving
+/// entities. Does nothing without a collision world.
+pub fn collision_world(world: &mut World, _ctx: &mut Context) {
+    let mut collision_world = match world.resource_mut::<CollisionWorld>() {
+        Some(collision_world) => collision_world,
+        None => return,
+    };
+    let colliders = world.storage::<Collider>();
+    let transforms = world.storage::<Transform>();
+
+    collision_world.retain(|entity| colliders.contains(entity) && transforms.contains(entity));
+    for (entity, collider) in colliders.iter() {
+        if let Some(transform) = transforms.get(entity) {
+            collision_world.insert(entity, collider.world_shape(transform), collider.layer);
+        }
+    }
+
+    // the tile map is only copied again when changed
+    let tile_map = world.resource::<TileMap>();
+    if collision_world.tile_map() != tile_map.as_deref() {
+        collision_world.set_tile_map(tile_map.as_deref().cloned());
+    }
+}
+
+/// Places the sprites and shapes of entities at their `Transform`.
+/// Entities with a `PreviousTransform` are placed in between both, by the
+/// interpolation alpha of the `Time` resource. Entities with a `SceneNode`
+/// are placed where their node is in the world, once the `SceneGraph`
+/// resource is propagated.
+pub fn sync_transforms(world: &mut World, _ctx: &mut Context) {
+    let placements = placements(world);
+
+    for (entity, sprite) in world.storage_mut::<Sprite>().iter_mut() {
+        if let Some(transform) = placements.get(&entity) {
+            sprite.set_transform(transform);
+        }
+    }
+
+    for (entity, shape) in world.storage_mut::<Shape>().iter_mut() {
+        if let Some(transform) = placements.get(&entity) {
+            shape.set_transform(transform);
+        }
+    }
+}
+
+/// Computes where every entity with a `Transform` should be drawn, moving
+/// the nodes of the `SceneGraph` resource along with their entities.
+fn placements(world: &World) -> HashMap<Entity, Transform> {
+    let alpha = world.resource::<Time>().map_or(1.0, |time| time.alpha());
+    let transforms = world.storage::<Transform>();
+    let previous_transforms = world.storage::<PreviousTransform>();
+
+    let mut placements: HashMap<Entity, Transform> = transforms
+        .iter()
+        .map(|(entity, transform)| {
+            let placement = match previous_transforms.get(entity) {
+                Some(previous) => previous.0.lerp(transform, alpha),
+                None => *transform,
+            };
+            (entity, placement)
+        })
+        .collect();
+
+    if let Some(mut scene_graph) = world.resource_mut::<SceneGraph>() {
+        let scene_nodes = world.storage::<SceneNode>();
+        for (entity, scene_node) in scene_nodes.iter() {
+            if let (Some(placement), Some(node)) =
+                (placements.get(&entity), scene_graph.get_mut(scene_node.0))
+            {
+                node.transform = *placement;
+            }
+        }
+
+        scene_graph.propagate();
+        for (entity, scene_node) in scene_nodes.iter() {
+            if let (Some(placement), Some(global)) = (
+                placements.get_mut(&entity),
+                scene_graph.global(scene_node.0),
+            ) {
+                *placement = global;
+            }
+        }
+    }
+
+    placements
+}
+
+/// Draws every shape, then every sprite, as seen by `camera`.
+pub fn draw(world: &mut World, ctx: &mut Context, camera: &Camera2D) {
+    for (_, shape) in world.storage_mut::<Shape>().iter_mut() {
+        shape.draw_with_camera(ctx, camera);
+    }
+
+    for (_, sprite) in world.storage_mut::<Sprite>().iter_mut() {
+        sprite.draw_with_camera(ctx, camera);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec2;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn assert_near(transform: Transform, translation: Vec2, rotation: f32) {
+        assert!(
+            transform.translation.distance(translation) < 1e-4,
+            "{:?} should be at {:?}",
+            transform,
+            translation
+        );
+        assert!((transform.rotation - rotation).abs() < 1e-4);
+    }
+
+    #[test]
+    fn places_entities_at_their_transform() {
+        let mut world = World::new();
+        let entity = world.spawn();
+        world.insert(entity, Transform::from_translation(Vec2::new(3.0, 4.0)));
+
+        assert_near(placements(&world)[&entity], Vec2::new(3.0, 4.0), 0.0);
+    }
+
+    #[test]
+    fn places_scene_nodes_relative_to_their_parent() {
+        let mut world = World::new();
+        let mut scene_graph = SceneGraph::new();
+        let ship_node = scene_graph.add("ship", Transform::IDENTITY);
+        let cannon_node = scene_graph
+            .add_child(ship_node, "cannon", Transform::IDENTITY)
+            .unwrap();
+        world.insert_resource(scene_graph);
+
+        let ship = world.spawn();
+        world.insert(ship, SceneNode(ship_node));
+        world.insert(
+            ship,
+            Transform {
+                rotation: FRAC_PI_2,
+                ..Transform::from_translation(Vec2::new(10.0, 0.0))
+            },
+        );
+        let cannon = world.spawn();
+        world.insert(cannon, SceneNode(cannon_node));
+        world.insert(cannon, Transform::from_translation(Vec2::new(5.0, 0.0)));
+
+        let placements = placements(&world);
+        assert_near(placements[&ship], Vec2::new(10.0, 0.0), FRAC_PI_2);
+        // the cannon turns along with the ship
+        assert_near(placements[&cannon], Vec2::new(10.0, 5.0), FRAC_PI_2);
+        let scene_graph = world.resource::<SceneGraph>().unwrap();
+        assert_near(
+            scene_graph.global(cannon_node).unwrap(),
+            Vec2::new(10.0, 5.0),
+            FRAC_PI_2,
+        );
+    }
+
+    #[test]
+    fn places_scene_nodes_without_a_graph_at_their_transform() {
+        let mut world = World::new();
+        let node = SceneGraph::new().add("orphan", Transform::IDENTITY);
+        let entity = world.spawn();
+        world.insert(entity, SceneNode(node));
+        world.insert(entity, Transform::from_translation(Vec2::new(1.0, 2.0)));
+
+        assert_near(placements(&world)[&entity], Vec2::new(1.0, 2.0), 0.0);
+    }
+}
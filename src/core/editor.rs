@@ -1,12 +1,21 @@
-use std::path::Path;
-
 use egui::*;
 use egui_miniquad::EguiMq;
 use miniquad::*;
+use std::path::Path;
 
+use super::assets::Handle;
+use super::input_map::{AxisSide, Binding, InputMap};
+use super::post_process::PostProcess;
 use super::sprite::Sprite;
+use super::texture::Texture;
+use super::viewport::Viewport;
+
+/// The path of the default background image. It should be loaded with a
+/// repeating wrap mode.
+pub const BACKGROUND_PATH: &str = "src/content/editor-background.png";
 
-const BACKGROUND_PATH: &str = "src/content/editor-background.png";
+/// The path of the input bindings, edited in the controls section.
+pub const INPUT_MAP_PATH: &str = "src/content/input.toml";
 
 // TODO: add more documentation!
 /// A level editor.
@@ -17,19 +26,13 @@ pub struct Editor {
 }
 
 impl Editor {
-    /// Creates an editor with a GUI.
-    pub fn new(ctx: &mut miniquad::Context) -> Self {
+    /// Creates an editor with a GUI, tiling `background` behind everything.
+    pub fn new(ctx: &mut miniquad::Context, background: Handle<Texture>) -> Self {
         let image_pos = glam::Vec2::new(0.0, 0.0);
-        let image_path = Path::new(BACKGROUND_PATH);
-        let texture_params = TextureParams {
-            filter: FilterMode::Nearest,
-            wrap: TextureWrap::Repeat,
-            ..Default::default()
-        };
-        let (screen_size_x, screen_size_y) = ctx.screen_size();
+        let virtual_size = Viewport::default().virtual_size;
 
-        let mut background_image = Sprite::with_params(ctx, image_pos, image_path, texture_params);
-        background_image.scale_to(screen_size_x, screen_size_y);
+        let mut background_image = Sprite::new(image_pos, background);
+        background_image.scale_to(virtual_size.x, virtual_size.y);
 
         Self {
             egui_mq: EguiMq::new(ctx),
@@ -38,10 +41,32 @@ impl Editor {
     }
 }
 
-impl EventHandler for Editor {
-    fn update(&mut self, _ctx: &mut miniquad::Context) {}
+impl Editor {
+    /// Checks whether the GUI is using the mouse, because it's over a panel
+    /// or dragging something. Games should ignore clicks meanwhile.
+    pub fn wants_pointer(&self) -> bool {
+        self.egui_mq.egui_ctx().wants_pointer_input()
+    }
 
-    fn draw(&mut self, ctx: &mut miniquad::Context) {
+    /// Draws the GUI, with a section listing the effects of `post_process`
+    /// that allows toggling, tuning and reordering them, and another one
+    /// listing the actions of `input_map` that allows rebinding them.
+    pub fn draw_with_settings(
+        &mut self,
+        ctx: &mut miniquad::Context,
+        post_process: &mut PostProcess,
+        input_map: &mut InputMap,
+    ) {
+        self.draw_ui(ctx, Some(post_process), Some(input_map));
+    }
+
+    /// Runs and draws the GUI.
+    fn draw_ui(
+        &mut self,
+        ctx: &mut miniquad::Context,
+        post_process: Option<&mut PostProcess>,
+        input_map: Option<&mut InputMap>,
+    ) {
         let mut ordered_quit = false;
 
         self.egui_mq.run(ctx, |_ctx, egui_ctx| {
@@ -55,6 +80,16 @@ impl EventHandler for Editor {
 
             SidePanel::left("left_panel").show(egui_ctx, |ui| {
                 ui.heading("Rusty Engine");
+
+                if let Some(post_process) = post_process {
+                    ui.separator();
+                    post_process_ui(ui, post_process);
+                }
+
+                if let Some(input_map) = input_map {
+                    ui.separator();
+                    input_map_ui(ui, input_map);
+                }
             });
         });
 
@@ -64,6 +99,120 @@ impl EventHandler for Editor {
 
         self.egui_mq.draw(ctx);
     }
+}
+
+/// Lists the effects of `post_process`, in the order they are applied.
+fn post_process_ui(ui: &mut Ui, post_process: &mut PostProcess) {
+    enum Move {
+        Up(usize),
+        Down(usize),
+    }
+
+    ui.label("Post processing");
+
+    let count = post_process.effects().len();
+    let mut requested_move = None;
+
+    for (i, effect) in post_process.effects_mut().iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            if ui.add_enabled(i > 0, Button::new("⏶")).clicked() {
+                requested_move = Some(Move::Up(i));
+            }
+            if ui.add_enabled(i + 1 < count, Button::new("⏷")).clicked() {
+                requested_move = Some(Move::Down(i));
+            }
+            ui.checkbox(&mut effect.enabled, effect.name.as_str());
+        });
+        ui.add(Slider::new(&mut effect.intensity, 0.0..=1.0).text("intensity"));
+    }
+
+    match requested_move {
+        Some(Move::Up(i)) => post_process.move_up(i),
+        Some(Move::Down(i)) => post_process.move_down(i),
+        None => (),
+    }
+}
+
+/// Lists the actions of `input_map` along with their bindings. Clicking a
+/// binding waits for an input to replace it with.
+fn input_map_ui(ui: &mut Ui, input_map: &mut InputMap) {
+    ui.label("Controls");
+
+    let actions: Vec<String> = input_map.actions().map(str::to_owned).collect();
+    let mut requested_unbind = None;
+
+    for action in &actions {
+        ui.horizontal_wrapped(|ui| {
+            ui.label(action.as_str());
+
+            let bindings = input_map.bindings(action).to_vec();
+            for (i, binding) in bindings.iter().enumerate() {
+                let sides = match binding {
+                    Binding::KeyAxis { negative, positive } => vec![
+                        (Some(AxisSide::Negative), format!("{:?}", negative)),
+                        (Some(AxisSide::Positive), format!("{:?}", positive)),
+                    ],
+                    binding => vec![(None, binding.to_string())],
+                };
+
+                for (side, text) in sides {
+                    let text = if input_map.is_listening_to(action, i, side) {
+                        "Press any input...".to_owned()
+                    } else {
+                        text
+                    };
+                    if ui.button(text).clicked() {
+                        input_map.listen(action, i, side);
+                    }
+                }
+                if ui.small_button("✖").clicked() {
+                    requested_unbind = Some((action.clone(), i));
+                }
+            }
+
+            let add_text = if input_map.is_listening_to(action, bindings.len(), None) {
+                "Press any input..."
+            } else {
+                "+"
+            };
+            if ui.button(add_text).clicked() {
+                input_map.listen(action, bindings.len(), None);
+            }
+        });
+    }
+
+    if let Some((action, i)) = requested_unbind {
+        input_map.unbind(&action, i);
+    }
+
+    ui.horizontal(|ui| {
+        let path = Path::new(INPUT_MAP_PATH);
+        if ui.button("Save").clicked() {
+            if let Err(error) = input_map.save(path) {
+                error!("{}", error);
+            }
+        }
+        if ui.button("Reload").clicked() {
+            match InputMap::load(path) {
+                Ok(loaded) => *input_map = loaded,
+                Err(error) => error!("{}", error),
+            }
+        }
+        if ui
+            .add_enabled(input_map.is_listening(), Button::new("Cancel"))
+            .clicked()
+        {
+            input_map.stop_listening();
+        }
+    });
+}
+
+impl EventHandler for Editor {
+    fn update(&mut self, _ctx: &mut miniquad::Context) {}
+
+    fn draw(&mut self, ctx: &mut miniquad::Context) {
+        self.draw_ui(ctx, None, None);
+    }
 
     fn mouse_motion_event(&mut self, _: &mut miniquad::Context, x: f32, y: f32) {
         self.egui_mq.mouse_motion_event(x, y);
@@ -116,8 +265,4 @@ impl EventHandler for Editor {
     fn key_up_event(&mut self, _ctx: &mut miniquad::Context, keycode: KeyCode, keymods: KeyMods) {
         self.egui_mq.key_up_event(keycode, keymods);
     }
-
-    fn resize_event(&mut self, _ctx: &mut GraphicsContext, width: f32, height: f32) {
-        self.background_image.scale_to(width, height);
-    }
 }
@@ -0,0 +1,78 @@
+use image::ImageError;
+use miniquad::ShaderError;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// An error raised by the engine while loading or creating resources.
+#[derive(Debug)]
+pub enum EngineError {
+    /// A file could not be opened or read.
+    Io {
+        /// The path of the file.
+        path: PathBuf,
+        /// The underlying error.
+        source: io::Error,
+    },
+    /// An image could not be decoded.
+    Decode {
+        /// The path of the image.
+        path: PathBuf,
+        /// The underlying error.
+        source: ImageError,
+    },
+    /// A data file could be read, but its contents are not valid.
+    Parse {
+        /// The path of the file.
+        path: PathBuf,
+        /// What went wrong.
+        message: String,
+    },
+    /// Data could not be turned into the format of the file it's saved to.
+    Serialize {
+        /// The path of the file.
+        path: PathBuf,
+        /// What went wrong.
+        message: String,
+    },
+    /// A vertex or fragment shader failed to compile.
+    ShaderCompile(ShaderError),
+    /// The shaders compiled, but they could not be linked into a pipeline.
+    Pipeline(String),
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, source } => write!(f, "cannot open {:?}: {}", path, source),
+            Self::Decode { path, source } => write!(f, "cannot decode {:?}: {}", path, source),
+            Self::Parse { path, message } => write!(f, "cannot parse {:?}: {}", path, message),
+            Self::Serialize { path, message } => {
+                write!(f, "cannot serialize {:?}: {}", path, message)
+            }
+            Self::ShaderCompile(error) => write!(f, "cannot compile shader: {}", error),
+            Self::Pipeline(message) => write!(f, "cannot create pipeline: {}", message),
+        }
+    }
+}
+
+impl Error for EngineError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Decode { source, .. } => Some(source),
+            Self::ShaderCompile(error) => Some(error),
+            Self::Parse { .. } | Self::Serialize { .. } | Self::Pipeline(_) => None,
+        }
+    }
+}
+
+impl From<ShaderError> for EngineError {
+    fn from(error: ShaderError) -> Self {
+        match error {
+            ShaderError::LinkError(message) => Self::Pipeline(message),
+            error => Self::ShaderCompile(error),
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use glam::Vec2;
+
+/// The amount of gamepads, and so of local players, tracked at once.
+pub const MAX_PLAYERS: usize = 4;
+
+/// Something that happened to a gamepad during the current frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GamepadEvent {
+    /// A gamepad was plugged in, and given to a player.
+    Connected {
+        /// The slot of the player, from 0 to `MAX_PLAYERS - 1`.
+        player: usize,
+    },
+    /// The gamepad of a player was unplugged. The slot is kept for it, so
+    /// plugging it back in gives it to the same player.
+    Disconnected {
+        /// The slot of the player, from 0 to `MAX_PLAYERS - 1`.
+        player: usize,
+    },
+}
+
+/// How a deadzone handles the two axes of a stick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DeadzoneShape {
+    /// Each axis is handled on its own, which snaps diagonals to the axes.
+    Axial,
+    /// The distance from the center is handled, which keeps the direction
+    /// of the stick. Best for free movement.
+    #[default]
+    Radial,
+}
+
+/// How the values left by a deadzone are mapped, from 0.0 to 1.0.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ResponseCurve {
+    /// Values are kept as they are.
+    #[default]
+    Linear,
+    /// Values are squared, giving more precision to small movements.
+    Quadratic,
+    /// Values are cubed, giving even more precision to small movements.
+    Cubic,
+    /// Values are raised to the given power.
+    Exponent(f32),
+}
+
+impl ResponseCurve {
+    /// Maps a value from 0.0 to 1.0.
+    pub fn apply(&self, value: f32) -> f32 {
+        match *self {
+            Self::Linear => value,
+            Self::Quadratic => value * value,
+            Self::Cubic => value * value * value,
+            Self::Exponent(exponent) => value.powf(exponent),
+        }
+    }
+}
+
+/// Ignores the small values reported by worn sticks and triggers at rest,
+/// and rescales the rest so they still reach the full range.
+///
+/// # Example
+/// ```rust
+/// let deadzone = Deadzone {
+///     curve: ResponseCurve::Quadratic,
+///     ..Deadzone::stick()
+/// };
+///
+/// assert_eq!(deadzone.apply_stick(Vec2::new(0.1, 0.0)), Vec2::ZERO);
+/// assert_eq!(deadzone.apply_stick(Vec2::new(1.0, 0.0)), Vec2::new(1.0, 0.0));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Deadzone {
+    pub shape: DeadzoneShape,
+    /// Values below this are ignored.
+    pub inner: f32,
+    /// Values above this count as fully pressed.
+    pub outer: f32,
+    pub curve: ResponseCurve,
+}
+
+impl Deadzone {
+    /// Creates the default deadzone for sticks.
+    pub const fn stick() -> Self {
+        Self {
+            shape: DeadzoneShape::Radial,
+            inner: 0.15,
+            outer: 0.95,
+            curve: ResponseCurve::Linear,
+        }
+    }
+
+    /// Creates the default deadzone for triggers.
+    pub const fn trigger() -> Self {
+        Self {
+            shape: DeadzoneShape::Axial,
+            inner: 0.05,
+            outer: 1.0,
+            curve: ResponseCurve::Linear,
+        }
+    }
+
+    /// Applies the deadzone to a single axis, from -1.0 to 1.0.
+    pub fn apply_axis(&self, value: f32) -> f32 {
+        value.signum() * self.rescale(value.abs())
+    }
+
+    /// Applies the deadzone to both axes of a stick.
+    pub fn apply_stick(&self, value: Vec2) -> Vec2 {
+        match self.shape {
+            DeadzoneShape::Axial => Vec2::new(self.apply_axis(value.x), self.apply_axis(value.y)),
+            DeadzoneShape::Radial => {
+                let length = value.length();
+                if length <= self.inner {
+                    return Vec2::ZERO;
+                }
+
+                value / length * self.rescale(length)
+            }
+        }
+    }
+
+    /// Maps a distance from the rest position into 0.0 to 1.0.
+    fn rescale(&self, distance: f32) -> f32 {
+        if distance <= self.inner {
+            return 0.0;
+        }
+
+        let range = (self.outer - self.inner).max(f32::EPSILON);
+        self.curve
+            .apply(((distance - self.inner) / range).clamp(0.0, 1.0))
+    }
+}
+
+impl Default for Deadzone {
+    fn default() -> Self {
+        Self::stick()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CURVES: [ResponseCurve; 5] = [
+        ResponseCurve::Linear,
+        ResponseCurve::Quadratic,
+        ResponseCurve::Cubic,
+        ResponseCurve::Exponent(0.5),
+        ResponseCurve::Exponent(2.5),
+    ];
+
+    fn deadzone(shape: DeadzoneShape, inner: f32, outer: f32) -> Deadzone {
+        Deadzone {
+            shape,
+            inner,
+            outer,
+            curve: ResponseCurve::Linear,
+        }
+    }
+
+    #[test]
+    fn maps_the_endpoints_of_every_curve_to_themselves() {
+        for curve in CURVES {
+            assert_eq!(curve.apply(0.0), 0.0, "{:?}", curve);
+            assert_eq!(curve.apply(1.0), 1.0, "{:?}", curve);
+            assert!(curve.apply(0.5) > 0.0 && curve.apply(0.5) < 1.0);
+        }
+        assert_eq!(ResponseCurve::Quadratic.apply(0.5), 0.25);
+        assert_eq!(ResponseCurve::Cubic.apply(0.5), 0.125);
+    }
+
+    #[test]
+    fn rescales_values_between_the_inner_and_outer_edges() {
+        let deadzone = deadzone(DeadzoneShape::Axial, 0.2, 0.8);
+
+        assert_eq!(deadzone.apply_axis(0.1), 0.0);
+        assert_eq!(deadzone.apply_axis(0.2), 0.0);
+        assert!((deadzone.apply_axis(0.5) - 0.5).abs() < 1e-6);
+        assert_eq!(deadzone.apply_axis(0.8), 1.0);
+        assert_eq!(deadzone.apply_axis(0.95), 1.0);
+    }
+
+    #[test]
+    fn applies_the_curve_after_rescaling() {
+        let deadzone = Deadzone {
+            curve: ResponseCurve::Quadratic,
+            ..deadzone(DeadzoneShape::Axial, 0.2, 0.8)
+        };
+
+        assert!((deadzone.apply_axis(0.5) - 0.25).abs() < 1e-6);
+        assert_eq!(deadzone.apply_axis(1.0), 1.0);
+    }
+
+    #[test]
+    fn keeps_the_sign_of_axes() {
+        let deadzone = Deadzone {
+            curve: ResponseCurve::Quadratic,
+            ..deadzone(DeadzoneShape::Axial, 0.2, 0.8)
+        };
+
+        assert!((deadzone.apply_axis(-0.5) + 0.25).abs() < 1e-6);
+        assert_eq!(deadzone.apply_axis(-1.0), -1.0);
+        assert_eq!(deadzone.apply_axis(-0.1), 0.0);
+    }
+
+    #[test]
+    fn snaps_diagonals_to_the_axes_when_axial() {
+        let deadzone = deadzone(DeadzoneShape::Axial, 0.2, 1.0);
+
+        // barely off the horizontal axis
+        let value = deadzone.apply_stick(Vec2::new(0.9, 0.1));
+
+        assert_eq!(value.y, 0.0);
+        assert!((value.x - 0.875).abs() < 1e-6);
+    }
+
+    #[test]
+    fn keeps_the_direction_when_radial() {
+        let deadzone = deadzone(DeadzoneShape::Radial, 0.2, 1.0);
+        let stick = Vec2::new(0.6, 0.1);
+
+        let value = deadzone.apply_stick(stick);
+
+        assert!(value.normalize().abs_diff_eq(stick.normalize(), 1e-6));
+        assert!((value.length() - (stick.length() - 0.2) / 0.8).abs() < 1e-6);
+        // a diagonal neither axis would get past on its own
+        assert_ne!(deadzone.apply_stick(Vec2::new(0.18, 0.18)), Vec2::ZERO);
+        assert_eq!(deadzone.apply_stick(Vec2::new(0.1, 0.1)), Vec2::ZERO);
+    }
+
+    #[test]
+    fn reaches_the_full_range_past_the_outer_edge() {
+        for shape in [DeadzoneShape::Axial, DeadzoneShape::Radial] {
+            let deadzone = Deadzone {
+                shape,
+                ..Deadzone::stick()
+            };
+
+            let value = deadzone.apply_stick(Vec2::new(0.0, -1.0));
+
+            assert_eq!(value, Vec2::new(0.0, -1.0), "{:?}", shape);
+        }
+    }
+
+    #[test]
+    fn doesnt_divide_by_zero_when_the_edges_meet_or_cross() {
+        for (inner, outer) in [(0.5, 0.5), (0.5, 0.3)] {
+            let deadzone = deadzone(DeadzoneShape::Radial, inner, outer);
+
+            assert_eq!(deadzone.apply_axis(0.4), 0.0);
+            assert_eq!(deadzone.apply_axis(0.6), 1.0);
+            assert_eq!(deadzone.apply_axis(-0.6), -1.0);
+            assert_eq!(
+                deadzone.apply_stick(Vec2::new(0.6, 0.0)),
+                Vec2::new(1.0, 0.0)
+            );
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use super::error::EngineError;
 use super::vertex::Vertex;
 use miniquad::*;
 
@@ -20,9 +21,49 @@ pub struct ShaderParams {
     pub meta: ShaderMeta,
 }
 
+/// How drawn colors are combined with the colors already drawn below them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlendMode {
+    /// Replaces the colors below, ignoring the alpha.
+    Opaque,
+    /// Mixes with the colors below depending on the alpha. Used by default.
+    #[default]
+    Alpha,
+    /// Adds to the colors below, scaled by the alpha. Useful for lights and fire.
+    Additive,
+    /// Multiplies the colors below, ignoring the alpha. Useful for shadows.
+    Multiply,
+}
+
+impl BlendMode {
+    /// Gets the blend state a pipeline needs to blend this way.
+    pub fn blend_state(self) -> Option<BlendState> {
+        let (source, destination) = match self {
+            BlendMode::Opaque => return None,
+            BlendMode::Alpha => (
+                BlendFactor::Value(BlendValue::SourceAlpha),
+                BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+            ),
+            BlendMode::Additive => (
+                BlendFactor::Value(BlendValue::SourceAlpha),
+                BlendFactor::One,
+            ),
+            BlendMode::Multiply => (
+                BlendFactor::Value(BlendValue::DestinationColor),
+                BlendFactor::Zero,
+            ),
+        };
+
+        Some(BlendState::new(Equation::Add, source, destination))
+    }
+}
+
 impl GraphicsHandler {
     /// Creates a new instance by defining a `Pipeline` with the `primitive_type` and `shader_params` in it.
     /// Constructs a binding by creating a vertex and index buffers with the provided vertices and indices arrays.
+    ///
+    /// # Panics
+    /// Panics if the shaders fail to compile. See `try_new` for a fallible version.
     pub fn new(
         ctx: &mut Context,
         vertices: &[Vertex],
@@ -30,6 +71,18 @@ impl GraphicsHandler {
         primitive_type: PrimitiveType,
         shader_params: ShaderParams,
     ) -> Self {
+        Self::try_new(ctx, vertices, indices, primitive_type, shader_params)
+            .expect("Cannot create the graphics handler")
+    }
+
+    /// Same as `new`, but returns an error if the shaders fail to compile.
+    pub fn try_new(
+        ctx: &mut Context,
+        vertices: &[Vertex],
+        indices: &[u16],
+        primitive_type: PrimitiveType,
+        shader_params: ShaderParams,
+    ) -> Result<Self, EngineError> {
         info!("Creating new GraphicsHandler");
 
         let vertex_buffer = Buffer::immutable(ctx, BufferType::VertexBuffer, vertices);
@@ -44,13 +97,7 @@ impl GraphicsHandler {
             images: vec![texture],
         };
 
-        let shader = Shader::new(
-            ctx,
-            shader_params.vertex_shader,
-            shader_params.fragment_shader,
-            shader_params.meta,
-        )
-        .unwrap();
+        let shader = compile_shader(ctx, shader_params)?;
 
         // set the pipeline's parameters, as well as its shader attributes
         let pipeline = Pipeline::with_params(
@@ -58,16 +105,17 @@ impl GraphicsHandler {
             &[BufferLayout::default()],
             &[
                 VertexAttribute::new("pos", VertexFormat::Float2),
-                VertexAttribute::new("color0", VertexFormat::Float3),
+                VertexAttribute::new("color0", VertexFormat::Float4),
             ],
             shader,
             PipelineParams {
                 primitive_type,
+                color_blend: BlendMode::default().blend_state(),
                 ..Default::default()
             },
         );
 
-        Self { pipeline, bindings }
+        Ok(Self { pipeline, bindings })
     }
 
     /// Allocates a new texture into the GPU and returns the pipeline
@@ -90,6 +138,10 @@ impl GraphicsHandler {
     }
 
     /// Allocates a new texture into the GPU with the texture wrap mode specified.
+    ///
+    /// # Panics
+    /// Panics if the shaders fail to compile.
+    /// See `try_from_texture_with_params` for a fallible version.
     pub fn from_texture_with_params(
         ctx: &mut Context,
         img_size: (u32, u32),
@@ -97,9 +149,43 @@ impl GraphicsHandler {
         shader_params: ShaderParams,
         texture_params: TextureParams,
     ) -> Self {
+        Self::try_from_texture_with_params(ctx, img_size, img_bytes, shader_params, texture_params)
+            .expect("Cannot create the graphics handler")
+    }
+
+    /// Same as `from_texture_with_params`, but returns an error if the shaders fail to compile.
+    pub fn try_from_texture_with_params(
+        ctx: &mut Context,
+        img_size: (u32, u32),
+        img_bytes: &[u8],
+        shader_params: ShaderParams,
+        texture_params: TextureParams,
+    ) -> Result<Self, EngineError> {
         info!("Creating new GraphicsHandler from a texture");
 
         let (img_width, img_height) = img_size;
+        let texture = Texture::new(
+            ctx,
+            TextureAccess::Static,
+            Some(img_bytes),
+            TextureParams {
+                width: img_width,
+                height: img_height,
+                ..texture_params
+            },
+        );
+
+        Self::try_from_gpu_texture(ctx, texture, shader_params)
+    }
+
+    /// Creates a quad covering the whole clip space (-1.0 to 1.0), textured
+    /// with an already allocated GPU texture, such as the color attachment of
+    /// a render pass.
+    pub fn try_from_gpu_texture(
+        ctx: &mut Context,
+        texture: Texture,
+        shader_params: ShaderParams,
+    ) -> Result<Self, EngineError> {
         let vertices: [Vertex; 4] = [
             Vertex::with_tex(-1.0, 1.0, 0.0, 1.0),
             Vertex::with_tex(1.0, 1.0, 1.0, 1.0),
@@ -110,44 +196,98 @@ impl GraphicsHandler {
 
         let vertex_buffer = Buffer::immutable(ctx, BufferType::VertexBuffer, &vertices);
         let index_buffer = Buffer::immutable(ctx, BufferType::IndexBuffer, &indices);
-        let texture = Texture::new(
+
+        let bindings = Bindings {
+            index_buffer,
+            vertex_buffers: vec![vertex_buffer],
+            images: vec![texture],
+        };
+
+        let shader = compile_shader(ctx, shader_params)?;
+
+        // set the pipeline's parameters, as well as its shader attributes
+        let pipeline = Pipeline::with_params(
             ctx,
-            TextureAccess::Static,
-            Some(img_bytes),
-            TextureParams {
-                width: img_width,
-                height: img_height,
-                ..texture_params
+            &[BufferLayout::default()],
+            &[
+                VertexAttribute::new("pos", VertexFormat::Float2),
+                VertexAttribute::new("color0", VertexFormat::Float4),
+                VertexAttribute::new("tex0", VertexFormat::Float2),
+            ],
+            shader,
+            PipelineParams {
+                color_blend: BlendMode::default().blend_state(),
+                ..Default::default()
             },
         );
 
+        Ok(Self { pipeline, bindings })
+    }
+
+    /// Creates a pipeline with dynamic vertex and index buffers, meant to be
+    /// refilled every frame. Both buffers are sized to hold up to `max_vertices`
+    /// and `max_indices` elements. The texture slot starts empty and has to be
+    /// set before drawing, see `bindings_mut`.
+    ///
+    /// # Panics
+    /// Panics if the shaders fail to compile. See `try_streaming` for a fallible version.
+    pub fn streaming(
+        ctx: &mut Context,
+        max_vertices: usize,
+        max_indices: usize,
+        shader_params: ShaderParams,
+    ) -> Self {
+        Self::try_streaming(ctx, max_vertices, max_indices, shader_params)
+            .expect("Cannot create the graphics handler")
+    }
+
+    /// Same as `streaming`, but returns an error if the shaders fail to compile.
+    pub fn try_streaming(
+        ctx: &mut Context,
+        max_vertices: usize,
+        max_indices: usize,
+        shader_params: ShaderParams,
+    ) -> Result<Self, EngineError> {
+        info!(
+            "Creating new streaming GraphicsHandler for {} vertices",
+            max_vertices
+        );
+
+        let vertex_buffer = Buffer::stream(
+            ctx,
+            BufferType::VertexBuffer,
+            max_vertices * std::mem::size_of::<Vertex>(),
+        );
+        let index_buffer = Buffer::stream(
+            ctx,
+            BufferType::IndexBuffer,
+            max_indices * std::mem::size_of::<u16>(),
+        );
+
         let bindings = Bindings {
             index_buffer,
             vertex_buffers: vec![vertex_buffer],
-            images: vec![texture],
+            images: vec![Texture::empty()],
         };
 
-        let shader = Shader::new(
-            ctx,
-            shader_params.vertex_shader,
-            shader_params.fragment_shader,
-            shader_params.meta,
-        )
-        .unwrap();
+        let shader = compile_shader(ctx, shader_params)?;
 
-        // set the pipeline's parameters, as well as its shader attributes
-        let pipeline = Pipeline::new(
+        let pipeline = Pipeline::with_params(
             ctx,
             &[BufferLayout::default()],
             &[
                 VertexAttribute::new("pos", VertexFormat::Float2),
-                VertexAttribute::new("color0", VertexFormat::Float3),
+                VertexAttribute::new("color0", VertexFormat::Float4),
                 VertexAttribute::new("tex0", VertexFormat::Float2),
             ],
             shader,
+            PipelineParams {
+                color_blend: BlendMode::default().blend_state(),
+                ..Default::default()
+            },
         );
 
-        Self { pipeline, bindings }
+        Ok(Self { pipeline, bindings })
     }
 
     /// Returns its pipeline.
@@ -159,4 +299,39 @@ impl GraphicsHandler {
     pub const fn bindings(&self) -> &Bindings {
         &self.bindings
     }
+
+    /// Frees the buffers and textures of its bindings from the GPU.
+    pub fn delete(&self) {
+        self.bindings.index_buffer.delete();
+        self.bindings
+            .vertex_buffers
+            .iter()
+            .for_each(|buffer| buffer.delete());
+        self.bindings
+            .images
+            .iter()
+            .for_each(|texture| texture.delete());
+    }
+
+    /// Changes how its pipeline blends the colors it draws.
+    pub fn set_blend_mode(&self, ctx: &mut Context, blend_mode: BlendMode) {
+        self.pipeline.set_blend(ctx, blend_mode.blend_state());
+    }
+
+    /// Returns its bindings, allowing to swap buffers or textures in between draws.
+    pub fn bindings_mut(&mut self) -> &mut Bindings {
+        &mut self.bindings
+    }
+}
+
+/// Compiles the shaders in `shader_params` into a shader program.
+fn compile_shader(ctx: &mut Context, shader_params: ShaderParams) -> Result<Shader, EngineError> {
+    let shader = Shader::new(
+        ctx,
+        shader_params.vertex_shader,
+        shader_params.fragment_shader,
+        shader_params.meta,
+    )?;
+
+    Ok(shader)
 }
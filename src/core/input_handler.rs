@@ -1,8 +1,14 @@
-use gilrs::{Axis, ConnectedGamepadsIterator, Gamepad, GamepadId, Gilrs};
+use super::camera::Camera2D;
+use super::gamepad::{Deadzone, GamepadEvent, MAX_PLAYERS};
+use super::viewport::Viewport;
+use gilrs::ff::{BaseEffect, BaseEffectType, Effect, EffectBuilder, Replay, Ticks};
+use gilrs::{Axis, Button, Event, EventType, Gamepad, GamepadId, Gilrs};
+use glam::Vec2;
 use miniquad::{EventHandler, KeyCode, MouseButton};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 /// Represents an analog stick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Stick {
     /// The left analog stick.
     Left,
@@ -10,44 +16,335 @@ pub enum Stick {
     Right,
 }
 
-/// Handles the input.
-/// Keeps track of the current gamepads connected as well as provides different input functionality.
-pub struct InputHandler {
-    #[doc(hidden)]
-    gilrs: Gilrs,
-    /// The current gamepad ID. Used to get the actual gamepad when needed.
-    current_gamepad_id: Option<GamepadId>,
+impl Stick {
+    /// Gets the horizontal and vertical axes of the stick.
+    const fn axes(self) -> (Axis, Axis) {
+        match self {
+            Self::Left => (Axis::LeftStickX, Axis::LeftStickY),
+            Self::Right => (Axis::RightStickX, Axis::RightStickY),
+        }
+    }
+}
+
+/// Represents an analog trigger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    /// The left trigger, usually called L2 or LT.
+    Left,
+    /// The right trigger, usually called R2 or RT.
+    Right,
+}
+
+/// The gamepad given to a player. Generic over the id of the gamepad, so
+/// players can be given gamepads without Gilrs.
+struct GamepadSlot<Id = GamepadId> {
+    /// The gamepad, which may be unplugged.
+    id: Option<Id>,
+    connected: bool,
+    /// The buttons that went down during this frame.
+    buttons_just_pressed: HashSet<Button>,
+    /// The buttons that went up during this frame.
+    buttons_just_released: HashSet<Button>,
+    /// The rumble playing, which stops when dropped.
+    rumble: Option<Effect>,
+}
+
+impl<Id> Default for GamepadSlot<Id> {
+    fn default() -> Self {
+        let id = None;
+        let connected = false;
+        let buttons_just_pressed = HashSet::new();
+        let buttons_just_released = HashSet::new();
+        let rumble = None;
+
+        Self {
+            id,
+            connected,
+            buttons_just_pressed,
+            buttons_just_released,
+            rumble,
+        }
+    }
+}
+
+/// Gives a gamepad plugged in to the player who had it before, or else to
+/// the first player who never had one, or else to the first player whose
+/// gamepad is unplugged. Returns the player, if there's room for it.
+fn connect_slot<Id: Copy + PartialEq>(slots: &mut [GamepadSlot<Id>], id: Id) -> Option<usize> {
+    let player = slot_of(slots, id)
+        .or_else(|| slots.iter().position(|slot| slot.id.is_none()))
+        .or_else(|| slots.iter().position(|slot| !slot.connected))?;
+
+    let slot = &mut slots[player];
+    if slot.id != Some(id) {
+        *slot = GamepadSlot::default();
+        slot.id = Some(id);
+    }
+    slot.connected = true;
+
+    Some(player)
+}
+
+/// Marks a gamepad as unplugged, keeping it for its player so plugging it
+/// back in gives it to them. Returns the player, if it had one.
+fn disconnect_slot<Id: Copy + PartialEq>(slots: &mut [GamepadSlot<Id>], id: Id) -> Option<usize> {
+    let player = slot_of(slots, id)?;
+    let slot = &mut slots[player];
+    slot.connected = false;
+    slot.rumble = None;
+
+    Some(player)
+}
+
+/// Gets the player having a gamepad.
+fn slot_of<Id: Copy + PartialEq>(slots: &[GamepadSlot<Id>], id: Id) -> Option<usize> {
+    slots.iter().position(|slot| slot.id == Some(id))
+}
+
+/// What the window reports: the keys and mouse buttons held, what changed
+/// during the frame, and the focus. Kept apart from the gamepads, so it
+/// doesn't need Gilrs.
+#[derive(Debug, Clone)]
+pub(crate) struct InputState {
     /// All the unique keys pressed at a given time.
     keys_pressed: HashSet<KeyCode>,
+    /// The keys that went down during this frame.
+    keys_just_pressed: HashSet<KeyCode>,
+    /// The keys that went up during this frame.
+    keys_just_released: HashSet<KeyCode>,
+    /// For how many seconds each held key has been held, up to the end of
+    /// the last frame.
+    key_held_durations: HashMap<KeyCode, f32>,
     /// Similar to `keys_pressed`, but for mouse buttons.
     mouse_buttons_pressed: HashSet<MouseButton>,
-    /// Stores the last key that was pressed after an event.
-    last_key_down: Option<KeyCode>,
-    /// Stores the last mouse position recorded after an event.
-    last_mouse_position: (usize, usize),
+    /// The mouse buttons that went down during this frame.
+    mouse_buttons_just_pressed: HashSet<MouseButton>,
+    /// The mouse buttons that went up during this frame.
+    mouse_buttons_just_released: HashSet<MouseButton>,
+    /// Where the mouse is, in window pixels from the top-left corner.
+    mouse_position: Vec2,
+    /// How much the mouse moved during this frame, in window pixels.
+    mouse_delta: Vec2,
+    /// How much the mouse wheel scrolled during this frame.
+    mouse_wheel_delta: Vec2,
+    /// Whether the window has focus.
+    has_focus: bool,
+}
+
+impl InputState {
+    pub(crate) fn key_down(&mut self, keycode: KeyCode, repeat: bool) {
+        // a repeat of a released key means its release was swallowed, so it's
+        // held again without counting as a new press
+        if self.keys_pressed.insert(keycode) {
+            if !repeat {
+                self.keys_just_pressed.insert(keycode);
+            }
+            self.key_held_durations.insert(keycode, 0.0);
+        }
+    }
+
+    pub(crate) fn key_up(&mut self, keycode: KeyCode) {
+        if self.keys_pressed.remove(&keycode) {
+            self.keys_just_released.insert(keycode);
+            self.key_held_durations.remove(&keycode);
+        }
+    }
+
+    fn mouse_button_down(&mut self, button: MouseButton, position: Vec2) {
+        if self.mouse_buttons_pressed.insert(button) {
+            self.mouse_buttons_just_pressed.insert(button);
+        }
+        self.mouse_motion(position);
+    }
+
+    fn mouse_button_up(&mut self, button: MouseButton, position: Vec2) {
+        if self.mouse_buttons_pressed.remove(&button) {
+            self.mouse_buttons_just_released.insert(button);
+        }
+        self.mouse_motion(position);
+    }
+
+    fn mouse_motion(&mut self, position: Vec2) {
+        self.mouse_delta += position - self.mouse_position;
+        self.mouse_position = position;
+    }
+
+    fn mouse_wheel(&mut self, delta: Vec2) {
+        self.mouse_wheel_delta += delta;
+    }
+
+    fn resized(&mut self) {
+        // going fullscreen or back swallows the release of what's held
+        self.release_all();
+    }
+
+    fn minimized(&mut self) {
+        self.has_focus = false;
+        self.release_all();
+    }
+
+    fn restored(&mut self) {
+        self.has_focus = true;
+    }
+
+    /// Releases every held key and mouse button, as if their release events
+    /// came in.
+    fn release_all(&mut self) {
+        for keycode in self.keys_pressed.drain() {
+            self.keys_just_released.insert(keycode);
+        }
+        self.key_held_durations.clear();
+
+        for button in self.mouse_buttons_pressed.drain() {
+            self.mouse_buttons_just_released.insert(button);
+        }
+    }
+
+    /// Forgets what changed during the frame, which lasted `delta` seconds,
+    /// and counts the time keys were held.
+    fn end_frame(&mut self, delta: f32) {
+        self.keys_just_pressed.clear();
+        self.keys_just_released.clear();
+        self.mouse_buttons_just_pressed.clear();
+        self.mouse_buttons_just_released.clear();
+        self.mouse_delta = Vec2::ZERO;
+        self.mouse_wheel_delta = Vec2::ZERO;
+
+        for duration in self.key_held_durations.values_mut() {
+            *duration += delta;
+        }
+    }
+}
+
+impl Default for InputState {
+    fn default() -> Self {
+        let keys_pressed = HashSet::new();
+        let keys_just_pressed = HashSet::new();
+        let keys_just_released = HashSet::new();
+        let key_held_durations = HashMap::new();
+        let mouse_buttons_pressed = HashSet::new();
+        let mouse_buttons_just_pressed = HashSet::new();
+        let mouse_buttons_just_released = HashSet::new();
+        let mouse_position = Vec2::ZERO;
+        let mouse_delta = Vec2::ZERO;
+        let mouse_wheel_delta = Vec2::ZERO;
+        let has_focus = true;
+
+        Self {
+            keys_pressed,
+            keys_just_pressed,
+            keys_just_released,
+            key_held_durations,
+            mouse_buttons_pressed,
+            mouse_buttons_just_pressed,
+            mouse_buttons_just_released,
+            mouse_position,
+            mouse_delta,
+            mouse_wheel_delta,
+            has_focus,
+        }
+    }
+}
+
+/// Handles the input.
+/// Keeps track of the current gamepads connected as well as provides different input functionality.
+///
+/// Each gamepad plugged in is given to a player, from 0 to `MAX_PLAYERS - 1`,
+/// who keeps it even if it's unplugged and plugged back in. Methods that
+/// don't take a player read the current gamepad, the one used last.
+///
+/// Besides what's held, it remembers what was pressed and released during
+/// the current frame, so games can react to the moment a button goes down.
+/// Those are forgotten by `end_frame`, which should be called once at the
+/// end of every update, after everything that reads them.
+///
+/// The window can swallow the release of what's held, for example when it's
+/// minimized or resized to fullscreen. Every key and mouse button held is
+/// then released, and shows up as just released, so nothing stays stuck.
+///
+/// # Example
+/// ```rust
+/// fn update(&mut self, ctx: &mut Context, time: &Time) -> SceneCommand {
+///     self.input_handler.update(ctx);
+///
+///     if self.input_handler.is_key_just_pressed(KeyCode::Space) {
+///         println!("Jump!");
+///     }
+///     if self.input_handler.key_held_duration(KeyCode::Space) > 0.5 {
+///         println!("Charging a higher jump");
+///     }
+///
+///     self.input_handler.end_frame(time.unscaled_delta());
+///     SceneCommand::None
+/// }
+/// ```
+pub struct InputHandler {
+    /// Reads the gamepads. There is none when the platform doesn't support
+    /// them.
+    #[doc(hidden)]
+    gilrs: Option<Gilrs>,
+    /// The gamepad of every player.
+    slots: [GamepadSlot; MAX_PLAYERS],
+    /// The player whose gamepad was used last.
+    current_player: Option<usize>,
+    /// The gamepads plugged in or unplugged during this frame.
+    gamepad_events: Vec<GamepadEvent>,
+    /// The deadzone applied to sticks.
+    pub stick_deadzone: Deadzone,
+    /// The deadzone applied to triggers.
+    pub trigger_deadzone: Deadzone,
+    /// The keys and mouse buttons held, and the focus of the window.
+    state: InputState,
 }
 
 impl InputHandler {
     /// Creates a default input handler.
     pub fn new() -> Self {
-        Self {
-            ..Default::default()
-        }
+        Self::default()
     }
 
     /// Gets all the connected gamepads.
-    pub fn gamepads(&self) -> ConnectedGamepadsIterator {
-        self.gilrs.gamepads()
+    pub fn gamepads(&self) -> impl Iterator<Item = (GamepadId, Gamepad<'_>)> {
+        self.gilrs.iter().flat_map(Gilrs::gamepads)
+    }
+
+    /// Gets the player whose gamepad was used last, if it's plugged in.
+    pub const fn current_player(&self) -> Option<usize> {
+        self.current_player
     }
 
     /// Gets the current used gamepad.
-    pub fn current_gamepad(&self) -> Option<Gamepad> {
-        if let Some(current_gamepad_id) = self.current_gamepad_id {
-            let gamepad = self.gilrs.gamepad(current_gamepad_id);
-            return Some(gamepad);
-        }
+    pub fn current_gamepad(&self) -> Option<Gamepad<'_>> {
+        self.current_player
+            .and_then(|player| self.player_gamepad(player))
+    }
+
+    /// Gets the gamepad of a player, if it's plugged in.
+    pub fn player_gamepad(&self, player: usize) -> Option<Gamepad<'_>> {
+        let id = self.slots.get(player)?.id?;
+
+        self.gilrs.as_ref()?.connected_gamepad(id)
+    }
+
+    /// Iterates over the players having a gamepad plugged in.
+    pub fn connected_players(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..MAX_PLAYERS).filter(|player| self.slots[*player].connected)
+    }
 
-        None
+    /// Gets the gamepads plugged in or unplugged during this frame.
+    pub fn gamepad_events(&self) -> &[GamepadEvent] {
+        &self.gamepad_events
+    }
+
+    /// Takes the gamepad away from a player, so the next one plugged in
+    /// can be given to them.
+    pub fn release_player(&mut self, player: usize) {
+        if let Some(slot) = self.slots.get_mut(player) {
+            *slot = GamepadSlot::default();
+        }
+        if self.current_player == Some(player) {
+            self.current_player = None;
+        }
     }
 
     /// Gets the given stick's axis values, ranging from -1.0 to 1.0.
@@ -60,87 +357,451 @@ impl InputHandler {
     /// println!("Pressing the left analog stick! X: {}, Y: {}", axis_value_x, axis_value_y);
     /// ```
     pub fn axis_values(&self, stick: Stick) -> (f32, f32) {
-        let (mut value_x, mut value_y) = (0.0, 0.0);
+        let value = self
+            .current_player
+            .map_or(Vec2::ZERO, |player| self.player_stick(player, stick));
 
-        if let Some(current_gamepad) = self.current_gamepad() {
-            let (axis_x, axis_y) = match stick {
-                Stick::Left => (Axis::LeftStickX, Axis::LeftStickY),
-                Stick::Right => (Axis::RightStickX, Axis::RightStickY),
-            };
+        (value.x, value.y)
+    }
+
+    /// Gets the position of a stick of a player's gamepad, ranging from -1.0
+    /// to 1.0 on each axis, with the `stick_deadzone` applied. Up is positive.
+    pub fn player_stick(&self, player: usize, stick: Stick) -> Vec2 {
+        let gamepad = match self.player_gamepad(player) {
+            Some(gamepad) => gamepad,
+            None => return Vec2::ZERO,
+        };
+        let (axis_x, axis_y) = stick.axes();
+        let value = Vec2::new(gamepad.value(axis_x), gamepad.value(axis_y));
+
+        self.stick_deadzone.apply_stick(value)
+    }
 
-            if let Some(axis_data_x) = current_gamepad.axis_data(axis_x) {
-                value_x = axis_data_x.value();
+    /// Gets how much a trigger of a player's gamepad is pressed, from 0.0 to
+    /// 1.0, with the `trigger_deadzone` applied.
+    pub fn player_trigger(&self, player: usize, trigger: Trigger) -> f32 {
+        let button = match trigger {
+            Trigger::Left => Button::LeftTrigger2,
+            Trigger::Right => Button::RightTrigger2,
+        };
+        let value = self
+            .player_gamepad(player)
+            .and_then(|gamepad| gamepad.button_data(button).map(|data| data.value()))
+            .unwrap_or(0.0);
+
+        self.trigger_deadzone.apply_axis(value)
+    }
+
+    /// Gets the value of an axis of a player's gamepad, ranging from -1.0 to
+    /// 1.0, with the deadzones applied. It's 0.0 when there's no gamepad.
+    pub fn player_axis_value(&self, player: usize, axis: Axis) -> f32 {
+        match axis {
+            Axis::LeftStickX => self.player_stick(player, Stick::Left).x,
+            Axis::LeftStickY => self.player_stick(player, Stick::Left).y,
+            Axis::RightStickX => self.player_stick(player, Stick::Right).x,
+            Axis::RightStickY => self.player_stick(player, Stick::Right).y,
+            axis => {
+                let value = self
+                    .player_gamepad(player)
+                    .map_or(0.0, |gamepad| gamepad.value(axis));
+                if matches!(axis, Axis::LeftZ | Axis::RightZ) {
+                    self.trigger_deadzone.apply_axis(value)
+                } else {
+                    value
+                }
             }
+        }
+    }
+
+    /// Checks whether the given button of a player's gamepad is held down.
+    pub fn is_player_button_pressed(&self, player: usize, button: Button) -> bool {
+        self.player_gamepad(player)
+            .is_some_and(|gamepad| gamepad.is_pressed(button))
+    }
+
+    /// Checks whether the given button of a player's gamepad went down
+    /// during this frame.
+    pub fn is_player_button_just_pressed(&self, player: usize, button: Button) -> bool {
+        self.slots
+            .get(player)
+            .is_some_and(|slot| slot.buttons_just_pressed.contains(&button))
+    }
 
-            if let Some(axis_data_y) = current_gamepad.axis_data(axis_y) {
-                value_y = axis_data_y.value();
+    /// Checks whether the given button of a player's gamepad went up during
+    /// this frame.
+    pub fn is_player_button_just_released(&self, player: usize, button: Button) -> bool {
+        self.slots
+            .get(player)
+            .is_some_and(|slot| slot.buttons_just_released.contains(&button))
+    }
+
+    /// Rumbles the gamepad of a player for `duration` seconds, replacing the
+    /// rumble playing. The strong motor shakes slowly and the weak one
+    /// quickly, with strengths from 0.0 to 1.0.
+    /// Returns whether the gamepad supports it.
+    pub fn rumble(&mut self, player: usize, strong: f32, weak: f32, duration: f32) -> bool {
+        let (id, gilrs) = match (self.slots.get(player), self.gilrs.as_mut()) {
+            (
+                Some(GamepadSlot {
+                    id: Some(id),
+                    connected: true,
+                    ..
+                }),
+                Some(gilrs),
+            ) => (*id, gilrs),
+            _ => return false,
+        };
+
+        let scheduling = Replay {
+            play_for: Ticks::from_ms((duration.max(0.0) * 1000.0) as u32),
+            ..Default::default()
+        };
+        let magnitude = |strength: f32| (strength.clamp(0.0, 1.0) * u16::MAX as f32) as u16;
+        let effect = EffectBuilder::new()
+            .add_effect(BaseEffect {
+                kind: BaseEffectType::Strong {
+                    magnitude: magnitude(strong),
+                },
+                scheduling,
+                ..Default::default()
+            })
+            .add_effect(BaseEffect {
+                kind: BaseEffectType::Weak {
+                    magnitude: magnitude(weak),
+                },
+                scheduling,
+                ..Default::default()
+            })
+            .gamepads(&[id])
+            .finish(gilrs)
+            .and_then(|effect| effect.play().map(|_| effect));
+
+        match effect {
+            Ok(effect) => {
+                self.slots[player].rumble = Some(effect);
+                true
+            }
+            Err(error) => {
+                debug!("Cannot rumble the gamepad of player {}: {}", player, error);
+                false
             }
         }
+    }
 
-        (value_x, value_y)
+    /// Stops the rumble of a player's gamepad.
+    pub fn stop_rumble(&mut self, player: usize) {
+        if let Some(slot) = self.slots.get_mut(player) {
+            slot.rumble = None;
+        }
     }
 
     /// Gets all pressed keys at a given moment.
     pub const fn pressed_keys(&self) -> &HashSet<KeyCode> {
-        &self.keys_pressed
+        &self.state.keys_pressed
+    }
+
+    /// Gets the keys that went down during this frame.
+    pub const fn just_pressed_keys(&self) -> &HashSet<KeyCode> {
+        &self.state.keys_just_pressed
     }
 
     /// Check whether the given key is in the last pressed ones.
     pub fn is_key_pressed(&self, key: KeyCode) -> bool {
-        self.keys_pressed.contains(&key)
+        self.state.keys_pressed.contains(&key)
+    }
+
+    /// Checks whether the given key went down during this frame.
+    /// Key repeats don't count.
+    pub fn is_key_just_pressed(&self, key: KeyCode) -> bool {
+        self.state.keys_just_pressed.contains(&key)
+    }
+
+    /// Checks whether the given key went up during this frame.
+    pub fn is_key_just_released(&self, key: KeyCode) -> bool {
+        self.state.keys_just_released.contains(&key)
+    }
+
+    /// Gets for how many seconds the given key has been held, up to the end
+    /// of the last frame. It's 0.0 for keys that aren't held, or that went
+    /// down during this frame.
+    pub fn key_held_duration(&self, key: KeyCode) -> f32 {
+        self.state
+            .key_held_durations
+            .get(&key)
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    /// Gets where the mouse is, in window pixels from the top-left corner.
+    pub const fn mouse_position(&self) -> Vec2 {
+        self.state.mouse_position
     }
 
-    /// Helper to apply _little_ code after a key is released.
-    /// Emphasis on _little_, as this does not allow passing function calls into `func`.
+    /// Gets where the mouse is in virtual resolution coordinates, for a
+    /// window of `window_size` pixels. It's outside of the virtual
+    /// resolution while over the bars around it.
     ///
     /// # Example
     /// ```rust
-    /// use miniquad::KeyCode;
-    ///
-    /// let input_handler = InputHandler::new();
-    /// input_handler.on_key_release(KeyCode::Space, || println!("Stop jump!"));
+    /// let (width, height) = ctx.screen_size();
+    /// let position = input_handler.mouse_virtual_position(&viewport, Vec2::new(width, height));
     /// ```
-    pub fn on_key_release<F>(&mut self, key: KeyCode, mut func: F)
-    where
-        F: FnMut(),
-    {
-        if self.last_key_down == Some(key) {
-            func();
-        }
+    pub fn mouse_virtual_position(&self, viewport: &Viewport, window_size: Vec2) -> Vec2 {
+        viewport.window_to_virtual(self.state.mouse_position, window_size)
+    }
+
+    /// Gets where the mouse is in the world seen by `camera`, for a window
+    /// of `window_size` pixels.
+    pub fn mouse_world_position(
+        &self,
+        camera: &Camera2D,
+        viewport: &Viewport,
+        window_size: Vec2,
+    ) -> Vec2 {
+        camera.window_to_world(self.state.mouse_position, window_size, viewport)
+    }
+
+    /// Gets how much the mouse moved during this frame, in window pixels.
+    pub const fn mouse_delta(&self) -> Vec2 {
+        self.state.mouse_delta
+    }
+
+    /// Gets how much the mouse wheel scrolled during this frame. Scrolling
+    /// up is positive on the y axis.
+    pub const fn mouse_wheel_delta(&self) -> Vec2 {
+        self.state.mouse_wheel_delta
+    }
+
+    /// Gets all pressed mouse buttons at a given moment.
+    pub const fn pressed_mouse_buttons(&self) -> &HashSet<MouseButton> {
+        &self.state.mouse_buttons_pressed
+    }
+
+    /// Gets the mouse buttons that went down during this frame.
+    pub const fn just_pressed_mouse_buttons(&self) -> &HashSet<MouseButton> {
+        &self.state.mouse_buttons_just_pressed
+    }
+
+    /// Checks whether the given mouse button is held down.
+    pub fn is_mouse_button_pressed(&self, button: MouseButton) -> bool {
+        self.state.mouse_buttons_pressed.contains(&button)
+    }
+
+    /// Checks whether the given mouse button went down during this frame.
+    pub fn is_mouse_button_just_pressed(&self, button: MouseButton) -> bool {
+        self.state.mouse_buttons_just_pressed.contains(&button)
+    }
 
-        self.last_key_down = None;
+    /// Checks whether the given mouse button went up during this frame.
+    pub fn is_mouse_button_just_released(&self, button: MouseButton) -> bool {
+        self.state.mouse_buttons_just_released.contains(&button)
     }
 
-    /// Checks if a gamepad is found. If it's a new one, mark it as the currently used.
+    /// Checks whether the given button of the current gamepad is held down.
+    pub fn is_gamepad_button_pressed(&self, button: Button) -> bool {
+        self.current_player
+            .is_some_and(|player| self.is_player_button_pressed(player, button))
+    }
+
+    /// Checks whether the given button of the current gamepad went down
+    /// during this frame.
+    pub fn is_gamepad_button_just_pressed(&self, button: Button) -> bool {
+        self.current_player
+            .is_some_and(|player| self.is_player_button_just_pressed(player, button))
+    }
+
+    /// Checks whether the given button of the current gamepad went up
+    /// during this frame.
+    pub fn is_gamepad_button_just_released(&self, button: Button) -> bool {
+        self.current_player
+            .is_some_and(|player| self.is_player_button_just_released(player, button))
+    }
+
+    /// Gets the value of an axis of the current gamepad, ranging from -1.0
+    /// to 1.0, with the deadzones applied. It's 0.0 when there's no gamepad.
+    pub fn gamepad_axis_value(&self, axis: Axis) -> f32 {
+        self.current_player
+            .map_or(0.0, |player| self.player_axis_value(player, axis))
+    }
+
+    /// Checks whether the window has focus. miniquad only reports the window
+    /// being minimized and restored, so that's what it follows.
+    pub const fn has_focus(&self) -> bool {
+        self.state.has_focus
+    }
+
+    /// Releases every held key and mouse button, as if their release events
+    /// came in. They count as just released during this frame.
+    ///
+    /// Gamepad buttons are left alone: Gilrs reads them from the device
+    /// rather than from the window, so their releases are never swallowed,
+    /// and they still read as held while they are.
+    pub fn release_all(&mut self) {
+        self.state.release_all();
+    }
+
+    /// Ends the current frame, which lasted `delta` seconds: forgets what
+    /// was just pressed and released and how much the mouse moved, and
+    /// counts the time keys were held.
+    pub fn end_frame(&mut self, delta: f32) {
+        self.state.end_frame(delta);
+        self.gamepad_events.clear();
+        for slot in &mut self.slots {
+            slot.buttons_just_pressed.clear();
+            slot.buttons_just_released.clear();
+        }
+    }
+
+    /// Processes the pending gamepad events. The gamepad sending one is
+    /// marked as the currently used.
     fn check_for_gamepad(&mut self) {
-        if let Some(event) = self.gilrs.next_event() {
-            if self.current_gamepad_id == Some(event.id) {
-                return;
+        while let Some(Event { id, event, .. }) = self.gilrs.as_mut().and_then(Gilrs::next_event) {
+            let player = match event {
+                EventType::Connected => self.connect(id),
+                EventType::Disconnected => {
+                    self.disconnect(id);
+                    continue;
+                }
+                _ => self.player_of(id),
+            };
+            let player = match player {
+                Some(player) => player,
+                None => continue,
+            };
+
+            let slot = &mut self.slots[player];
+            match event {
+                EventType::ButtonPressed(button, _) => {
+                    slot.buttons_just_pressed.insert(button);
+                }
+                EventType::ButtonReleased(button, _) => {
+                    slot.buttons_just_released.insert(button);
+                }
+                _ => (),
+            }
+            self.current_player = Some(player);
+        }
+    }
+
+    /// Gives a gamepad to a player. Returns the player, if there's room for
+    /// it.
+    fn connect(&mut self, id: GamepadId) -> Option<usize> {
+        let player = connect_slot(&mut self.slots, id);
+
+        match player {
+            Some(player) => {
+                info!("Gamepad {} connected for player {}", id, player);
+                self.gamepad_events.push(GamepadEvent::Connected { player });
+            }
+            None => warn!(
+                "Ignoring gamepad {}, as all {} players have one",
+                id, MAX_PLAYERS
+            ),
+        }
+
+        player
+    }
+
+    /// Marks the gamepad of a player as unplugged, keeping it for them.
+    fn disconnect(&mut self, id: GamepadId) {
+        if let Some(player) = disconnect_slot(&mut self.slots, id) {
+            if self.current_player == Some(player) {
+                self.current_player = None;
             }
 
-            debug!("Detected a new gamepad with ID {}", event.id);
-            self.current_gamepad_id = Some(event.id);
+            info!("Gamepad {} of player {} disconnected", id, player);
+            self.gamepad_events
+                .push(GamepadEvent::Disconnected { player });
         }
     }
+
+    /// Gets the player having a gamepad.
+    fn player_of(&self, id: GamepadId) -> Option<usize> {
+        slot_of(&self.slots, id)
+    }
+
+    // the window events, forwarded by the callbacks of `EventHandler`, which
+    // need a `Context`
+
+    fn key_down(&mut self, keycode: KeyCode, repeat: bool) {
+        self.state.key_down(keycode, repeat);
+    }
+
+    fn key_up(&mut self, keycode: KeyCode) {
+        self.state.key_up(keycode);
+    }
+
+    fn mouse_button_down(&mut self, button: MouseButton, position: Vec2) {
+        self.state.mouse_button_down(button, position);
+    }
+
+    fn mouse_button_up(&mut self, button: MouseButton, position: Vec2) {
+        self.state.mouse_button_up(button, position);
+    }
+
+    fn mouse_motion(&mut self, position: Vec2) {
+        self.state.mouse_motion(position);
+    }
+
+    fn mouse_wheel(&mut self, delta: Vec2) {
+        self.state.mouse_wheel(delta);
+    }
+
+    fn resized(&mut self) {
+        self.state.resized();
+    }
+
+    fn minimized(&mut self) {
+        self.state.minimized();
+    }
+
+    fn restored(&mut self) {
+        self.state.restored();
+    }
+
+    /// Creates an input handler without gamepads, whose keys and mouse
+    /// buttons are the ones held in `state`, so tests don't need a window
+    /// nor Gilrs.
+    #[cfg(test)]
+    pub(crate) fn headless(state: InputState) -> Self {
+        Self::with_gilrs(None, state)
+    }
+
+    /// Creates an input handler reading gamepads from `gilrs`, if any.
+    fn with_gilrs(gilrs: Option<Gilrs>, state: InputState) -> Self {
+        let slots = Default::default();
+        let current_player = None;
+        let gamepad_events = Vec::new();
+        let stick_deadzone = Deadzone::stick();
+        let trigger_deadzone = Deadzone::trigger();
+
+        let mut input_handler = Self {
+            gilrs,
+            slots,
+            current_player,
+            gamepad_events,
+            stick_deadzone,
+            trigger_deadzone,
+            state,
+        };
+
+        // gamepads plugged in before starting don't send connection events
+        let connected: Vec<GamepadId> = input_handler.gamepads().map(|(id, _)| id).collect();
+        for id in connected {
+            input_handler.connect(id);
+        }
+
+        input_handler
+    }
 }
 
 impl Default for InputHandler {
     fn default() -> Self {
-        let gilrs = Gilrs::new().unwrap();
-        let current_gamepad_id = None;
-        let keys_pressed = HashSet::new();
-        let mouse_buttons_pressed = HashSet::new();
-        let last_key_down = None;
-        let last_mouse_position = (0, 0);
+        let gilrs = Gilrs::new()
+            .map_err(|error| warn!("Gamepads are not available: {}", error))
+            .ok();
 
-        Self {
-            gilrs,
-            current_gamepad_id,
-            keys_pressed,
-            mouse_buttons_pressed,
-            last_key_down,
-            last_mouse_position,
-        }
+        Self::with_gilrs(gilrs, InputState::default())
     }
 }
 
@@ -154,11 +815,11 @@ impl EventHandler for InputHandler {
     fn key_down_event(
         &mut self,
         _ctx: &mut miniquad::Context,
-        keycode: miniquad::KeyCode,
+        keycode: KeyCode,
         _keymods: miniquad::KeyMods,
-        _repeat: bool,
+        repeat: bool,
     ) {
-        self.keys_pressed.insert(keycode);
+        self.key_down(keycode, repeat);
     }
 
     fn key_up_event(
@@ -167,10 +828,7 @@ impl EventHandler for InputHandler {
         keycode: KeyCode,
         _keymods: miniquad::KeyMods,
     ) {
-        // FIXME: when another event is called before letting the key up, this won't be called
-        // e.g. setting the window to fullscreen while pressing W.
-        self.keys_pressed.remove(&keycode);
-        self.last_key_down = Some(keycode);
+        self.key_up(keycode);
     }
 
     fn mouse_button_down_event(
@@ -180,17 +838,292 @@ impl EventHandler for InputHandler {
         x: f32,
         y: f32,
     ) {
-        self.mouse_buttons_pressed.insert(button);
-        self.last_mouse_position = (x as usize, y as usize);
+        self.mouse_button_down(button, Vec2::new(x, y));
     }
 
     fn mouse_button_up_event(
         &mut self,
         _ctx: &mut miniquad::Context,
         button: MouseButton,
-        _x: f32,
-        _y: f32,
+        x: f32,
+        y: f32,
     ) {
-        self.mouse_buttons_pressed.remove(&button);
+        self.mouse_button_up(button, Vec2::new(x, y));
+    }
+
+    fn mouse_motion_event(&mut self, _ctx: &mut miniquad::Context, x: f32, y: f32) {
+        self.mouse_motion(Vec2::new(x, y));
+    }
+
+    fn mouse_wheel_event(&mut self, _ctx: &mut miniquad::Context, x: f32, y: f32) {
+        self.mouse_wheel(Vec2::new(x, y));
+    }
+
+    fn resize_event(&mut self, _ctx: &mut miniquad::Context, _width: f32, _height: f32) {
+        self.resized();
+    }
+
+    fn window_minimized_event(&mut self, _ctx: &mut miniquad::Context) {
+        self.minimized();
+    }
+
+    fn window_restored_event(&mut self, _ctx: &mut miniquad::Context) {
+        self.restored();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the callbacks of `InputHandler` need a `Context`, so the window events
+    // they forward are sent directly here, as there's no window
+
+    #[test]
+    fn reports_both_edges_of_a_key_pressed_and_released_in_one_frame() {
+        let mut state = InputState::default();
+
+        state.key_down(KeyCode::Space, false);
+        state.key_up(KeyCode::Space);
+
+        assert!(state.keys_just_pressed.contains(&KeyCode::Space));
+        assert!(state.keys_just_released.contains(&KeyCode::Space));
+        assert!(!state.keys_pressed.contains(&KeyCode::Space));
+
+        state.end_frame(0.1);
+        assert!(state.keys_just_pressed.is_empty());
+        assert!(state.keys_just_released.is_empty());
+    }
+
+    #[test]
+    fn doesnt_count_repeats_as_presses() {
+        let mut state = InputState::default();
+        state.key_down(KeyCode::Left, false);
+        state.end_frame(0.1);
+
+        state.key_down(KeyCode::Left, true);
+        state.key_down(KeyCode::Left, true);
+
+        assert!(state.keys_pressed.contains(&KeyCode::Left));
+        assert!(state.keys_just_pressed.is_empty());
+    }
+
+    #[test]
+    fn reports_every_key_released_at_once() {
+        let mut state = InputState::default();
+        let keys = [KeyCode::W, KeyCode::A, KeyCode::LeftShift];
+        for key in keys {
+            state.key_down(key, false);
+        }
+        state.end_frame(0.1);
+
+        for key in keys {
+            state.key_up(key);
+        }
+
+        assert_eq!(state.keys_just_released, HashSet::from(keys));
+        assert!(state.keys_pressed.is_empty());
+    }
+
+    #[test]
+    fn accumulates_the_time_keys_are_held_across_frames() {
+        let mut state = InputState::default();
+        state.key_down(KeyCode::Space, false);
+        assert_eq!(state.key_held_durations[&KeyCode::Space], 0.0);
+
+        state.end_frame(0.25);
+        state.end_frame(0.5);
+        // repeats don't restart the count
+        state.key_down(KeyCode::Space, true);
+        state.end_frame(0.25);
+        assert_eq!(state.key_held_durations[&KeyCode::Space], 1.0);
+
+        state.key_up(KeyCode::Space);
+        assert!(!state.key_held_durations.contains_key(&KeyCode::Space));
+    }
+
+    #[test]
+    fn tracks_mouse_buttons_and_motion() {
+        let mut state = InputState::default();
+
+        state.mouse_button_down(MouseButton::Left, Vec2::new(10.0, 20.0));
+        state.mouse_motion(Vec2::new(15.0, 30.0));
+        state.mouse_wheel(Vec2::new(0.0, 1.0));
+        state.mouse_wheel(Vec2::new(0.0, 2.0));
+
+        assert!(state
+            .mouse_buttons_just_pressed
+            .contains(&MouseButton::Left));
+        assert_eq!(state.mouse_position, Vec2::new(15.0, 30.0));
+        assert_eq!(state.mouse_delta, Vec2::new(15.0, 30.0));
+        assert_eq!(state.mouse_wheel_delta, Vec2::new(0.0, 3.0));
+
+        state.end_frame(0.1);
+        state.mouse_button_up(MouseButton::Left, Vec2::new(15.0, 30.0));
+
+        assert!(state
+            .mouse_buttons_just_released
+            .contains(&MouseButton::Left));
+        assert_eq!(state.mouse_delta, Vec2::ZERO);
+        assert_eq!(state.mouse_wheel_delta, Vec2::ZERO);
+    }
+
+    #[test]
+    fn releases_keys_held_through_a_resize() {
+        let mut state = InputState::default();
+        state.key_down(KeyCode::D, false);
+        state.mouse_button_down(MouseButton::Right, Vec2::ZERO);
+        state.end_frame(0.1);
+
+        // the release is swallowed by going fullscreen
+        state.resized();
+
+        assert!(state.keys_pressed.is_empty());
+        assert!(state.keys_just_released.contains(&KeyCode::D));
+        assert!(state.key_held_durations.is_empty());
+        assert!(state.mouse_buttons_pressed.is_empty());
+        assert!(state
+            .mouse_buttons_just_released
+            .contains(&MouseButton::Right));
+    }
+
+    #[test]
+    fn loses_focus_while_minimized() {
+        let mut state = InputState::default();
+        assert!(state.has_focus);
+        state.key_down(KeyCode::D, false);
+
+        state.minimized();
+        assert!(!state.has_focus);
+        assert!(state.keys_just_released.contains(&KeyCode::D));
+
+        state.restored();
+        assert!(state.has_focus);
+        assert!(!state.keys_pressed.contains(&KeyCode::D));
+    }
+
+    #[test]
+    fn doesnt_count_repeats_after_a_swallowed_release_as_presses() {
+        let mut state = InputState::default();
+        state.key_down(KeyCode::D, false);
+        state.end_frame(0.1);
+        state.resized();
+        state.end_frame(0.1);
+
+        // the key was held all along, and keeps repeating
+        state.key_down(KeyCode::D, true);
+
+        assert!(state.keys_pressed.contains(&KeyCode::D));
+        assert!(state.keys_just_pressed.is_empty());
+        assert_eq!(state.key_held_durations[&KeyCode::D], 0.0);
+
+        // pressing it again after letting go is a press
+        state.key_up(KeyCode::D);
+        state.key_down(KeyCode::D, false);
+        assert!(state.keys_just_pressed.contains(&KeyCode::D));
+    }
+
+    #[test]
+    fn forwards_the_window_events_to_its_state() {
+        let mut input_handler = InputHandler::headless(InputState::default());
+
+        input_handler.key_down(KeyCode::Space, false);
+        input_handler.mouse_button_down(MouseButton::Left, Vec2::new(4.0, 2.0));
+        input_handler.mouse_wheel(Vec2::new(0.0, 1.0));
+        assert!(input_handler.is_key_just_pressed(KeyCode::Space));
+        assert!(input_handler.is_mouse_button_pressed(MouseButton::Left));
+        assert_eq!(input_handler.mouse_position(), Vec2::new(4.0, 2.0));
+        assert_eq!(input_handler.mouse_wheel_delta(), Vec2::new(0.0, 1.0));
+
+        input_handler.end_frame(0.5);
+        input_handler.resized();
+        assert!(input_handler.is_key_just_released(KeyCode::Space));
+        assert!(input_handler.is_mouse_button_just_released(MouseButton::Left));
+        assert_eq!(input_handler.key_held_duration(KeyCode::Space), 0.0);
+
+        input_handler.key_down(KeyCode::Up, false);
+        input_handler.minimized();
+        assert!(!input_handler.has_focus());
+        assert!(!input_handler.is_key_pressed(KeyCode::Up));
+        input_handler.restored();
+        assert!(input_handler.has_focus());
+    }
+
+    #[test]
+    fn forgets_the_gamepad_edges_at_the_end_of_the_frame() {
+        let mut input_handler = InputHandler::headless(InputState::default());
+        input_handler.slots[0]
+            .buttons_just_pressed
+            .insert(Button::South);
+        input_handler.slots[1]
+            .buttons_just_released
+            .insert(Button::Start);
+        input_handler.current_player = Some(0);
+        input_handler
+            .gamepad_events
+            .push(GamepadEvent::Connected { player: 0 });
+        assert!(input_handler.is_gamepad_button_just_pressed(Button::South));
+        assert!(input_handler.is_player_button_just_released(1, Button::Start));
+
+        input_handler.end_frame(0.1);
+
+        assert!(!input_handler.is_gamepad_button_just_pressed(Button::South));
+        assert!(!input_handler.is_player_button_just_released(1, Button::Start));
+        assert!(input_handler.gamepad_events().is_empty());
+    }
+
+    /// Creates the slots of every player, plugging in the gamepads `ids`.
+    fn slots(ids: &[u32]) -> [GamepadSlot<u32>; MAX_PLAYERS] {
+        let mut slots: [GamepadSlot<u32>; MAX_PLAYERS] = Default::default();
+        for id in ids {
+            connect_slot(&mut slots, *id);
+        }
+
+        slots
+    }
+
+    #[test]
+    fn gives_gamepads_to_the_first_players_without_one() {
+        let mut slots = slots(&[]);
+
+        assert_eq!(connect_slot(&mut slots, 10), Some(0));
+        assert_eq!(connect_slot(&mut slots, 11), Some(1));
+        assert_eq!(slot_of(&slots, 11), Some(1));
+        assert!(slots[1].connected);
+    }
+
+    #[test]
+    fn gives_gamepads_back_to_their_player_when_plugged_back_in() {
+        let mut slots = slots(&[10, 11, 12]);
+        slots[1].buttons_just_pressed.insert(Button::South);
+
+        assert_eq!(disconnect_slot(&mut slots, 11), Some(1));
+        assert!(!slots[1].connected);
+        // a new gamepad doesn't take the slot kept for the unplugged one
+        assert_eq!(connect_slot(&mut slots, 13), Some(3));
+        assert_eq!(connect_slot(&mut slots, 11), Some(1));
+
+        assert!(slots[1].connected);
+        assert!(slots[1].buttons_just_pressed.contains(&Button::South));
+    }
+
+    #[test]
+    fn ignores_gamepads_past_the_last_player() {
+        let mut slots = slots(&[10, 11, 12, 13]);
+
+        assert_eq!(connect_slot(&mut slots, 14), None);
+        assert_eq!(slot_of(&slots, 14), None);
+        assert_eq!(disconnect_slot(&mut slots, 14), None);
+    }
+
+    #[test]
+    fn gives_the_slots_of_unplugged_gamepads_once_every_player_has_one() {
+        let mut slots = slots(&[10, 11, 12, 13]);
+        disconnect_slot(&mut slots, 12);
+
+        assert_eq!(connect_slot(&mut slots, 14), Some(2));
+        assert_eq!(slot_of(&slots, 12), None);
+        // the gamepad unplugged has no player anymore
+        assert_eq!(connect_slot(&mut slots, 12), None);
     }
 }
@@ -0,0 +1,935 @@
+use super::error::EngineError;
+use super::input_handler::InputHandler;
+use gilrs::{Axis, Button};
+use miniquad::{KeyCode, MouseButton};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// How far the value of an action has to go, in either direction, for the
+/// action to count as pressed.
+pub const PRESS_THRESHOLD: f32 = 0.5;
+
+/// The gamepad buttons checked while listening for a new binding.
+const GAMEPAD_BUTTONS: [Button; 19] = [
+    Button::South,
+    Button::East,
+    Button::North,
+    Button::West,
+    Button::C,
+    Button::Z,
+    Button::LeftTrigger,
+    Button::LeftTrigger2,
+    Button::RightTrigger,
+    Button::RightTrigger2,
+    Button::Select,
+    Button::Start,
+    Button::Mode,
+    Button::LeftThumb,
+    Button::RightThumb,
+    Button::DPadUp,
+    Button::DPadDown,
+    Button::DPadLeft,
+    Button::DPadRight,
+];
+
+/// The gamepad axes checked while listening for a new binding.
+const GAMEPAD_AXES: [Axis; 8] = [
+    Axis::LeftStickX,
+    Axis::LeftStickY,
+    Axis::LeftZ,
+    Axis::RightStickX,
+    Axis::RightStickY,
+    Axis::RightZ,
+    Axis::DPadX,
+    Axis::DPadY,
+];
+
+/// A physical input an action can be bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Binding {
+    /// A key, worth 1.0 while held.
+    Key(#[serde(with = "KeyCodeDef")] KeyCode),
+    /// A mouse button, worth 1.0 while held.
+    MouseButton(#[serde(with = "MouseButtonDef")] MouseButton),
+    /// A gamepad button, worth 1.0 while held.
+    GamepadButton(Button),
+    /// A gamepad axis, ranging from -1.0 to 1.0, with the deadzones of the
+    /// `InputHandler` applied.
+    GamepadAxis {
+        axis: Axis,
+        /// Whether the values are flipped, e.g. to make up negative on a
+        /// stick, like on screen.
+        #[serde(default)]
+        inverted: bool,
+    },
+    /// Two keys acting as an axis, worth -1.0 while `negative` is held and
+    /// 1.0 while `positive` is.
+    KeyAxis {
+        #[serde(with = "KeyCodeDef")]
+        negative: KeyCode,
+        #[serde(with = "KeyCodeDef")]
+        positive: KeyCode,
+    },
+}
+
+impl Binding {
+    /// Gets how much the input is pressed, from -1.0 to 1.0.
+    pub fn value(&self, input_handler: &InputHandler) -> f32 {
+        self.value_for(input_handler, input_handler.current_player())
+    }
+
+    /// Gets how much the input is pressed, from -1.0 to 1.0, reading the
+    /// gamepad of `player` instead of the current one.
+    pub fn player_value(&self, input_handler: &InputHandler, player: usize) -> f32 {
+        self.value_for(input_handler, Some(player))
+    }
+
+    /// Gets how much the input is pressed, reading the gamepad of `player`.
+    fn value_for(&self, input_handler: &InputHandler, player: Option<usize>) -> f32 {
+        let held = |pressed: bool| if pressed { 1.0 } else { 0.0 };
+
+        match *self {
+            Self::Key(key) => held(input_handler.is_key_pressed(key)),
+            Self::MouseButton(button) => held(input_handler.is_mouse_button_pressed(button)),
+            Self::GamepadButton(button) => held(
+                player.is_some_and(|player| input_handler.is_player_button_pressed(player, button)),
+            ),
+            Self::GamepadAxis { axis, inverted } => {
+                let value =
+                    player.map_or(0.0, |player| input_handler.player_axis_value(player, axis));
+                if inverted {
+                    -value
+                } else {
+                    value
+                }
+            }
+            Self::KeyAxis { negative, positive } => {
+                held(input_handler.is_key_pressed(positive))
+                    - held(input_handler.is_key_pressed(negative))
+            }
+        }
+    }
+
+    /// Checks whether the input went down during this frame. Gamepad axes
+    /// never do, as they don't have edges.
+    pub fn is_just_pressed(&self, input_handler: &InputHandler) -> bool {
+        self.is_just_pressed_for(input_handler, input_handler.current_player())
+    }
+
+    /// Checks whether the input went down during this frame, reading the
+    /// gamepad of `player` instead of the current one.
+    pub fn player_is_just_pressed(&self, input_handler: &InputHandler, player: usize) -> bool {
+        self.is_just_pressed_for(input_handler, Some(player))
+    }
+
+    /// Checks whether the input went up during this frame. Gamepad axes
+    /// never do, as they don't have edges.
+    pub fn is_just_released(&self, input_handler: &InputHandler) -> bool {
+        self.is_just_released_for(input_handler, input_handler.current_player())
+    }
+
+    /// Checks whether the input went up during this frame, reading the
+    /// gamepad of `player` instead of the current one.
+    pub fn player_is_just_released(&self, input_handler: &InputHandler, player: usize) -> bool {
+        self.is_just_released_for(input_handler, Some(player))
+    }
+
+    /// Checks whether the input went down during this frame, reading the
+    /// gamepad of `player`.
+    fn is_just_pressed_for(&self, input_handler: &InputHandler, player: Option<usize>) -> bool {
+        match *self {
+            Self::Key(key) => input_handler.is_key_just_pressed(key),
+            Self::MouseButton(button) => input_handler.is_mouse_button_just_pressed(button),
+            Self::GamepadButton(button) => player
+                .is_some_and(|player| input_handler.is_player_button_just_pressed(player, button)),
+            Self::GamepadAxis { .. } => false,
+            Self::KeyAxis { negative, positive } => {
+                input_handler.is_key_just_pressed(negative)
+                    || input_handler.is_key_just_pressed(positive)
+            }
+        }
+    }
+
+    /// Checks whether the input went up during this frame, reading the
+    /// gamepad of `player`.
+    fn is_just_released_for(&self, input_handler: &InputHandler, player: Option<usize>) -> bool {
+        match *self {
+            Self::Key(key) => input_handler.is_key_just_released(key),
+            Self::MouseButton(button) => input_handler.is_mouse_button_just_released(button),
+            Self::GamepadButton(button) => player
+                .is_some_and(|player| input_handler.is_player_button_just_released(player, button)),
+            Self::GamepadAxis { .. } => false,
+            Self::KeyAxis { negative, positive } => {
+                input_handler.is_key_just_released(negative)
+                    || input_handler.is_key_just_released(positive)
+            }
+        }
+    }
+
+    /// Gets an input that went down during this frame, if any.
+    fn just_pressed(input_handler: &InputHandler) -> Option<Self> {
+        let key = input_handler.just_pressed_keys().iter().next();
+        let mouse_button = input_handler.just_pressed_mouse_buttons().iter().next();
+        let gamepad_button = GAMEPAD_BUTTONS
+            .into_iter()
+            .find(|button| input_handler.is_gamepad_button_just_pressed(*button));
+
+        key.map(|key| Self::Key(*key))
+            .or_else(|| mouse_button.map(|button| Self::MouseButton(*button)))
+            .or_else(|| gamepad_button.map(Self::GamepadButton))
+    }
+
+    /// Gets the gamepad axis pushed the furthest past `PRESS_THRESHOLD`,
+    /// if any.
+    fn pushed_axis(input_handler: &InputHandler) -> Option<Self> {
+        GAMEPAD_AXES
+            .into_iter()
+            .map(|axis| (axis, input_handler.gamepad_axis_value(axis)))
+            .filter(|(_, value)| value.abs() >= PRESS_THRESHOLD)
+            .max_by(|(_, a), (_, b)| a.abs().total_cmp(&b.abs()))
+            .map(|(axis, value)| Self::GamepadAxis {
+                axis,
+                inverted: value < 0.0,
+            })
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Key(key) => write!(f, "{:?}", key),
+            Self::MouseButton(button) => write!(f, "Mouse {:?}", button),
+            Self::GamepadButton(button) => write!(f, "Gamepad {:?}", button),
+            Self::GamepadAxis { axis, inverted } => {
+                write!(f, "Gamepad {:?}", axis)?;
+                if *inverted {
+                    write!(f, " (inverted)")?;
+                }
+                Ok(())
+            }
+            Self::KeyAxis { negative, positive } => write!(f, "{:?} / {:?}", negative, positive),
+        }
+    }
+}
+
+/// One of the two keys of a `Binding::KeyAxis`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AxisSide {
+    Negative,
+    Positive,
+}
+
+/// A binding waiting for the player to press something.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Listening {
+    action: String,
+    /// The binding replaced. Bindings past the last one are added instead.
+    index: usize,
+    /// The key replaced, when the binding is a `KeyAxis`.
+    side: Option<AxisSide>,
+    /// Whether every gamepad axis was let go since listening started. Axes
+    /// have no edges, so a stick held already would be captured otherwise.
+    armed: bool,
+}
+
+/// Maps named actions, such as "jump" or "move_x", to the inputs that
+/// trigger them. Games check actions instead of keys, so players can change
+/// their controls.
+///
+/// Bindings are saved as TOML, one array of bindings per action:
+/// ```toml
+/// [actions]
+/// jump = [{ key = "Space" }, { gamepad_button = "South" }]
+/// move_y = [
+///     { key_axis = { negative = "W", positive = "S" } },
+///     { gamepad_axis = { axis = "LeftStickY", inverted = true } },
+/// ]
+/// ```
+///
+/// # Example
+/// ```rust
+/// let input_map = InputMap::load(Path::new("src/content/input.toml"))?;
+///
+/// let speed = input_map.value("move_y", &input_handler) * 240.0;
+/// if input_map.is_just_pressed("jump", &input_handler) {
+///     println!("Jumping!");
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InputMap {
+    /// The bindings of every action, sorted by name.
+    #[serde(default)]
+    actions: BTreeMap<String, Vec<Binding>>,
+    /// The binding being rebound, if any.
+    #[serde(skip)]
+    listening: Option<Listening>,
+}
+
+impl InputMap {
+    /// Creates a map without actions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a map out of its TOML representation.
+    pub fn parse(toml: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(toml)
+    }
+
+    /// Gets the TOML representation of the map.
+    pub fn to_toml(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string_pretty(self)
+    }
+
+    /// Loads a map out of a TOML file.
+    pub fn load(path: &Path) -> Result<Self, EngineError> {
+        info!("Loading input bindings from {:?}", path);
+        let toml = fs::read_to_string(path).map_err(|source| EngineError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        Self::parse(&toml).map_err(|error| EngineError::Parse {
+            path: path.to_path_buf(),
+            message: error.to_string(),
+        })
+    }
+
+    /// Saves the map into a TOML file, replacing it if it exists.
+    pub fn save(&self, path: &Path) -> Result<(), EngineError> {
+        info!("Saving input bindings to {:?}", path);
+        let toml = self.to_toml().map_err(|error| EngineError::Serialize {
+            path: path.to_path_buf(),
+            message: error.to_string(),
+        })?;
+
+        fs::write(path, toml).map_err(|source| EngineError::Io {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// Binds an input to an action, creating the action if needed.
+    /// Does nothing if the input is already bound to it.
+    pub fn bind(&mut self, action: &str, binding: Binding) {
+        let bindings = self.actions.entry(action.to_owned()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// Removes a binding of an action, returning it.
+    pub fn unbind(&mut self, action: &str, index: usize) -> Option<Binding> {
+        let bindings = self.actions.get_mut(action)?;
+
+        (index < bindings.len()).then(|| bindings.remove(index))
+    }
+
+    /// Removes every binding of an action, keeping the action around.
+    pub fn clear(&mut self, action: &str) {
+        if let Some(bindings) = self.actions.get_mut(action) {
+            bindings.clear();
+        }
+    }
+
+    /// Iterates over the names of every action, in alphabetical order.
+    pub fn actions(&self) -> impl Iterator<Item = &str> {
+        self.actions.keys().map(String::as_str)
+    }
+
+    /// Gets the bindings of an action.
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.actions.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Gets how much an action is pressed, from -1.0 to 1.0. When several
+    /// of its bindings are pressed, the one furthest from 0.0 wins.
+    pub fn value(&self, action: &str, input_handler: &InputHandler) -> f32 {
+        self.value_for(action, |binding| binding.value(input_handler))
+    }
+
+    /// Gets how much an action is pressed by a player, from -1.0 to 1.0.
+    /// Keys and mouse buttons are shared, but gamepad bindings only read the
+    /// gamepad of `player`, so each player can use the same action with
+    /// their own gamepad.
+    pub fn player_value(&self, action: &str, player: usize, input_handler: &InputHandler) -> f32 {
+        self.value_for(action, |binding| {
+            binding.player_value(input_handler, player)
+        })
+    }
+
+    /// Gets how much an action is pressed, out of the value of each binding.
+    fn value_for(&self, action: &str, binding_value: impl Fn(&Binding) -> f32) -> f32 {
+        self.bindings(action)
+            .iter()
+            .map(binding_value)
+            .fold(0.0_f32, |value, binding_value| {
+                if binding_value.abs() > value.abs() {
+                    binding_value
+                } else {
+                    value
+                }
+            })
+            .clamp(-1.0, 1.0)
+    }
+
+    /// Checks whether an action is pressed, in either direction.
+    pub fn is_pressed(&self, action: &str, input_handler: &InputHandler) -> bool {
+        self.value(action, input_handler).abs() >= PRESS_THRESHOLD
+    }
+
+    /// Checks whether any binding of an action went down during this frame.
+    pub fn is_just_pressed(&self, action: &str, input_handler: &InputHandler) -> bool {
+        self.bindings(action)
+            .iter()
+            .any(|binding| binding.is_just_pressed(input_handler))
+    }
+
+    /// Checks whether a binding of an action went up during this frame,
+    /// leaving the action released.
+    pub fn is_just_released(&self, action: &str, input_handler: &InputHandler) -> bool {
+        self.bindings(action)
+            .iter()
+            .any(|binding| binding.is_just_released(input_handler))
+            && !self.is_pressed(action, input_handler)
+    }
+
+    /// Checks whether a player pressed any binding of an action during this
+    /// frame. Gamepad bindings only read the gamepad of `player`.
+    pub fn player_is_just_pressed(
+        &self,
+        action: &str,
+        player: usize,
+        input_handler: &InputHandler,
+    ) -> bool {
+        self.bindings(action)
+            .iter()
+            .any(|binding| binding.player_is_just_pressed(input_handler, player))
+    }
+
+    /// Checks whether a player released a binding of an action during this
+    /// frame, leaving the action released for them. Gamepad bindings only
+    /// read the gamepad of `player`.
+    pub fn player_is_just_released(
+        &self,
+        action: &str,
+        player: usize,
+        input_handler: &InputHandler,
+    ) -> bool {
+        self.bindings(action)
+            .iter()
+            .any(|binding| binding.player_is_just_released(input_handler, player))
+            && self.player_value(action, player, input_handler).abs() < PRESS_THRESHOLD
+    }
+
+    /// Waits for the player to press something to rebind an action with.
+    /// The input replaces the binding at `index`, or one of its keys if it's
+    /// a `KeyAxis` and `side` is given. An `index` past the last binding adds
+    /// a new one.
+    ///
+    /// Inputs are captured by `capture`, which should be called every update.
+    pub fn listen(&mut self, action: &str, index: usize, side: Option<AxisSide>) {
+        self.listening = Some(Listening {
+            action: action.to_owned(),
+            index,
+            side,
+            armed: false,
+        });
+    }
+
+    /// Stops waiting for an input, keeping the bindings as they were.
+    pub fn stop_listening(&mut self) {
+        self.listening = None;
+    }
+
+    /// Checks whether the map is waiting for an input. Games should ignore
+    /// their actions meanwhile.
+    pub const fn is_listening(&self) -> bool {
+        self.listening.is_some()
+    }
+
+    /// Checks whether the map is waiting for an input to replace the given
+    /// binding.
+    pub fn is_listening_to(&self, action: &str, index: usize, side: Option<AxisSide>) -> bool {
+        matches!(
+            &self.listening,
+            Some(listening) if listening.action == action && listening.index == index && listening.side == side
+        )
+    }
+
+    /// Binds the first input pressed after `listen` was called. Keys and
+    /// buttons are captured when they go down, so one held already isn't.
+    /// Returns whether a binding changed.
+    pub fn capture(&mut self, input_handler: &InputHandler) -> bool {
+        self.capture_binding(
+            Binding::just_pressed(input_handler),
+            Binding::pushed_axis(input_handler),
+        )
+    }
+
+    /// Binds the input that went down, or else the axis pushed, as long as
+    /// every axis was let go since listening started.
+    fn capture_binding(&mut self, pressed: Option<Binding>, axis: Option<Binding>) -> bool {
+        let listening = match &mut self.listening {
+            Some(listening) => listening,
+            None => return false,
+        };
+
+        let binding = pressed.or(axis.filter(|_| listening.armed));
+        listening.armed |= axis.is_none();
+        let binding = match binding {
+            Some(binding) => binding,
+            None => return false,
+        };
+
+        let Listening {
+            action,
+            index,
+            side,
+            ..
+        } = self.listening.take().unwrap();
+        let bindings = self.actions.entry(action.clone()).or_default();
+
+        match (bindings.get_mut(index), side, binding) {
+            (
+                Some(Binding::KeyAxis { negative, .. }),
+                Some(AxisSide::Negative),
+                Binding::Key(key),
+            ) => {
+                *negative = key;
+            }
+            (
+                Some(Binding::KeyAxis { positive, .. }),
+                Some(AxisSide::Positive),
+                Binding::Key(key),
+            ) => {
+                *positive = key;
+            }
+            (Some(current), _, binding) => *current = binding,
+            (None, _, binding) => bindings.push(binding),
+        }
+        info!("Rebound {} to {}", action, binding);
+
+        true
+    }
+}
+
+/// Mirrors `KeyCode`, so it can be (de)serialized by name.
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "KeyCode")]
+enum KeyCodeDef {
+    Space,
+    Apostrophe,
+    Comma,
+    Minus,
+    Period,
+    Slash,
+    Key0,
+    Key1,
+    Key2,
+    Key3,
+    Key4,
+    Key5,
+    Key6,
+    Key7,
+    Key8,
+    Key9,
+    Semicolon,
+    Equal,
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+    LeftBracket,
+    Backslash,
+    RightBracket,
+    GraveAccent,
+    World1,
+    World2,
+    Escape,
+    Enter,
+    Tab,
+    Backspace,
+    Insert,
+    Delete,
+    Right,
+    Left,
+    Down,
+    Up,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    CapsLock,
+    ScrollLock,
+    NumLock,
+    PrintScreen,
+    Pause,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    F13,
+    F14,
+    F15,
+    F16,
+    F17,
+    F18,
+    F19,
+    F20,
+    F21,
+    F22,
+    F23,
+    F24,
+    F25,
+    Kp0,
+    Kp1,
+    Kp2,
+    Kp3,
+    Kp4,
+    Kp5,
+    Kp6,
+    Kp7,
+    Kp8,
+    Kp9,
+    KpDecimal,
+    KpDivide,
+    KpMultiply,
+    KpSubtract,
+    KpAdd,
+    KpEnter,
+    KpEqual,
+    LeftShift,
+    LeftControl,
+    LeftAlt,
+    LeftSuper,
+    RightShift,
+    RightControl,
+    RightAlt,
+    RightSuper,
+    Menu,
+    Unknown,
+}
+
+/// Mirrors `MouseButton`, so it can be (de)serialized by name.
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "MouseButton")]
+enum MouseButtonDef {
+    Right,
+    Left,
+    Middle,
+    Unknown,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::input_handler::InputState;
+
+    const MOVE_Y: Binding = Binding::KeyAxis {
+        negative: KeyCode::W,
+        positive: KeyCode::S,
+    };
+
+    const LEFT_STICK_UP: Binding = Binding::GamepadAxis {
+        axis: Axis::LeftStickY,
+        inverted: true,
+    };
+
+    /// Creates an input handler without gamepads, holding `keys`.
+    fn holding(keys: &[KeyCode]) -> InputHandler {
+        let mut state = InputState::default();
+        for key in keys {
+            state.key_down(*key, false);
+        }
+
+        InputHandler::headless(state)
+    }
+
+    #[test]
+    fn parses_the_documented_bindings() {
+        let input_map = InputMap::parse(
+            r#"
+            [actions]
+            jump = [{ key = "Space" }, { gamepad_button = "South" }]
+            move_y = [
+                { key_axis = { negative = "W", positive = "S" } },
+                { gamepad_axis = { axis = "LeftStickY", inverted = true } },
+            ]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(input_map.actions().collect::<Vec<_>>(), ["jump", "move_y"]);
+        assert_eq!(
+            input_map.bindings("jump"),
+            [
+                Binding::Key(KeyCode::Space),
+                Binding::GamepadButton(Button::South)
+            ]
+        );
+        assert_eq!(input_map.bindings("move_y"), [MOVE_Y, LEFT_STICK_UP]);
+    }
+
+    #[test]
+    fn doesnt_invert_gamepad_axes_by_default() {
+        let input_map = InputMap::parse(
+            r#"
+            [actions]
+            move_x = [{ gamepad_axis = { axis = "LeftStickX" } }]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            input_map.bindings("move_x"),
+            [Binding::GamepadAxis {
+                axis: Axis::LeftStickX,
+                inverted: false
+            }]
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_key_names() {
+        let error = InputMap::parse(
+            r#"
+            [actions]
+            jump = [{ key = "Spacebar" }]
+            "#,
+        )
+        .unwrap_err();
+
+        assert!(error.to_string().contains("Spacebar"), "{}", error);
+    }
+
+    #[test]
+    fn survives_a_round_trip_through_toml() {
+        let mut input_map = InputMap::new();
+        input_map.bind("jump", Binding::Key(KeyCode::Space));
+        input_map.bind("jump", Binding::GamepadButton(Button::South));
+        input_map.bind("move_y", MOVE_Y);
+        input_map.bind("move_y", LEFT_STICK_UP);
+        input_map.bind("shoot", Binding::MouseButton(MouseButton::Left));
+        input_map.bind("pause", Binding::Key(KeyCode::Escape));
+        input_map.clear("pause");
+
+        let parsed = InputMap::parse(&input_map.to_toml().unwrap()).unwrap();
+
+        assert_eq!(parsed.actions, input_map.actions);
+    }
+
+    #[test]
+    fn combines_the_keys_of_a_key_axis() {
+        assert_eq!(MOVE_Y.value(&holding(&[])), 0.0);
+        assert_eq!(MOVE_Y.value(&holding(&[KeyCode::W])), -1.0);
+        assert_eq!(MOVE_Y.value(&holding(&[KeyCode::S])), 1.0);
+        assert_eq!(MOVE_Y.value(&holding(&[KeyCode::W, KeyCode::S])), 0.0);
+    }
+
+    #[test]
+    fn reads_no_gamepad_input_without_gamepads() {
+        let input_handler = holding(&[]);
+
+        assert_eq!(LEFT_STICK_UP.value(&input_handler), 0.0);
+        assert_eq!(LEFT_STICK_UP.player_value(&input_handler, 0), 0.0);
+        assert!(!Binding::GamepadButton(Button::South).player_is_just_pressed(&input_handler, 0));
+    }
+
+    #[test]
+    fn picks_the_binding_furthest_from_zero() {
+        let mut input_map = InputMap::new();
+        input_map.bind("move_y", MOVE_Y);
+        input_map.bind("move_y", LEFT_STICK_UP);
+        input_map.bind("move_y", Binding::Key(KeyCode::Down));
+
+        let value = |values: [f32; 3]| {
+            input_map.value_for("move_y", |binding| {
+                let index = input_map
+                    .bindings("move_y")
+                    .iter()
+                    .position(|other| other == binding);
+                values[index.unwrap()]
+            })
+        };
+
+        assert_eq!(value([0.0, 0.0, 0.0]), 0.0);
+        assert_eq!(value([0.0, -0.6, 0.3]), -0.6);
+        assert_eq!(value([0.2, 0.0, -0.1]), 0.2);
+        // ties go to the first binding
+        assert_eq!(value([-1.0, 1.0, 0.0]), -1.0);
+        assert_eq!(value([0.0, 2.0, 0.0]), 1.0);
+        assert_eq!(input_map.value_for("missing", |_| 1.0), 0.0);
+    }
+
+    #[test]
+    fn reads_actions_out_of_the_keys_held() {
+        let mut input_map = InputMap::new();
+        input_map.bind("move_y", MOVE_Y);
+        input_map.bind("move_y", Binding::Key(KeyCode::Down));
+        let input_handler = holding(&[KeyCode::W]);
+
+        assert_eq!(input_map.value("move_y", &input_handler), -1.0);
+        assert!(input_map.is_pressed("move_y", &input_handler));
+        assert!(input_map.is_just_pressed("move_y", &input_handler));
+        // keys are shared by every player
+        assert_eq!(input_map.player_value("move_y", 1, &input_handler), -1.0);
+        assert!(input_map.player_is_just_pressed("move_y", 1, &input_handler));
+        assert!(!input_map.player_is_just_released("move_y", 1, &input_handler));
+    }
+
+    #[test]
+    fn binds_inputs_once() {
+        let mut input_map = InputMap::new();
+        input_map.bind("jump", Binding::Key(KeyCode::Space));
+        input_map.bind("jump", Binding::Key(KeyCode::Space));
+        input_map.bind("jump", Binding::GamepadButton(Button::South));
+
+        assert_eq!(
+            input_map.bindings("jump"),
+            [
+                Binding::Key(KeyCode::Space),
+                Binding::GamepadButton(Button::South)
+            ]
+        );
+    }
+
+    #[test]
+    fn unbinds_inputs_by_index() {
+        let mut input_map = InputMap::new();
+        input_map.bind("jump", Binding::Key(KeyCode::Space));
+        input_map.bind("jump", Binding::Key(KeyCode::Up));
+
+        assert_eq!(input_map.unbind("jump", 2), None);
+        assert_eq!(input_map.unbind("missing", 0), None);
+        assert_eq!(
+            input_map.unbind("jump", 0),
+            Some(Binding::Key(KeyCode::Space))
+        );
+        assert_eq!(input_map.bindings("jump"), [Binding::Key(KeyCode::Up)]);
+    }
+
+    #[test]
+    fn captures_nothing_unless_listening() {
+        let mut input_map = InputMap::new();
+
+        assert!(!input_map.capture_binding(Some(Binding::Key(KeyCode::A)), None));
+        assert_eq!(input_map.actions().count(), 0);
+    }
+
+    #[test]
+    fn replaces_one_key_of_a_key_axis() {
+        let mut input_map = InputMap::new();
+        input_map.bind("move_y", MOVE_Y);
+        input_map.listen("move_y", 0, Some(AxisSide::Positive));
+        assert!(input_map.is_listening_to("move_y", 0, Some(AxisSide::Positive)));
+
+        assert!(input_map.capture(&holding(&[KeyCode::Down])));
+
+        assert!(!input_map.is_listening());
+        assert_eq!(
+            input_map.bindings("move_y"),
+            [Binding::KeyAxis {
+                negative: KeyCode::W,
+                positive: KeyCode::Down
+            }]
+        );
+    }
+
+    #[test]
+    fn replaces_a_whole_key_axis_with_other_inputs() {
+        let mut input_map = InputMap::new();
+        input_map.bind("move_y", MOVE_Y);
+        input_map.listen("move_y", 0, Some(AxisSide::Negative));
+
+        assert!(input_map.capture_binding(Some(Binding::GamepadButton(Button::DPadUp)), None));
+
+        assert_eq!(
+            input_map.bindings("move_y"),
+            [Binding::GamepadButton(Button::DPadUp)]
+        );
+    }
+
+    #[test]
+    fn adds_bindings_when_listening_past_the_last_one() {
+        let mut input_map = InputMap::new();
+        input_map.bind("jump", Binding::Key(KeyCode::Space));
+        input_map.listen("jump", 5, None);
+
+        assert!(input_map.capture(&holding(&[KeyCode::Up])));
+
+        assert_eq!(
+            input_map.bindings("jump"),
+            [Binding::Key(KeyCode::Space), Binding::Key(KeyCode::Up)]
+        );
+    }
+
+    #[test]
+    fn waits_for_sticks_held_already_to_be_let_go() {
+        let mut input_map = InputMap::new();
+        input_map.listen("move_y", 0, None);
+
+        // held since before listening
+        assert!(!input_map.capture_binding(None, Some(LEFT_STICK_UP)));
+        assert!(!input_map.capture_binding(None, Some(LEFT_STICK_UP)));
+        assert!(input_map.is_listening());
+
+        assert!(!input_map.capture_binding(None, None));
+        assert!(input_map.capture_binding(None, Some(LEFT_STICK_UP)));
+        assert_eq!(input_map.bindings("move_y"), [LEFT_STICK_UP]);
+    }
+
+    #[test]
+    fn captures_presses_while_a_stick_is_held() {
+        let mut input_map = InputMap::new();
+        input_map.listen("jump", 0, None);
+
+        assert!(input_map.capture_binding(Some(Binding::Key(KeyCode::Space)), Some(LEFT_STICK_UP)));
+        assert_eq!(input_map.bindings("jump"), [Binding::Key(KeyCode::Space)]);
+    }
+
+    #[test]
+    fn keeps_the_bindings_when_listening_stops() {
+        let mut input_map = InputMap::new();
+        input_map.bind("jump", Binding::Key(KeyCode::Space));
+        input_map.listen("jump", 0, None);
+        input_map.stop_listening();
+
+        assert!(!input_map.capture(&holding(&[KeyCode::Up])));
+        assert_eq!(input_map.bindings("jump"), [Binding::Key(KeyCode::Space)]);
+    }
+}
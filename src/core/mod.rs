@@ -1,8 +1,26 @@
+pub mod animation;
+pub mod assets;
+pub mod atlas;
+pub mod camera;
+pub mod collision;
 pub mod color;
+pub mod ecs;
 pub mod editor;
+pub mod error;
+pub mod gamepad;
 pub mod graphics_handler;
 pub mod input_handler;
+pub mod input_map;
+pub mod post_process;
+pub mod rect;
+pub mod render_target;
+pub mod scene;
+pub mod scene_graph;
 pub mod shape;
 pub mod sprite;
+pub mod sprite_batch;
 pub mod texture;
+pub mod time;
+pub mod transform;
 pub mod vertex;
+pub mod viewport;
@@ -0,0 +1,409 @@
+use super::assets::Handle;
+use super::error::EngineError;
+use super::graphics_handler::{GraphicsHandler, ShaderParams};
+use super::render_target::RenderTarget;
+use super::texture::Texture;
+use miniquad::*;
+
+/// A fullscreen shader pass applied to the whole frame.
+///
+/// Effect shaders receive the frame in the `tex` image, and the uniforms
+/// described by `effect_uniforms`. They may take a second `lut` image.
+pub struct PostEffect {
+    /// The name shown in the editor.
+    pub name: String,
+    /// Whether the effect is applied.
+    pub enabled: bool,
+    /// How strong the effect is, usually from `0.0` to `1.0`.
+    pub intensity: f32,
+    /// How much the intensity drops every second. Used by effects that fade away.
+    pub decay: f32,
+    /// A color the effect may use, as `rgba` from `0.0` to `1.0`.
+    pub color: [f32; 4],
+    /// The lookup texture sampled by the effect, if any. Kept here so it
+    /// stays loaded while the effect exists.
+    lut: Option<Handle<Texture>>,
+    #[doc(hidden)]
+    graphics_handler: GraphicsHandler,
+}
+
+impl PostEffect {
+    /// Creates an effect out of a shader. Its vertex shader must take the
+    /// same attributes as a sprite, and its fragment shader must declare the
+    /// `effect_uniforms`.
+    ///
+    /// # Example
+    /// ```rust
+    /// let params = ShaderParams {
+    ///     vertex_shader: post_process::VERTEX,
+    ///     fragment_shader: MY_FRAGMENT,
+    ///     meta: post_process::effect_meta(false),
+    /// };
+    /// let effect = PostEffect::new(ctx, "Sepia", params)?;
+    /// ```
+    pub fn new(
+        ctx: &mut Context,
+        name: &str,
+        shader_params: ShaderParams,
+    ) -> Result<Self, EngineError> {
+        info!("Creating post processing effect {:?}", name);
+        let graphics_handler =
+            GraphicsHandler::try_from_gpu_texture(ctx, miniquad::Texture::empty(), shader_params)?;
+
+        Ok(Self {
+            name: name.to_string(),
+            enabled: true,
+            intensity: 1.0,
+            decay: 0.0,
+            color: [1.0, 1.0, 1.0, 1.0],
+            lut: None,
+            graphics_handler,
+        })
+    }
+
+    /// Darkens every other row, like an old CRT screen.
+    pub fn scanlines(ctx: &mut Context) -> Result<Self, EngineError> {
+        let mut effect = Self::new(
+            ctx,
+            "CRT scanlines",
+            effect_params(shader::SCANLINES, false),
+        )?;
+        effect.intensity = 0.35;
+
+        Ok(effect)
+    }
+
+    /// Darkens the borders of the frame.
+    pub fn vignette(ctx: &mut Context) -> Result<Self, EngineError> {
+        let mut effect = Self::new(ctx, "Vignette", effect_params(shader::VIGNETTE, false))?;
+        effect.intensity = 0.5;
+
+        Ok(effect)
+    }
+
+    /// Remaps every color through a 256x16 lookup texture: 16 slices of
+    /// 16x16 pixels side by side, red growing to the right, green
+    /// downwards, and blue from one slice to the next. The texture should be
+    /// loaded with linear filtering.
+    pub fn color_grading(ctx: &mut Context, lut: Handle<Texture>) -> Result<Self, EngineError> {
+        let mut effect = Self::new(
+            ctx,
+            "Color grading",
+            effect_params(shader::COLOR_GRADING, true),
+        )?;
+        effect
+            .graphics_handler
+            .bindings_mut()
+            .images
+            .push(lut.raw());
+        effect.lut = Some(lut);
+
+        Ok(effect)
+    }
+
+    /// Covers the frame with `color`. Starts invisible, see `trigger`.
+    pub fn flash(ctx: &mut Context) -> Result<Self, EngineError> {
+        let mut effect = Self::new(ctx, "Screen flash", effect_params(shader::FLASH, false))?;
+        effect.intensity = 0.0;
+
+        Ok(effect)
+    }
+
+    /// Makes the frame blockier. At full intensity, blocks are 16 pixels wide.
+    pub fn pixelation(ctx: &mut Context) -> Result<Self, EngineError> {
+        let mut effect = Self::new(ctx, "Pixelation", effect_params(shader::PIXELATION, false))?;
+        effect.intensity = 0.25;
+
+        Ok(effect)
+    }
+
+    /// Sets the effect to `intensity` and fades it out over `duration` seconds.
+    ///
+    /// # Example
+    /// ```rust
+    /// // flash red for a quarter of a second when hit
+    /// flash.color = [1.0, 0.0, 0.0, 1.0];
+    /// flash.trigger(1.0, 0.25);
+    /// ```
+    pub fn trigger(&mut self, intensity: f32, duration: f32) {
+        self.intensity = intensity;
+        self.decay = if duration > 0.0 {
+            intensity / duration
+        } else {
+            intensity
+        };
+    }
+}
+
+/// A stack of effects applied one after the other to a frame.
+pub struct PostProcess {
+    effects: Vec<PostEffect>,
+    /// Two targets, each effect reading from one and writing into the other.
+    targets: [RenderTarget; 2],
+    /// The seconds elapsed, handed to the effects.
+    time: f32,
+}
+
+impl PostProcess {
+    /// Creates an empty stack, for frames of `width` by `height` pixels.
+    pub fn new(ctx: &mut Context, width: u32, height: u32) -> Result<Self, EngineError> {
+        let targets = [
+            RenderTarget::try_new(ctx, width, height)?,
+            RenderTarget::try_new(ctx, width, height)?,
+        ];
+
+        Ok(Self {
+            effects: Vec::new(),
+            targets,
+            time: 0.0,
+        })
+    }
+
+    /// Adds an effect on top of the stack, applied after every other one.
+    pub fn push(&mut self, effect: PostEffect) {
+        self.effects.push(effect);
+    }
+
+    /// Gets the effects, in the order they are applied.
+    pub fn effects(&self) -> &[PostEffect] {
+        &self.effects
+    }
+
+    /// Gets the effects mutably, in the order they are applied.
+    pub fn effects_mut(&mut self) -> &mut [PostEffect] {
+        &mut self.effects
+    }
+
+    /// Gets the first effect with the given name.
+    pub fn effect_mut(&mut self, name: &str) -> Option<&mut PostEffect> {
+        self.effects.iter_mut().find(|effect| effect.name == name)
+    }
+
+    /// Moves the effect at `index` one step earlier in the stack.
+    pub fn move_up(&mut self, index: usize) {
+        if index > 0 && index < self.effects.len() {
+            self.effects.swap(index, index - 1);
+        }
+    }
+
+    /// Moves the effect at `index` one step later in the stack.
+    pub fn move_down(&mut self, index: usize) {
+        if index + 1 < self.effects.len() {
+            self.effects.swap(index, index + 1);
+        }
+    }
+
+    /// Advances the time by `dt` seconds, fading out decaying effects.
+    pub fn update(&mut self, dt: f32) {
+        self.time += dt;
+
+        for effect in self.effects.iter_mut().filter(|effect| effect.decay > 0.0) {
+            effect.intensity = (effect.intensity - effect.decay * dt).max(0.0);
+            if effect.intensity == 0.0 {
+                effect.decay = 0.0;
+            }
+        }
+    }
+
+    /// Applies every enabled effect to the contents of `scene`, and returns
+    /// the target holding the result. That is `scene` itself if there is
+    /// nothing to apply.
+    pub fn apply<'a>(&'a self, ctx: &mut Context, scene: &'a RenderTarget) -> &'a RenderTarget {
+        let mut output = scene;
+
+        for (i, effect) in self.effects.iter().filter(|e| e.enabled).enumerate() {
+            let target = &self.targets[i % 2];
+            let (width, height) = target.size();
+
+            let mut bindings = effect.graphics_handler.bindings().clone();
+            bindings.images[0] = output.texture();
+
+            target.begin(ctx, PassAction::Nothing);
+            ctx.apply_pipeline(effect.graphics_handler.pipeline());
+            ctx.apply_bindings(&bindings);
+            ctx.apply_uniforms(&shader::Uniforms {
+                time: self.time,
+                intensity: effect.intensity,
+                resolution: (width as f32, height as f32),
+                color: effect.color,
+            });
+            ctx.draw(0, 6, 1);
+            target.end(ctx);
+
+            output = target;
+        }
+
+        output
+    }
+}
+
+/// The vertex shader shared by every effect, drawing a fullscreen quad.
+pub const VERTEX: &str = shader::VERTEX;
+
+/// Describes the images and uniforms every effect shader takes.
+/// `lut` adds a second image, called `lut`.
+pub fn effect_meta(lut: bool) -> ShaderMeta {
+    shader::meta(lut)
+}
+
+/// Builds the params of an effect out of its fragment shader.
+fn effect_params(fragment_shader: &'static str, lut: bool) -> ShaderParams {
+    ShaderParams {
+        vertex_shader: shader::VERTEX,
+        fragment_shader,
+        meta: shader::meta(lut),
+    }
+}
+
+mod shader {
+    use miniquad::*;
+
+    pub const VERTEX: &str = r#"#version 100
+    attribute vec2 pos;
+    attribute vec4 color0;
+    attribute vec2 tex0;
+
+    varying lowp vec2 texcoord;
+
+    void main() {
+        gl_Position = vec4(pos, 0, 1);
+        texcoord = tex0;
+    }"#;
+
+    pub const SCANLINES: &str = r#"#version 100
+    precision mediump float;
+
+    varying lowp vec2 texcoord;
+
+    uniform sampler2D tex;
+    uniform float time;
+    uniform float intensity;
+    uniform vec2 resolution;
+    uniform vec4 color;
+
+    void main() {
+        vec4 frame = texture2D(tex, texcoord);
+        float row = floor(texcoord.y * resolution.y);
+        float line = mod(row, 2.0);
+        float flicker = 0.03 * sin(time * 60.0);
+        float darken = intensity * (line + flicker);
+        gl_FragColor = vec4(frame.rgb * (1.0 - clamp(darken, 0.0, 1.0)), frame.a);
+    }
+    "#;
+
+    pub const VIGNETTE: &str = r#"#version 100
+    precision mediump float;
+
+    varying lowp vec2 texcoord;
+
+    uniform sampler2D tex;
+    uniform float time;
+    uniform float intensity;
+    uniform vec2 resolution;
+    uniform vec4 color;
+
+    void main() {
+        vec4 frame = texture2D(tex, texcoord);
+        float distance = length(texcoord - vec2(0.5)) * 1.4142;
+        float darken = smoothstep(0.4, 1.0, distance) * intensity;
+        gl_FragColor = vec4(frame.rgb * (1.0 - darken), frame.a);
+    }
+    "#;
+
+    pub const COLOR_GRADING: &str = r#"#version 100
+    precision mediump float;
+
+    varying lowp vec2 texcoord;
+
+    uniform sampler2D tex;
+    uniform sampler2D lut;
+    uniform float time;
+    uniform float intensity;
+    uniform vec2 resolution;
+    uniform vec4 color;
+
+    vec3 grade(vec3 original) {
+        float blue = original.b * 15.0;
+        float slice_low = floor(blue);
+        float slice_high = min(slice_low + 1.0, 15.0);
+
+        float x = (original.r * 15.0 + 0.5) / 256.0;
+        float y = (original.g * 15.0 + 0.5) / 16.0;
+
+        vec3 low = texture2D(lut, vec2(x + slice_low / 16.0, y)).rgb;
+        vec3 high = texture2D(lut, vec2(x + slice_high / 16.0, y)).rgb;
+
+        return mix(low, high, blue - slice_low);
+    }
+
+    void main() {
+        vec4 frame = texture2D(tex, texcoord);
+        vec3 graded = grade(clamp(frame.rgb, 0.0, 1.0));
+        gl_FragColor = vec4(mix(frame.rgb, graded, intensity), frame.a);
+    }
+    "#;
+
+    pub const FLASH: &str = r#"#version 100
+    precision mediump float;
+
+    varying lowp vec2 texcoord;
+
+    uniform sampler2D tex;
+    uniform float time;
+    uniform float intensity;
+    uniform vec2 resolution;
+    uniform vec4 color;
+
+    void main() {
+        vec4 frame = texture2D(tex, texcoord);
+        float amount = clamp(intensity * color.a, 0.0, 1.0);
+        gl_FragColor = vec4(mix(frame.rgb, color.rgb, amount), frame.a);
+    }
+    "#;
+
+    pub const PIXELATION: &str = r#"#version 100
+    precision mediump float;
+
+    varying lowp vec2 texcoord;
+
+    uniform sampler2D tex;
+    uniform float time;
+    uniform float intensity;
+    uniform vec2 resolution;
+    uniform vec4 color;
+
+    void main() {
+        float block = max(1.0, floor(1.0 + intensity * 15.0));
+        vec2 cell = block / resolution;
+        vec2 uv = (floor(texcoord / cell) + 0.5) * cell;
+        gl_FragColor = texture2D(tex, uv);
+    }
+    "#;
+
+    pub fn meta(lut: bool) -> ShaderMeta {
+        let mut images = vec!["tex".to_string()];
+        if lut {
+            images.push("lut".to_string());
+        }
+
+        ShaderMeta {
+            images,
+            uniforms: UniformBlockLayout {
+                uniforms: vec![
+                    UniformDesc::new("time", UniformType::Float1),
+                    UniformDesc::new("intensity", UniformType::Float1),
+                    UniformDesc::new("resolution", UniformType::Float2),
+                    UniformDesc::new("color", UniformType::Float4),
+                ],
+            },
+        }
+    }
+
+    #[repr(C)]
+    pub struct Uniforms {
+        pub time: f32,
+        pub intensity: f32,
+        pub resolution: (f32, f32),
+        pub color: [f32; 4],
+    }
+}
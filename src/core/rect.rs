@@ -0,0 +1,50 @@
+use glam::Vec2;
+
+/// An axis-aligned rectangle, with its origin at the top-left corner.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Rect {
+    /// The left edge of the rectangle.
+    pub x: f32,
+    /// The top edge of the rectangle.
+    pub y: f32,
+    /// The width of the rectangle.
+    pub w: f32,
+    /// The height of the rectangle.
+    pub h: f32,
+}
+
+impl Rect {
+    /// Creates a rectangle at `x`, `y` with the given width and height.
+    pub const fn new(x: f32, y: f32, w: f32, h: f32) -> Self {
+        Self { x, y, w, h }
+    }
+
+    /// Creates a rectangle from its top-left corner and its size.
+    pub fn from_position_size(position: Vec2, size: Vec2) -> Self {
+        Self::new(position.x, position.y, size.x, size.y)
+    }
+
+    /// Gets the top-left corner of the rectangle.
+    pub fn position(&self) -> Vec2 {
+        Vec2::new(self.x, self.y)
+    }
+
+    /// Gets the width and height of the rectangle.
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.w, self.h)
+    }
+
+    /// Gets the bottom-right corner of the rectangle.
+    pub fn max(&self) -> Vec2 {
+        Vec2::new(self.x + self.w, self.y + self.h)
+    }
+
+    /// Checks whether the rectangle overlaps `other`. Rectangles only
+    /// touching by an edge don't overlap.
+    pub fn overlaps(&self, other: &Rect) -> bool {
+        self.x < other.x + other.w
+            && self.x + self.w > other.x
+            && self.y < other.y + other.h
+            && self.y + self.h > other.y
+    }
+}
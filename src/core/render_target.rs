@@ -0,0 +1,148 @@
+use super::error::EngineError;
+use super::graphics_handler::GraphicsHandler;
+use super::viewport::Viewport;
+use miniquad::*;
+
+/// An offscreen texture that can be drawn into instead of the window, and
+/// then drawn onto the window itself.
+///
+/// # Example
+/// ```rust
+/// let target = RenderTarget::new(ctx, 512, 288);
+///
+/// target.begin(ctx, PassAction::clear_color(0.0, 0.0, 0.0, 1.0));
+/// sprite.draw(ctx);
+/// target.end(ctx);
+///
+/// ctx.begin_default_pass(PassAction::clear_color(0.0, 0.0, 0.0, 1.0));
+/// target.blit(ctx, &Viewport::default());
+/// ctx.end_render_pass();
+/// ```
+pub struct RenderTarget {
+    /// The size of the texture, in pixels.
+    size: (u32, u32),
+    /// The pass drawing into the texture.
+    pass: RenderPass,
+    #[doc(hidden)]
+    graphics_handler: GraphicsHandler,
+}
+
+impl RenderTarget {
+    /// Creates a render target of `width` by `height` pixels, upscaled with
+    /// nearest filtering.
+    ///
+    /// # Panics
+    /// Panics if the shaders fail to compile. See `try_new` for a fallible version.
+    pub fn new(ctx: &mut Context, width: u32, height: u32) -> Self {
+        Self::try_new(ctx, width, height).expect("Cannot create the render target")
+    }
+
+    /// Same as `new`, but returns an error if the shaders fail to compile.
+    pub fn try_new(ctx: &mut Context, width: u32, height: u32) -> Result<Self, EngineError> {
+        info!("Creating a render target of {}x{}", width, height);
+        let texture = Texture::new_render_texture(
+            ctx,
+            TextureParams {
+                width,
+                height,
+                format: TextureFormat::RGBA8,
+                filter: FilterMode::Nearest,
+                wrap: TextureWrap::Clamp,
+            },
+        );
+        let pass = RenderPass::new(ctx, texture, None);
+        let graphics_handler =
+            GraphicsHandler::try_from_gpu_texture(ctx, texture, shader::get_shader_params())?;
+
+        Ok(Self {
+            size: (width, height),
+            pass,
+            graphics_handler,
+        })
+    }
+
+    /// Starts drawing into the texture. Everything drawn until `end` is called
+    /// lands in it instead of the window.
+    pub fn begin(&self, ctx: &mut Context, action: PassAction) {
+        ctx.begin_pass(self.pass, action);
+        ctx.apply_viewport(0, 0, self.size.0 as i32, self.size.1 as i32);
+    }
+
+    /// Stops drawing into the texture.
+    pub fn end(&self, ctx: &mut Context) {
+        ctx.end_render_pass();
+    }
+
+    /// Draws the texture into the area of the current pass covered by `viewport`.
+    /// The viewport is reset to the whole window afterwards.
+    pub fn blit(&self, ctx: &mut Context, viewport: &Viewport) {
+        viewport.apply(ctx);
+
+        ctx.apply_pipeline(self.graphics_handler.pipeline());
+        ctx.apply_bindings(self.graphics_handler.bindings());
+        ctx.draw(0, 6, 1);
+
+        Viewport::reset(ctx);
+    }
+
+    /// Gets the texture being drawn into.
+    pub fn texture(&self) -> Texture {
+        self.graphics_handler.bindings().images[0]
+    }
+
+    /// Gets the size of the texture, in pixels.
+    pub const fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    /// Frees the texture and the pass from the GPU.
+    pub fn delete(&self, ctx: &mut Context) {
+        // deleting the pass deletes its texture as well
+        self.pass.delete(ctx);
+        self.graphics_handler.bindings().index_buffer.delete();
+        self.graphics_handler.bindings().vertex_buffers[0].delete();
+    }
+}
+
+mod shader {
+    use miniquad::*;
+
+    use crate::core::graphics_handler::ShaderParams;
+
+    pub const VERTEX: &str = r#"#version 100
+    attribute vec2 pos;
+    attribute vec4 color0;
+    attribute vec2 tex0;
+
+    varying lowp vec2 texcoord;
+
+    void main() {
+        gl_Position = vec4(pos, 0, 1);
+        texcoord = tex0;
+    }"#;
+
+    pub const FRAGMENT: &str = r#"#version 100
+    varying lowp vec2 texcoord;
+
+    uniform sampler2D tex;
+
+    void main() {
+        gl_FragColor = texture2D(tex, texcoord);
+    }
+    "#;
+
+    pub fn meta() -> ShaderMeta {
+        ShaderMeta {
+            images: vec!["tex".to_string()],
+            uniforms: UniformBlockLayout { uniforms: vec![] },
+        }
+    }
+
+    pub fn get_shader_params() -> ShaderParams {
+        ShaderParams {
+            vertex_shader: VERTEX,
+            fragment_shader: FRAGMENT,
+            meta: meta(),
+        }
+    }
+}
@@ -0,0 +1,618 @@
+use super::color::Color;
+use super::error::EngineError;
+use super::graphics_handler::GraphicsHandler;
+use super::time::{Clock, SystemClock, Time, DEFAULT_FIXED_STEP};
+use glam::{Vec2, Vec4};
+use miniquad::*;
+
+/// An event sent by the window to the scenes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SceneEvent {
+    /// A key was pressed.
+    KeyDown {
+        keycode: KeyCode,
+        keymods: KeyMods,
+        repeat: bool,
+    },
+    /// A key was released.
+    KeyUp { keycode: KeyCode, keymods: KeyMods },
+    /// A character was typed.
+    Char {
+        character: char,
+        keymods: KeyMods,
+        repeat: bool,
+    },
+    /// The mouse moved, to a position in window pixels.
+    MouseMotion { x: f32, y: f32 },
+    /// The mouse wheel was scrolled.
+    MouseWheel { x: f32, y: f32 },
+    /// A mouse button was pressed.
+    MouseButtonDown { button: MouseButton, x: f32, y: f32 },
+    /// A mouse button was released.
+    MouseButtonUp { button: MouseButton, x: f32, y: f32 },
+    /// The window was resized. Sent to every scene, not only the top ones.
+    Resize { width: f32, height: f32 },
+    /// The window was minimized, losing focus. Sent to every scene.
+    Minimized,
+    /// The window was restored after being minimized. Sent to every scene.
+    Restored,
+}
+
+impl SceneEvent {
+    /// Checks whether the event comes from the player, rather than the window.
+    pub const fn is_input(&self) -> bool {
+        !matches!(
+            self,
+            SceneEvent::Resize { .. } | SceneEvent::Minimized | SceneEvent::Restored
+        )
+    }
+
+    /// Calls the method of `handler` matching the event. Useful for scenes
+    /// that already implement `EventHandler`.
+    pub fn dispatch(&self, ctx: &mut Context, handler: &mut impl EventHandler) {
+        match *self {
+            SceneEvent::KeyDown {
+                keycode,
+                keymods,
+                repeat,
+            } => handler.key_down_event(ctx, keycode, keymods, repeat),
+            SceneEvent::KeyUp { keycode, keymods } => handler.key_up_event(ctx, keycode, keymods),
+            SceneEvent::Char {
+                character,
+                keymods,
+                repeat,
+            } => handler.char_event(ctx, character, keymods, repeat),
+            SceneEvent::MouseMotion { x, y } => handler.mouse_motion_event(ctx, x, y),
+            SceneEvent::MouseWheel { x, y } => handler.mouse_wheel_event(ctx, x, y),
+            SceneEvent::MouseButtonDown { button, x, y } => {
+                handler.mouse_button_down_event(ctx, button, x, y)
+            }
+            SceneEvent::MouseButtonUp { button, x, y } => {
+                handler.mouse_button_up_event(ctx, button, x, y)
+            }
+            SceneEvent::Resize { width, height } => handler.resize_event(ctx, width, height),
+            SceneEvent::Minimized => handler.window_minimized_event(ctx),
+            SceneEvent::Restored => handler.window_restored_event(ctx),
+        }
+    }
+}
+
+/// What a scene asks the `SceneManager` to do after updating.
+pub enum SceneCommand {
+    /// Keep running the current scene.
+    None,
+    /// Pause the current scene and run a new one on top of it.
+    Push(Box<dyn Scene>, Transition),
+    /// Exit the current scene and resume the one below it.
+    Pop(Transition),
+    /// Exit the current scene and run a new one in its place.
+    Replace(Box<dyn Scene>, Transition),
+    /// Close the application.
+    Quit,
+}
+
+/// A screen of the game, such as a level, a menu or a dialogue box.
+/// Scenes are stacked by a `SceneManager`, and only the top one is updated.
+pub trait Scene {
+    /// Called when the scene is added to the stack.
+    fn enter(&mut self, _ctx: &mut Context) {}
+
+    /// Called when the scene is removed from the stack.
+    fn exit(&mut self, _ctx: &mut Context) {}
+
+    /// Called when another scene is pushed on top of this one.
+    fn pause(&mut self, _ctx: &mut Context) {}
+
+    /// Called when the scene on top of this one is popped.
+    fn resume(&mut self, _ctx: &mut Context) {}
+
+    /// Runs a step of the physics and game logic, lasting exactly
+    /// `time.fixed_delta()` seconds. It may run several times per frame, or
+    /// not at all, and it always runs before `update`.
+    fn fixed_update(&mut self, _ctx: &mut Context, _time: &Time) {}
+
+    /// Updates the scene once per frame, returning what the manager should
+    /// do next.
+    fn update(&mut self, ctx: &mut Context, time: &Time) -> SceneCommand;
+
+    /// Draws the scene. Scenes begin and end their own render passes, but
+    /// must not commit the frame. Objects moved in `fixed_update` can be
+    /// interpolated with `time.alpha()`.
+    fn draw(&mut self, ctx: &mut Context, time: &Time);
+
+    /// Handles an event of the window.
+    fn event(&mut self, _ctx: &mut Context, _event: &SceneEvent) {}
+
+    /// Whether input events also reach the scene below this one.
+    fn passthrough(&self) -> bool {
+        false
+    }
+
+    /// Whether the scene below this one is drawn first, so this one can be
+    /// drawn on top of it, like a pause menu.
+    fn is_overlay(&self) -> bool {
+        false
+    }
+}
+
+/// The direction a wipe transition covers the screen in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WipeDirection {
+    /// From the right edge to the left one.
+    Left,
+    /// From the left edge to the right one.
+    Right,
+    /// From the bottom edge to the top one.
+    Up,
+    /// From the top edge to the bottom one.
+    Down,
+}
+
+impl WipeDirection {
+    /// Gets the direction as a vector, with y going down.
+    fn vector(self) -> Vec2 {
+        match self {
+            WipeDirection::Left => Vec2::new(-1.0, 0.0),
+            WipeDirection::Right => Vec2::new(1.0, 0.0),
+            WipeDirection::Up => Vec2::new(0.0, -1.0),
+            WipeDirection::Down => Vec2::new(0.0, 1.0),
+        }
+    }
+}
+
+/// How the screen is covered while changing scenes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransitionKind {
+    /// Change scenes right away.
+    Cut,
+    /// Fade into a color, then out of it.
+    Fade,
+    /// Cover the screen with a color moving in a direction, then uncover it.
+    Wipe(WipeDirection),
+}
+
+/// A transition between scenes. The screen is covered during the first
+/// half of the duration, the scenes change, and the screen is uncovered
+/// during the second half.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transition {
+    /// How the screen is covered.
+    pub kind: TransitionKind,
+    /// How long the whole transition lasts, in seconds.
+    pub duration: f32,
+    /// The color covering the screen.
+    pub color: Color,
+}
+
+impl Transition {
+    /// Changes scenes right away.
+    pub const CUT: Transition = Transition {
+        kind: TransitionKind::Cut,
+        duration: 0.0,
+        color: Color::BLACK,
+    };
+
+    /// Fades into `color` and out of it, over `duration` seconds.
+    pub const fn fade(duration: f32, color: Color) -> Self {
+        Self {
+            kind: TransitionKind::Fade,
+            duration,
+            color,
+        }
+    }
+
+    /// Wipes the screen with `color` towards `direction`, over `duration` seconds.
+    pub const fn wipe(direction: WipeDirection, duration: f32, color: Color) -> Self {
+        Self {
+            kind: TransitionKind::Wipe(direction),
+            duration,
+            color,
+        }
+    }
+}
+
+/// A change of the scene stack.
+enum StackChange {
+    Push(Box<dyn Scene>),
+    Pop,
+    Replace(Box<dyn Scene>),
+}
+
+/// A transition being played.
+struct ActiveTransition {
+    transition: Transition,
+    /// The seconds elapsed since it started.
+    elapsed: f32,
+    /// The change to apply once the screen is covered.
+    change: Option<StackChange>,
+}
+
+impl ActiveTransition {
+    /// Gets how much of the screen is covered, from 0.0 to 1.0.
+    fn coverage(&self) -> f32 {
+        let half = self.transition.duration / 2.0;
+        if half <= 0.0 {
+            return 0.0;
+        }
+
+        if self.elapsed < half {
+            self.elapsed / half
+        } else {
+            (1.0 - (self.elapsed - half) / half).max(0.0)
+        }
+    }
+}
+
+/// Runs a stack of scenes, only updating the top one.
+/// It owns the timing of the game loop, running fixed steps and frames.
+///
+/// # Example
+/// ```rust
+/// miniquad::start(conf, |ctx| {
+///     let game = Game::new(ctx).unwrap();
+///     Box::new(SceneManager::new(ctx, Box::new(game)).unwrap())
+/// });
+/// ```
+pub struct SceneManager {
+    scenes: Vec<Box<dyn Scene>>,
+    transition: Option<ActiveTransition>,
+    /// The timing of the frames and fixed steps.
+    time: Time,
+    /// Where the time comes from.
+    clock: Box<dyn Clock>,
+    #[doc(hidden)]
+    graphics_handler: GraphicsHandler,
+}
+
+impl SceneManager {
+    /// Creates a manager running `scene`, timed by the system clock with
+    /// the default fixed step.
+    pub fn new(ctx: &mut Context, scene: Box<dyn Scene>) -> Result<Self, EngineError> {
+        Self::with_clock(ctx, scene, Box::new(SystemClock), DEFAULT_FIXED_STEP)
+    }
+
+    /// Creates a manager running `scene`, timed by `clock` with fixed steps
+    /// of `fixed_delta` seconds.
+    pub fn with_clock(
+        ctx: &mut Context,
+        mut scene: Box<dyn Scene>,
+        clock: Box<dyn Clock>,
+        fixed_delta: f32,
+    ) -> Result<Self, EngineError> {
+        info!("Creating the scene manager");
+        let graphics_handler = GraphicsHandler::try_from_gpu_texture(
+            ctx,
+            Texture::empty(),
+            shader::get_shader_params(),
+        )?;
+        scene.enter(ctx);
+
+        Ok(Self {
+            scenes: vec![scene],
+            transition: None,
+            time: Time::new(fixed_delta),
+            clock,
+            graphics_handler,
+        })
+    }
+
+    /// Pauses the top scene and runs `scene` on top of it.
+    pub fn push(&mut self, ctx: &mut Context, scene: Box<dyn Scene>, transition: Transition) {
+        self.start(ctx, StackChange::Push(scene), transition);
+    }
+
+    /// Exits the top scene and resumes the one below it. Closes the
+    /// application when popping the last scene.
+    pub fn pop(&mut self, ctx: &mut Context, transition: Transition) {
+        self.start(ctx, StackChange::Pop, transition);
+    }
+
+    /// Exits the top scene and runs `scene` in its place.
+    pub fn replace(&mut self, ctx: &mut Context, scene: Box<dyn Scene>, transition: Transition) {
+        self.start(ctx, StackChange::Replace(scene), transition);
+    }
+
+    /// Gets the amount of scenes in the stack.
+    pub fn len(&self) -> usize {
+        self.scenes.len()
+    }
+
+    /// Checks whether there are no scenes left.
+    pub fn is_empty(&self) -> bool {
+        self.scenes.is_empty()
+    }
+
+    /// Gets the timing of the game loop.
+    pub const fn time(&self) -> &Time {
+        &self.time
+    }
+
+    /// Gets the timing of the game loop mutably, to change its time scale.
+    pub fn time_mut(&mut self) -> &mut Time {
+        &mut self.time
+    }
+
+    /// Checks whether a transition is being played.
+    pub fn is_transitioning(&self) -> bool {
+        self.transition.is_some()
+    }
+
+    /// Starts a transition, or changes the stack right away for cuts.
+    fn start(&mut self, ctx: &mut Context, change: StackChange, transition: Transition) {
+        if self.transition.is_some() {
+            warn!("Ignoring a scene change requested during a transition");
+            return;
+        }
+
+        if transition.kind == TransitionKind::Cut || transition.duration <= 0.0 {
+            self.apply(ctx, change);
+            return;
+        }
+
+        self.transition = Some(ActiveTransition {
+            transition,
+            elapsed: 0.0,
+            change: Some(change),
+        });
+    }
+
+    /// Changes the stack, calling the hooks of the scenes involved.
+    fn apply(&mut self, ctx: &mut Context, change: StackChange) {
+        match change {
+            StackChange::Push(mut scene) => {
+                if let Some(top) = self.scenes.last_mut() {
+                    top.pause(ctx);
+                }
+                scene.enter(ctx);
+                self.scenes.push(scene);
+            }
+            StackChange::Pop => {
+                if let Some(mut top) = self.scenes.pop() {
+                    top.exit(ctx);
+                }
+                match self.scenes.last_mut() {
+                    Some(top) => top.resume(ctx),
+                    None => ctx.order_quit(),
+                }
+            }
+            StackChange::Replace(mut scene) => {
+                if let Some(mut top) = self.scenes.pop() {
+                    top.exit(ctx);
+                }
+                scene.enter(ctx);
+                self.scenes.push(scene);
+            }
+        }
+    }
+
+    /// Advances the transition, changing the stack once the screen is covered.
+    fn update_transition(&mut self, ctx: &mut Context, dt: f32) {
+        let transition = match &mut self.transition {
+            Some(transition) => transition,
+            None => return,
+        };
+
+        transition.elapsed += dt;
+        if transition.elapsed >= transition.transition.duration / 2.0 {
+            if let Some(change) = transition.change.take() {
+                self.apply(ctx, change);
+            }
+        }
+
+        if let Some(transition) = &self.transition {
+            if transition.elapsed >= transition.transition.duration {
+                self.transition = None;
+            }
+        }
+    }
+
+    /// Draws the color covering the screen during transitions.
+    fn draw_transition(&mut self, ctx: &mut Context) {
+        let transition = match &self.transition {
+            Some(transition) => transition,
+            None => return,
+        };
+
+        let (wipe, direction) = match transition.transition.kind {
+            TransitionKind::Wipe(direction) => (1.0, direction.vector()),
+            _ => (0.0, Vec2::ZERO),
+        };
+
+        ctx.begin_default_pass(PassAction::Nothing);
+        ctx.apply_pipeline(self.graphics_handler.pipeline());
+        ctx.apply_bindings(self.graphics_handler.bindings());
+        ctx.apply_uniforms(&shader::Uniforms {
+            color: Vec4::from(transition.transition.color),
+            coverage: transition.coverage(),
+            wipe,
+            direction: direction.into(),
+        });
+        ctx.draw(0, 6, 1);
+        ctx.end_render_pass();
+    }
+
+    /// Sends an event to the top scene, and to the ones below it for as
+    /// long as they let input pass through. Window events reach every scene.
+    fn send(&mut self, ctx: &mut Context, event: SceneEvent) {
+        for scene in self.scenes.iter_mut().rev() {
+            scene.event(ctx, &event);
+
+            if event.is_input() && !scene.passthrough() {
+                break;
+            }
+        }
+    }
+}
+
+impl EventHandler for SceneManager {
+    fn update(&mut self, ctx: &mut Context) {
+        self.time.tick(self.clock.now());
+
+        // transitions keep going when the game is slowed down or paused
+        self.update_transition(ctx, self.time.unscaled_delta());
+
+        let top = match self.scenes.last_mut() {
+            Some(top) => top,
+            None => return,
+        };
+
+        for _ in 0..self.time.fixed_steps() {
+            top.fixed_update(ctx, &self.time);
+        }
+        let command = top.update(ctx, &self.time);
+
+        match command {
+            SceneCommand::None => (),
+            SceneCommand::Push(scene, transition) => self.push(ctx, scene, transition),
+            SceneCommand::Pop(transition) => self.pop(ctx, transition),
+            SceneCommand::Replace(scene, transition) => self.replace(ctx, scene, transition),
+            SceneCommand::Quit => ctx.order_quit(),
+        }
+    }
+
+    fn draw(&mut self, ctx: &mut Context) {
+        // draw the scenes below overlays first
+        let first_visible = self
+            .scenes
+            .iter()
+            .rposition(|scene| !scene.is_overlay())
+            .unwrap_or(0);
+
+        for scene in &mut self.scenes[first_visible..] {
+            scene.draw(ctx, &self.time);
+        }
+
+        self.draw_transition(ctx);
+
+        ctx.commit_frame();
+    }
+
+    fn key_down_event(
+        &mut self,
+        ctx: &mut Context,
+        keycode: KeyCode,
+        keymods: KeyMods,
+        repeat: bool,
+    ) {
+        self.send(
+            ctx,
+            SceneEvent::KeyDown {
+                keycode,
+                keymods,
+                repeat,
+            },
+        );
+    }
+
+    fn key_up_event(&mut self, ctx: &mut Context, keycode: KeyCode, keymods: KeyMods) {
+        self.send(ctx, SceneEvent::KeyUp { keycode, keymods });
+    }
+
+    fn char_event(&mut self, ctx: &mut Context, character: char, keymods: KeyMods, repeat: bool) {
+        self.send(
+            ctx,
+            SceneEvent::Char {
+                character,
+                keymods,
+                repeat,
+            },
+        );
+    }
+
+    fn mouse_motion_event(&mut self, ctx: &mut Context, x: f32, y: f32) {
+        self.send(ctx, SceneEvent::MouseMotion { x, y });
+    }
+
+    fn mouse_wheel_event(&mut self, ctx: &mut Context, x: f32, y: f32) {
+        self.send(ctx, SceneEvent::MouseWheel { x, y });
+    }
+
+    fn mouse_button_down_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+        self.send(ctx, SceneEvent::MouseButtonDown { button, x, y });
+    }
+
+    fn mouse_button_up_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+        self.send(ctx, SceneEvent::MouseButtonUp { button, x, y });
+    }
+
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) {
+        self.send(ctx, SceneEvent::Resize { width, height });
+    }
+
+    fn window_minimized_event(&mut self, ctx: &mut Context) {
+        self.send(ctx, SceneEvent::Minimized);
+    }
+
+    fn window_restored_event(&mut self, ctx: &mut Context) {
+        self.send(ctx, SceneEvent::Restored);
+    }
+}
+
+mod shader {
+    use miniquad::*;
+
+    use crate::core::graphics_handler::ShaderParams;
+
+    pub const VERTEX: &str = r#"#version 100
+    attribute vec2 pos;
+    attribute vec4 color0;
+    attribute vec2 tex0;
+
+    varying lowp vec2 texcoord;
+
+    void main() {
+        gl_Position = vec4(pos, 0, 1);
+        texcoord = tex0;
+    }"#;
+
+    pub const FRAGMENT: &str = r#"#version 100
+    precision mediump float;
+
+    varying lowp vec2 texcoord;
+
+    uniform vec4 color;
+    uniform float coverage;
+    uniform float wipe;
+    uniform vec2 direction;
+
+    void main() {
+        // texture coordinates go up, while wipe directions go down
+        vec2 point = vec2(texcoord.x, 1.0 - texcoord.y) - 0.5;
+        float along = dot(point, direction) + 0.5;
+        float wiped = step(along, coverage);
+
+        float alpha = mix(coverage, wiped, wipe) * color.a;
+        gl_FragColor = vec4(color.rgb, alpha);
+    }
+    "#;
+
+    pub fn meta() -> ShaderMeta {
+        ShaderMeta {
+            images: vec![],
+            uniforms: UniformBlockLayout {
+                uniforms: vec![
+                    UniformDesc::new("color", UniformType::Float4),
+                    UniformDesc::new("coverage", UniformType::Float1),
+                    UniformDesc::new("wipe", UniformType::Float1),
+                    UniformDesc::new("direction", UniformType::Float2),
+                ],
+            },
+        }
+    }
+
+    pub fn get_shader_params() -> ShaderParams {
+        ShaderParams {
+            vertex_shader: VERTEX,
+            fragment_shader: FRAGMENT,
+            meta: meta(),
+        }
+    }
+
+    #[repr(C)]
+    pub struct Uniforms {
+        pub color: glam::Vec4,
+        pub coverage: f32,
+        pub wipe: f32,
+        pub direction: (f32, f32),
+    }
+}
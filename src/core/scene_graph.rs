@@ -0,0 +1,524 @@
+use super::transform::{Spatial, Transform};
+use std::error::Error;
+use std::fmt;
+
+/// Identifies a node of a `SceneGraph`. Ids of removed nodes are never
+/// reused, so they can be kept around safely.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId {
+    /// The slot of the node in the graph.
+    index: u32,
+    /// How many nodes used the slot before this one.
+    generation: u32,
+}
+
+/// An error raised when changing the hierarchy of a `SceneGraph`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HierarchyError {
+    /// The node doesn't exist, or was removed.
+    InvalidNode(NodeId),
+    /// The new parent is the node itself, or one of its descendants.
+    Cycle(NodeId),
+}
+
+impl fmt::Display for HierarchyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidNode(id) => write!(f, "node {:?} does not exist", id),
+            Self::Cycle(id) => write!(f, "node {:?} cannot be its own ancestor", id),
+        }
+    }
+}
+
+impl Error for HierarchyError {}
+
+/// A node of a `SceneGraph`. It is placed relative to its parent.
+#[derive(Debug, Clone)]
+pub struct Node {
+    /// The name of the node, useful when debugging.
+    pub name: String,
+    /// The transform relative to the parent, or to the world for root nodes.
+    pub transform: Transform,
+    /// The transform relative to the world, as of the last propagation.
+    global: Transform,
+    /// The node this one is attached to.
+    parent: Option<NodeId>,
+    /// The nodes attached to this one.
+    children: Vec<NodeId>,
+}
+
+impl Node {
+    /// Gets the transform relative to the world, as of the last time the
+    /// graph was propagated.
+    pub const fn global(&self) -> Transform {
+        self.global
+    }
+
+    /// Gets the node this one is attached to.
+    pub const fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    /// Gets the nodes attached to this one.
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+}
+
+/// A slot of the graph, which may hold a node.
+#[derive(Debug, Clone, Default)]
+struct Slot {
+    generation: u32,
+    node: Option<Node>,
+}
+
+/// A tree of transforms. Children follow their parent, inheriting its
+/// translation, rotation and scale.
+///
+/// Global transforms are computed once per frame by `propagate`, and can
+/// then be applied to sprites, shapes and anything else that is `Spatial`.
+///
+/// # Example
+/// ```rust
+/// let mut graph = SceneGraph::new();
+/// let ship = graph.add("ship", Transform::from_translation(Vec2::new(64.0, 64.0)));
+/// let cannon = graph.add_child(ship, "cannon", Transform::from_translation(Vec2::new(8.0, 0.0)))?;
+///
+/// graph.get_mut(ship).unwrap().transform.rotation += 0.1;
+/// graph.propagate();
+/// graph.apply(cannon, &mut cannon_sprite);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SceneGraph {
+    slots: Vec<Slot>,
+    /// Slots left empty by removed nodes.
+    free: Vec<u32>,
+    /// The nodes without a parent, in the order they were added.
+    roots: Vec<NodeId>,
+}
+
+impl SceneGraph {
+    /// Creates an empty graph.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a node without a parent.
+    pub fn add(&mut self, name: &str, transform: Transform) -> NodeId {
+        let id = self.insert(Node {
+            name: name.to_string(),
+            transform,
+            global: transform,
+            parent: None,
+            children: Vec::new(),
+        });
+        self.roots.push(id);
+
+        id
+    }
+
+    /// Adds a node attached to `parent`.
+    /// Returns an error if `parent` doesn't exist.
+    pub fn add_child(
+        &mut self,
+        parent: NodeId,
+        name: &str,
+        transform: Transform,
+    ) -> Result<NodeId, HierarchyError> {
+        let parent_global = self
+            .get(parent)
+            .ok_or(HierarchyError::InvalidNode(parent))?
+            .global;
+
+        let id = self.insert(Node {
+            name: name.to_string(),
+            transform,
+            global: parent_global * transform,
+            parent: Some(parent),
+            children: Vec::new(),
+        });
+        self.node_mut(parent).children.push(id);
+
+        Ok(id)
+    }
+
+    /// Removes a node along with all of its descendants.
+    /// Returns whether the node existed.
+    pub fn remove(&mut self, id: NodeId) -> bool {
+        let parent = match self.get(id) {
+            Some(node) => node.parent,
+            None => return false,
+        };
+        self.detach(id, parent);
+
+        let mut pending = vec![id];
+        while let Some(id) = pending.pop() {
+            let slot = &mut self.slots[id.index as usize];
+            if let Some(node) = slot.node.take() {
+                pending.extend(node.children);
+            }
+            slot.generation += 1;
+            self.free.push(id.index);
+        }
+
+        true
+    }
+
+    /// Attaches a node to `parent`, or makes it a root node if `parent` is
+    /// `None`. If `keep_global` is set, the local transform of the node is
+    /// changed so it stays where it was in the world, as of the last
+    /// propagation. Otherwise, it keeps its local transform and moves along
+    /// with its new parent.
+    /// Returns an error if either node doesn't exist, or if `parent` is the
+    /// node itself or one of its descendants.
+    pub fn set_parent(
+        &mut self,
+        id: NodeId,
+        parent: Option<NodeId>,
+        keep_global: bool,
+    ) -> Result<(), HierarchyError> {
+        let old_parent = self.get(id).ok_or(HierarchyError::InvalidNode(id))?.parent;
+
+        let parent_global = match parent {
+            Some(parent) => {
+                let parent_node = self
+                    .get(parent)
+                    .ok_or(HierarchyError::InvalidNode(parent))?;
+                if self.is_ancestor(id, parent) {
+                    return Err(HierarchyError::Cycle(id));
+                }
+                parent_node.global
+            }
+            None => Transform::IDENTITY,
+        };
+
+        self.detach(id, old_parent);
+        match parent {
+            Some(parent) => self.node_mut(parent).children.push(id),
+            None => self.roots.push(id),
+        }
+
+        let node = self.node_mut(id);
+        node.parent = parent;
+        if keep_global {
+            node.transform = parent_global.inverse() * node.global;
+        }
+
+        Ok(())
+    }
+
+    /// Computes the global transform of every node out of the local ones.
+    /// Should be called once per frame, after moving nodes around and
+    /// before drawing.
+    pub fn propagate(&mut self) {
+        let mut pending: Vec<(NodeId, Transform)> = self
+            .roots
+            .iter()
+            .map(|&root| (root, Transform::IDENTITY))
+            .collect();
+
+        while let Some((id, parent_global)) = pending.pop() {
+            let node = self.node_mut(id);
+            node.global = parent_global * node.transform;
+
+            let global = node.global;
+            pending.extend(node.children.iter().map(|&child| (child, global)));
+        }
+    }
+
+    /// Places `target` where the node is in the world, as of the last
+    /// propagation. Does nothing if the node doesn't exist.
+    pub fn apply(&self, id: NodeId, target: &mut impl Spatial) {
+        if let Some(node) = self.get(id) {
+            target.set_transform(&node.global);
+        }
+    }
+
+    /// Gets a node, if it exists.
+    pub fn get(&self, id: NodeId) -> Option<&Node> {
+        self.slots
+            .get(id.index as usize)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.node.as_ref())
+    }
+
+    /// Gets a node mutably, if it exists.
+    pub fn get_mut(&mut self, id: NodeId) -> Option<&mut Node> {
+        self.slots
+            .get_mut(id.index as usize)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.node.as_mut())
+    }
+
+    /// Gets the global transform of a node, as of the last propagation.
+    pub fn global(&self, id: NodeId) -> Option<Transform> {
+        self.get(id).map(Node::global)
+    }
+
+    /// Gets the nodes without a parent.
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    /// Gets the amount of nodes in the graph.
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    /// Checks whether the graph has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Stores a node in a free slot.
+    fn insert(&mut self, node: Node) -> NodeId {
+        match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.node = Some(node);
+
+                NodeId {
+                    index,
+                    generation: slot.generation,
+                }
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    node: Some(node),
+                });
+
+                NodeId {
+                    index: self.slots.len() as u32 - 1,
+                    generation: 0,
+                }
+            }
+        }
+    }
+
+    /// Removes a node from the children of `parent`, or from the roots.
+    fn detach(&mut self, id: NodeId, parent: Option<NodeId>) {
+        let siblings = match parent {
+            Some(parent) => &mut self.node_mut(parent).children,
+            None => &mut self.roots,
+        };
+        siblings.retain(|&sibling| sibling != id);
+    }
+
+    /// Checks whether `ancestor` is `id` itself or one of its ancestors.
+    fn is_ancestor(&self, ancestor: NodeId, id: NodeId) -> bool {
+        let mut current = Some(id);
+        while let Some(id) = current {
+            if id == ancestor {
+                return true;
+            }
+            current = self.get(id).and_then(|node| node.parent);
+        }
+
+        false
+    }
+
+    /// Gets a node that is known to exist.
+    fn node_mut(&mut self, id: NodeId) -> &mut Node {
+        self.get_mut(id).expect("The node should exist")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec2;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn at(x: f32, y: f32) -> Transform {
+        Transform::from_translation(Vec2::new(x, y))
+    }
+
+    fn assert_near(a: Transform, b: Transform) {
+        assert!(
+            a.translation.distance(b.translation) < 1e-4
+                && (a.rotation - b.rotation).abs() < 1e-5
+                && (a.scale - b.scale).abs() < 1e-5,
+            "{:?} should be {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn composes_transforms_through_every_level() {
+        let mut graph = SceneGraph::new();
+        let ship = graph.add(
+            "ship",
+            Transform {
+                rotation: FRAC_PI_2,
+                scale: 2.0,
+                ..at(100.0, 50.0)
+            },
+        );
+        let turret = graph
+            .add_child(
+                ship,
+                "turret",
+                Transform {
+                    rotation: FRAC_PI_2,
+                    ..at(10.0, 0.0)
+                },
+            )
+            .unwrap();
+        let barrel = graph
+            .add_child(
+                turret,
+                "barrel",
+                Transform {
+                    scale: 0.5,
+                    ..at(4.0, 0.0)
+                },
+            )
+            .unwrap();
+
+        graph.propagate();
+
+        // the turret is twice as far, and turned by both
+        assert_near(
+            graph.global(turret).unwrap(),
+            Transform {
+                translation: Vec2::new(100.0, 70.0),
+                rotation: FRAC_PI_2 * 2.0,
+                scale: 2.0,
+            },
+        );
+        // the barrel is pointing backward, a half turn from the world
+        assert_near(
+            graph.global(barrel).unwrap(),
+            Transform {
+                translation: Vec2::new(92.0, 70.0),
+                rotation: FRAC_PI_2 * 2.0,
+                scale: 1.0,
+            },
+        );
+    }
+
+    #[test]
+    fn moves_children_along_with_their_parent() {
+        let mut graph = SceneGraph::new();
+        let parent = graph.add("parent", at(10.0, 0.0));
+        let child = graph.add_child(parent, "child", at(5.0, 5.0)).unwrap();
+        graph.propagate();
+
+        graph.get_mut(parent).unwrap().transform = at(20.0, 0.0);
+        // until the next propagation, the child stays where it was
+        assert_near(graph.global(child).unwrap(), at(15.0, 5.0));
+        graph.propagate();
+
+        assert_near(graph.global(child).unwrap(), at(25.0, 5.0));
+    }
+
+    #[test]
+    fn keeps_the_global_transform_when_reparenting_if_asked() {
+        let mut graph = SceneGraph::new();
+        let ship = graph.add(
+            "ship",
+            Transform {
+                rotation: FRAC_PI_2,
+                scale: 2.0,
+                ..at(100.0, 50.0)
+            },
+        );
+        let crate_node = graph.add("crate", at(80.0, 60.0));
+        graph.propagate();
+
+        graph.set_parent(crate_node, Some(ship), true).unwrap();
+        graph.propagate();
+
+        assert_near(graph.global(crate_node).unwrap(), at(80.0, 60.0));
+        assert_eq!(graph.get(crate_node).unwrap().parent(), Some(ship));
+        assert_eq!(graph.get(ship).unwrap().children(), [crate_node]);
+        assert_eq!(graph.roots(), [ship]);
+    }
+
+    #[test]
+    fn keeps_the_local_transform_when_reparenting_otherwise() {
+        let mut graph = SceneGraph::new();
+        let ship = graph.add("ship", at(100.0, 50.0));
+        let crate_node = graph.add("crate", at(5.0, 0.0));
+        graph.propagate();
+
+        graph.set_parent(crate_node, Some(ship), false).unwrap();
+        graph.propagate();
+        assert_near(graph.global(crate_node).unwrap(), at(105.0, 50.0));
+
+        // detaching it keeps it where it was in the world
+        graph.set_parent(crate_node, None, true).unwrap();
+        graph.propagate();
+        assert_near(graph.global(crate_node).unwrap(), at(105.0, 50.0));
+        assert_eq!(graph.roots(), [ship, crate_node]);
+        assert!(graph.get(ship).unwrap().children().is_empty());
+    }
+
+    #[test]
+    fn rejects_cycles() {
+        let mut graph = SceneGraph::new();
+        let root = graph.add("root", Transform::IDENTITY);
+        let child = graph.add_child(root, "child", Transform::IDENTITY).unwrap();
+        let grandchild = graph
+            .add_child(child, "grandchild", Transform::IDENTITY)
+            .unwrap();
+
+        assert_eq!(
+            graph.set_parent(root, Some(grandchild), false),
+            Err(HierarchyError::Cycle(root))
+        );
+        assert_eq!(
+            graph.set_parent(child, Some(child), false),
+            Err(HierarchyError::Cycle(child))
+        );
+        // nothing changed
+        assert_eq!(graph.roots(), [root]);
+        assert_eq!(graph.get(root).unwrap().children(), [child]);
+        assert_eq!(graph.get(grandchild).unwrap().parent(), Some(child));
+    }
+
+    #[test]
+    fn removes_nodes_with_their_descendants() {
+        let mut graph = SceneGraph::new();
+        let root = graph.add("root", Transform::IDENTITY);
+        let child = graph.add_child(root, "child", Transform::IDENTITY).unwrap();
+        let grandchild = graph
+            .add_child(child, "grandchild", Transform::IDENTITY)
+            .unwrap();
+        let sibling = graph
+            .add_child(root, "sibling", Transform::IDENTITY)
+            .unwrap();
+
+        assert!(graph.remove(child));
+
+        assert!(graph.get(child).is_none());
+        assert!(graph.get(grandchild).is_none());
+        assert_eq!(graph.get(root).unwrap().children(), [sibling]);
+        assert_eq!(graph.len(), 2);
+        assert!(!graph.remove(child));
+        assert_eq!(
+            graph.set_parent(grandchild, Some(root), false),
+            Err(HierarchyError::InvalidNode(grandchild))
+        );
+        assert_eq!(
+            graph.add_child(child, "orphan", Transform::IDENTITY),
+            Err(HierarchyError::InvalidNode(child))
+        );
+    }
+
+    #[test]
+    fn doesnt_hand_the_ids_of_removed_nodes_to_new_ones() {
+        let mut graph = SceneGraph::new();
+        let removed = graph.add("removed", Transform::IDENTITY);
+        graph.remove(removed);
+
+        let added = graph.add("added", Transform::IDENTITY);
+
+        assert_ne!(added, removed);
+        assert!(graph.get(removed).is_none());
+        assert_eq!(graph.get(added).unwrap().name, "added");
+        assert_eq!(graph.roots(), [added]);
+    }
+}
@@ -1,7 +1,10 @@
+use super::camera::{Camera2D, Drawable};
 use super::color::Color;
-use super::graphics_handler::GraphicsHandler;
+use super::error::EngineError;
+use super::graphics_handler::{BlendMode, GraphicsHandler};
+use super::transform::{Spatial, Transform};
 use super::vertex::Vertex;
-use glam::{Mat4, Vec2, Vec3};
+use glam::{BVec2, Mat4, Vec2, Vec3};
 use miniquad::*;
 
 const NUMBER_OF_SIDES_IN_CIRCLE: usize = 20;
@@ -24,19 +27,30 @@ pub enum ShapeType {
     TriangleLines(f32, f32),
     /// A circle with a radius.
     Circle(f32),
-    /// A circle with a radius, represented by lines.
-    CircleLines(f32),
-    /// A line with an starting point and an ending point.
-    Line(f32, f32),
 }
 
-/// A center position. Determines whether the center should be
-/// at the middle or at (-1.0, 1.0) vertex coordinates of the texture.
+/// A center position. Determines which point of the shape is placed at its
+/// position, and is the pivot it rotates and scales around.
 pub enum ShapeCenterPosition {
     /// The middle of the texture, that is, (0.0, 0.0).
     Middle,
     /// The top-left edge of the texture, that is, (-1.0, 1.0).
     TopLeft,
+    /// Any point of the shape, from (0.0, 0.0) at its top-left edge to
+    /// (1.0, 1.0) at its bottom-right edge.
+    Custom(Vec2),
+}
+
+impl ShapeCenterPosition {
+    /// Gets the pivot, from (0.0, 0.0) at the top-left edge of the shape to
+    /// (1.0, 1.0) at its bottom-right edge.
+    pub const fn pivot(&self) -> Vec2 {
+        match self {
+            ShapeCenterPosition::Middle => Vec2::new(0.5, 0.5),
+            ShapeCenterPosition::TopLeft => Vec2::ZERO,
+            ShapeCenterPosition::Custom(pivot) => *pivot,
+        }
+    }
 }
 
 /// Represents the shape settings that can be applied.
@@ -58,12 +72,21 @@ impl Default for ShapeParams {
 pub struct Shape {
     /// The shape type, having its size in it.
     pub shape_type: ShapeType,
-    /// Where the shape should be placed in screen coordinates.
+    /// Where the shape should be placed in virtual resolution coordinates.
     pub position: Vec2,
     /// The size of the shape. This is the length of its sides, or the radius.
     pub size: Vec2,
     /// The params of the shape.
     pub params: ShapeParams,
+    /// The rotation around the center position, in radians. Positive
+    /// values rotate clockwise.
+    pub rotation: f32,
+    /// A factor applied on top of the size, around the center position.
+    pub scale: f32,
+    /// Whether the shape is mirrored horizontally, in place.
+    pub flip_x: bool,
+    /// Whether the shape is mirrored vertically, in place.
+    pub flip_y: bool,
     #[doc(hidden)]
     graphics_handler: GraphicsHandler,
 }
@@ -83,6 +106,16 @@ impl Shape {
         Self::with_params(ctx, shape_type, position, color, Default::default())
     }
 
+    /// Same as `new`, but returns an error if the shape's shaders fail to compile.
+    pub fn try_new(
+        ctx: &mut Context,
+        shape_type: ShapeType,
+        position: Vec2,
+        color: Color,
+    ) -> Result<Self, EngineError> {
+        Self::try_with_params(ctx, shape_type, position, color, Default::default())
+    }
+
     /// Constructs a shape with the given params.
     ///
     /// # Example
@@ -94,6 +127,9 @@ impl Shape {
     /// let params = ShapeParams { center: ShapeCenterPosition::Middle };
     /// let shape = Shape::with_params(ctx, ShapeType::Circle(radius), pos, Color::WHITE, params);
     /// ```
+    ///
+    /// # Panics
+    /// Panics if the shaders fail to compile. See `try_with_params` for a fallible version.
     pub fn with_params(
         ctx: &mut Context,
         shape_type: ShapeType,
@@ -101,6 +137,18 @@ impl Shape {
         color: Color,
         params: ShapeParams,
     ) -> Self {
+        Self::try_with_params(ctx, shape_type, position, color, params)
+            .expect("Cannot create the shape")
+    }
+
+    /// Same as `with_params`, but returns an error if the shape's shaders fail to compile.
+    pub fn try_with_params(
+        ctx: &mut Context,
+        shape_type: ShapeType,
+        position: Vec2,
+        color: Color,
+        params: ShapeParams,
+    ) -> Result<Self, EngineError> {
         debug!("creating shape {:?} at {}", shape_type, position);
         match &shape_type {
             ShapeType::Square(size) => Self::new_square(ctx, position, *size, color, params),
@@ -124,7 +172,6 @@ impl Shape {
                 Self::new_triangle_lines(ctx, position, size, color, params)
             }
             ShapeType::Circle(r) => Self::new_circle(ctx, position, *r, color, params),
-            _ => unimplemented!(),
         }
     }
 
@@ -135,7 +182,7 @@ impl Shape {
         size: f32,
         color: Color,
         params: ShapeParams,
-    ) -> Self {
+    ) -> Result<Self, EngineError> {
         let size = Vec2::new(size, size);
         Self::new_rectangle(ctx, position, size, color, params)
     }
@@ -147,7 +194,7 @@ impl Shape {
         size: f32,
         color: Color,
         params: ShapeParams,
-    ) -> Self {
+    ) -> Result<Self, EngineError> {
         let size = Vec2::new(size, size);
         Self::new_rectangle_lines(ctx, position, size, color, params)
     }
@@ -159,7 +206,7 @@ impl Shape {
         size: Vec2,
         color: Color,
         params: ShapeParams,
-    ) -> Self {
+    ) -> Result<Self, EngineError> {
         let vertices: [Vertex; 4] = [
             Vertex::new(-1.0, 1.0, color),
             Vertex::new(1.0, 1.0, color),
@@ -171,15 +218,19 @@ impl Shape {
         let shader_params = shader::get_shader_params();
         let draw_mode = PrimitiveType::Triangles;
         let graphics_handler =
-            GraphicsHandler::new(ctx, &vertices, &indices, draw_mode, shader_params);
+            GraphicsHandler::try_new(ctx, &vertices, &indices, draw_mode, shader_params)?;
 
-        Self {
+        Ok(Self {
             position,
             size,
             graphics_handler,
             params,
+            rotation: 0.0,
+            scale: 1.0,
+            flip_x: false,
+            flip_y: false,
             shape_type: ShapeType::Rectangle(size.x, size.y),
-        }
+        })
     }
 
     /// Creates a rectangle's contour.
@@ -189,7 +240,7 @@ impl Shape {
         size: Vec2,
         color: Color,
         params: ShapeParams,
-    ) -> Self {
+    ) -> Result<Self, EngineError> {
         let vertices: [Vertex; 8] = [
             Vertex::new(-1.0, 1.0, color),
             Vertex::new(1.0, 1.0, color),
@@ -207,15 +258,19 @@ impl Shape {
         let shader_params = shader::get_shader_params();
         let draw_mode = PrimitiveType::Lines;
         let graphics_handler =
-            GraphicsHandler::new(ctx, &vertices, &indices, draw_mode, shader_params);
+            GraphicsHandler::try_new(ctx, &vertices, &indices, draw_mode, shader_params)?;
 
-        Self {
+        Ok(Self {
             position,
             size,
             graphics_handler,
             params,
+            rotation: 0.0,
+            scale: 1.0,
+            flip_x: false,
+            flip_y: false,
             shape_type: ShapeType::RectangleLines(size.x, size.y),
-        }
+        })
     }
 
     /// Creates a triangle with the position and size given.  
@@ -225,7 +280,7 @@ impl Shape {
         size: Vec2,
         color: Color,
         params: ShapeParams,
-    ) -> Self {
+    ) -> Result<Self, EngineError> {
         let vertices: [Vertex; 3] = [
             Vertex::new(-1.0, -1.0, color),
             Vertex::new(0.0, 1.0, color),
@@ -235,15 +290,19 @@ impl Shape {
         let shader_params = shader::get_shader_params();
         let draw_mode = PrimitiveType::Triangles;
         let graphics_handler =
-            GraphicsHandler::new(ctx, &vertices, &indices, draw_mode, shader_params);
+            GraphicsHandler::try_new(ctx, &vertices, &indices, draw_mode, shader_params)?;
 
-        Self {
+        Ok(Self {
             position,
             size,
             graphics_handler,
             params,
+            rotation: 0.0,
+            scale: 1.0,
+            flip_x: false,
+            flip_y: false,
             shape_type: ShapeType::Triangle(size.x, size.y),
-        }
+        })
     }
 
     /// Creates a triangle's contour.
@@ -253,7 +312,7 @@ impl Shape {
         size: Vec2,
         color: Color,
         params: ShapeParams,
-    ) -> Self {
+    ) -> Result<Self, EngineError> {
         let vertices: [Vertex; 6] = [
             Vertex::new(-1.0, -1.0, color),
             Vertex::new(0.0, 1.0, color),
@@ -268,15 +327,19 @@ impl Shape {
         let shader_params = shader::get_shader_params();
         let draw_mode = PrimitiveType::Lines;
         let graphics_handler =
-            GraphicsHandler::new(ctx, &vertices, &indices, draw_mode, shader_params);
+            GraphicsHandler::try_new(ctx, &vertices, &indices, draw_mode, shader_params)?;
 
-        Self {
+        Ok(Self {
             position,
             size,
             graphics_handler,
             params,
+            rotation: 0.0,
+            scale: 1.0,
+            flip_x: false,
+            flip_y: false,
             shape_type: ShapeType::TriangleLines(size.x, size.y),
-        }
+        })
     }
 
     /// Creates a new circle with the given position and radius.
@@ -286,7 +349,7 @@ impl Shape {
         radius: f32,
         color: Color,
         params: ShapeParams,
-    ) -> Self {
+    ) -> Result<Self, EngineError> {
         // https://github.com/not-fl3/macroquad/blob/master/src/shapes.rs#L126
         let mut vertices = Vec::<Vertex>::with_capacity(NUMBER_OF_SIDES_IN_CIRCLE + 2);
         let mut indices = Vec::<u16>::with_capacity(NUMBER_OF_SIDES_IN_CIRCLE * 3);
@@ -310,52 +373,91 @@ impl Shape {
         let shader_params = shader::get_shader_params();
         let draw_mode = PrimitiveType::Triangles;
         let graphics_handler =
-            GraphicsHandler::new(ctx, &vertices, &indices, draw_mode, shader_params);
+            GraphicsHandler::try_new(ctx, &vertices, &indices, draw_mode, shader_params)?;
 
-        Self {
+        Ok(Self {
             position,
             size,
             graphics_handler,
             shape_type: ShapeType::Circle(radius),
             params,
-        }
+            rotation: 0.0,
+            scale: 1.0,
+            flip_x: false,
+            flip_y: false,
+        })
+    }
+
+    /// Gets the matrix placing the vertices of the shape, from -1.0 to 1.0,
+    /// into the world.
+    pub fn model_matrix(&self) -> Mat4 {
+        model_matrix(
+            self.position,
+            self.size,
+            self.params.center.pivot(),
+            self.rotation,
+            self.scale,
+            BVec2::new(self.flip_x, self.flip_y),
+        )
+    }
+
+    /// Changes how the shape blends with what is below it.
+    pub fn set_blend_mode(&self, ctx: &mut Context, blend_mode: BlendMode) {
+        self.graphics_handler.set_blend_mode(ctx, blend_mode);
     }
 
     // TODO: add new_circle_lines()
     // fn new_circle_lines() {}
 }
 
+/// Gets the matrix placing vertices from -1.0 to 1.0 into the world, as
+/// described by the fields of `Shape`.
+fn model_matrix(
+    position: Vec2,
+    size: Vec2,
+    pivot: Vec2,
+    rotation: f32,
+    scale: f32,
+    flip: BVec2,
+) -> Mat4 {
+    // move the pivot to the origin, so it is the point rotated around
+    let offset = Vec2::ONE - pivot * 2.0;
+    let flip = Vec3::new(
+        if flip.x { -1.0 } else { 1.0 },
+        if flip.y { -1.0 } else { 1.0 },
+        1.0,
+    );
+
+    Mat4::from_translation(position.extend(0.0))
+        * Mat4::from_rotation_z(rotation)
+        * Mat4::from_scale((size * scale).extend(1.0))
+        * Mat4::from_translation(offset.extend(0.0))
+        * Mat4::from_scale(flip)
+}
+
 impl EventHandler for Shape {
     fn update(&mut self, _ctx: &mut Context) {}
 
     fn draw(&mut self, ctx: &mut Context) {
-        let (window_width, window_height) = ctx.screen_size();
-
-        // TODO: Adjust position to be in respect to the size of the screen
-        let (position_x, position_y): (f32, f32) = self.position.into();
-        let translation = Vec3::new(position_x, position_y, 0.0);
-        let translation_matrix = Mat4::from_translation(translation);
-
-        let (scale_x, scale_y): (f32, f32) = self.size.into();
-        let scale = Vec3::new(scale_x, scale_y, 1.0);
-        let scale_matrix = Mat4::from_scale(scale);
-
-        let ortho_matrix =
-            Mat4::orthographic_rh_gl(0.0, window_width, window_height, 0.0, -1.0, 1.0);
+        self.draw_with_camera(ctx, &Camera2D::default());
+    }
+}
 
-        let mvp = ortho_matrix * translation_matrix * scale_matrix;
+impl Spatial for Shape {
+    fn set_transform(&mut self, transform: &Transform) {
+        self.position = transform.translation;
+        self.rotation = transform.rotation;
+        self.scale = transform.scale;
+    }
+}
 
-        let (offset_x, offset_y) = match self.params.center {
-            ShapeCenterPosition::Middle => (0.0, 0.0),
-            ShapeCenterPosition::TopLeft => (1.0, 1.0),
-        };
+impl Drawable for Shape {
+    fn draw_with_camera(&mut self, ctx: &mut Context, camera: &Camera2D) {
+        let mvp = camera.view_projection() * self.model_matrix();
 
         ctx.apply_pipeline(self.graphics_handler.pipeline());
         ctx.apply_bindings(self.graphics_handler.bindings());
-        ctx.apply_uniforms(&shader::Uniforms {
-            offset: (offset_x, offset_y),
-            mvp,
-        });
+        ctx.apply_uniforms(&shader::Uniforms { mvp });
 
         match &self.shape_type {
             ShapeType::Square(_) | ShapeType::Rectangle(_, _) => ctx.draw(0, 6, 1),
@@ -363,7 +465,6 @@ impl EventHandler for Shape {
             ShapeType::Triangle(_, _) => ctx.draw(0, 3, 1),
             ShapeType::TriangleLines(_, _) => ctx.draw(0, 12, 1),
             ShapeType::Circle(_) => ctx.draw(0, NUMBER_OF_SIDES_IN_CIRCLE as i32 * 3, 1),
-            _ => unimplemented!(),
         }
     }
 }
@@ -378,17 +479,15 @@ mod shader {
 
     pub const VERTEX: &str = r#"#version 100
     attribute vec2 pos;
-    attribute vec3 color0;
+    attribute vec4 color0;
 
-    uniform vec2 offset;
     uniform mat4 mvp;
 
     varying lowp vec4 color;
 
     void main() {
-        vec4 pos = vec4(pos + offset, 0, 1);
-        gl_Position = mvp * pos;
-        color = vec4(color0, 1.0);
+        gl_Position = mvp * vec4(pos, 0, 1);
+        color = color0;
     }"#;
 
     pub const FRAGMENT: &str = r#"#version 100
@@ -403,10 +502,7 @@ mod shader {
         ShaderMeta {
             images: vec!["tex".to_string()],
             uniforms: UniformBlockLayout {
-                uniforms: vec![
-                    UniformDesc::new("offset", UniformType::Float2),
-                    UniformDesc::new("mvp", UniformType::Mat4),
-                ],
+                uniforms: vec![UniformDesc::new("mvp", UniformType::Mat4)],
             },
         }
     }
@@ -421,7 +517,80 @@ mod shader {
 
     #[repr(C)]
     pub struct Uniforms {
-        pub offset: (f32, f32),
         pub mvp: glam::Mat4,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    const SIZE: Vec2 = Vec2::new(8.0, 4.0);
+    const NO_FLIP: BVec2 = BVec2::new(false, false);
+    const TOP_LEFT: Vec2 = ShapeCenterPosition::TopLeft.pivot();
+    const MIDDLE: Vec2 = ShapeCenterPosition::Middle.pivot();
+
+    fn assert_places(matrix: Mat4, vertex: Vec2, expected: Vec2) {
+        let placed = matrix.transform_point3(vertex.extend(0.0)).truncate();
+        assert!(
+            placed.abs_diff_eq(expected, 1e-4),
+            "{vertex} went to {placed}, not {expected}"
+        );
+    }
+
+    #[test]
+    fn translates_the_top_left_corner_to_the_position() {
+        let position = Vec2::new(10.0, 20.0);
+        let matrix = model_matrix(position, SIZE, TOP_LEFT, 0.0, 1.0, NO_FLIP);
+
+        // the vertices span twice the size
+        assert_places(matrix, -Vec2::ONE, position);
+        assert_places(matrix, Vec2::ONE, position + SIZE * 2.0);
+    }
+
+    #[test]
+    fn rotates_around_the_pivot() {
+        let position = Vec2::new(100.0, 100.0);
+        let matrix = model_matrix(position, SIZE, MIDDLE, FRAC_PI_2, 1.0, NO_FLIP);
+
+        assert_places(matrix, Vec2::ZERO, position);
+        assert_places(matrix, Vec2::new(1.0, -1.0), position + Vec2::new(4.0, 8.0));
+    }
+
+    #[test]
+    fn scales_both_sides_by_the_same_factor() {
+        let position = Vec2::new(5.0, 5.0);
+        let matrix = model_matrix(position, SIZE, TOP_LEFT, 0.0, 2.0, NO_FLIP);
+
+        assert_places(matrix, -Vec2::ONE, position);
+        assert_places(matrix, Vec2::ONE, position + Vec2::new(32.0, 16.0));
+    }
+
+    #[test]
+    fn flips_in_place() {
+        let position = Vec2::new(10.0, 20.0);
+
+        let flip_x = model_matrix(position, SIZE, TOP_LEFT, 0.0, 1.0, BVec2::new(true, false));
+        assert_places(flip_x, -Vec2::ONE, position + Vec2::new(16.0, 0.0));
+        assert_places(flip_x, Vec2::new(1.0, -1.0), position);
+
+        let flip_y = model_matrix(position, SIZE, TOP_LEFT, 0.0, 1.0, BVec2::new(false, true));
+        assert_places(flip_y, -Vec2::ONE, position + Vec2::new(0.0, 8.0));
+        assert_places(flip_y, Vec2::new(-1.0, 1.0), position);
+    }
+
+    #[test]
+    fn flips_then_pivots_then_scales_then_rotates_then_translates() {
+        let position = Vec2::new(50.0, 50.0);
+        let matrix = model_matrix(position, SIZE, Vec2::ONE, PI, 2.0, BVec2::new(false, true));
+
+        // (-1, -1) flips to (-1, 1), which is (-2, 0) from the pivot,
+        // (-32, 0) scaled, and (32, 0) upside down
+        assert_places(matrix, -Vec2::ONE, position + Vec2::new(32.0, 0.0));
+        // (1, 1) flips to (1, -1), which is (0, -2) from the pivot,
+        // (0, -16) scaled, and (0, 16) upside down
+        assert_places(matrix, Vec2::ONE, position + Vec2::new(0.0, 16.0));
+        assert_places(matrix, Vec2::new(1.0, -1.0), position);
+    }
+}
@@ -1,158 +1,265 @@
-use crate::{VIRTUAL_RESOLUTION_X, VIRTUAL_RESOLUTION_Y, WINDOW_HEIGHT, WINDOW_WIDTH};
-
+use super::assets::{Assets, Handle};
+use super::camera::{Camera2D, Drawable};
+use super::color::Color;
+use super::error::EngineError;
+use super::graphics_handler::ShaderParams;
+use super::rect::Rect;
+use super::sprite_batch::Quad;
 use super::texture::Texture;
-use glam::{Mat4, Vec2, Vec3};
+use super::transform::{Spatial, Transform};
+use glam::{BVec2, Mat4, Vec2, Vec3, Vec4};
 use miniquad::*;
 use std::path::Path;
 
 /// A sprite. Represents an image on the screen.
 pub struct Sprite {
-    /// The position where the sprite will be located, with a center at the
-    /// top-left corner of the sprite.
+    /// The position where the sprite will be located. This is where its
+    /// pivot ends up, which is the top-left corner of the sprite by default.
     pub position: Vec2,
     /// The size of the sprite. It can be scaled without keeping track of the
     /// dimensions of the loaded texture.
     pub size: Vec2,
+    /// The point of the sprite placed at its position, and around which it
+    /// rotates and scales. Goes from (0.0, 0.0) at its top-left corner to
+    /// (1.0, 1.0) at its bottom-right corner.
+    pub pivot: Vec2,
+    /// The rotation around the pivot, in radians. Positive values rotate
+    /// clockwise.
+    pub rotation: f32,
+    /// A factor applied on top of the size, around the pivot.
+    pub scale: f32,
+    /// Whether the sprite is mirrored horizontally, in place.
+    pub flip_x: bool,
+    /// Whether the sprite is mirrored vertically, in place.
+    pub flip_y: bool,
+    /// The layer of the sprite when drawn through a `SpriteBatch`.
+    /// Higher layers are drawn on top of lower ones.
+    pub layer: i32,
+    /// The region of the texture to render, in pixels. The whole texture
+    /// is rendered when `None`.
+    pub source: Option<Rect>,
+    /// The color the texture is multiplied by. White leaves it untouched.
+    pub tint: Color,
+    /// How opaque the sprite is, from 0.0 (invisible) to 1.0. It is
+    /// combined with the alpha of the tint.
+    pub opacity: f32,
     /// The texture the sprite will be rendering.
-    texture: Box<Texture>,
+    texture: Handle<Texture>,
     /// The dimensions of the texture. This may be different than the size, as is
     /// the original size of the texture loaded, rather than the size of the sprite.
     dimensions: (u32, u32),
 }
 
 impl Sprite {
-    /// Creates a new sprite at a given position. `image_path` is the path
-    /// where the image we want to load is located.
+    /// Creates a new sprite at a given position, rendering the given texture.
+    /// Textures are loaded and shared through `Assets`.
     ///
     /// # Example
     /// ```rust
     /// use std::path::Path;
     /// use glam::Vec2;
     ///
-    /// let image_path = Path::new("sprite.png");
+    /// let texture = assets.load_texture(ctx, Path::new("sprite.png"));
     /// let position = Vec2::new(500.0, 500.0);
-    /// let sprite = Sprite::new(position, image_path);
+    /// let sprite = Sprite::new(position, texture);
     /// ```
-    pub fn new(ctx: &mut Context, position: Vec2, image_path: &Path) -> Self {
-        Self::with_params(
-            ctx,
-            position,
-            image_path,
-            TextureParams {
-                filter: FilterMode::Nearest,
-                ..Default::default()
-            },
-        )
-    }
-
-    /// Creates a sprite at a given position, with the texture wrap mode specified.
-    pub fn with_params(
-        ctx: &mut Context,
-        position: Vec2,
-        image_path: &Path,
-        texture_params: TextureParams,
-    ) -> Self {
+    pub fn new(position: Vec2, texture: Handle<Texture>) -> Self {
         info!("Creating new sprite at [{}, {}]", position.x, position.y);
-        let shader_params = shader::get_shader_params();
-        let texture = Box::new(Texture::with_params(
-            ctx,
-            image_path,
-            shader_params,
-            texture_params,
-        ));
         let (size_x, size_y) = texture.size();
         let size = Vec2::new(size_x as f32, size_y as f32);
 
         Self {
             position,
             size,
+            pivot: Vec2::ZERO,
+            rotation: 0.0,
+            scale: 1.0,
+            flip_x: false,
+            flip_y: false,
+            layer: 0,
+            source: None,
+            tint: Color::WHITE,
+            opacity: 1.0,
             texture,
             dimensions: (size_x, size_y),
         }
     }
 
+    /// Creates a new sprite at a given position, rendering only the `source`
+    /// region of the texture. The sprite takes the size of the region.
+    ///
+    /// # Example
+    /// ```rust
+    /// use glam::Vec2;
+    ///
+    /// // the second 16x16 frame of a sprite sheet
+    /// let source = Rect::new(16.0, 0.0, 16.0, 16.0);
+    /// let sprite = Sprite::from_region(Vec2::new(32.0, 32.0), texture, source);
+    /// ```
+    pub fn from_region(position: Vec2, texture: Handle<Texture>, source: Rect) -> Self {
+        let mut sprite = Self::new(position, texture);
+        sprite.size = source.size();
+        sprite.source = Some(source);
+
+        sprite
+    }
+
+    /// Creates a new sprite at a given position, loading the image at
+    /// `image_path` through `assets`.
+    /// Returns an error if the image can't be loaded.
+    pub fn load(
+        ctx: &mut Context,
+        assets: &mut Assets,
+        position: Vec2,
+        image_path: &Path,
+    ) -> Result<Self, EngineError> {
+        let texture = assets.load_texture(ctx, image_path)?;
+
+        Ok(Self::new(position, texture))
+    }
+
+    /// Gets the texture the sprite is rendering.
+    pub const fn texture(&self) -> &Handle<Texture> {
+        &self.texture
+    }
+
     /// Scales the sprite to a given width and height.
     pub fn scale_to(&mut self, x: f32, y: f32) {
         self.size.x = x;
         self.size.y = y;
     }
+
+    /// Gets the texture coordinates of the top-left and bottom-right corners
+    /// of the `source` region, or of the whole texture if there is none.
+    pub fn uv_rect(&self) -> (Vec2, Vec2) {
+        let dimensions = Vec2::new(self.dimensions.0 as f32, self.dimensions.1 as f32);
+
+        match self.source {
+            Some(source) => (source.position() / dimensions, source.max() / dimensions),
+            None => (Vec2::ZERO, Vec2::ONE),
+        }
+    }
+
+    /// Gets the color the texture is multiplied by, including the opacity.
+    pub fn color(&self) -> Vec4 {
+        let mut color = Vec4::from(self.tint);
+        color.w *= self.opacity.clamp(0.0, 1.0);
+
+        color
+    }
+
+    /// Gets the matrix placing the sprite, a square from (0.0, 0.0) to
+    /// (1.0, 1.0), into the world.
+    ///
+    /// # Example
+    /// ```rust
+    /// // a 16x16 sprite spinning around its center
+    /// sprite.pivot = Vec2::new(0.5, 0.5);
+    /// sprite.rotation = std::f32::consts::FRAC_PI_2;
+    /// let top_left = sprite.model_matrix().transform_point3(Vec3::ZERO);
+    /// ```
+    pub fn model_matrix(&self) -> Mat4 {
+        model_matrix(
+            self.position,
+            self.size,
+            self.pivot,
+            self.rotation,
+            self.scale,
+            BVec2::new(self.flip_x, self.flip_y),
+        )
+    }
+
+    /// Builds the quad used to draw the sprite through a `SpriteBatch`.
+    pub fn quad(&self) -> Quad {
+        // if the texture repeats, tile it over the sprite at its original size
+        let (uv_min, uv_max) = match self.texture.wrap_mode() {
+            TextureWrap::Repeat => (
+                Vec2::ZERO,
+                Vec2::new(
+                    self.size.x / self.dimensions.0 as f32,
+                    self.size.y / self.dimensions.1 as f32,
+                ),
+            ),
+            _ => self.uv_rect(),
+        };
+
+        Quad::from_matrix(
+            self.texture.clone(),
+            self.layer,
+            self.model_matrix(),
+            uv_min,
+            uv_max,
+        )
+        .with_color(self.color())
+    }
+}
+
+/// Gets the matrix placing a square from (0.0, 0.0) to (1.0, 1.0) into the
+/// world, as described by the fields of `Sprite`.
+fn model_matrix(
+    position: Vec2,
+    size: Vec2,
+    pivot: Vec2,
+    rotation: f32,
+    scale: f32,
+    flip: BVec2,
+) -> Mat4 {
+    // mirror around the middle of the sprite, so it stays in place
+    let flip = Vec3::new(
+        if flip.x { -1.0 } else { 1.0 },
+        if flip.y { -1.0 } else { 1.0 },
+        1.0,
+    );
+    let half = Vec3::new(0.5, 0.5, 0.0);
+    let flip_matrix =
+        Mat4::from_translation(half) * Mat4::from_scale(flip) * Mat4::from_translation(-half);
+
+    Mat4::from_translation(position.extend(0.0))
+        * Mat4::from_rotation_z(rotation)
+        * Mat4::from_scale(Vec3::new(scale, scale, 1.0))
+        * Mat4::from_translation((-pivot * size).extend(0.0))
+        * Mat4::from_scale(size.extend(1.0))
+        * flip_matrix
 }
 
 impl EventHandler for Sprite {
     fn update(&mut self, _ctx: &mut Context) {}
 
     fn draw(&mut self, ctx: &mut Context) {
-        // TODO: Move this code into mvp.rs
-        let (original_width, original_height) = (WINDOW_WIDTH, WINDOW_HEIGHT);
-
-        // we need to convert the given position & scale to virtual resolution
-        // so we want to see what is the proportion between the original size
-        // and the size we want to emulate
-        let virtual_proportion_x = (original_width / VIRTUAL_RESOLUTION_X) as f32;
-        let virtual_proportion_y = (original_height / VIRTUAL_RESOLUTION_Y) as f32;
-
-        let (current_window_width, current_window_height) = ctx.screen_size();
-
-        let (position_x, position_y): (f32, f32) = self.position.into();
-        let position_x = position_x * virtual_proportion_x;
-        let position_y = position_y * virtual_proportion_y;
-
-        // get the proportion of the original window size
-        // this way when resizing the window the sprite should be placed
-        // at the same position relative to the window as before
-        let (position_proportion_x, position_proportion_y): (f32, f32) = (
-            position_x / original_width as f32,
-            position_y / original_height as f32,
-        );
-        let translation = Vec3::new(
-            current_window_width * position_proportion_x,
-            current_window_height * position_proportion_y,
-            0.0,
-        );
-        let translation_matrix = Mat4::from_translation(translation);
-
-        let (scale_x, scale_y): (f32, f32) = self.size.into();
-        let scale_x = scale_x * virtual_proportion_x;
-        let scale_y = scale_y * virtual_proportion_y;
-
-        // same as position
-        let (scale_proportion_x, scale_proportion_y) = (
-            // we divide by 2 as the center is at the middle of the sprite,
-            // so it will actually scale both sides
-            (scale_x / 2.0) / original_width as f32,
-            (scale_y / 2.0) / original_height as f32,
-        );
-        let scale = Vec3::new(
-            current_window_width * scale_proportion_x,
-            current_window_height * scale_proportion_y,
-            1.0,
-        );
-        let scale_matrix = Mat4::from_scale(scale);
-
-        let ortho_matrix = Mat4::orthographic_rh_gl(
-            0.0,
-            current_window_width,
-            current_window_height,
-            0.0,
-            -1.0,
-            1.0,
-        );
+        self.draw_with_camera(ctx, &Camera2D::default());
+    }
+}
 
-        // generate the mvp matrix
-        let mvp = ortho_matrix * translation_matrix * scale_matrix;
+impl Spatial for Sprite {
+    fn set_transform(&mut self, transform: &Transform) {
+        self.position = transform.translation;
+        self.rotation = transform.rotation;
+        self.scale = transform.scale;
+    }
+}
 
-        // set the pos of the image to be in respect to the top left corner
-        // otherwise it will position the middle of the image in the specified coordinates
+impl Drawable for Sprite {
+    fn draw_with_camera(&mut self, ctx: &mut Context, camera: &Camera2D) {
+        // the quad goes from -1.0 to 1.0, while the model expects it from 0.0 to 1.0
+        let mvp = camera.view_projection()
+            * self.model_matrix()
+            * Mat4::from_scale(Vec3::new(0.5, 0.5, 1.0));
         let offset = (1.0, 1.0);
 
         // if the texture repeats, scale the texture coords
         // instead of scaling the image itself
-        let tex_scale = match self.texture.wrap_mode() {
+        let (tex_offset, tex_scale) = match self.texture.wrap_mode() {
             TextureWrap::Repeat => (
-                current_window_width / self.dimensions.0 as f32,
-                current_window_height / self.dimensions.1 as f32,
+                (0.0, 0.0),
+                (
+                    self.size.x / self.dimensions.0 as f32,
+                    self.size.y / self.dimensions.1 as f32,
+                ),
             ),
-            _ => (1.0, 1.0),
+            _ => {
+                let (uv_min, uv_max) = self.uv_rect();
+                (uv_min.into(), (uv_max - uv_min).into())
+            }
         };
 
         let pipeline = self.texture.pipeline();
@@ -163,13 +270,20 @@ impl EventHandler for Sprite {
         ctx.apply_uniforms(&shader::Uniforms {
             offset,
             mvp,
+            tex_offset,
             tex_scale,
+            tint: self.color(),
         });
 
         ctx.draw(0, 6, 1);
     }
 }
 
+/// Gets the shader used to render sprites.
+pub(crate) fn shader_params() -> ShaderParams {
+    shader::get_shader_params()
+}
+
 mod shader {
     use miniquad::*;
 
@@ -177,12 +291,14 @@ mod shader {
 
     pub const VERTEX: &str = r#"#version 100
     attribute vec2 pos;
-    attribute vec3 color0;
+    attribute vec4 color0;
     attribute vec2 tex0;
 
     uniform vec2 offset;
     uniform mat4 mvp;
+    uniform vec2 tex_offset;
     uniform vec2 tex_scale;
+    uniform vec4 tint;
 
     varying lowp vec4 color;
     varying lowp vec2 texcoord;
@@ -190,8 +306,8 @@ mod shader {
     void main() {
         vec4 pos = vec4(pos + offset, 0, 1);
         gl_Position = mvp * pos;
-        color = vec4(color0, 1.0);
-        texcoord = tex0 * tex_scale;
+        color = color0 * tint;
+        texcoord = tex_offset + tex0 * tex_scale;
     }"#;
 
     pub const FRAGMENT: &str = r#"#version 100
@@ -201,7 +317,7 @@ mod shader {
     uniform sampler2D tex;
 
     void main() {
-        gl_FragColor = texture2D(tex, texcoord);
+        gl_FragColor = texture2D(tex, texcoord) * color;
     }
     "#;
 
@@ -212,7 +328,9 @@ mod shader {
                 uniforms: vec![
                     UniformDesc::new("offset", UniformType::Float2),
                     UniformDesc::new("mvp", UniformType::Mat4),
+                    UniformDesc::new("tex_offset", UniformType::Float2),
                     UniformDesc::new("tex_scale", UniformType::Float2),
+                    UniformDesc::new("tint", UniformType::Float4),
                 ],
             },
         }
@@ -230,6 +348,113 @@ mod shader {
     pub struct Uniforms {
         pub offset: (f32, f32),
         pub mvp: glam::Mat4,
+        pub tex_offset: (f32, f32),
         pub tex_scale: (f32, f32),
+        pub tint: glam::Vec4,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    const SIZE: Vec2 = Vec2::new(16.0, 8.0);
+    const NO_FLIP: BVec2 = BVec2::new(false, false);
+
+    /// Places the unit square corner `corner` with `matrix`.
+    fn place(matrix: Mat4, corner: Vec2) -> Vec2 {
+        matrix.transform_point3(corner.extend(0.0)).truncate()
+    }
+
+    fn assert_places(matrix: Mat4, corner: Vec2, expected: Vec2) {
+        let placed = place(matrix, corner);
+        assert!(
+            placed.abs_diff_eq(expected, 1e-4),
+            "{corner} went to {placed}, not {expected}"
+        );
+    }
+
+    #[test]
+    fn translates_the_top_left_corner_to_the_position() {
+        let position = Vec2::new(10.0, 20.0);
+        let matrix = model_matrix(position, SIZE, Vec2::ZERO, 0.0, 1.0, NO_FLIP);
+
+        assert_places(matrix, Vec2::ZERO, position);
+        assert_places(matrix, Vec2::X, position + Vec2::new(16.0, 0.0));
+        assert_places(matrix, Vec2::ONE, position + SIZE);
+    }
+
+    #[test]
+    fn rotates_around_the_pivot() {
+        let position = Vec2::new(100.0, 100.0);
+        let pivot = Vec2::new(0.5, 0.5);
+        let matrix = model_matrix(position, SIZE, pivot, FRAC_PI_2, 1.0, NO_FLIP);
+
+        // the pivot stays put, while the rest turns clockwise around it
+        assert_places(matrix, pivot, position);
+        assert_places(matrix, Vec2::ZERO, position + Vec2::new(4.0, -8.0));
+        assert_places(matrix, Vec2::ONE, position + Vec2::new(-4.0, 8.0));
+    }
+
+    #[test]
+    fn scales_both_sides_by_the_same_factor() {
+        let position = Vec2::new(5.0, 5.0);
+        let matrix = model_matrix(position, SIZE, Vec2::ZERO, 0.0, 2.0, NO_FLIP);
+
+        assert_places(matrix, Vec2::ZERO, position);
+        assert_places(matrix, Vec2::ONE, position + Vec2::new(32.0, 16.0));
+    }
+
+    #[test]
+    fn scales_around_the_pivot() {
+        let position = Vec2::new(50.0, 50.0);
+        let matrix = model_matrix(position, SIZE, Vec2::ONE, 0.0, 0.5, NO_FLIP);
+
+        assert_places(matrix, Vec2::ONE, position);
+        assert_places(matrix, Vec2::ZERO, position - Vec2::new(8.0, 4.0));
+    }
+
+    #[test]
+    fn flips_in_place() {
+        let position = Vec2::new(10.0, 20.0);
+
+        let flip_x = model_matrix(
+            position,
+            SIZE,
+            Vec2::ZERO,
+            0.0,
+            1.0,
+            BVec2::new(true, false),
+        );
+        assert_places(flip_x, Vec2::ZERO, position + Vec2::new(16.0, 0.0));
+        assert_places(flip_x, Vec2::X, position);
+        assert_places(flip_x, Vec2::Y, position + SIZE);
+
+        let flip_y = model_matrix(
+            position,
+            SIZE,
+            Vec2::ZERO,
+            0.0,
+            1.0,
+            BVec2::new(false, true),
+        );
+        assert_places(flip_y, Vec2::ZERO, position + Vec2::new(0.0, 8.0));
+        assert_places(flip_y, Vec2::Y, position);
+        assert_places(flip_y, Vec2::X, position + SIZE);
+    }
+
+    #[test]
+    fn flips_then_pivots_then_scales_then_rotates_then_translates() {
+        let position = Vec2::new(50.0, 50.0);
+        let matrix = model_matrix(position, SIZE, Vec2::ONE, PI, 2.0, BVec2::new(true, false));
+
+        // (0, 0) flips to (1, 0), which is (16, 0) on the sprite, (0, -8)
+        // from the pivot, (0, -16) scaled, and (0, 16) upside down
+        assert_places(matrix, Vec2::ZERO, position + Vec2::new(0.0, 16.0));
+        // (1, 1) flips to (0, 1), which is (0, 8), (-16, 0) from the pivot,
+        // (-32, 0) scaled, and (32, 0) upside down
+        assert_places(matrix, Vec2::ONE, position + Vec2::new(32.0, 0.0));
+        assert_places(matrix, Vec2::new(0.0, 1.0), position);
     }
 }
@@ -0,0 +1,587 @@
+use super::assets::Handle;
+use super::camera::Camera2D;
+use super::graphics_handler::{BlendMode, GraphicsHandler, ShaderParams};
+use super::sprite::Sprite;
+use super::texture::Texture;
+use super::vertex::Vertex;
+use glam::{Mat4, Vec2, Vec3, Vec4};
+use miniquad::*;
+
+/// The amount of quads a `SpriteBatch` uploads at once by default.
+pub const DEFAULT_BATCH_CAPACITY: usize = 4096;
+
+/// The maximum amount of quads a single upload can hold. Vertices are
+/// addressed with `u16` indices, so there can't be more than 65536 of them.
+pub const MAX_BATCH_CAPACITY: usize = (u16::MAX as usize + 1) / VERTICES_PER_QUAD;
+
+const VERTICES_PER_QUAD: usize = 4;
+const INDICES_PER_QUAD: usize = 6;
+
+/// The order in which queued quads are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortMode {
+    /// Sorts by layer first and by texture second. Quads sharing a layer may
+    /// be reordered between them in order to merge draw calls.
+    LayerThenTexture,
+    /// Keeps the order in which quads were queued. Only consecutive quads
+    /// sharing a texture are merged into the same draw call.
+    Submission,
+}
+
+/// What quads are textured with. Quads sharing a texture are merged into
+/// the same draw call.
+pub trait BatchTexture: Clone {
+    /// Gets the GPU texture to bind.
+    fn raw(&self) -> miniquad::Texture;
+}
+
+impl BatchTexture for miniquad::Texture {
+    fn raw(&self) -> miniquad::Texture {
+        *self
+    }
+}
+
+/// Quads hold on to the texture until drawn, so it can't be unloaded while
+/// they're queued.
+impl BatchTexture for Handle<Texture> {
+    fn raw(&self) -> miniquad::Texture {
+        Texture::raw(self)
+    }
+}
+
+/// A textured rectangle waiting to be drawn.
+#[derive(Debug, Clone)]
+pub struct Quad<T = Handle<Texture>> {
+    /// The texture sampled by the quad.
+    pub texture: T,
+    /// The layer of the quad. Higher layers are drawn on top of lower ones.
+    pub layer: i32,
+    /// The corners of the quad, in virtual resolution pixels, clockwise
+    /// starting at the top-left one.
+    pub vertices: [Vertex; 4],
+}
+
+impl<T: BatchTexture> Quad<T> {
+    /// Creates a quad covering `size` pixels from its top-left `position`,
+    /// sampling the texture from `uv_min` to `uv_max`.
+    pub fn new(
+        texture: T,
+        layer: i32,
+        position: Vec2,
+        size: Vec2,
+        uv_min: Vec2,
+        uv_max: Vec2,
+    ) -> Self {
+        let (x, y) = (position.x, position.y);
+        let (w, h) = (size.x, size.y);
+        let vertices = [
+            Vertex::with_tex(x, y, uv_min.x, uv_min.y),
+            Vertex::with_tex(x + w, y, uv_max.x, uv_min.y),
+            Vertex::with_tex(x + w, y + h, uv_max.x, uv_max.y),
+            Vertex::with_tex(x, y + h, uv_min.x, uv_max.y),
+        ];
+
+        Self {
+            texture,
+            layer,
+            vertices,
+        }
+    }
+
+    /// Creates a quad out of a square from (0.0, 0.0) to (1.0, 1.0),
+    /// placed into the world by `model`, sampling the texture from `uv_min`
+    /// to `uv_max`.
+    pub fn from_matrix(texture: T, layer: i32, model: Mat4, uv_min: Vec2, uv_max: Vec2) -> Self {
+        let corner = |x: f32, y: f32| model.transform_point3(Vec3::new(x, y, 0.0));
+        let (top_left, top_right) = (corner(0.0, 0.0), corner(1.0, 0.0));
+        let (bottom_right, bottom_left) = (corner(1.0, 1.0), corner(0.0, 1.0));
+        let vertices = [
+            Vertex::with_tex(top_left.x, top_left.y, uv_min.x, uv_min.y),
+            Vertex::with_tex(top_right.x, top_right.y, uv_max.x, uv_min.y),
+            Vertex::with_tex(bottom_right.x, bottom_right.y, uv_max.x, uv_max.y),
+            Vertex::with_tex(bottom_left.x, bottom_left.y, uv_min.x, uv_max.y),
+        ];
+
+        Self {
+            texture,
+            layer,
+            vertices,
+        }
+    }
+
+    /// Multiplies the texture by `color`, as `rgba` values from 0.0 to 1.0.
+    pub fn with_color(mut self, color: Vec4) -> Self {
+        for vertex in &mut self.vertices {
+            vertex.color = color;
+        }
+
+        self
+    }
+}
+
+/// A single draw call. Renders `index_count` indices starting at
+/// `first_index`, with `texture` bound.
+#[derive(Debug, Clone)]
+pub struct DrawCall<T = Handle<Texture>> {
+    /// The texture bound during the draw call.
+    pub texture: T,
+    /// The first index to render.
+    pub first_index: usize,
+    /// The amount of indices to render.
+    pub index_count: usize,
+}
+
+/// A group of quads small enough to be uploaded with a single buffer update.
+#[derive(Debug)]
+pub struct BatchChunk<T = Handle<Texture>> {
+    /// The vertices to upload.
+    pub vertices: Vec<Vertex>,
+    /// The indices to upload, relative to the start of `vertices`.
+    pub indices: Vec<u16>,
+    /// The draw calls needed to render every quad in the chunk.
+    pub draw_calls: Vec<DrawCall<T>>,
+}
+
+impl<T: BatchTexture> BatchChunk<T> {
+    /// Appends a quad, merging it into the last draw call if they share a texture.
+    fn push(&mut self, quad: &Quad<T>) {
+        let base = self.vertices.len() as u16;
+        let first_index = self.indices.len();
+
+        self.vertices.extend_from_slice(&quad.vertices);
+        self.indices
+            .extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+
+        match self.draw_calls.last_mut() {
+            Some(draw_call) if draw_call.texture.raw() == quad.texture.raw() => {
+                draw_call.index_count += INDICES_PER_QUAD;
+            }
+            _ => self.draw_calls.push(DrawCall {
+                texture: quad.texture.clone(),
+                first_index,
+                index_count: INDICES_PER_QUAD,
+            }),
+        }
+    }
+}
+
+/// The CPU side of a sprite batch. Collects quads during the frame and turns
+/// them into vertex data and as few draw calls as possible.
+/// It never touches the GPU.
+pub struct Batcher<T = Handle<Texture>> {
+    quads: Vec<Quad<T>>,
+    /// The maximum amount of quads per chunk.
+    capacity: usize,
+    sort_mode: SortMode,
+}
+
+impl<T: BatchTexture> Batcher<T> {
+    /// Creates a batcher that splits its quads in chunks of at most `capacity` quads.
+    pub fn new(capacity: usize, sort_mode: SortMode) -> Self {
+        let capacity = capacity.clamp(1, MAX_BATCH_CAPACITY);
+
+        Self {
+            quads: Vec::with_capacity(capacity),
+            capacity,
+            sort_mode,
+        }
+    }
+
+    /// Queues a quad to be drawn.
+    pub fn push(&mut self, quad: Quad<T>) {
+        self.quads.push(quad);
+    }
+
+    /// Returns the amount of queued quads.
+    pub fn len(&self) -> usize {
+        self.quads.len()
+    }
+
+    /// Whether there are no queued quads.
+    pub fn is_empty(&self) -> bool {
+        self.quads.is_empty()
+    }
+
+    /// Gets the maximum amount of quads per chunk.
+    pub const fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Sorts the queued quads and builds the chunks needed to render them,
+    /// leaving the batcher empty.
+    ///
+    /// # Example
+    /// ```rust
+    /// let mut batcher = Batcher::new(DEFAULT_BATCH_CAPACITY, SortMode::LayerThenTexture);
+    /// batcher.push(Quad::new(texture, 0, position, size, Vec2::ZERO, Vec2::ONE));
+    ///
+    /// for chunk in batcher.build() {
+    ///     println!("{} draw calls", chunk.draw_calls.len());
+    /// }
+    /// ```
+    pub fn build(&mut self) -> Vec<BatchChunk<T>> {
+        if self.sort_mode == SortMode::LayerThenTexture {
+            // a stable sort, so quads sharing layer and texture keep their order
+            self.quads
+                .sort_by_key(|quad| (quad.layer, quad.texture.raw().gl_internal_id()));
+        }
+
+        let chunks = self
+            .quads
+            .chunks(self.capacity)
+            .map(|quads| {
+                let mut chunk = BatchChunk {
+                    vertices: Vec::with_capacity(quads.len() * VERTICES_PER_QUAD),
+                    indices: Vec::with_capacity(quads.len() * INDICES_PER_QUAD),
+                    draw_calls: Vec::new(),
+                };
+                quads.iter().for_each(|quad| chunk.push(quad));
+
+                chunk
+            })
+            .collect();
+
+        self.quads.clear();
+
+        chunks
+    }
+}
+
+/// Renders many sprites sharing a single pipeline.
+/// Sprites are queued during the frame and drawn with `flush`, filling a
+/// dynamic vertex buffer and issuing one draw call per run of quads sharing a texture.
+pub struct SpriteBatch {
+    batcher: Batcher,
+    #[doc(hidden)]
+    graphics_handler: GraphicsHandler,
+}
+
+impl SpriteBatch {
+    /// Creates a sprite batch with the default shader and capacity.
+    pub fn new(ctx: &mut Context) -> Self {
+        Self::with_params(
+            ctx,
+            shader::get_shader_params(),
+            DEFAULT_BATCH_CAPACITY,
+            SortMode::LayerThenTexture,
+        )
+    }
+
+    /// Creates a sprite batch using `shader_params` for every quad, able to
+    /// upload `capacity` quads at once.
+    /// The shader must take the same attributes and uniforms as the default one.
+    pub fn with_params(
+        ctx: &mut Context,
+        shader_params: ShaderParams,
+        capacity: usize,
+        sort_mode: SortMode,
+    ) -> Self {
+        let batcher = Batcher::new(capacity, sort_mode);
+        let capacity = batcher.capacity();
+        let graphics_handler = GraphicsHandler::streaming(
+            ctx,
+            capacity * VERTICES_PER_QUAD,
+            capacity * INDICES_PER_QUAD,
+            shader_params,
+        );
+
+        Self {
+            batcher,
+            graphics_handler,
+        }
+    }
+
+    /// Changes how the quads of the batch blend with what is below them.
+    pub fn set_blend_mode(&self, ctx: &mut Context, blend_mode: BlendMode) {
+        self.graphics_handler.set_blend_mode(ctx, blend_mode);
+    }
+
+    /// Queues a sprite to be drawn on the next flush.
+    pub fn draw_sprite(&mut self, sprite: &Sprite) {
+        self.batcher.push(sprite.quad());
+    }
+
+    /// Queues a quad to be drawn on the next flush.
+    pub fn draw_quad(&mut self, quad: Quad) {
+        self.batcher.push(quad);
+    }
+
+    /// Draws every queued quad, as seen by a camera showing the whole
+    /// virtual resolution, and empties the queue.
+    /// Returns the amount of draw calls issued.
+    pub fn flush(&mut self, ctx: &mut Context) -> usize {
+        self.flush_with_camera(ctx, &Camera2D::default())
+    }
+
+    /// Draws every queued quad as seen by `camera`, and empties the queue.
+    /// Returns the amount of draw calls issued.
+    pub fn flush_with_camera(&mut self, ctx: &mut Context, camera: &Camera2D) -> usize {
+        if self.batcher.is_empty() {
+            return 0;
+        }
+
+        let mvp = camera.view_projection();
+
+        let mut draw_calls = 0;
+        ctx.apply_pipeline(self.graphics_handler.pipeline());
+
+        for chunk in self.batcher.build() {
+            let bindings = self.graphics_handler.bindings_mut();
+            bindings.vertex_buffers[0].update(ctx, &chunk.vertices);
+            bindings.index_buffer.update(ctx, &chunk.indices);
+
+            for draw_call in &chunk.draw_calls {
+                self.graphics_handler.bindings_mut().images[0] = draw_call.texture.raw();
+                ctx.apply_bindings(self.graphics_handler.bindings());
+                ctx.apply_uniforms(&shader::Uniforms { mvp });
+                ctx.draw(
+                    draw_call.first_index as i32,
+                    draw_call.index_count as i32,
+                    1,
+                );
+            }
+
+            draw_calls += chunk.draw_calls.len();
+        }
+
+        draw_calls
+    }
+}
+
+mod shader {
+    use miniquad::*;
+
+    use crate::core::graphics_handler::ShaderParams;
+
+    pub const VERTEX: &str = r#"#version 100
+    attribute vec2 pos;
+    attribute vec4 color0;
+    attribute vec2 tex0;
+
+    uniform mat4 mvp;
+
+    varying lowp vec4 color;
+    varying lowp vec2 texcoord;
+
+    void main() {
+        gl_Position = mvp * vec4(pos, 0, 1);
+        color = color0;
+        texcoord = tex0;
+    }"#;
+
+    pub const FRAGMENT: &str = r#"#version 100
+    varying lowp vec2 texcoord;
+    varying lowp vec4 color;
+
+    uniform sampler2D tex;
+
+    void main() {
+        gl_FragColor = texture2D(tex, texcoord) * color;
+    }
+    "#;
+
+    pub fn meta() -> ShaderMeta {
+        ShaderMeta {
+            images: vec!["tex".to_string()],
+            uniforms: UniformBlockLayout {
+                uniforms: vec![UniformDesc::new("mvp", UniformType::Mat4)],
+            },
+        }
+    }
+
+    pub fn get_shader_params() -> ShaderParams {
+        ShaderParams {
+            vertex_shader: VERTEX,
+            fragment_shader: FRAGMENT,
+            meta: meta(),
+        }
+    }
+
+    #[repr(C)]
+    pub struct Uniforms {
+        pub mvp: glam::Mat4,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a texture that only exists as an id, as batching never
+    /// touches the GPU.
+    fn texture(id: u32) -> miniquad::Texture {
+        unsafe { miniquad::Texture::from_raw_id(id) }
+    }
+
+    /// A quad textured with a bare GPU texture.
+    type RawQuad = Quad<miniquad::Texture>;
+
+    fn quad(texture_id: u32, layer: i32) -> RawQuad {
+        Quad::new(
+            texture(texture_id),
+            layer,
+            Vec2::ZERO,
+            Vec2::ONE,
+            Vec2::ZERO,
+            Vec2::ONE,
+        )
+    }
+
+    /// Gets the texture id and index count of every draw call.
+    fn draw_calls(chunk: &BatchChunk<miniquad::Texture>) -> Vec<(u32, usize)> {
+        chunk
+            .draw_calls
+            .iter()
+            .map(|draw_call| (draw_call.texture.gl_internal_id(), draw_call.index_count))
+            .collect()
+    }
+
+    #[test]
+    fn sorts_by_layer_then_texture() {
+        let mut batcher = Batcher::new(DEFAULT_BATCH_CAPACITY, SortMode::LayerThenTexture);
+        for (texture_id, layer) in [(2, 1), (2, 0), (1, 1), (1, 0), (2, 0)] {
+            batcher.push(quad(texture_id, layer));
+        }
+
+        let chunks = batcher.build();
+
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(draw_calls(&chunks[0]), [(1, 6), (2, 12), (1, 6), (2, 6)]);
+    }
+
+    #[test]
+    fn keeps_the_submission_order() {
+        let mut batcher = Batcher::new(DEFAULT_BATCH_CAPACITY, SortMode::Submission);
+        for (texture_id, layer) in [(2, 1), (2, 0), (1, 1), (1, 0)] {
+            batcher.push(quad(texture_id, layer));
+        }
+
+        let chunks = batcher.build();
+
+        assert_eq!(draw_calls(&chunks[0]), [(2, 12), (1, 12)]);
+    }
+
+    #[test]
+    fn splits_draw_calls_when_the_texture_changes() {
+        let mut batcher = Batcher::new(DEFAULT_BATCH_CAPACITY, SortMode::Submission);
+        for texture_id in [1, 1, 2, 1] {
+            batcher.push(quad(texture_id, 0));
+        }
+
+        let chunks = batcher.build();
+        let first_indices: Vec<usize> = chunks[0]
+            .draw_calls
+            .iter()
+            .map(|draw_call| draw_call.first_index)
+            .collect();
+
+        assert_eq!(draw_calls(&chunks[0]), [(1, 12), (2, 6), (1, 6)]);
+        assert_eq!(first_indices, [0, 12, 18]);
+    }
+
+    #[test]
+    fn builds_the_vertices_and_indices_of_a_quad() {
+        let mut batcher = Batcher::new(DEFAULT_BATCH_CAPACITY, SortMode::LayerThenTexture);
+        batcher.push(
+            Quad::new(
+                texture(1),
+                0,
+                Vec2::new(10.0, 20.0),
+                Vec2::new(4.0, 8.0),
+                Vec2::new(0.25, 0.5),
+                Vec2::new(0.75, 1.0),
+            )
+            .with_color(Vec4::new(1.0, 0.5, 0.0, 1.0)),
+        );
+
+        let chunks = batcher.build();
+        let chunk = &chunks[0];
+        let positions: Vec<Vec2> = chunk.vertices.iter().map(|vertex| vertex.pos).collect();
+        let uvs: Vec<Vec2> = chunk.vertices.iter().map(|vertex| vertex.tex).collect();
+
+        assert_eq!(
+            positions,
+            [
+                Vec2::new(10.0, 20.0),
+                Vec2::new(14.0, 20.0),
+                Vec2::new(14.0, 28.0),
+                Vec2::new(10.0, 28.0),
+            ]
+        );
+        assert_eq!(
+            uvs,
+            [
+                Vec2::new(0.25, 0.5),
+                Vec2::new(0.75, 0.5),
+                Vec2::new(0.75, 1.0),
+                Vec2::new(0.25, 1.0),
+            ]
+        );
+        assert!(chunk
+            .vertices
+            .iter()
+            .all(|vertex| vertex.color == Vec4::new(1.0, 0.5, 0.0, 1.0)));
+        assert_eq!(chunk.indices, [0, 1, 2, 0, 2, 3]);
+    }
+
+    #[test]
+    fn places_quads_by_their_matrix() {
+        let model = Mat4::from_translation(Vec3::new(5.0, 6.0, 0.0))
+            * Mat4::from_scale(Vec3::new(2.0, 3.0, 1.0));
+        let quad: RawQuad = Quad::from_matrix(texture(1), 0, model, Vec2::ZERO, Vec2::ONE);
+        let positions: Vec<Vec2> = quad.vertices.iter().map(|vertex| vertex.pos).collect();
+
+        assert_eq!(
+            positions,
+            [
+                Vec2::new(5.0, 6.0),
+                Vec2::new(7.0, 6.0),
+                Vec2::new(7.0, 9.0),
+                Vec2::new(5.0, 9.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_chunks_at_the_capacity() {
+        let mut batcher = Batcher::new(2, SortMode::Submission);
+        for _ in 0..5 {
+            batcher.push(quad(1, 0));
+        }
+
+        let chunks = batcher.build();
+        let quads_per_chunk: Vec<usize> = chunks
+            .iter()
+            .map(|chunk| chunk.vertices.len() / VERTICES_PER_QUAD)
+            .collect();
+
+        assert_eq!(quads_per_chunk, [2, 2, 1]);
+        // indices restart in every chunk, as each one is uploaded on its own
+        assert!(chunks
+            .iter()
+            .all(|chunk| chunk.indices[..6] == [0, 1, 2, 0, 2, 3]));
+        assert!(batcher.is_empty());
+    }
+
+    #[test]
+    fn fills_chunks_up_to_the_largest_index() {
+        let mut batcher = Batcher::new(usize::MAX, SortMode::Submission);
+        for _ in 0..=MAX_BATCH_CAPACITY {
+            batcher.push(quad(1, 0));
+        }
+
+        let chunks = batcher.build();
+
+        assert_eq!(batcher.capacity(), MAX_BATCH_CAPACITY);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].indices.iter().max(), Some(&u16::MAX));
+        assert_eq!(chunks[1].vertices.len(), VERTICES_PER_QUAD);
+    }
+
+    #[test]
+    fn clamps_the_capacity_to_at_least_a_quad() {
+        let batcher: Batcher<miniquad::Texture> = Batcher::new(0, SortMode::Submission);
+
+        assert_eq!(batcher.capacity(), 1);
+    }
+}
@@ -1,9 +1,16 @@
-use super::graphics_handler::{GraphicsHandler, ShaderParams};
+use super::color::Color;
+use super::error::EngineError;
+use super::graphics_handler::{BlendMode, GraphicsHandler, ShaderParams};
 use image::io::Reader as ImageReader;
 use image::GenericImageView;
 use miniquad::*;
 use std::path::Path;
 
+/// The amount of pixels on each side of a checkerboard cell in the missing texture.
+const MISSING_TEXTURE_CELL_SIZE: u32 = 8;
+/// The amount of cells on each side of the missing texture.
+const MISSING_TEXTURE_CELLS: u32 = 2;
+
 /// A GPU allocated texture.
 pub struct Texture {
     /// The size of the image loaded.
@@ -37,19 +44,58 @@ impl Texture {
         )
     }
 
+    /// Same as `from_path`, but returns an error if the image can't be
+    /// opened or decoded, or if the shaders fail to compile.
+    pub fn try_from_path(
+        ctx: &mut Context,
+        path: &Path,
+        shader_params: ShaderParams,
+    ) -> Result<Self, EngineError> {
+        Self::try_with_params(
+            ctx,
+            path,
+            shader_params,
+            TextureParams {
+                filter: FilterMode::Nearest,
+                ..Default::default()
+            },
+        )
+    }
+
     /// Loads a texture to the GPU with the texture wrap mode specified.
+    ///
+    /// # Panics
+    /// Panics if the image can't be loaded. See `try_with_params` for a fallible version.
     pub fn with_params(
         ctx: &mut Context,
         path: &Path,
         shader_params: ShaderParams,
         texture_params: TextureParams,
     ) -> Self {
+        Self::try_with_params(ctx, path, shader_params, texture_params)
+            .unwrap_or_else(|error| panic!("Cannot load texture: {}", error))
+    }
+
+    /// Same as `with_params`, but returns an error if the image can't be
+    /// opened or decoded, or if the shaders fail to compile.
+    pub fn try_with_params(
+        ctx: &mut Context,
+        path: &Path,
+        shader_params: ShaderParams,
+        texture_params: TextureParams,
+    ) -> Result<Self, EngineError> {
         // open the image and get its dimensions and bytes
         info!("Trying to open an image with path {:?}", path);
         let img = ImageReader::open(path)
-            .expect("Cannot open file.")
+            .map_err(|source| EngineError::Io {
+                path: path.to_path_buf(),
+                source,
+            })?
             .decode()
-            .unwrap();
+            .map_err(|source| EngineError::Decode {
+                path: path.to_path_buf(),
+                source,
+            })?;
 
         let img_dimensions = img.dimensions();
         let img_bytes = img.as_bytes();
@@ -60,19 +106,58 @@ impl Texture {
             img_dimensions
         );
 
-        let graphics_handler = GraphicsHandler::from_texture_with_params(
+        let graphics_handler = GraphicsHandler::try_from_texture_with_params(
             ctx,
             img_dimensions,
             img_bytes,
             shader_params,
             texture_params,
-        );
+        )?;
 
-        Self {
+        Ok(Self {
             graphics_handler,
             size: img_dimensions,
             wrap_mode: texture_params.wrap,
-        }
+        })
+    }
+
+    /// Creates a magenta and black checkerboard, meant to stand in for
+    /// textures that failed to load during development.
+    pub fn missing(ctx: &mut Context, shader_params: ShaderParams) -> Result<Self, EngineError> {
+        let side = MISSING_TEXTURE_CELL_SIZE * MISSING_TEXTURE_CELLS;
+        let bytes: Vec<u8> = (0..side * side)
+            .flat_map(|i| {
+                let (cell_x, cell_y) = (
+                    (i % side) / MISSING_TEXTURE_CELL_SIZE,
+                    (i / side) / MISSING_TEXTURE_CELL_SIZE,
+                );
+                let color = match (cell_x + cell_y) % 2 {
+                    0 => Color::MAGENTA,
+                    _ => Color::BLACK,
+                };
+
+                color.to_rgba8()
+            })
+            .collect();
+
+        let texture_params = TextureParams {
+            filter: FilterMode::Nearest,
+            wrap: TextureWrap::Repeat,
+            ..Default::default()
+        };
+        let graphics_handler = GraphicsHandler::try_from_texture_with_params(
+            ctx,
+            (side, side),
+            &bytes,
+            shader_params,
+            texture_params,
+        )?;
+
+        Ok(Self {
+            graphics_handler,
+            size: (side, side),
+            wrap_mode: texture_params.wrap,
+        })
     }
 
     /// Returns the pipeline used to render to the screen.
@@ -85,6 +170,18 @@ impl Texture {
         self.graphics_handler.bindings()
     }
 
+    /// Changes how every sprite drawing the texture on its own blends with
+    /// what is below it. Sprites drawn through a `SpriteBatch` use the blend
+    /// mode of the batch instead.
+    pub fn set_blend_mode(&self, ctx: &mut Context, blend_mode: BlendMode) {
+        self.graphics_handler.set_blend_mode(ctx, blend_mode);
+    }
+
+    /// Returns the underlying GPU texture.
+    pub fn raw(&self) -> miniquad::Texture {
+        self.graphics_handler.bindings().images[0]
+    }
+
     /// Gets the size of the texture.
     pub const fn size(&self) -> (u32, u32) {
         self.size
@@ -95,3 +192,10 @@ impl Texture {
         self.wrap_mode
     }
 }
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        info!("Freeing a texture with dimensions {:?}", self.size);
+        self.graphics_handler.delete();
+    }
+}
@@ -0,0 +1,345 @@
+use std::cell::Cell;
+
+/// The fixed timestep used by default, 60 steps per second.
+pub const DEFAULT_FIXED_STEP: f32 = 1.0 / 60.0;
+
+/// The maximum amount of fixed steps run in a single frame by default.
+pub const DEFAULT_MAX_FIXED_STEPS: u32 = 8;
+
+/// The longest a frame can last, in seconds. Longer frames, such as the ones
+/// after the window was dragged around, are shortened to this.
+pub const DEFAULT_MAX_DELTA: f32 = 0.25;
+
+/// How much of a fixed step can be missing from the accumulator for the
+/// step to still run, so rounding errors don't delay steps by a frame.
+const FIXED_STEP_TOLERANCE: f64 = 1e-4;
+
+/// A source of time, in seconds.
+pub trait Clock {
+    /// Gets the current time, in seconds.
+    fn now(&self) -> f64;
+}
+
+/// The clock of the system.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> f64 {
+        miniquad::date::now()
+    }
+}
+
+/// A clock that only moves when told to, making the timing deterministic.
+///
+/// # Example
+/// ```rust
+/// let clock = FakeClock::new();
+/// let mut time = Time::new(1.0 / 60.0);
+///
+/// time.tick(clock.now());
+/// clock.advance(0.05);
+/// time.tick(clock.now());
+///
+/// assert_eq!(time.fixed_steps(), 3);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FakeClock {
+    now: Cell<f64>,
+}
+
+impl FakeClock {
+    /// Creates a clock starting at 0.0 seconds.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Moves the clock `seconds` forward.
+    pub fn advance(&self, seconds: f64) {
+        self.now.set(self.now.get() + seconds);
+    }
+
+    /// Sets the time of the clock.
+    pub fn set(&self, now: f64) {
+        self.now.set(now);
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> f64 {
+        self.now.get()
+    }
+}
+
+/// Keeps track of the time between frames, and of the fixed steps that
+/// should run in each one.
+///
+/// Frames last however long the monitor and the game take, while fixed
+/// steps always last `fixed_delta` seconds. Every frame, its duration is
+/// added to an accumulator, and as many fixed steps as fit in it are run.
+/// What is left over is the interpolation `alpha`, used to draw objects in
+/// between their last two fixed steps.
+#[derive(Debug, Clone)]
+pub struct Time {
+    /// The seconds the last frame lasted, scaled by `time_scale`.
+    delta: f32,
+    /// The seconds the last frame lasted.
+    unscaled_delta: f32,
+    /// The scaled seconds elapsed since the first frame.
+    elapsed: f64,
+    /// The amount of frames since the first one.
+    frame_count: u64,
+    /// When the last frame started, according to the clock.
+    last_tick: Option<f64>,
+    /// The seconds each fixed step lasts.
+    fixed_delta: f32,
+    /// The scaled seconds not consumed by fixed steps yet.
+    accumulator: f64,
+    /// How fast time goes by. 1.0 is real time, 0.5 is slow motion, and 0.0
+    /// pauses the game.
+    pub time_scale: f32,
+    /// The longest a frame can last, in seconds.
+    pub max_delta: f32,
+    /// The maximum amount of fixed steps run in a single frame. When the
+    /// game can't keep up, the remaining steps are dropped, slowing the game
+    /// down instead of freezing it.
+    pub max_fixed_steps: u32,
+}
+
+impl Time {
+    /// Creates a timer running fixed steps of `fixed_delta` seconds.
+    pub fn new(fixed_delta: f32) -> Self {
+        Self {
+            fixed_delta: fixed_delta.max(f32::EPSILON),
+            ..Default::default()
+        }
+    }
+
+    /// Starts a new frame at `now` seconds, usually `Clock::now()`.
+    /// The first frame lasts no time at all.
+    pub fn tick(&mut self, now: f64) {
+        let unscaled_delta = match self.last_tick {
+            Some(last_tick) => ((now - last_tick) as f32).clamp(0.0, self.max_delta),
+            None => 0.0,
+        };
+        self.last_tick = Some(now);
+
+        self.unscaled_delta = unscaled_delta;
+        self.delta = unscaled_delta * self.time_scale.max(0.0);
+        self.elapsed += self.delta as f64;
+        self.frame_count += 1;
+        self.accumulator += self.delta as f64;
+    }
+
+    /// Gets the amount of fixed steps to run this frame, consuming them
+    /// from the accumulator. Should be called once per frame, after `tick`.
+    pub fn fixed_steps(&mut self) -> u32 {
+        let fixed_delta = self.fixed_delta as f64;
+        let available = (self.accumulator / fixed_delta + FIXED_STEP_TOLERANCE).floor() as u32;
+        let steps = available.min(self.max_fixed_steps);
+        self.accumulator = (self.accumulator - steps as f64 * fixed_delta).max(0.0);
+
+        if available > steps {
+            warn!("Dropping {} fixed steps", available - steps);
+            self.accumulator %= fixed_delta;
+        }
+
+        steps
+    }
+
+    /// Gets the seconds the last frame lasted, scaled by `time_scale`.
+    pub const fn delta(&self) -> f32 {
+        self.delta
+    }
+
+    /// Gets the seconds the last frame lasted, ignoring `time_scale`.
+    /// Useful for menus and transitions that keep going while the game is
+    /// paused.
+    pub const fn unscaled_delta(&self) -> f32 {
+        self.unscaled_delta
+    }
+
+    /// Gets the seconds each fixed step lasts.
+    pub const fn fixed_delta(&self) -> f32 {
+        self.fixed_delta
+    }
+
+    /// Gets the scaled seconds elapsed since the first frame.
+    pub const fn elapsed(&self) -> f64 {
+        self.elapsed
+    }
+
+    /// Gets the amount of frames since the first one, this one included.
+    pub const fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Gets how far the game is between the last fixed step and the next
+    /// one, from 0.0 to 1.0. Objects drawn at
+    /// `previous.lerp(current, alpha)` move smoothly at any frame rate.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.fixed_delta as f64).clamp(0.0, 1.0) as f32
+    }
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        let delta = 0.0;
+        let unscaled_delta = 0.0;
+        let elapsed = 0.0;
+        let frame_count = 0;
+        let last_tick = None;
+        let fixed_delta = DEFAULT_FIXED_STEP;
+        let accumulator = 0.0;
+        let time_scale = 1.0;
+        let max_delta = DEFAULT_MAX_DELTA;
+        let max_fixed_steps = DEFAULT_MAX_FIXED_STEPS;
+
+        Self {
+            delta,
+            unscaled_delta,
+            elapsed,
+            frame_count,
+            last_tick,
+            fixed_delta,
+            accumulator,
+            time_scale,
+            max_delta,
+            max_fixed_steps,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: f32 = 1.0 / 16.0;
+
+    /// Starts a timer on its first frame, at the time of `clock`.
+    fn start(clock: &FakeClock) -> Time {
+        let mut time = Time::new(STEP);
+        time.tick(clock.now());
+        assert_eq!(time.fixed_steps(), 0);
+
+        time
+    }
+
+    /// Runs a frame lasting `seconds`, getting its fixed steps.
+    fn frame(time: &mut Time, clock: &FakeClock, seconds: f64) -> u32 {
+        clock.advance(seconds);
+        time.tick(clock.now());
+        time.fixed_steps()
+    }
+
+    #[test]
+    fn lasts_no_time_on_the_first_frame() {
+        let clock = FakeClock::new();
+        clock.set(100.0);
+        let time = start(&clock);
+
+        assert_eq!(time.delta(), 0.0);
+        assert_eq!(time.frame_count(), 1);
+    }
+
+    #[test]
+    fn carries_the_leftover_time_over_to_the_next_frame() {
+        let clock = FakeClock::new();
+        let mut time = start(&clock);
+
+        // 2.4 steps, and 0.4 left over
+        assert_eq!(frame(&mut time, &clock, 0.15), 2);
+        assert!((time.alpha() - 0.4).abs() < 1e-4);
+        // the 0.4 left over and another 1.6 steps
+        assert_eq!(frame(&mut time, &clock, 0.1), 2);
+        assert!(time.alpha() < 1e-4);
+        // not enough for a step, which is delayed to the next frame
+        assert_eq!(frame(&mut time, &clock, 0.05), 0);
+        assert_eq!(frame(&mut time, &clock, 0.05), 1);
+    }
+
+    #[test]
+    fn drops_the_steps_past_the_maximum() {
+        let clock = FakeClock::new();
+        let mut time = start(&clock);
+        time.max_fixed_steps = 2;
+
+        // 3.2 steps, only 2 of them run
+        assert_eq!(frame(&mut time, &clock, 0.2), 2);
+        // the dropped step is gone, but the fraction left over isn't
+        assert!((time.alpha() - 0.2).abs() < 1e-4);
+        assert_eq!(frame(&mut time, &clock, 0.0), 0);
+        assert_eq!(frame(&mut time, &clock, 0.05), 1);
+    }
+
+    #[test]
+    fn shortens_frames_longer_than_the_maximum() {
+        let clock = FakeClock::new();
+        let mut time = start(&clock);
+        time.max_fixed_steps = 100;
+
+        assert_eq!(frame(&mut time, &clock, 10.0), 4);
+        assert_eq!(time.unscaled_delta(), DEFAULT_MAX_DELTA);
+    }
+
+    #[test]
+    fn runs_steps_missing_less_than_the_tolerance() {
+        let clock = FakeClock::new();
+        let mut time = start(&clock);
+        let step = STEP as f64;
+
+        let almost = step * (1.0 - FIXED_STEP_TOLERANCE / 2.0);
+        assert_eq!(frame(&mut time, &clock, almost), 1);
+        // the missing bit isn't owed by the next step
+        assert_eq!(time.alpha(), 0.0);
+        assert_eq!(frame(&mut time, &clock, step), 1);
+
+        let too_short = step * (1.0 - FIXED_STEP_TOLERANCE * 2.0);
+        assert_eq!(frame(&mut time, &clock, too_short), 0);
+    }
+
+    #[test]
+    fn keeps_alpha_between_zero_and_one() {
+        let clock = FakeClock::new();
+        let mut time = start(&clock);
+        time.max_fixed_steps = 3;
+
+        for i in 0..200 {
+            // an uneven mix of short, long and too long frames
+            let seconds = (i * 37 % 23) as f64 / 40.0;
+            frame(&mut time, &clock, seconds);
+
+            let alpha = time.alpha();
+            assert!((0.0..1.0).contains(&alpha), "{alpha} after {seconds}s");
+        }
+    }
+
+    #[test]
+    fn pauses_with_a_time_scale_of_zero() {
+        let clock = FakeClock::new();
+        let mut time = start(&clock);
+        assert_eq!(frame(&mut time, &clock, 0.1), 1);
+        let (elapsed, alpha) = (time.elapsed(), time.alpha());
+
+        time.time_scale = 0.0;
+        for _ in 0..10 {
+            assert_eq!(frame(&mut time, &clock, 0.1), 0);
+        }
+
+        assert_eq!(time.delta(), 0.0);
+        assert!((time.unscaled_delta() - 0.1).abs() < 1e-6);
+        assert_eq!(time.elapsed(), elapsed);
+        assert_eq!(time.alpha(), alpha);
+        assert_eq!(time.frame_count(), 12);
+    }
+
+    #[test]
+    fn scales_the_time_fed_to_the_steps() {
+        let clock = FakeClock::new();
+        let mut time = start(&clock);
+        time.time_scale = 0.5;
+
+        assert_eq!(frame(&mut time, &clock, 0.25), 2);
+        assert!((time.delta() - 0.125).abs() < 1e-6);
+    }
+}
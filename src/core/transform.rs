@@ -0,0 +1,186 @@
+use glam::{Mat4, Vec2, Vec3};
+use std::ops::Mul;
+
+/// A translation, a rotation and a uniform scale, in virtual resolution
+/// pixels and radians.
+/// Transforms can be combined with `*`, applying the right one first, so
+/// `parent * child` places `child` relative to `parent`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    /// The translation, in virtual resolution pixels.
+    pub translation: Vec2,
+    /// The rotation, in radians. Positive values rotate clockwise.
+    pub rotation: f32,
+    /// The scale, the same on both axes.
+    pub scale: f32,
+}
+
+impl Transform {
+    /// A transform leaving everything in place.
+    pub const IDENTITY: Transform = Transform {
+        translation: Vec2::ZERO,
+        rotation: 0.0,
+        scale: 1.0,
+    };
+
+    /// Creates a transform that only moves things by `translation`.
+    pub const fn from_translation(translation: Vec2) -> Self {
+        Self {
+            translation,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Applies the transform to a point.
+    pub fn transform_point(&self, point: Vec2) -> Vec2 {
+        self.translation + Vec2::from_angle(self.rotation).rotate(point * self.scale)
+    }
+
+    /// Applies the transform to a direction, ignoring the translation.
+    pub fn transform_vector(&self, vector: Vec2) -> Vec2 {
+        Vec2::from_angle(self.rotation).rotate(vector * self.scale)
+    }
+
+    /// Gets the transform undoing this one. A scale of zero can't be
+    /// undone, and is left as is.
+    pub fn inverse(&self) -> Self {
+        if self.scale == 0.0 {
+            return *self;
+        }
+
+        let rotation = -self.rotation;
+        let scale = 1.0 / self.scale;
+        let translation = Vec2::from_angle(rotation).rotate(-self.translation) * scale;
+
+        Self {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    /// Mixes this transform with `other`. A `t` of 0.0 gives this transform,
+    /// and 1.0 gives `other`.
+    pub fn lerp(&self, other: &Transform, t: f32) -> Self {
+        Self {
+            translation: self.translation.lerp(other.translation, t),
+            rotation: self.rotation + (other.rotation - self.rotation) * t,
+            scale: self.scale + (other.scale - self.scale) * t,
+        }
+    }
+
+    /// Gets the transform as a matrix.
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::from_translation(self.translation.extend(0.0))
+            * Mat4::from_rotation_z(self.rotation)
+            * Mat4::from_scale(Vec3::new(self.scale, self.scale, 1.0))
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Mul for Transform {
+    type Output = Transform;
+
+    fn mul(self, child: Transform) -> Self::Output {
+        Self {
+            translation: self.transform_point(child.translation),
+            rotation: self.rotation + child.rotation,
+            scale: self.scale * child.scale,
+        }
+    }
+}
+
+/// Something that can be placed in the world by a `Transform`, such as the
+/// node of a `SceneGraph` it is attached to.
+pub trait Spatial {
+    /// Moves, rotates and scales the object to match `transform`.
+    fn set_transform(&mut self, transform: &Transform);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    fn transform(x: f32, y: f32, rotation: f32, scale: f32) -> Transform {
+        Transform {
+            translation: Vec2::new(x, y),
+            rotation,
+            scale,
+        }
+    }
+
+    fn assert_near(a: Transform, b: Transform) {
+        assert!(
+            a.translation.distance(b.translation) < 1e-4
+                && (a.rotation - b.rotation).abs() < 1e-5
+                && (a.scale - b.scale).abs() < 1e-5,
+            "{:?} should be {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn scales_then_rotates_then_translates_points() {
+        let transform = transform(10.0, 20.0, FRAC_PI_2, 2.0);
+
+        assert!(transform
+            .transform_point(Vec2::new(1.0, 0.0))
+            .abs_diff_eq(Vec2::new(10.0, 22.0), 1e-5));
+        assert!(transform
+            .transform_vector(Vec2::new(1.0, 0.0))
+            .abs_diff_eq(Vec2::new(0.0, 2.0), 1e-5));
+    }
+
+    #[test]
+    fn places_children_relative_to_their_parent() {
+        let parent = transform(10.0, 0.0, FRAC_PI_2, 2.0);
+        let child = transform(3.0, 0.0, PI, 0.5);
+        let point = Vec2::new(1.0, 2.0);
+
+        let combined = parent * child;
+
+        assert_near(combined, transform(10.0, 6.0, FRAC_PI_2 + PI, 1.0));
+        assert!(combined
+            .transform_point(point)
+            .abs_diff_eq(parent.transform_point(child.transform_point(point)), 1e-4));
+    }
+
+    #[test]
+    fn undoes_itself_when_combined_with_its_inverse() {
+        let transforms = [
+            Transform::IDENTITY,
+            transform(10.0, -4.0, 0.0, 1.0),
+            transform(-3.0, 7.0, 1.2, 0.25),
+            transform(100.0, 50.0, -2.5, 4.0),
+        ];
+
+        for transform in transforms {
+            assert_near(transform * transform.inverse(), Transform::IDENTITY);
+            assert_near(transform.inverse() * transform, Transform::IDENTITY);
+        }
+    }
+
+    #[test]
+    fn leaves_a_zero_scale_as_is_when_inverted() {
+        let flat = transform(1.0, 2.0, 0.5, 0.0);
+
+        assert_eq!(flat.inverse(), flat);
+    }
+
+    #[test]
+    fn mixes_every_part_of_two_transforms() {
+        let from = transform(0.0, 0.0, 0.0, 1.0);
+        let to = transform(10.0, -20.0, 1.0, 3.0);
+
+        assert_eq!(from.lerp(&to, 0.0), from);
+        assert_eq!(from.lerp(&to, 1.0), to);
+        assert_near(from.lerp(&to, 0.5), transform(5.0, -10.0, 0.5, 2.0));
+    }
+}
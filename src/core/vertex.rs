@@ -1,13 +1,15 @@
 use super::color::Color;
-use glam::{Vec2, Vec3};
+use glam::{Vec2, Vec4};
 
-/// A vertex. It consists of a position (a Vector2) and a color (a Vector3).
+/// A vertex. It consists of a position (a Vector2), a color (a Vector4) and
+/// texture coordinates (a Vector2).
 #[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Vertex {
     /// The position of the vertex, in vertex coordinates (-1.0 to 1.0).
     pub pos: Vec2,
-    /// A color, constructed by `rgb` values.
-    pub color: Vec3,
+    /// A color, as `rgba` values from 0.0 to 1.0.
+    pub color: Vec4,
     /// The texture coordinates.
     pub tex: Vec2,
 }
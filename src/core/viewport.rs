@@ -0,0 +1,242 @@
+use crate::{VIRTUAL_RESOLUTION_X, VIRTUAL_RESOLUTION_Y};
+
+use super::rect::Rect;
+use glam::Vec2;
+use miniquad::Context;
+
+/// Determines how the virtual resolution is fit into the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScalingMode {
+    /// Fills the whole window, distorting the image if the aspect ratios differ.
+    Stretch,
+    /// Scales by the biggest integer factor that fits in the window, so that
+    /// every virtual pixel is the same size. The remaining space is filled
+    /// with letterbox or pillarbox bars.
+    PixelPerfect,
+    /// Scales uniformly until the whole window is covered, cropping whatever
+    /// doesn't fit.
+    FitCrop,
+}
+
+/// Maps a fixed virtual resolution into a window of any size.
+///
+/// Everything is drawn in virtual resolution coordinates, and the viewport
+/// decides which area of the window they end up in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    /// The size of the virtual resolution, in virtual pixels.
+    pub virtual_size: Vec2,
+    /// How the virtual resolution is fit into the window.
+    pub mode: ScalingMode,
+}
+
+impl Viewport {
+    /// Creates a viewport for the given virtual resolution.
+    pub const fn new(virtual_size: Vec2, mode: ScalingMode) -> Self {
+        Self { virtual_size, mode }
+    }
+
+    /// Gets how many window pixels a virtual pixel covers on each axis.
+    pub fn scale(&self, window_size: Vec2) -> Vec2 {
+        let ratio = window_size / self.virtual_size;
+
+        match self.mode {
+            ScalingMode::Stretch => ratio,
+            ScalingMode::PixelPerfect => Vec2::splat(ratio.min_element().floor().max(1.0)),
+            ScalingMode::FitCrop => Vec2::splat(ratio.max_element()),
+        }
+    }
+
+    /// Gets the area of the window the virtual resolution is drawn into, in
+    /// window pixels from the top-left corner. It may be smaller than the
+    /// window (leaving bars around it), or bigger (cropping its borders).
+    ///
+    /// # Example
+    /// ```rust
+    /// let viewport = Viewport::new(Vec2::new(512.0, 288.0), ScalingMode::PixelPerfect);
+    /// let area = viewport.rect(Vec2::new(1600.0, 900.0));
+    ///
+    /// // scaled 3 times, centered
+    /// assert_eq!(area, Rect::new(32.0, 18.0, 1536.0, 864.0));
+    /// ```
+    pub fn rect(&self, window_size: Vec2) -> Rect {
+        let size = (self.virtual_size * self.scale(window_size)).round();
+        let position = ((window_size - size) / 2.0).floor();
+
+        Rect::from_position_size(position, size)
+    }
+
+    /// Converts a point in window pixels into virtual resolution coordinates.
+    /// Points over the bars end up outside of the virtual resolution.
+    pub fn window_to_virtual(&self, point: Vec2, window_size: Vec2) -> Vec2 {
+        let rect = self.rect(window_size);
+
+        (point - rect.position()) / rect.size() * self.virtual_size
+    }
+
+    /// Converts a point in virtual resolution coordinates into window pixels.
+    pub fn virtual_to_window(&self, point: Vec2, window_size: Vec2) -> Vec2 {
+        let rect = self.rect(window_size);
+
+        rect.position() + point / self.virtual_size * rect.size()
+    }
+
+    /// Makes everything drawn afterwards land in the viewport's area of the window.
+    pub fn apply(&self, ctx: &mut Context) {
+        let (width, height) = ctx.screen_size();
+        let rect = self.rect(Vec2::new(width, height));
+
+        // the GL viewport starts at the bottom-left corner
+        let bottom = height - rect.y - rect.h;
+        ctx.apply_viewport(rect.x as i32, bottom as i32, rect.w as i32, rect.h as i32);
+    }
+
+    /// Makes everything drawn afterwards cover the whole window again.
+    pub fn reset(ctx: &mut Context) {
+        let (width, height) = ctx.screen_size();
+        ctx.apply_viewport(0, 0, width as i32, height as i32);
+    }
+}
+
+impl Default for Viewport {
+    /// A pixel perfect viewport for the virtual resolution.
+    fn default() -> Self {
+        Self::new(
+            Vec2::new(VIRTUAL_RESOLUTION_X as f32, VIRTUAL_RESOLUTION_Y as f32),
+            ScalingMode::PixelPerfect,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VIRTUAL_SIZE: Vec2 = Vec2::new(512.0, 288.0);
+
+    fn pixel_perfect() -> Viewport {
+        Viewport::new(VIRTUAL_SIZE, ScalingMode::PixelPerfect)
+    }
+
+    #[test]
+    fn scales_exact_multiples_to_fill_the_window() {
+        let window = Vec2::new(1536.0, 864.0);
+
+        assert_eq!(pixel_perfect().scale(window), Vec2::splat(3.0));
+        assert_eq!(
+            pixel_perfect().rect(window),
+            Rect::new(0.0, 0.0, 1536.0, 864.0)
+        );
+    }
+
+    #[test]
+    fn scales_by_whole_factors_in_bigger_windows() {
+        let window = Vec2::new(1600.0, 900.0);
+
+        assert_eq!(pixel_perfect().scale(window), Vec2::splat(3.0));
+        assert_eq!(
+            pixel_perfect().rect(window),
+            Rect::new(32.0, 18.0, 1536.0, 864.0)
+        );
+    }
+
+    #[test]
+    fn never_scales_below_one_in_smaller_windows() {
+        let window = Vec2::new(400.0, 200.0);
+
+        assert_eq!(pixel_perfect().scale(window), Vec2::ONE);
+        // centered, cropping every border
+        assert_eq!(
+            pixel_perfect().rect(window),
+            Rect::new(-56.0, -44.0, 512.0, 288.0)
+        );
+    }
+
+    #[test]
+    fn letterboxes_taller_windows() {
+        let rect = pixel_perfect().rect(Vec2::new(1536.0, 1000.0));
+
+        assert_eq!(rect, Rect::new(0.0, 68.0, 1536.0, 864.0));
+    }
+
+    #[test]
+    fn pillarboxes_wider_windows() {
+        let rect = pixel_perfect().rect(Vec2::new(2000.0, 864.0));
+
+        assert_eq!(rect, Rect::new(232.0, 0.0, 1536.0, 864.0));
+    }
+
+    #[test]
+    fn stretches_each_axis_on_its_own() {
+        let viewport = Viewport::new(VIRTUAL_SIZE, ScalingMode::Stretch);
+        let window = Vec2::new(1024.0, 864.0);
+
+        assert_eq!(viewport.scale(window), Vec2::new(2.0, 3.0));
+        assert_eq!(viewport.rect(window), Rect::new(0.0, 0.0, 1024.0, 864.0));
+    }
+
+    #[test]
+    fn crops_to_cover_the_window() {
+        let viewport = Viewport::new(VIRTUAL_SIZE, ScalingMode::FitCrop);
+        let rect = viewport.rect(Vec2::new(1600.0, 1000.0));
+
+        assert_eq!(rect.h, 1000.0);
+        assert_eq!(rect.y, 0.0);
+        assert!(rect.w > 1600.0 && rect.x < 0.0);
+        assert_eq!(rect.x, ((1600.0 - rect.w) / 2.0).floor());
+    }
+
+    #[test]
+    fn maps_the_corners_of_the_rect_to_the_virtual_corners() {
+        let window = Vec2::new(2000.0, 1000.0);
+        let viewport = pixel_perfect();
+        let rect = viewport.rect(window);
+
+        assert_eq!(
+            viewport.window_to_virtual(rect.position(), window),
+            Vec2::ZERO
+        );
+        assert_eq!(
+            viewport.window_to_virtual(rect.position() + rect.size(), window),
+            VIRTUAL_SIZE
+        );
+    }
+
+    #[test]
+    fn round_trips_between_window_and_virtual_coordinates() {
+        let points = [
+            Vec2::ZERO,
+            Vec2::new(100.0, 50.0),
+            Vec2::new(511.0, 287.0),
+            Vec2::new(256.5, 144.25),
+        ];
+
+        for mode in [
+            ScalingMode::Stretch,
+            ScalingMode::PixelPerfect,
+            ScalingMode::FitCrop,
+        ] {
+            let viewport = Viewport::new(VIRTUAL_SIZE, mode);
+            for window in [Vec2::new(1600.0, 900.0), Vec2::new(1000.0, 1300.0)] {
+                for point in points {
+                    let window_point = viewport.virtual_to_window(point, window);
+                    let back = viewport.window_to_virtual(window_point, window);
+
+                    assert!(back.abs_diff_eq(point, 1e-3), "{mode:?}: {back}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn maps_points_over_the_bars_outside_of_the_virtual_resolution() {
+        let window = Vec2::new(2000.0, 864.0);
+        let viewport = pixel_perfect();
+
+        let left_bar = viewport.window_to_virtual(Vec2::new(100.0, 432.0), window);
+        let right_bar = viewport.window_to_virtual(Vec2::new(1900.0, 432.0), window);
+
+        assert_eq!(left_bar, Vec2::new(-44.0, 144.0));
+        assert!(right_bar.x > VIRTUAL_SIZE.x);
+    }
+}
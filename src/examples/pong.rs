@@ -1,294 +1,397 @@
 use glam::Vec2;
-use miniquad::{Context, EventHandler, KeyCode};
+use miniquad::{Context, EventHandler, KeyCode, KeyMods, MouseButton, PassAction};
 
 use crate::core::{
+    camera::Camera2D,
+    collision::{Collider, ContinuousCollision},
     color::Color,
+    ecs::{
+        components::{Controller, PreviousTransform, Velocity},
+        schedule::Schedule,
+        systems, Entity, World,
+    },
     input_handler::InputHandler,
+    input_map::InputMap,
+    scene::{Scene, SceneCommand, SceneEvent, Transition},
     shape::{Shape, ShapeType},
+    time::Time,
+    transform::Transform,
+    viewport::Viewport,
 };
 
-pub struct Collider {
-    pub position: Vec2,
-    pub size: Vec2,
-    pub shape: Shape,
-}
-
-impl Collider {
-    pub fn new(ctx: &mut Context, x: f32, y: f32, w: f32, h: f32) -> Self {
-        let position = Vec2::new(x, y);
-        let size = Vec2::new(w, h);
-
-        Self {
-            position,
-            size,
-            shape: Shape::new(ctx, ShapeType::RectangleLines(w, h), position, Color::GREEN),
-        }
-    }
+/// The space kept between the paddles and the top and bottom of the screen.
+const PADDING: f32 = 15.0;
 
-    pub const fn pos(&self) -> (f32, f32) {
-        (self.position.x, self.position.y)
-    }
-
-    pub const fn size(&self) -> (f32, f32) {
-        (self.size.x, self.size.y)
-    }
-
-    pub fn collides_with(&self, collider: &Collider) -> bool {
-        let (sx, sy) = self.pos();
-        let (sw, sh) = self.size();
-        let (ox, oy) = collider.pos();
-        let (ow, oh) = collider.size();
-
-        sx < ox + ow && sx + sw > ox && sy < oy + oh && sy + sh > oy
-    }
+/// How fast players move their paddles, in pixels per second.
+const PADDLE_SPEED: f32 = 240.0;
 
-    pub fn move_by(&mut self, vel: Vec2) {
-        self.position += vel;
-        self.shape.position += vel;
-    }
-}
+/// Marks the paddles, which the ball bounces off.
+pub struct Paddle;
 
-impl EventHandler for Collider {
-    fn update(&mut self, _ctx: &mut Context) {}
+/// Marks the ball.
+pub struct Ball;
 
-    fn draw(&mut self, ctx: &mut Context) {
-        self.shape.draw(ctx);
-    }
+/// Moves a paddle up and down on its own, turning around at the edges.
+pub struct Patrol {
+    pub speed: f32,
 }
 
 pub struct Pong {
-    player: Player,
-    ball: Ball,
-    enemy: Enemy,
+    world: World,
+    schedule: Schedule,
+    viewport: Viewport,
+    /// What the scene manager should do after the next update.
+    command: Option<SceneCommand>,
 }
 
-pub struct Player {
-    position: Vec2,
-    movement_speed: f32,
-    shape: Shape,
-    collider: Collider,
-    input_handler: InputHandler,
-}
-
-pub struct Ball {
-    position: Vec2,
-    initial_velocity: Vec2,
-    shape: Shape,
-    collider: Collider,
-}
-
-pub struct Enemy {
-    position: Vec2,
-    movement_speed: f32,
-    shape: Shape,
-    collider: Collider,
-}
-
-impl Player {
-    pub fn new(ctx: &mut Context, position: Vec2, movement_speed: f32) -> Self {
+impl Pong {
+    /// Creates a match, controlled with the "move_y" and "back" actions of
+    /// `input_map`. The right paddle moves on its own until a second player
+    /// plugs in a gamepad or uses the "move_y_2" action.
+    pub fn new(ctx: &mut Context, input_map: InputMap) -> Self {
+        let mut world = World::new();
+        world.register::<Paddle>();
+        world.register::<Ball>();
+        world.register::<Patrol>();
+        world.insert_resource(InputHandler::new());
+        world.insert_resource(input_map);
+
+        let virtual_size = Viewport::default().virtual_size;
         let (w, h) = (16.0, 48.0);
 
+        let player = spawn_paddle(ctx, &mut world, Vec2::new(32.0, (virtual_size.y - h) / 2.0));
+        world.insert(
+            player,
+            Controller::vertical("move_y", PADDLE_SPEED).for_player(0),
+        );
+
+        let enemy_position = Vec2::new(virtual_size.x - 32.0 - w, (virtual_size.y - h) / 2.0);
+        let enemy = spawn_paddle(ctx, &mut world, enemy_position);
+        world.insert(enemy, Patrol { speed: 180.0 });
+
+        let radius = 16.0;
+        let ball = world.spawn();
+        let position = virtual_size / 2.0 - radius;
+        world.insert(ball, Ball);
+        world.insert(ball, Transform::from_translation(position));
+        world.insert(
+            ball,
+            PreviousTransform(Transform::from_translation(position)),
+        );
+        world.insert(ball, Velocity(Vec2::new(180.0, 120.0)));
+        world.insert(ball, Collider::circle(radius));
+        // the ball is fast enough to go through paddles in a single step
+        world.insert(ball, ContinuousCollision::default());
+        world.insert(
+            ball,
+            Shape::new(ctx, ShapeType::Circle(radius), position, Color::WHITE),
+        );
+
+        let mut schedule = Schedule::new();
+        schedule.add("remember transforms", systems::remember_transforms);
+        schedule.add("controllers", systems::controllers);
+        schedule.add("join second player", join_second_player);
+        schedule.add("patrol", patrol);
+        schedule.add("movement", systems::movement);
+        schedule.add("keep paddles inside", keep_paddles_inside);
+        schedule.add("bounce ball", bounce_ball);
+
         Self {
-            position,
-            movement_speed,
-            shape: Shape::new(ctx, ShapeType::Rectangle(w, h), position, Color::WHITE),
-            collider: Collider::new(ctx, position.x, position.y, w, h),
-            input_handler: InputHandler::new(),
+            world,
+            schedule,
+            viewport: Viewport::default(),
+            command: None,
         }
     }
-
-    pub const fn collider(&self) -> &Collider {
-        &self.collider
-    }
-
-    fn move_y(&mut self, y: f32) {
-        self.shape.position.y += y;
-        self.collider.move_by((0.0, y).into());
-        self.position.y += y;
-    }
 }
 
-impl EventHandler for Player {
-    fn update(&mut self, ctx: &mut Context) {
-        let (_, window_height) = ctx.screen_size();
-        let padding = 15.0;
-
-        self.input_handler.update(ctx);
+/// Spawns a paddle with its top-left corner at `position`.
+fn spawn_paddle(ctx: &mut Context, world: &mut World, position: Vec2) -> Entity {
+    let (w, h) = (16.0, 48.0);
+
+    let paddle = world.spawn();
+    world.insert(paddle, Paddle);
+    world.insert(paddle, Transform::from_translation(position));
+    world.insert(
+        paddle,
+        PreviousTransform(Transform::from_translation(position)),
+    );
+    world.insert(paddle, Velocity::default());
+    world.insert(paddle, Collider::new(w, h));
+    world.insert(
+        paddle,
+        // shapes span twice their size, from -1.0 to 1.0
+        Shape::new(
+            ctx,
+            ShapeType::Rectangle(w / 2.0, h / 2.0),
+            position,
+            Color::WHITE,
+        ),
+    );
 
-        let mut movement_dir = Vec2::ZERO;
-        if self.input_handler.is_key_pressed(KeyCode::W) && self.position.y > padding {
-            movement_dir.y -= 1.0 * self.movement_speed;
-        }
+    paddle
+}
 
-        if self.input_handler.is_key_pressed(KeyCode::S)
-            && self.position.y + self.shape.size.y < window_height - padding
-        {
-            movement_dir.y += 1.0 * self.movement_speed;
+/// Hands the patrolling paddles over to the second player, as soon as they
+/// plug in a gamepad or move with their keys.
+fn join_second_player(world: &mut World, _ctx: &mut Context) {
+    let joined = match (
+        world.resource::<InputHandler>(),
+        world.resource::<InputMap>(),
+    ) {
+        (Some(input_handler), Some(input_map)) => {
+            input_handler.player_gamepad(1).is_some()
+                || input_map.player_value("move_y_2", 1, &input_handler) != 0.0
         }
-
-        self.move_y(movement_dir.y);
+        _ => false,
+    };
+    if !joined {
+        return;
     }
 
-    fn draw(&mut self, ctx: &mut Context) {
-        self.shape.draw(ctx);
-        self.collider.draw(ctx);
+    let patrolling: Vec<Entity> = world
+        .storage::<Patrol>()
+        .iter()
+        .map(|(entity, _)| entity)
+        .collect();
+    for paddle in patrolling {
+        info!("The second player joined");
+        world.remove::<Patrol>(paddle);
+        world.insert(
+            paddle,
+            Controller::vertical("move_y_2", PADDLE_SPEED).for_player(1),
+        );
     }
+}
 
-    fn key_down_event(
-        &mut self,
-        _ctx: &mut Context,
-        keycode: miniquad::KeyCode,
-        _keymods: miniquad::KeyMods,
-        _repeat: bool,
-    ) {
-        self.input_handler
-            .key_down_event(_ctx, keycode, _keymods, _repeat);
+/// Turns patrolling paddles around when they get too close to an edge.
+fn patrol(world: &mut World, _ctx: &mut Context) {
+    let window_height = Viewport::default().virtual_size.y;
+    let patrols = world.storage::<Patrol>();
+    let transforms = world.storage::<Transform>();
+    let colliders = world.storage::<Collider>();
+    let mut velocities = world.storage_mut::<Velocity>();
+
+    for (entity, patrol, transform) in patrols.join(&transforms) {
+        let (velocity, collider) = match (velocities.get_mut(entity), colliders.get(entity)) {
+            (Some(velocity), Some(collider)) => (velocity, collider),
+            _ => continue,
+        };
+
+        let rect = collider.rect(transform);
+        if velocity.0.y == 0.0 {
+            velocity.0.y = patrol.speed;
+        }
+        if rect.y < PADDING {
+            velocity.0.y = patrol.speed.abs();
+        }
+        if rect.max().y > window_height - PADDING {
+            velocity.0.y = -patrol.speed.abs();
+        }
     }
+}
 
-    fn key_up_event(&mut self, _ctx: &mut Context, keycode: KeyCode, _keymods: miniquad::KeyMods) {
-        self.input_handler.key_up_event(_ctx, keycode, _keymods);
+/// Keeps the paddles from leaving the screen.
+fn keep_paddles_inside(world: &mut World, _ctx: &mut Context) {
+    let window_height = Viewport::default().virtual_size.y;
+    let paddles = world.storage::<Paddle>();
+    let colliders = world.storage::<Collider>();
+    let mut transforms = world.storage_mut::<Transform>();
+
+    for (entity, _, collider) in paddles.join(&colliders) {
+        if let Some(transform) = transforms.get_mut(entity) {
+            let rect = collider.rect(transform);
+            let offset = rect.y - transform.translation.y;
+            let max_y = window_height - PADDING - rect.h;
+            transform.translation.y = rect.y.clamp(PADDING, max_y) - offset;
+        }
     }
 }
 
-impl Ball {
-    pub fn new(ctx: &mut Context, initial_velocity: Vec2) -> Self {
-        let (window_width, window_height) = ctx.screen_size();
-        let position = Vec2::new(window_width / 2.0, window_height / 2.0);
-
-        Self {
-            position,
-            initial_velocity,
-            shape: Shape::new(ctx, ShapeType::Circle(16.0), position, Color::WHITE),
-            collider: Collider::new(ctx, position.x, position.y, 16.0, 16.0),
+/// Bounces the ball off the edges of the screen and the paddles, rumbling
+/// the gamepad of the player hitting it.
+fn bounce_ball(world: &mut World, _ctx: &mut Context) {
+    let window_size = Viewport::default().virtual_size;
+    let balls = world.storage::<Ball>();
+    let paddles = world.storage::<Paddle>();
+    let colliders = world.storage::<Collider>();
+    let controllers = world.storage::<Controller>();
+    let continuous = world.storage::<ContinuousCollision>();
+    let mut transforms = world.storage_mut::<Transform>();
+    let mut velocities = world.storage_mut::<Velocity>();
+    let mut input_handler = world.resource_mut::<InputHandler>();
+
+    for (ball, _, collider) in balls.join(&colliders) {
+        let (transform, velocity) = match (transforms.get(ball), velocities.get_mut(ball)) {
+            (Some(transform), Some(velocity)) => (*transform, velocity),
+            _ => continue,
+        };
+        let rect = collider.rect(&transform);
+
+        // only turn around when heading out, so the ball can't get stuck
+        if rect.x < 0.0 {
+            velocity.0.x = velocity.0.x.abs();
+        }
+        if rect.max().x > window_size.x {
+            velocity.0.x = -velocity.0.x.abs();
+        }
+        if rect.y < 0.0 {
+            velocity.0.y = velocity.0.y.abs();
+        }
+        if rect.max().y > window_size.y {
+            velocity.0.y = -velocity.0.y.abs();
         }
-    }
 
-    pub const fn collider(&self) -> &Collider {
-        &self.collider
-    }
+        // the ball stops at paddles in its way, and gets pushed out of the
+        // ones moving into it
+        let swept_hit = continuous
+            .get(ball)
+            .and_then(|continuous| continuous.collision)
+            .filter(|(paddle, _)| paddles.get(*paddle).is_some())
+            .map(|(paddle, collision)| (paddle, collision.normal));
+        let hit = swept_hit.or_else(|| {
+            paddles
+                .iter()
+                .find_map(|(paddle, _)| {
+                    let paddle_collider = colliders.get(paddle)?;
+                    let paddle_transform = transforms.get(paddle)?;
+                    let manifold =
+                        collider.contact(&transform, paddle_collider, paddle_transform)?;
+                    Some((paddle, manifold))
+                })
+                .map(|(paddle, manifold)| {
+                    if let Some(transform) = transforms.get_mut(ball) {
+                        transform.translation += manifold.separation();
+                    }
+                    (paddle, manifold.normal)
+                })
+        });
+        if let Some((paddle, normal)) = hit {
+            debug!("Collided!");
 
-    fn move_by(&mut self, vel: Vec2) {
-        self.shape.position += vel;
-        self.collider.move_by(vel);
-        self.position += vel;
+            // reflect the ball off the side it hit, unless it's already
+            // moving away from it
+            let speed_into = velocity.0.dot(normal);
+            if speed_into < 0.0 {
+                velocity.0 -= 2.0 * speed_into * normal;
+            }
+
+            let player = controllers
+                .get(paddle)
+                .and_then(|controller| controller.player);
+            if let (Some(player), Some(input_handler)) = (player, input_handler.as_mut()) {
+                input_handler.rumble(player, 0.4, 0.6, 0.1);
+            }
+        }
     }
 }
 
-impl EventHandler for Ball {
+impl EventHandler for Pong {
     fn update(&mut self, ctx: &mut Context) {
-        let (window_width, window_height) = ctx.screen_size();
-        let left_bound_x = self.position.x - 16.0;
-        let right_bound_x = self.position.x + 16.0;
-        let top_bound_y = self.position.y - 16.0;
-        let bottom_bound_y = self.position.y + 16.0;
-
-        if right_bound_x > window_width || left_bound_x < 0.0 {
-            self.initial_velocity.x *= -1.0;
-        }
+        self.schedule.run(&mut self.world, ctx);
+    }
 
-        if bottom_bound_y > window_height || top_bound_y < 0.0 {
-            self.initial_velocity.y *= -1.0;
-        }
+    fn draw(&mut self, ctx: &mut Context) {
+        systems::sync_transforms(&mut self.world, ctx);
 
-        self.move_by(self.initial_velocity)
+        ctx.begin_default_pass(PassAction::clear_color(0.0, 0.0, 0.0, 1.0));
+        self.viewport.apply(ctx);
+        systems::draw(&mut self.world, ctx, &Camera2D::default());
+        Viewport::reset(ctx);
+        ctx.end_render_pass();
     }
 
-    fn draw(&mut self, ctx: &mut Context) {
-        self.shape.draw(ctx);
-        self.collider.draw(ctx);
+    fn key_down_event(
+        &mut self,
+        ctx: &mut Context,
+        keycode: KeyCode,
+        keymods: KeyMods,
+        repeat: bool,
+    ) {
+        if let Some(mut input_handler) = self.world.resource_mut::<InputHandler>() {
+            input_handler.key_down_event(ctx, keycode, keymods, repeat);
+        }
     }
-}
 
-impl Enemy {
-    pub fn new(ctx: &mut Context, position: Vec2, movement_speed: f32) -> Self {
-        let (w, h) = (16.0, 48.0);
+    fn key_up_event(&mut self, ctx: &mut Context, keycode: KeyCode, keymods: KeyMods) {
+        if let Some(mut input_handler) = self.world.resource_mut::<InputHandler>() {
+            input_handler.key_up_event(ctx, keycode, keymods);
+        }
+    }
 
-        Self {
-            position,
-            movement_speed,
-            shape: Shape::new(ctx, ShapeType::Rectangle(w, h), position, Color::WHITE),
-            collider: Collider::new(ctx, position.x, position.y, w, h),
+    fn mouse_motion_event(&mut self, ctx: &mut Context, x: f32, y: f32) {
+        if let Some(mut input_handler) = self.world.resource_mut::<InputHandler>() {
+            input_handler.mouse_motion_event(ctx, x, y);
         }
     }
 
-    pub const fn collider(&self) -> &Collider {
-        &self.collider
+    fn mouse_wheel_event(&mut self, ctx: &mut Context, x: f32, y: f32) {
+        if let Some(mut input_handler) = self.world.resource_mut::<InputHandler>() {
+            input_handler.mouse_wheel_event(ctx, x, y);
+        }
     }
 
-    fn move_y(&mut self, y: f32) {
-        self.shape.position.y += y;
-        self.collider.move_by((0.0, y).into());
-        self.position.y += y;
+    fn mouse_button_down_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+        if let Some(mut input_handler) = self.world.resource_mut::<InputHandler>() {
+            input_handler.mouse_button_down_event(ctx, button, x, y);
+        }
     }
-}
 
-impl EventHandler for Enemy {
-    fn update(&mut self, ctx: &mut Context) {
-        let (_, window_height) = ctx.screen_size();
-        let padding = 15.0;
-
-        if self.position.y < padding
-            || self.position.y + self.shape.size.y > window_height - padding
-        {
-            debug!("pos + size: {}", self.position.y + self.shape.size.y);
-            debug!("windowh - pad: {}", window_height - padding);
-            self.movement_speed *= -1.0;
+    fn mouse_button_up_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+        if let Some(mut input_handler) = self.world.resource_mut::<InputHandler>() {
+            input_handler.mouse_button_up_event(ctx, button, x, y);
         }
+    }
 
-        self.move_y(self.movement_speed);
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) {
+        if let Some(mut input_handler) = self.world.resource_mut::<InputHandler>() {
+            input_handler.resize_event(ctx, width, height);
+        }
     }
 
-    fn draw(&mut self, ctx: &mut Context) {
-        self.shape.draw(ctx);
-        self.collider.draw(ctx);
+    fn window_minimized_event(&mut self, ctx: &mut Context) {
+        if let Some(mut input_handler) = self.world.resource_mut::<InputHandler>() {
+            input_handler.window_minimized_event(ctx);
+        }
     }
-}
 
-impl Pong {
-    pub fn new(player: Player, ball: Ball, enemy: Enemy) -> Self {
-        Self {
-            player,
-            ball,
-            enemy,
+    fn window_restored_event(&mut self, ctx: &mut Context) {
+        if let Some(mut input_handler) = self.world.resource_mut::<InputHandler>() {
+            input_handler.window_restored_event(ctx);
         }
     }
 }
 
-impl EventHandler for Pong {
-    fn update(&mut self, ctx: &mut Context) {
-        let player_collider = self.player.collider();
-        let ball_collider = self.ball.collider();
-        let enemy_collider = self.enemy.collider();
+impl Scene for Pong {
+    fn fixed_update(&mut self, ctx: &mut Context, time: &Time) {
+        self.world.insert_resource(time.clone());
+        EventHandler::update(self, ctx);
+    }
 
-        if ball_collider.collides_with(player_collider)
-            || ball_collider.collides_with(enemy_collider)
-        {
-            debug!("Collided!");
-            self.ball.initial_velocity.x *= -1.0;
+    fn update(&mut self, ctx: &mut Context, time: &Time) -> SceneCommand {
+        // polled once per frame, however many fixed steps run
+        systems::input(&mut self.world, ctx);
+
+        if let (Some(mut input_handler), Some(input_map)) = (
+            self.world.resource_mut::<InputHandler>(),
+            self.world.resource::<InputMap>(),
+        ) {
+            if input_map.is_just_pressed("back", &input_handler) {
+                let transition = Transition::fade(0.5, Color::BLACK);
+                self.command = Some(SceneCommand::Pop(transition));
+            }
+            input_handler.end_frame(time.unscaled_delta());
         }
 
-        self.player.update(ctx);
-        self.ball.update(ctx);
-        self.enemy.update(ctx);
-    }
-
-    fn draw(&mut self, ctx: &mut Context) {
-        self.player.draw(ctx);
-        self.ball.draw(ctx);
-        self.enemy.draw(ctx);
+        self.command.take().unwrap_or(SceneCommand::None)
     }
 
-    fn key_down_event(
-        &mut self,
-        ctx: &mut Context,
-        keycode: KeyCode,
-        _keymods: miniquad::KeyMods,
-        _repeat: bool,
-    ) {
-        self.player.key_down_event(ctx, keycode, _keymods, _repeat)
+    fn draw(&mut self, ctx: &mut Context, time: &Time) {
+        self.world.insert_resource(time.clone());
+        EventHandler::draw(self, ctx);
     }
 
-    fn key_up_event(&mut self, _ctx: &mut Context, keycode: KeyCode, _keymods: miniquad::KeyMods) {
-        self.player.key_up_event(_ctx, keycode, _keymods)
+    fn event(&mut self, ctx: &mut Context, event: &SceneEvent) {
+        event.dispatch(ctx, self);
     }
 }
@@ -1,32 +1,78 @@
 use std::path::Path;
 
-use crate::core::{editor::Editor, sprite::Sprite};
+use crate::core::{
+    assets::Assets,
+    camera::Camera2D,
+    color::Color,
+    editor::{self, Editor},
+    error::EngineError,
+    input_handler::InputHandler,
+    input_map::InputMap,
+    post_process::{PostEffect, PostProcess},
+    render_target::RenderTarget,
+    scene::{Scene, SceneCommand, SceneEvent, Transition, WipeDirection},
+    sprite::Sprite,
+    sprite_batch::SpriteBatch,
+    time::Time,
+    viewport::Viewport,
+};
 use glam::Vec2;
 use log::info;
 use miniquad::*;
 
+use crate::examples::pong::Pong;
+
 /// The game. :)
 #[repr(C)]
 pub struct Game {
     pub sprite: Sprite,
     pub editor: Editor,
+    pub sprite_batch: SpriteBatch,
+    pub assets: Assets,
+    pub camera: Camera2D,
+    pub viewport: Viewport,
+    pub render_target: RenderTarget,
+    pub post_process: PostProcess,
+    pub input_handler: InputHandler,
+    pub input_map: InputMap,
+    /// What the scene manager should do after the next update.
+    command: Option<SceneCommand>,
 }
 
 /// Core game loop.
 impl EventHandler for Game {
     fn update(&mut self, ctx: &mut Context) {
         self.sprite.update(ctx);
+        self.input_handler.update(ctx);
+        // inputs pressed while rebinding belong to the new binding
+        if self.input_map.is_listening() {
+            self.input_map.capture(&self.input_handler);
+        } else {
+            self.handle_actions(ctx);
+        }
+        self.assets.unload_unused();
     }
 
     fn draw(&mut self, ctx: &mut Context) {
-        ctx.begin_default_pass(Default::default());
+        // draw the world at the virtual resolution
+        self.render_target
+            .begin(ctx, PassAction::clear_color(0.0, 0.0, 0.0, 1.0));
 
-        self.editor.background_image.draw(ctx);
-        self.sprite.draw(ctx);
-        self.editor.draw(ctx); // draw the editor on top of everything
+        self.sprite_batch.draw_sprite(&self.editor.background_image);
+        self.sprite_batch.draw_sprite(&self.sprite);
+        self.sprite_batch.flush_with_camera(ctx, &self.camera);
 
+        self.render_target.end(ctx);
+
+        // apply the effects, then upscale the result into the window
+        let output = self.post_process.apply(ctx, &self.render_target);
+        ctx.begin_default_pass(PassAction::clear_color(0.0, 0.0, 0.0, 1.0));
+        output.blit(ctx, &self.viewport);
         ctx.end_render_pass();
-        ctx.commit_frame();
+
+        // draw the editor on top of everything, at the window resolution
+        self.editor
+            .draw_with_settings(ctx, &mut self.post_process, &mut self.input_map);
     }
 
     fn key_down_event(
@@ -38,56 +84,167 @@ impl EventHandler for Game {
     ) {
         self.editor.key_down_event(ctx, keycode, _keymods, _repeat);
         self.sprite.key_down_event(ctx, keycode, _keymods, _repeat);
-        match keycode {
-            KeyCode::Escape => ctx.order_quit(),
-            _ => (),
-        }
+        self.input_handler
+            .key_down_event(ctx, keycode, _keymods, _repeat);
     }
 
     fn key_up_event(&mut self, ctx: &mut Context, keycode: KeyCode, _keymods: KeyMods) {
         self.editor.key_up_event(ctx, keycode, _keymods);
-        self.sprite.key_up_event(ctx, keycode, _keymods)
+        self.sprite.key_up_event(ctx, keycode, _keymods);
+        self.input_handler.key_up_event(ctx, keycode, _keymods);
     }
 
     fn char_event(&mut self, ctx: &mut Context, character: char, _keymods: KeyMods, _repeat: bool) {
         self.editor.char_event(ctx, character, _keymods, _repeat);
-        match character {
-            'z' => ctx.set_fullscreen(true),
-            'x' => ctx.set_fullscreen(false),
-            _ => (),
-        }
     }
 
     fn mouse_motion_event(&mut self, ctx: &mut Context, x: f32, y: f32) {
         self.editor.mouse_motion_event(ctx, x, y);
+        self.input_handler.mouse_motion_event(ctx, x, y);
     }
 
     fn mouse_wheel_event(&mut self, ctx: &mut Context, x: f32, y: f32) {
         self.editor.mouse_wheel_event(ctx, x, y);
+        if !self.editor.wants_pointer() {
+            self.input_handler.mouse_wheel_event(ctx, x, y);
+        }
     }
 
     fn mouse_button_down_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
         self.editor.mouse_button_down_event(ctx, button, x, y);
+        // clicks on the editor's panels don't reach the game
+        if !self.editor.wants_pointer() {
+            self.input_handler
+                .mouse_button_down_event(ctx, button, x, y);
+        }
     }
 
     fn mouse_button_up_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
         self.editor.mouse_button_up_event(ctx, button, x, y);
+        self.input_handler.mouse_button_up_event(ctx, button, x, y);
     }
 
     fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) {
         self.editor.resize_event(ctx, width, height);
+        self.input_handler.resize_event(ctx, width, height);
+    }
+
+    fn window_minimized_event(&mut self, ctx: &mut Context) {
+        self.input_handler.window_minimized_event(ctx);
+    }
+
+    fn window_restored_event(&mut self, ctx: &mut Context) {
+        self.input_handler.window_restored_event(ctx);
     }
 }
 
 impl Game {
+    /// Reacts to the actions pressed during this frame.
+    fn handle_actions(&mut self, ctx: &mut Context) {
+        let just_pressed = |action| self.input_map.is_just_pressed(action, &self.input_handler);
+
+        if just_pressed("quit") {
+            self.command = Some(SceneCommand::Quit);
+        }
+        if just_pressed("fullscreen") {
+            ctx.set_fullscreen(true);
+        }
+        if just_pressed("windowed") {
+            ctx.set_fullscreen(false);
+        }
+        if just_pressed("play_pong") {
+            let pong = Box::new(Pong::new(ctx, self.input_map.clone()));
+            let transition = Transition::wipe(WipeDirection::Right, 0.6, Color::BLACK);
+            self.command = Some(SceneCommand::Push(pong, transition));
+        }
+        if just_pressed("flash") {
+            if let Some(flash) = self.post_process.effect_mut("Screen flash") {
+                flash.trigger(1.0, 0.3);
+            }
+        }
+    }
+
     /// Constructs the game with all the necessary elements into it.
-    pub fn new(ctx: &mut Context) -> Self {
+    /// Returns an error if any of its assets can't be loaded.
+    pub fn new(ctx: &mut Context) -> Result<Self, EngineError> {
         info!("Creating the Game instance");
         let position = Vec2::new(64.0, 32.0);
+        let mut assets = Assets::new();
+
         let image_path = Path::new("src/content/chest.png");
-        let sprite = Sprite::new(ctx, position, image_path);
-        let editor = Editor::new(ctx);
+        let mut sprite = Sprite::load(ctx, &mut assets, position, image_path)?;
+        sprite.layer = 1;
+
+        let background_path = Path::new(editor::BACKGROUND_PATH);
+        let background = assets.load_texture_with_params(
+            ctx,
+            background_path,
+            TextureParams {
+                filter: FilterMode::Nearest,
+                wrap: TextureWrap::Repeat,
+                ..Default::default()
+            },
+        )?;
+        let editor = Editor::new(ctx, background);
+        let sprite_batch = SpriteBatch::new(ctx);
+        let viewport = Viewport::default();
+        let render_target = RenderTarget::try_new(
+            ctx,
+            viewport.virtual_size.x as u32,
+            viewport.virtual_size.y as u32,
+        )?;
+
+        let mut post_process = PostProcess::new(
+            ctx,
+            viewport.virtual_size.x as u32,
+            viewport.virtual_size.y as u32,
+        )?;
+        let mut scanlines = PostEffect::scanlines(ctx)?;
+        scanlines.enabled = false;
+        let mut pixelation = PostEffect::pixelation(ctx)?;
+        pixelation.enabled = false;
+        post_process.push(pixelation);
+        post_process.push(scanlines);
+        post_process.push(PostEffect::vignette(ctx)?);
+        post_process.push(PostEffect::flash(ctx)?);
+
+        let input_map = InputMap::load(Path::new(editor::INPUT_MAP_PATH))?;
+
+        Ok(Self {
+            sprite,
+            editor,
+            sprite_batch,
+            assets,
+            camera: Camera2D::default(),
+            viewport,
+            render_target,
+            post_process,
+            input_handler: InputHandler::new(),
+            input_map,
+            command: None,
+        })
+    }
+}
+
+impl Scene for Game {
+    fn update(&mut self, ctx: &mut Context, time: &Time) -> SceneCommand {
+        self.post_process.update(time.delta());
+        EventHandler::update(self, ctx);
+        self.input_handler.end_frame(time.unscaled_delta());
+
+        self.command.take().unwrap_or(SceneCommand::None)
+    }
+
+    fn draw(&mut self, ctx: &mut Context, _time: &Time) {
+        EventHandler::draw(self, ctx);
+    }
+
+    fn pause(&mut self, _ctx: &mut Context) {
+        // the scene on top gets the releases of what's held now
+        self.input_handler.release_all();
+    }
 
-        Self { sprite, editor }
+    fn event(&mut self, ctx: &mut Context, event: &SceneEvent) {
+        event.dispatch(ctx, self);
     }
 }
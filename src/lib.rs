@@ -0,0 +1,11 @@
+#[macro_use]
+extern crate log;
+
+pub mod core;
+pub mod examples;
+pub mod game;
+
+// the window size has to be an integer mult of these
+// in order to have proper cell division in positions
+pub const VIRTUAL_RESOLUTION_X: i32 = 512;
+pub const VIRTUAL_RESOLUTION_Y: i32 = 288;
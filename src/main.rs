@@ -5,22 +5,17 @@ use flexi_logger::{
     colored_opt_format, FileSpec, FlexiLoggerError, Logger, LoggerHandle, WriteMode,
 };
 
-use game::Game;
-
-pub mod core;
-pub mod examples;
-pub mod game;
+use rustyengine::core::scene::SceneManager;
+use rustyengine::game::Game;
 
 const WINDOW_NAME: &str = "Rusty Engine";
 const LOG_FILE_NAME: &str = "log/app.log";
 const LOG_LEVEL: &str = "info";
 
-// these have to be integer mults of each other
+// these have to be integer mults of the virtual resolution
 // in order to have proper cell division in positions
 const WINDOW_WIDTH: i32 = 1536;
 const WINDOW_HEIGHT: i32 = 864;
-const VIRTUAL_RESOLUTION_X: i32 = 512;
-const VIRTUAL_RESOLUTION_Y: i32 = 288;
 
 fn start_logger(log_level: &str) -> Result<LoggerHandle, FlexiLoggerError> {
     let logger = Logger::try_with_str(log_level)?
@@ -45,6 +40,12 @@ fn main() {
             window_resizable: true,
             ..Default::default()
         },
-        |ctx| Box::new(Game::new(ctx)),
+        |ctx| match Game::new(ctx).and_then(|game| SceneManager::new(ctx, Box::new(game))) {
+            Ok(scene_manager) => Box::new(scene_manager),
+            Err(err) => {
+                error!("Cannot start the game: {}", err);
+                panic!("Cannot start the game: {}", err);
+            }
+        },
     );
 }